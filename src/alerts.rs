@@ -0,0 +1,151 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::*;
+use crate::executor::SharedExecutorState;
+use crate::health::SharedHealth;
+use crate::notify::{self, Notification, NotifySender};
+use crate::ta;
+
+const EVALUATION_INTERVAL: Duration = Duration::from_secs(10);
+
+fn default_rsi_period() -> usize {
+    14
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "condition", rename_all = "snake_case")]
+pub enum AlertCondition {
+    // Last price moves above / below `level`
+    PriceAbove {
+        level: f64,
+    },
+    PriceBelow {
+        level: f64,
+    },
+    // Unrealized PnL across open positions, quote currency
+    UnrealizedPnlBelow {
+        value: f64,
+    },
+    // No websocket message on any stream for this long
+    NoData {
+        minutes: u64,
+    },
+    // RSI of the 1m closes
+    RsiAbove {
+        value: f64,
+        #[serde(default = "default_rsi_period")]
+        period: usize,
+    },
+    RsiBelow {
+        value: f64,
+        #[serde(default = "default_rsi_period")]
+        period: usize,
+    },
+}
+
+/// A named condition from the profile's `[[alerts]]` tables
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub name: String,
+    #[serde(flatten)]
+    pub condition: AlertCondition,
+}
+
+/// What the rules are evaluated against
+struct Inputs {
+    price: f64,
+    unrealized: f64,
+    // Milliseconds since the newest websocket message, None before the first one
+    data_age_ms: Option<i64>,
+    closes: Vec<f64>,
+}
+
+impl AlertCondition {
+    // Some(description) while the condition holds
+    fn check(&self, inputs: &Inputs) -> Option<String> {
+        match self {
+            AlertCondition::PriceAbove { level } if inputs.price > *level => {
+                Some(format!("Price {} is above {}", inputs.price, level))
+            }
+            AlertCondition::PriceBelow { level } if inputs.price > 0.0 && inputs.price < *level => {
+                Some(format!("Price {} is below {}", inputs.price, level))
+            }
+            AlertCondition::UnrealizedPnlBelow { value } if inputs.unrealized < *value => Some(
+                format!("Unrealized PnL {:.2} is below {}", inputs.unrealized, value),
+            ),
+            AlertCondition::NoData { minutes } => match inputs.data_age_ms {
+                Some(age) if age > *minutes as i64 * 60_000 => {
+                    Some(format!("No market data for {} minutes", age / 60_000))
+                }
+                _ => None,
+            },
+            AlertCondition::RsiAbove { value, period } => {
+                ta::calculate_rsi(&inputs.closes, *period)
+                    .filter(|rsi| rsi > value)
+                    .map(|rsi| format!("RSI({}) {:.1} is above {}", period, rsi, value))
+            }
+            AlertCondition::RsiBelow { value, period } => {
+                ta::calculate_rsi(&inputs.closes, *period)
+                    .filter(|rsi| rsi < value)
+                    .map(|rsi| format!("RSI({}) {:.1} is below {}", period, rsi, value))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Evaluates the rules on live data, notifying when a rule starts to hold. A rule
+/// fires again only after its condition has cleared.
+pub async fn run_rules(
+    rules: Vec<AlertRule>,
+    market_data: Arc<Mutex<MarketData>>,
+    closes: Arc<Mutex<VecDeque<f64>>>,
+    state: SharedExecutorState,
+    health: SharedHealth,
+    notifiers: Vec<NotifySender>,
+) {
+    let mut active = vec![false; rules.len()];
+    let mut interval = tokio::time::interval(EVALUATION_INTERVAL);
+    loop {
+        interval.tick().await;
+        // Copy out first so the std mutexes are never held across an await
+        let price = from_decimal(market_data.lock().unwrap().last_price);
+        let closes: Vec<f64> = closes.lock().unwrap().iter().copied().collect();
+        let data_age_ms = health
+            .lock()
+            .unwrap()
+            .last_message
+            .values()
+            .max()
+            .map(|last| chrono::Utc::now().timestamp_millis() - last);
+        let unrealized = state.read().await.pnl_report().unrealized;
+        let inputs = Inputs {
+            price,
+            unrealized,
+            data_age_ms,
+            closes,
+        };
+
+        for (rule, active) in rules.iter().zip(active.iter_mut()) {
+            match rule.condition.check(&inputs) {
+                Some(message) if !*active => {
+                    *active = true;
+                    log::info!("Alert {}: {}", rule.name, message);
+                    notify::broadcast(
+                        &notifiers,
+                        Notification::Rule {
+                            name: rule.name.clone(),
+                            message,
+                        },
+                    );
+                }
+                Some(_) => {}
+                None => *active = false,
+            }
+        }
+    }
+}
@@ -0,0 +1,695 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::extract::ws::WebSocketUpgrade;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, Method, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{IntoParams, Modify, OpenApi, ToSchema};
+
+use crate::auth::{Role, Tokens};
+use crate::control::{
+    self, CommandError, ControlCommand, ControlReply, ControlSender, TuningCommand, TuningSender,
+};
+use crate::dashboard::{self, SharedDashboard};
+use crate::domain::*;
+use crate::executor::{SharedExecutorState, Trade};
+use crate::orders::{OrderState, TrackedOrder};
+use crate::portfolio::{PnlReport, PositionPnl};
+use crate::push::{self, PushSender, Subscription};
+use crate::strategy::{ParameterRange, ParameterValue, StrategyParameter};
+use crate::streams::{StreamInfo, StreamRegistry, StreamStatus};
+
+// Exchange calls behind a command can take a while; don't hold requests forever
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct ApiSettings {
+    pub addr: SocketAddr,
+    // One is required as a bearer token on every request
+    pub tokens: Tokens,
+}
+
+impl ApiSettings {
+    // API_ADDR plus an operator API_TOKEN and/or read-only API_READ_TOKEN; the
+    // API is not started without a token
+    pub fn from_env() -> Option<Self> {
+        let addr = dotenv::var("API_ADDR").ok()?;
+        let tokens = match Tokens::from_env("API_TOKEN", "API_READ_TOKEN") {
+            Ok(Some(tokens)) => tokens,
+            Err(e) => {
+                log::error!("{}, control API disabled", e);
+                return None;
+            }
+            Ok(None) => {
+                log::warn!(
+                    "API_ADDR set without API_TOKEN or API_READ_TOKEN, control API disabled"
+                );
+                return None;
+            }
+        };
+        match addr.parse() {
+            Ok(addr) => Some(ApiSettings { addr, tokens }),
+            Err(e) => {
+                log::error!("Invalid API_ADDR {}: {}", addr, e);
+                None
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+struct ApiState {
+    executor: SharedExecutorState,
+    control: ControlSender,
+    tuning: TuningSender,
+    tokens: Tokens,
+    dashboard: SharedDashboard,
+    push: PushSender,
+    streams: StreamRegistry,
+}
+
+// Status, message and, for trading errors, TradingError::code()
+struct ApiError(StatusCode, String, Option<&'static str>);
+
+// Body of every error response
+#[derive(Debug, Serialize, ToSchema)]
+struct ErrorBody {
+    error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            error: self.1,
+            code: self.2.map(str::to_string),
+        };
+        (self.0, Json(body)).into_response()
+    }
+}
+
+impl From<TradingError> for ApiError {
+    fn from(e: TradingError) -> Self {
+        let status = match &e {
+            TradingError::InvalidParameter(_) => StatusCode::BAD_REQUEST,
+            TradingError::UnknownOrder(_) => StatusCode::NOT_FOUND,
+            e if e.is_retryable() => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::BAD_GATEWAY,
+        };
+        ApiError(status, e.to_string(), Some(e.code()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenQuery {
+    // For browser websockets, which can't set headers
+    token: Option<String>,
+}
+
+// Reads are open to every role; anything that changes state needs an operator
+async fn authorize<B>(
+    State(api): State<ApiState>,
+    Query(query): Query<TokenQuery>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, ApiError> {
+    let role = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| api.tokens.bearer_role(value))
+        .or_else(|| query.token.and_then(|token| api.tokens.role(&token)));
+    match role {
+        None => Err(ApiError(
+            StatusCode::UNAUTHORIZED,
+            "Missing or wrong bearer token".to_string(),
+            None,
+        )),
+        Some(Role::ReadOnly) if request.method() != Method::GET => {
+            log::warn!(
+                "Refused read-only token for {} {}",
+                request.method(),
+                request.uri().path()
+            );
+            Err(ApiError(
+                StatusCode::FORBIDDEN,
+                "Needs an operator token".to_string(),
+                None,
+            ))
+        }
+        Some(_) => Ok(next.run(request).await),
+    }
+}
+
+impl From<CommandError> for ApiError {
+    fn from(e: CommandError) -> Self {
+        match e {
+            CommandError::NotRunning => ApiError(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Executor is not running".to_string(),
+                None,
+            ),
+            CommandError::TimedOut => ApiError(
+                StatusCode::GATEWAY_TIMEOUT,
+                "Command still running".to_string(),
+                None,
+            ),
+            CommandError::Failed(e) => e.into(),
+        }
+    }
+}
+
+async fn command(api: &ApiState, command: ControlCommand) -> Result<ControlReply, ApiError> {
+    Ok(control::send_and_wait(&api.control, command, "api", COMMAND_TIMEOUT).await?)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct Status {
+    paused: bool,
+    safe_mode: Option<String>,
+    open_positions: usize,
+    working_orders: usize,
+    daily_pnl: f64,
+    realized: f64,
+    unrealized: f64,
+    risk: RiskParameters,
+}
+
+#[utoipa::path(get, path = "/status", responses((status = 200, body = Status)))]
+async fn status(State(api): State<ApiState>) -> Json<Status> {
+    let state = api.executor.read().await;
+    let report = state.pnl_report();
+    Json(Status {
+        paused: state.paused,
+        safe_mode: state.safe_mode.clone(),
+        open_positions: state.positions.len(),
+        working_orders: state.working_orders.len(),
+        daily_pnl: state.daily_pnl(),
+        realized: report.realized,
+        unrealized: report.unrealized,
+        risk: state.risk.clone(),
+    })
+}
+
+// Realized PnL by day, symbol and strategy, and the open positions marked to market
+#[utoipa::path(get, path = "/pnl", responses((status = 200, body = PnlReport)))]
+async fn pnl(State(api): State<ApiState>) -> Json<PnlReport> {
+    Json(api.executor.read().await.pnl_report())
+}
+
+#[utoipa::path(get, path = "/positions", responses((status = 200, body = [PositionPnl])))]
+async fn positions(State(api): State<ApiState>) -> Json<Vec<PositionPnl>> {
+    Json(api.executor.read().await.pnl_report().open_positions)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct TradesQuery {
+    // Most recent trades only
+    limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/trades",
+    params(TradesQuery),
+    responses((status = 200, body = [Trade]))
+)]
+async fn trades(State(api): State<ApiState>, Query(query): Query<TradesQuery>) -> Json<Vec<Trade>> {
+    let state = api.executor.read().await;
+    let skip = query
+        .limit
+        .map_or(0, |limit| state.trades.len().saturating_sub(limit));
+    Json(state.trades[skip..].to_vec())
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct OrdersQuery {
+    // Only orders still New or PartiallyFilled
+    #[serde(default)]
+    open: bool,
+    // Most recently updated only; defaults to 100
+    limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders",
+    params(OrdersQuery),
+    responses((status = 200, body = [TrackedOrder]))
+)]
+async fn orders(
+    State(api): State<ApiState>,
+    Query(query): Query<OrdersQuery>,
+) -> Json<Vec<TrackedOrder>> {
+    let state = api.executor.read().await;
+    let orders = if query.open {
+        state.orders.open()
+    } else {
+        state.orders.recent(query.limit.unwrap_or(100))
+    };
+    Json(orders.into_iter().cloned().collect())
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders/{id}",
+    params(("id" = String, Path, description = "Exchange order id")),
+    responses((status = 200, body = TrackedOrder), (status = 404, body = ErrorBody))
+)]
+async fn order(
+    State(api): State<ApiState>,
+    Path(id): Path<String>,
+) -> Result<Json<TrackedOrder>, ApiError> {
+    match api.executor.read().await.orders.get(&id) {
+        Some(order) => Ok(Json(order.clone())),
+        None => Err(ApiError(
+            StatusCode::NOT_FOUND,
+            format!("Unknown order {}", id),
+            None,
+        )),
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct Balance {
+    // Quote currency
+    balance: Decimal,
+}
+
+#[utoipa::path(
+    get,
+    path = "/balances",
+    responses((status = 200, body = Balance), (status = 502, body = ErrorBody))
+)]
+async fn balances(State(api): State<ApiState>) -> Result<Json<Balance>, ApiError> {
+    match command(&api, ControlCommand::Balance).await? {
+        ControlReply::Balance(balance) => Ok(Json(Balance { balance })),
+        reply => Err(ApiError(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Unexpected reply {:?}", reply),
+            None,
+        )),
+    }
+}
+
+#[utoipa::path(get, path = "/streams", responses((status = 200, body = [StreamInfo])))]
+async fn list_streams(State(api): State<ApiState>) -> Json<Vec<StreamInfo>> {
+    Json(api.streams.list_subscriptions())
+}
+
+#[utoipa::path(
+    post,
+    path = "/streams/resubscribe",
+    responses((status = 200, body = [StreamInfo]), (status = 403, body = ErrorBody))
+)]
+async fn resubscribe_streams(State(api): State<ApiState>) -> Json<Vec<StreamInfo>> {
+    api.streams.resubscribe_all();
+    Json(api.streams.list_subscriptions())
+}
+
+#[utoipa::path(post, path = "/pause", responses((status = 204), (status = 403, body = ErrorBody)))]
+async fn pause(State(api): State<ApiState>) -> Result<StatusCode, ApiError> {
+    command(&api, ControlCommand::Pause).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(post, path = "/resume", responses((status = 204), (status = 403, body = ErrorBody)))]
+async fn resume(State(api): State<ApiState>) -> Result<StatusCode, ApiError> {
+    command(&api, ControlCommand::Resume).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(post, path = "/kill", responses((status = 204), (status = 403, body = ErrorBody)))]
+async fn kill(State(api): State<ApiState>) -> Result<StatusCode, ApiError> {
+    command(&api, ControlCommand::Kill).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    put,
+    path = "/risk",
+    request_body = RiskParameters,
+    responses(
+        (status = 200, body = RiskParameters),
+        (status = 400, body = ErrorBody),
+        (status = 403, body = ErrorBody)
+    )
+)]
+async fn update_risk(
+    State(api): State<ApiState>,
+    Json(risk): Json<RiskParameters>,
+) -> Result<Json<RiskParameters>, ApiError> {
+    risk.validate()?;
+    command(&api, ControlCommand::UpdateRisk(risk.clone())).await?;
+    Ok(Json(risk))
+}
+
+#[utoipa::path(
+    get,
+    path = "/strategy/parameters",
+    responses((status = 200, body = [StrategyParameter]))
+)]
+async fn strategy_parameters(
+    State(api): State<ApiState>,
+) -> Result<Json<Vec<StrategyParameter>>, ApiError> {
+    let parameters = control::tune(&api.tuning, TuningCommand::List, COMMAND_TIMEOUT).await?;
+    Ok(Json(parameters))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct ParameterUpdate {
+    value: ParameterValue,
+}
+
+// Answers with every parameter, as now in effect
+#[utoipa::path(
+    put,
+    path = "/strategy/parameters/{name}",
+    params(("name" = String, Path, description = "Parameter to change")),
+    request_body = ParameterUpdate,
+    responses(
+        (status = 200, body = [StrategyParameter]),
+        (status = 400, body = ErrorBody),
+        (status = 403, body = ErrorBody)
+    )
+)]
+async fn update_strategy_parameter(
+    State(api): State<ApiState>,
+    Path(name): Path<String>,
+    Json(update): Json<ParameterUpdate>,
+) -> Result<Json<Vec<StrategyParameter>>, ApiError> {
+    let command = TuningCommand::Update {
+        name,
+        value: update.value,
+    };
+    let parameters = control::tune(&api.tuning, command, COMMAND_TIMEOUT).await?;
+    Ok(Json(parameters))
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum ManualOrderType {
+    #[default]
+    Market,
+    // Entries only, resting at `price`
+    Limit,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct ManualOrder {
+    symbol: String,
+    side: OrderSide,
+    #[serde(default)]
+    order_type: ManualOrderType,
+    // Latest known price if unset; required for limit orders
+    price: Option<Decimal>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/orders",
+    request_body = ManualOrder,
+    responses(
+        (status = 202),
+        (status = 400, body = ErrorBody),
+        (status = 403, body = ErrorBody)
+    )
+)]
+async fn place_order(
+    State(api): State<ApiState>,
+    Json(order): Json<ManualOrder>,
+) -> Result<StatusCode, ApiError> {
+    let action = match order.side {
+        OrderSide::Buy => TradeAction::Buy,
+        OrderSide::Sell => TradeAction::Sell,
+    };
+    command(
+        &api,
+        ControlCommand::ManualOrder {
+            symbol: order.symbol.to_uppercase(),
+            action,
+            price: order.price,
+            limit: matches!(order.order_type, ManualOrderType::Limit),
+        },
+    )
+    .await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+// The page holds no data; it asks for the token and opens the websocket
+async fn dashboard_page() -> Html<&'static str> {
+    Html(include_str!("../static/dashboard.html"))
+}
+
+async fn dashboard_ws(State(api): State<ApiState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| dashboard::stream(socket, api.dashboard))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct PushQuery {
+    // Comma separated: market, signals, orders, fills, risk, positions
+    topics: Option<String>,
+    symbols: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/ws",
+    params(PushQuery),
+    responses((status = 101, description = "JSON events, one per text message"))
+)]
+async fn push_ws(
+    State(api): State<ApiState>,
+    Query(query): Query<PushQuery>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let subscription = Subscription::parse(query.topics.as_deref(), query.symbols.as_deref());
+    ws.on_upgrade(move |socket| push::stream(socket, api.push, subscription))
+}
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
+        }
+    }
+}
+
+/// The REST contract; read-only tokens may only GET
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "auto_trade control API"),
+    paths(
+        status,
+        pnl,
+        positions,
+        trades,
+        orders,
+        order,
+        balances,
+        list_streams,
+        resubscribe_streams,
+        pause,
+        resume,
+        kill,
+        update_risk,
+        strategy_parameters,
+        update_strategy_parameter,
+        place_order,
+        push_ws
+    ),
+    components(schemas(
+        Status,
+        Balance,
+        ErrorBody,
+        StreamInfo,
+        StreamStatus,
+        ManualOrder,
+        ManualOrderType,
+        ParameterUpdate,
+        PnlReport,
+        PositionPnl,
+        Trade,
+        TrackedOrder,
+        OrderState,
+        RiskParameters,
+        OrderSide,
+        StrategyParameter,
+        ParameterValue,
+        ParameterRange
+    )),
+    modifiers(&BearerAuth),
+    security(("bearer" = []))
+)]
+struct ApiDoc;
+
+pub fn openapi_json() -> String {
+    ApiDoc::openapi()
+        .to_pretty_json()
+        .expect("OpenAPI document serializes")
+}
+
+// Public like the dashboard page; it describes the API, not the bot's state
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Serves the control API, event websocket and dashboard on `settings.addr`,
+/// for operating the running bot
+pub async fn serve(
+    settings: ApiSettings,
+    executor: SharedExecutorState,
+    control: ControlSender,
+    tuning: TuningSender,
+    dashboard: SharedDashboard,
+    push: PushSender,
+    streams: StreamRegistry,
+) {
+    let api = ApiState {
+        executor,
+        control,
+        tuning,
+        tokens: settings.tokens,
+        dashboard,
+        push,
+        streams,
+    };
+    log::info!("Control API listening on {}", settings.addr);
+    if let Err(e) = axum::Server::bind(&settings.addr)
+        .serve(router(api).into_make_service())
+        .await
+    {
+        log::error!("Control API stopped: {}", e);
+    }
+}
+
+// Everything but the dashboard page and the OpenAPI document needs a token
+fn router(api: ApiState) -> Router {
+    Router::new()
+        .route("/status", get(status))
+        .route("/pnl", get(pnl))
+        .route("/positions", get(positions))
+        .route("/trades", get(trades))
+        .route("/balances", get(balances))
+        .route("/streams", get(list_streams))
+        .route("/streams/resubscribe", post(resubscribe_streams))
+        .route("/pause", post(pause))
+        .route("/resume", post(resume))
+        .route("/kill", post(kill))
+        .route("/risk", put(update_risk))
+        .route("/orders", get(orders).post(place_order))
+        .route("/orders/:id", get(order))
+        .route("/strategy/parameters", get(strategy_parameters))
+        .route("/strategy/parameters/:name", put(update_strategy_parameter))
+        .route("/ws", get(push_ws))
+        .route("/dashboard/ws", get(dashboard_ws))
+        .route_layer(middleware::from_fn_with_state(api.clone(), authorize))
+        .route("/dashboard", get(dashboard_page))
+        .route("/openapi.json", get(openapi))
+        .with_state(api)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use tokio::sync::{mpsc, RwLock};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::control::ControlRequest;
+    use crate::dashboard::Dashboard;
+    use crate::executor::ExecutorState;
+
+    // The API with an operator and a read-only token, and the signal task's mailbox
+    fn app() -> (Router, mpsc::UnboundedReceiver<ControlRequest>) {
+        let (control, control_rx) = mpsc::unbounded_channel();
+        let (tuning, _) = mpsc::unbounded_channel();
+        let api = ApiState {
+            executor: Arc::new(RwLock::new(ExecutorState::default())),
+            control,
+            tuning,
+            tokens: Tokens::new(Some("operate"), Some("read")).unwrap().unwrap(),
+            dashboard: Dashboard::new(),
+            push: push::channel(),
+            streams: StreamRegistry::new(Vec::new()),
+        };
+        (router(api), control_rx)
+    }
+
+    fn request(method: Method, uri: &str, token: Option<&str>) -> Request<Body> {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    async fn status_of(app: &Router, request: Request<Body>) -> StatusCode {
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn a_missing_or_unknown_token_is_unauthorized() {
+        let (app, _control) = app();
+        for token in [None, Some(""), Some("wrong")] {
+            let status = status_of(&app, request(Method::GET, "/status", token)).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+        }
+        let status = status_of(&app, request(Method::GET, "/status?token=wrong", None)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        // The API description needs no token
+        let status = status_of(&app, request(Method::GET, "/openapi.json", None)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn a_read_only_token_may_only_get() {
+        let (app, mut control) = app();
+        let status = status_of(&app, request(Method::GET, "/status", Some("read"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let status = status_of(&app, request(Method::GET, "/status?token=read", None)).await;
+        assert_eq!(status, StatusCode::OK);
+        for (method, uri) in [
+            (Method::POST, "/pause"),
+            (Method::POST, "/kill"),
+            (Method::PUT, "/risk"),
+            (Method::POST, "/orders"),
+        ] {
+            let status = status_of(&app, request(method, uri, Some("read"))).await;
+            assert_eq!(status, StatusCode::FORBIDDEN, "{}", uri);
+        }
+        // Nothing reached the signal task
+        assert!(control.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn an_operator_token_may_change_state() {
+        let (app, mut control) = app();
+        // Stands in for the signal task
+        let signal_task = tokio::spawn(async move {
+            let request = control.recv().await.unwrap();
+            let _ = request.reply.unwrap().send(Ok(ControlReply::Done));
+            request.command
+        });
+        let status = status_of(&app, request(Method::POST, "/pause", Some("operate"))).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(matches!(signal_task.await.unwrap(), ControlCommand::Pause));
+        let status = status_of(&app, request(Method::GET, "/status", Some("operate"))).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
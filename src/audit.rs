@@ -0,0 +1,307 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::domain::*;
+use crate::dto::Error;
+
+// prev_hash of the first entry
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Who or what caused an order action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditActor {
+    // A strategy's signal, local or received over the bus
+    Strategy { name: String },
+    // Kill switch pulled by an operator, e.g. "telegram:12345"
+    KillSwitch { requested_by: String },
+    // Order placed by hand through the control API
+    Operator { requested_by: String },
+    // Resting order cancelled after its TTL
+    OrderExpiry,
+    // Position exited by its client-side trailing stop
+    TrailingStop,
+    // Cleanup while the bot shuts down
+    Shutdown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum AuditAction {
+    Submit {
+        symbol: String,
+        side: OrderSide,
+        order_type: String,
+        quantity: Decimal,
+    },
+    SubmitBracket {
+        symbol: String,
+        quantity: Decimal,
+        stop_loss: Decimal,
+        take_profit: Decimal,
+    },
+    Cancel {
+        symbol: String,
+        order_id: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub seq: u64,
+    // Milliseconds
+    pub recorded_at: i64,
+    pub actor: AuditActor,
+    #[serde(flatten)]
+    pub action: AuditAction,
+    // Exchange order id on success, the error otherwise
+    pub order_id: Option<String>,
+    pub error: Option<String>,
+    // Hash of the previous entry, chaining every entry to all before it
+    pub prev_hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    #[serde(flatten)]
+    pub record: AuditRecord,
+    // SHA-256 of the record's JSON as written, prev_hash included
+    pub hash: String,
+}
+
+fn digest(json: &str) -> String {
+    Sha256::digest(json.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// The record's JSON exactly as it was hashed: its line without the trailing
+// hash field. Parsing and serializing again could print floats differently.
+fn record_json(line: &str, hash: &str) -> Option<String> {
+    let record = line.strip_suffix(&format!(",\"hash\":\"{}\"}}", hash))?;
+    Some(format!("{}}}", record))
+}
+
+/// Append-only, hash-chained log of order actions. Editing, removing or
+/// reordering entries breaks the chain from that point on; see `verify`.
+pub struct AuditLog {
+    out: BufWriter<File>,
+    next_seq: u64,
+    last_hash: String,
+}
+
+impl AuditLog {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let (next_seq, last_hash) = if path.exists() {
+            let entries = load_audit_log(path)?;
+            if let Err(e) = verify_entries(&entries) {
+                // Keep appending so new actions are still recorded; the break stays visible
+                log::error!("Audit log {} is broken: {}", path.display(), e);
+            }
+            match entries.last() {
+                Some((_, last)) => (last.record.seq + 1, last.hash.clone()),
+                None => (0, GENESIS_HASH.to_string()),
+            }
+        } else {
+            (0, GENESIS_HASH.to_string())
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(AuditLog {
+            out: BufWriter::new(file),
+            next_seq,
+            last_hash,
+        })
+    }
+
+    pub fn append(
+        &mut self,
+        actor: AuditActor,
+        action: AuditAction,
+        outcome: Result<&str, &TradingError>,
+    ) -> Result<(), Error> {
+        let record = AuditRecord {
+            seq: self.next_seq,
+            recorded_at: chrono::Utc::now().timestamp_millis(),
+            actor,
+            action,
+            order_id: outcome.ok().map(str::to_string),
+            error: outcome.err().map(|e| format!("{:?}", e)),
+            prev_hash: self.last_hash.clone(),
+        };
+        // The record's fields then its hash, so the hashed bytes are a prefix of the line
+        let json = serde_json::to_string(&record)?;
+        let hash = digest(&json);
+        writeln!(
+            self.out,
+            "{},\"hash\":\"{}\"}}",
+            json.strip_suffix('}').unwrap_or(&json),
+            hash
+        )?;
+        self.out.flush()?;
+        self.next_seq += 1;
+        self.last_hash = hash;
+        Ok(())
+    }
+}
+
+// Each entry with the line it was read from
+pub fn load_audit_log<P: AsRef<Path>>(path: P) -> Result<Vec<(String, AuditEntry)>, Error> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)?;
+        entries.push((line, entry));
+    }
+    Ok(entries)
+}
+
+fn verify_entries(lines: &[(String, AuditEntry)]) -> Result<(), TradingError> {
+    let mut prev_hash = GENESIS_HASH.to_string();
+    let mut prev_seq: Option<u64> = None;
+    for (line, entry) in lines {
+        let seq = entry.record.seq;
+        if let Some(prev_seq) = prev_seq.filter(|prev_seq| seq != prev_seq + 1) {
+            return Err(TradingError::DataError(format!(
+                "entry {} follows {}",
+                seq, prev_seq
+            )));
+        }
+        prev_seq = Some(seq);
+        if entry.record.prev_hash != prev_hash {
+            return Err(TradingError::DataError(format!(
+                "entry {} does not chain to the entry before it",
+                seq
+            )));
+        }
+        let hashed = record_json(line.trim_end(), &entry.hash);
+        if hashed.map(|json| digest(&json)) != Some(entry.hash.clone()) {
+            return Err(TradingError::DataError(format!(
+                "entry {} was modified",
+                seq
+            )));
+        }
+        prev_hash = entry.hash.clone();
+    }
+    Ok(())
+}
+
+// Checks the whole chain, returning the number of entries
+pub fn verify<P: AsRef<Path>>(path: P) -> Result<usize, TradingError> {
+    let entries = load_audit_log(path)?;
+    verify_entries(&entries)?;
+    Ok(entries.len())
+}
+
+pub type SharedAuditLog = Arc<Mutex<AuditLog>>;
+
+// Audit log at the path in AUDIT_LOG_PATH, if set
+pub fn from_env() -> Option<SharedAuditLog> {
+    let path = dotenv::var("AUDIT_LOG_PATH").ok()?;
+    match AuditLog::open(&path) {
+        Ok(log) => Some(Arc::new(Mutex::new(log))),
+        Err(e) => {
+            log::error!("Failed to open audit log {}: {}", path, e);
+            None
+        }
+    }
+}
+
+pub fn record(
+    audit: &Option<SharedAuditLog>,
+    actor: &AuditActor,
+    action: AuditAction,
+    outcome: Result<&str, &TradingError>,
+) {
+    if let Some(audit) = audit {
+        if let Err(e) = audit.lock().unwrap().append(actor.clone(), action, outcome) {
+            log::error!("Failed to append to audit log: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    // A fresh log in the temp directory with three entries, as its lines
+    fn written(name: &str) -> (std::path::PathBuf, Vec<String>) {
+        let path = std::env::temp_dir().join(format!(
+            "auto_trade-audit-{}-{}.jsonl",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let mut log = AuditLog::open(&path).unwrap();
+        // Quantities with long fractions and trailing zeros
+        for (i, quantity) in [
+            dec!(0.30000000000000004),
+            dec!(0.0000001),
+            dec!(123.4567890123450),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let action = AuditAction::Submit {
+                symbol: "BTCUSDT".to_string(),
+                side: OrderSide::Buy,
+                order_type: "LIMIT".to_string(),
+                quantity,
+            };
+            log.append(AuditActor::OrderExpiry, action, Ok(&i.to_string()))
+                .unwrap();
+        }
+        let lines = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        (path, lines)
+    }
+
+    fn rewritten(path: &Path, lines: &[String]) -> Result<usize, TradingError> {
+        std::fs::write(path, lines.join("\n") + "\n").unwrap();
+        let result = verify(path);
+        let _ = std::fs::remove_file(path);
+        result
+    }
+
+    #[test]
+    fn an_untouched_log_verifies() {
+        let (path, lines) = written("clean");
+        assert_eq!(rewritten(&path, &lines).unwrap(), 3);
+    }
+
+    #[test]
+    fn an_edited_entry_fails() {
+        let (path, mut lines) = written("edited");
+        lines[1] = lines[1].replace("\"order_id\":\"1\"", "\"order_id\":\"7\"");
+        assert!(rewritten(&path, &lines).is_err());
+    }
+
+    #[test]
+    fn a_deleted_entry_fails() {
+        let (path, mut lines) = written("deleted");
+        lines.remove(1);
+        assert!(rewritten(&path, &lines).is_err());
+    }
+
+    #[test]
+    fn reordered_entries_fail() {
+        let (path, mut lines) = written("reordered");
+        lines.swap(1, 2);
+        assert!(rewritten(&path, &lines).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use sha2::{Digest, Sha256};
+
+use crate::domain::TradingError;
+
+/// What a token may do on the control API and gRPC service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    // Status, positions, trades and streams
+    ReadOnly,
+    // Also pause, kill, risk and parameter changes and manual orders
+    Operator,
+}
+
+#[derive(Debug, Clone)]
+pub struct Tokens {
+    // Digests only, compared in constant time
+    tokens: Vec<([u8; 32], Role)>,
+}
+
+fn digest(token: &str) -> [u8; 32] {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+impl Tokens {
+    // An operator and/or a read-only token, empty ones ignored; None if neither
+    // is given. The two must differ, or role() couldn't tell which was meant.
+    pub fn new(
+        operator: Option<&str>,
+        read_only: Option<&str>,
+    ) -> Result<Option<Self>, TradingError> {
+        let operator = operator.filter(|token| !token.is_empty());
+        let read_only = read_only.filter(|token| !token.is_empty());
+        if operator.is_some() && operator == read_only {
+            return Err(TradingError::InvalidParameter(
+                "The operator and read-only tokens are the same".to_string(),
+            ));
+        }
+        let tokens: Vec<_> = [(operator, Role::Operator), (read_only, Role::ReadOnly)]
+            .into_iter()
+            .filter_map(|(token, role)| Some((digest(token?), role)))
+            .collect();
+        if tokens.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Tokens { tokens }))
+    }
+
+    // An operator token in `operator_var` and/or a read-only one in `read_var`
+    pub fn from_env(operator_var: &str, read_var: &str) -> Result<Option<Self>, TradingError> {
+        let operator = dotenv::var(operator_var).ok();
+        let read_only = dotenv::var(read_var).ok();
+        Tokens::new(operator.as_deref(), read_only.as_deref()).map_err(|_| {
+            TradingError::InvalidParameter(format!(
+                "{} and {} are the same token",
+                operator_var, read_var
+            ))
+        })
+    }
+
+    pub fn role(&self, token: &str) -> Option<Role> {
+        let given = digest(token);
+        let mut role = None;
+        for (expected, token_role) in &self.tokens {
+            let difference = given
+                .iter()
+                .zip(expected)
+                .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+            if difference == 0 {
+                role = Some(*token_role);
+            }
+        }
+        role
+    }
+
+    // From an "Authorization: Bearer <token>" header value
+    pub fn bearer_role(&self, header: &str) -> Option<Role> {
+        self.role(header.strip_prefix("Bearer ")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_token_gets_its_own_role() {
+        let tokens = Tokens::new(Some("operate"), Some("read")).unwrap().unwrap();
+        assert_eq!(tokens.role("operate"), Some(Role::Operator));
+        assert_eq!(tokens.bearer_role("Bearer read"), Some(Role::ReadOnly));
+        assert_eq!(tokens.role("other"), None);
+        assert_eq!(tokens.bearer_role("read"), None);
+    }
+
+    #[test]
+    fn one_token_for_both_roles_is_refused() {
+        assert!(Tokens::new(Some("same"), Some("same")).is_err());
+        // Empty ones count as not set
+        assert!(Tokens::new(Some(""), Some("")).unwrap().is_none());
+        assert!(Tokens::new(Some("operate"), Some("")).unwrap().is_some());
+    }
+}
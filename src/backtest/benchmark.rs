@@ -0,0 +1,83 @@
+use super::metrics::period_returns;
+use super::PriceHistory;
+
+// Close of `history` at or before each of `times` (seconds); the first close
+// stands in for times before the history starts
+pub fn aligned_closes(history: &PriceHistory, times: &[i64]) -> Vec<(i64, f64)> {
+    let mut candles = history.candles.iter().peekable();
+    let mut last = match history.candles.first() {
+        Some(candle) => candle.close_price,
+        None => return Vec::new(),
+    };
+    times
+        .iter()
+        .map(|time| {
+            while let Some(candle) = candles.peek() {
+                if candle.close_time.timestamp() > *time {
+                    break;
+                }
+                last = candle.close_price;
+                candles.next();
+            }
+            (*time, last)
+        })
+        .collect()
+}
+
+// Value of an equal-weight basket bought at the first time and held, starting at 1
+pub fn buy_and_hold(histories: &[PriceHistory], times: &[i64]) -> Vec<(i64, f64)> {
+    let series: Vec<Vec<(i64, f64)>> = histories
+        .iter()
+        .map(|history| aligned_closes(history, times))
+        .filter(|series| series.first().is_some_and(|(_, price)| *price > 0.0))
+        .collect();
+    if series.is_empty() {
+        return Vec::new();
+    }
+    times
+        .iter()
+        .enumerate()
+        .map(|(i, time)| {
+            let value = series.iter().map(|s| s[i].1 / s[0].1).sum::<f64>() / series.len() as f64;
+            (*time, value)
+        })
+        .collect()
+}
+
+pub fn total_return_pct(series: &[(i64, f64)]) -> f64 {
+    match (series.first(), series.last()) {
+        (Some((_, first)), Some((_, last))) if *first > 0.0 => (last / first - 1.0) * 100.0,
+        _ => 0.0,
+    }
+}
+
+// Beta and annualized alpha (percent) of `equity` against `benchmark`, risk-free
+// rate of zero. Both series must share the same timestamps.
+pub fn alpha_beta(equity: &[f64], benchmark: &[f64], periods_per_year: f64) -> (f64, f64) {
+    let returns = period_returns(equity);
+    let benchmark_returns = period_returns(benchmark);
+    let n = returns.len().min(benchmark_returns.len());
+    if n < 2 {
+        return (0.0, 0.0);
+    }
+    let (returns, benchmark_returns) = (&returns[..n], &benchmark_returns[..n]);
+    let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len() as f64;
+    let (mean_r, mean_b) = (mean(returns), mean(benchmark_returns));
+    let covariance = returns
+        .iter()
+        .zip(benchmark_returns)
+        .map(|(r, b)| (r - mean_r) * (b - mean_b))
+        .sum::<f64>()
+        / (n - 1) as f64;
+    let variance = benchmark_returns
+        .iter()
+        .map(|b| (b - mean_b).powi(2))
+        .sum::<f64>()
+        / (n - 1) as f64;
+    if variance == 0.0 {
+        return (0.0, 0.0);
+    }
+    let beta = covariance / variance;
+    let alpha = (mean_r - beta * mean_b) * periods_per_year * 100.0;
+    (alpha, beta)
+}
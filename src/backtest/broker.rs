@@ -0,0 +1,512 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::domain::*;
+use crate::dto::KlineResponse;
+use crate::executor::Trade;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Liquidity {
+    // Resting limit order that was hit
+    Maker,
+    // Order that crossed the spread
+    Taker,
+}
+
+/// Commission rates in percent of notional
+#[derive(Debug, Clone)]
+pub struct FeeSchedule {
+    pub maker_pct: f64,
+    pub taker_pct: f64,
+}
+
+impl Default for FeeSchedule {
+    // Binance spot base tier
+    fn default() -> Self {
+        FeeSchedule {
+            maker_pct: 0.1,
+            taker_pct: 0.1,
+        }
+    }
+}
+
+impl FeeSchedule {
+    pub fn rate_pct(&self, liquidity: Liquidity) -> f64 {
+        match liquidity {
+            Liquidity::Maker => self.maker_pct,
+            Liquidity::Taker => self.taker_pct,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SlippageModel {
+    None,
+    // Constant adverse move in basis points
+    FixedBps(f64),
+    // Grows with the order's share of the candle volume, capped at `max_bps`
+    VolumeProportional {
+        bps_per_pct_of_volume: f64,
+        max_bps: f64,
+    },
+}
+
+/// When a resting limit order counts as filled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LimitFillPolicy {
+    // Filled as soon as the candle touches the limit price
+    Optimistic,
+    // Filled only once price trades through the limit price
+    Conservative,
+    // The order joins the back of a queue estimated at `queue_ahead_pct` of the
+    // placing candle's volume. Candles that only touch the level trade
+    // `touch_volume_pct` of their volume there, working the queue off; trading
+    // through the level always fills.
+    Queue {
+        queue_ahead_pct: f64,
+        touch_volume_pct: f64,
+    },
+}
+
+/// How simulated orders get filled
+#[derive(Debug, Clone)]
+pub struct FillModel {
+    pub fees: FeeSchedule,
+    pub slippage: SlippageModel,
+    // Full bid/ask spread in basis points; takers pay half of it
+    pub spread_bps: f64,
+    pub limit_fills: LimitFillPolicy,
+}
+
+impl Default for FillModel {
+    fn default() -> Self {
+        FillModel {
+            fees: FeeSchedule::default(),
+            slippage: SlippageModel::FixedBps(1.0),
+            spread_bps: 1.0,
+            limit_fills: LimitFillPolicy::Conservative,
+        }
+    }
+}
+
+impl FromStr for FillModel {
+    type Err = TradingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(FillModel::default()),
+            "frictionless" => Ok(FillModel::frictionless()),
+            _ => Err(TradingError::InvalidParameter(format!(
+                "Unknown fill model {:?}, expected default or frictionless",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SimulatedFill {
+    pub price: f64,
+    // In quote currency
+    pub fee: f64,
+}
+
+impl FillModel {
+    // Idealised fills: no fees, no slippage, no spread
+    pub fn frictionless() -> Self {
+        FillModel {
+            fees: FeeSchedule {
+                maker_pct: 0.0,
+                taker_pct: 0.0,
+            },
+            slippage: SlippageModel::None,
+            spread_bps: 0.0,
+            limit_fills: LimitFillPolicy::Optimistic,
+        }
+    }
+
+    // Volume resting ahead of a limit order placed during a candle of `candle_volume`
+    pub fn initial_queue(&self, candle_volume: f64) -> f64 {
+        match self.limit_fills {
+            LimitFillPolicy::Queue {
+                queue_ahead_pct, ..
+            } => candle_volume * queue_ahead_pct / 100.0,
+            _ => 0.0,
+        }
+    }
+
+    // Whether a resting order on `side` at `price` fills during `candle`. Under the
+    // queue policy `queue_ahead` is worked off and goes negative as our own
+    // quantity trades.
+    pub fn limit_fills(
+        &self,
+        side: &OrderSide,
+        price: f64,
+        quantity: f64,
+        queue_ahead: &mut f64,
+        candle: &KlineResponse,
+    ) -> bool {
+        let (touched, through) = match side {
+            OrderSide::Buy => (candle.low_price <= price, candle.low_price < price),
+            OrderSide::Sell => (candle.high_price >= price, candle.high_price > price),
+        };
+        match self.limit_fills {
+            LimitFillPolicy::Optimistic => touched,
+            LimitFillPolicy::Conservative => through,
+            LimitFillPolicy::Queue {
+                touch_volume_pct, ..
+            } => {
+                if through {
+                    return true;
+                }
+                if touched {
+                    *queue_ahead -= candle.volume * touch_volume_pct / 100.0;
+                }
+                *queue_ahead <= -quantity
+            }
+        }
+    }
+
+    fn slippage_bps(&self, quantity: f64, candle_volume: f64) -> f64 {
+        match &self.slippage {
+            SlippageModel::None => 0.0,
+            SlippageModel::FixedBps(bps) => *bps,
+            SlippageModel::VolumeProportional {
+                bps_per_pct_of_volume,
+                max_bps,
+            } => {
+                if candle_volume <= 0.0 {
+                    return *max_bps;
+                }
+                let participation_pct = quantity / candle_volume * 100.0;
+                (participation_pct * bps_per_pct_of_volume).min(*max_bps)
+            }
+        }
+    }
+
+    pub fn fill(
+        &self,
+        side: &OrderSide,
+        reference_price: f64,
+        quantity: f64,
+        candle_volume: f64,
+        liquidity: Liquidity,
+    ) -> SimulatedFill {
+        let adverse_bps = match liquidity {
+            Liquidity::Maker => 0.0,
+            Liquidity::Taker => self.spread_bps / 2.0 + self.slippage_bps(quantity, candle_volume),
+        };
+        let price = match side {
+            OrderSide::Buy => reference_price * (1.0 + adverse_bps / 10_000.0),
+            OrderSide::Sell => reference_price * (1.0 - adverse_bps / 10_000.0),
+        };
+        SimulatedFill {
+            price,
+            fee: price * quantity * self.fees.rate_pct(liquidity) / 100.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SimPosition {
+    pub symbol: String,
+    pub strategy: String,
+    pub quantity: f64,
+    pub entry_price: f64,
+    pub entry_fee: f64,
+    pub bracket: Bracket,
+    // Queue ahead of the take-profit order
+    pub take_profit_queue: f64,
+    pub opened_at: i64,
+}
+
+// Resting limit buy waiting to open a position
+#[derive(Debug, Clone)]
+pub struct SimLimitOrder {
+    pub symbol: String,
+    pub strategy: String,
+    pub quantity: f64,
+    pub price: f64,
+    pub queue_ahead: f64,
+    pub placed_at: i64,
+    pub expires_at: i64,
+}
+
+/// Cash and long positions of a simulated account
+pub struct SimulatedBroker {
+    pub fill_model: FillModel,
+    cash: f64,
+    positions: HashMap<String, SimPosition>,
+    limit_orders: HashMap<String, SimLimitOrder>,
+}
+
+impl SimulatedBroker {
+    pub fn new(cash: f64, fill_model: FillModel) -> Self {
+        SimulatedBroker {
+            fill_model,
+            cash,
+            positions: HashMap::new(),
+            limit_orders: HashMap::new(),
+        }
+    }
+
+    pub fn cash(&self) -> f64 {
+        self.cash
+    }
+
+    pub fn position(&self, symbol: &str) -> Option<&SimPosition> {
+        self.positions.get(symbol)
+    }
+
+    // Positions plus resting entry orders, across all symbols
+    pub fn open_positions(&self) -> usize {
+        self.positions.len() + self.limit_orders.len()
+    }
+
+    // Cash plus positions marked at `prices`
+    pub fn equity(&self, prices: &HashMap<String, f64>) -> f64 {
+        self.cash
+            + self
+                .positions
+                .values()
+                .map(|p| p.quantity * prices.get(&p.symbol).copied().unwrap_or(p.entry_price))
+                .sum::<f64>()
+    }
+
+    pub fn position_value(&self, symbol: &str, price: f64) -> f64 {
+        self.positions
+            .get(symbol)
+            .map(|p| p.quantity * price)
+            .unwrap_or(0.0)
+    }
+
+    // Market buy of `quantity` at `reference_price`, scaled down if cash cannot
+    // cover it including fees. `candle` is the one the order executes in.
+    #[allow(clippy::too_many_arguments)]
+    pub fn market_buy(
+        &mut self,
+        symbol: &str,
+        strategy: &str,
+        quantity: f64,
+        reference_price: f64,
+        candle: &KlineResponse,
+        risk: &RiskParameters,
+        time: i64,
+    ) -> bool {
+        if self.positions.contains_key(symbol)
+            || quantity <= 0.0
+            || self.cash <= 0.0
+            || reference_price <= 0.0
+        {
+            return false;
+        }
+
+        let fill = self.fill_model.fill(
+            &OrderSide::Buy,
+            reference_price,
+            quantity,
+            candle.volume,
+            Liquidity::Taker,
+        );
+        self.open(symbol, strategy, quantity, fill, candle, risk, time);
+        true
+    }
+
+    // Rest a limit buy for `symbol`; at most one order or position per symbol
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_limit_buy(
+        &mut self,
+        symbol: &str,
+        strategy: &str,
+        quantity: f64,
+        price: f64,
+        candle: &KlineResponse,
+        ttl_secs: i64,
+        time: i64,
+    ) -> bool {
+        if self.positions.contains_key(symbol)
+            || self.limit_orders.contains_key(symbol)
+            || quantity <= 0.0
+            || price <= 0.0
+        {
+            return false;
+        }
+        self.limit_orders.insert(
+            symbol.to_string(),
+            SimLimitOrder {
+                symbol: symbol.to_string(),
+                strategy: strategy.to_string(),
+                quantity,
+                price,
+                queue_ahead: self.fill_model.initial_queue(candle.volume),
+                placed_at: time,
+                expires_at: time + ttl_secs,
+            },
+        );
+        true
+    }
+
+    pub fn cancel_limit_order(&mut self, symbol: &str) -> Option<SimLimitOrder> {
+        self.limit_orders.remove(symbol)
+    }
+
+    // Fill or expire the resting order for `symbol` against the candle that just
+    // closed. Returns true if it opened a position.
+    pub fn process_limit_order(
+        &mut self,
+        symbol: &str,
+        candle: &KlineResponse,
+        risk: &RiskParameters,
+        time: i64,
+    ) -> bool {
+        let mut order = match self.limit_orders.remove(symbol) {
+            Some(order) => order,
+            None => return false,
+        };
+        // Nothing can fill during the candle the order was placed in
+        if order.placed_at >= time {
+            self.limit_orders.insert(symbol.to_string(), order);
+            return false;
+        }
+        let filled = self.fill_model.limit_fills(
+            &OrderSide::Buy,
+            order.price,
+            order.quantity,
+            &mut order.queue_ahead,
+            candle,
+        );
+        if !filled {
+            if order.expires_at > time {
+                self.limit_orders.insert(symbol.to_string(), order);
+            }
+            return false;
+        }
+        let fill = self.fill_model.fill(
+            &OrderSide::Buy,
+            order.price,
+            order.quantity,
+            candle.volume,
+            Liquidity::Maker,
+        );
+        self.open(
+            symbol,
+            &order.strategy,
+            order.quantity,
+            fill,
+            candle,
+            risk,
+            time,
+        );
+        true
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn open(
+        &mut self,
+        symbol: &str,
+        strategy: &str,
+        quantity: f64,
+        mut fill: SimulatedFill,
+        candle: &KlineResponse,
+        risk: &RiskParameters,
+        time: i64,
+    ) {
+        let mut quantity = quantity;
+        let cost = quantity * fill.price + fill.fee;
+        if cost > self.cash {
+            // Scale down so slippage and fees still fit the cash
+            let scale = self.cash / cost;
+            quantity *= scale;
+            fill.fee *= scale;
+        }
+        self.cash -= quantity * fill.price + fill.fee;
+        self.positions.insert(
+            symbol.to_string(),
+            SimPosition {
+                symbol: symbol.to_string(),
+                strategy: strategy.to_string(),
+                quantity,
+                entry_price: fill.price,
+                entry_fee: fill.fee,
+                bracket: Bracket::from_risk(to_decimal(fill.price), &OrderSide::Buy, risk),
+                take_profit_queue: self.fill_model.initial_queue(candle.volume),
+                opened_at: time,
+            },
+        );
+    }
+
+    // Market sell of the whole position at `reference_price`
+    pub fn market_sell(
+        &mut self,
+        symbol: &str,
+        reference_price: f64,
+        candle: &KlineResponse,
+        time: i64,
+    ) -> Option<Trade> {
+        let position = self.positions.remove(symbol)?;
+        let fill = self.fill_model.fill(
+            &OrderSide::Sell,
+            reference_price,
+            position.quantity,
+            candle.volume,
+            Liquidity::Taker,
+        );
+        Some(self.close(position, fill, time))
+    }
+
+    // Exit through the bracket if the candle reached either leg. When both are
+    // inside the bar the stop is assumed to have hit first. The take-profit rests
+    // on the book, so it follows the fill model's limit policy.
+    pub fn check_bracket(
+        &mut self,
+        symbol: &str,
+        candle: &KlineResponse,
+        time: i64,
+    ) -> Option<Trade> {
+        let fill_model = &self.fill_model;
+        let position = self.positions.get_mut(symbol)?;
+        // Simulated in f64 like the rest of the backtest
+        let stop_loss = from_decimal(position.bracket.stop_loss);
+        let take_profit = from_decimal(position.bracket.take_profit);
+        let (price, liquidity) = if candle.low_price <= stop_loss {
+            // Stop triggers a market order
+            (stop_loss, Liquidity::Taker)
+        } else if fill_model.limit_fills(
+            &OrderSide::Sell,
+            take_profit,
+            position.quantity,
+            &mut position.take_profit_queue,
+            candle,
+        ) {
+            (take_profit, Liquidity::Maker)
+        } else {
+            return None;
+        };
+        let position = self.positions.remove(symbol)?;
+        let fill = self.fill_model.fill(
+            &OrderSide::Sell,
+            price,
+            position.quantity,
+            candle.volume,
+            liquidity,
+        );
+        Some(self.close(position, fill, time))
+    }
+
+    fn close(&mut self, position: SimPosition, fill: SimulatedFill, time: i64) -> Trade {
+        self.cash += position.quantity * fill.price - fill.fee;
+        let fees = position.entry_fee + fill.fee;
+        Trade {
+            symbol: position.symbol,
+            strategy: position.strategy,
+            side: OrderSide::Buy,
+            quantity: position.quantity,
+            entry_price: position.entry_price,
+            exit_price: fill.price,
+            fees,
+            pnl: (fill.price - position.entry_price) * position.quantity - fees,
+            opened_at: position.opened_at,
+            closed_at: time,
+        }
+    }
+}
@@ -0,0 +1,402 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::ops::Range;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use binance_spot_connector_rust::hyper::BinanceHttpClient;
+use binance_spot_connector_rust::market;
+
+use crate::domain::Interval;
+use crate::dto::{self, Error, KlineResponse};
+use crate::storage::CandleStore;
+
+// Binance caps a klines request at this many candles
+const KLINES_PAGE_LIMIT: u32 = 1000;
+
+/// Ordered, de-duplicated candles for one symbol
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceHistory {
+    pub symbol: String,
+    pub interval_ms: i64,
+    pub candles: Vec<KlineResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataIssue {
+    // Same open time seen more than once; the last one wins
+    Duplicate { open_time: i64 },
+    // Candle appeared before an earlier one in the source
+    OutOfOrder { open_time: i64 },
+    // `missing` candles absent between the two open times
+    Gap { from: i64, to: i64, missing: i64 },
+}
+
+impl PriceHistory {
+    // Sort and de-duplicate `candles`, reporting everything that had to be fixed up
+    // and any gaps that remain. An `interval_ms` of 0 infers it from the data.
+    pub fn from_candles(
+        symbol: &str,
+        interval_ms: i64,
+        mut candles: Vec<KlineResponse>,
+    ) -> (Self, Vec<DataIssue>) {
+        let mut issues = Vec::new();
+
+        for pair in candles.windows(2) {
+            if pair[1].open_time < pair[0].open_time {
+                issues.push(DataIssue::OutOfOrder {
+                    open_time: pair[1].open_time.timestamp_millis(),
+                });
+            }
+        }
+        // Stable sort, so for duplicates the later row in the source stays last
+        candles.sort_by_key(|candle| candle.open_time);
+
+        let mut deduped: Vec<KlineResponse> = Vec::with_capacity(candles.len());
+        for candle in candles {
+            match deduped.last_mut() {
+                Some(last) if last.open_time == candle.open_time => {
+                    issues.push(DataIssue::Duplicate {
+                        open_time: candle.open_time.timestamp_millis(),
+                    });
+                    *last = candle;
+                }
+                _ => deduped.push(candle),
+            }
+        }
+
+        let interval_ms = if interval_ms > 0 {
+            interval_ms
+        } else {
+            infer_interval(&deduped)
+        };
+        if interval_ms > 0 {
+            for pair in deduped.windows(2) {
+                let from = pair[0].open_time.timestamp_millis();
+                let to = pair[1].open_time.timestamp_millis();
+                if to - from > interval_ms {
+                    issues.push(DataIssue::Gap {
+                        from,
+                        to,
+                        missing: (to - from) / interval_ms - 1,
+                    });
+                }
+            }
+        }
+
+        let history = PriceHistory {
+            symbol: symbol.to_string(),
+            interval_ms,
+            candles: deduped,
+        };
+        (history, issues)
+    }
+
+    pub fn len(&self) -> usize {
+        self.candles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candles.is_empty()
+    }
+
+    pub fn closes(&self) -> Vec<f64> {
+        self.candles.iter().map(|c| c.close_price).collect()
+    }
+
+    // Candles opened in [from, to)
+    pub fn between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> PriceHistory {
+        PriceHistory {
+            symbol: self.symbol.clone(),
+            interval_ms: self.interval_ms,
+            candles: self
+                .candles
+                .iter()
+                .filter(|c| c.open_time >= from && c.open_time < to)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    // Candles opened in [from, to), without copying; history must be sorted
+    pub fn candles_within(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> &[KlineResponse] {
+        let start = self.candles.partition_point(|c| c.open_time < from);
+        let end = self.candles.partition_point(|c| c.open_time < to);
+        &self.candles[start..end.max(start)]
+    }
+
+    // Copy of the candles in `range`
+    pub fn slice(&self, range: Range<usize>) -> PriceHistory {
+        PriceHistory {
+            symbol: self.symbol.clone(),
+            interval_ms: self.interval_ms,
+            candles: self.candles[range].to_vec(),
+        }
+    }
+}
+
+// Smallest spacing between consecutive candles
+fn infer_interval(candles: &[KlineResponse]) -> i64 {
+    candles
+        .windows(2)
+        .map(|pair| (pair[1].open_time - pair[0].open_time).num_milliseconds())
+        .filter(|diff| *diff > 0)
+        .min()
+        .unwrap_or(0)
+}
+
+// Binance switched spot dumps to microseconds in 2025
+fn parse_time(field: &str) -> Result<DateTime<Utc>, Error> {
+    let value: i64 = field
+        .trim()
+        .parse()
+        .map_err(|_| Error::ParseError(format!("Invalid timestamp: {}", field)))?;
+    let millis = if value > 100_000_000_000_000 {
+        value / 1000
+    } else {
+        value
+    };
+    DateTime::from_timestamp_millis(millis)
+        .ok_or_else(|| Error::ParseError(format!("Invalid timestamp: {}", field)))
+}
+
+fn parse_float(field: &str) -> Result<f64, Error> {
+    field.trim().parse().map_err(Error::NumberParseError)
+}
+
+// Parse one row in Binance kline column order. Only the first six columns are
+// required; close time falls back to the open time plus `interval_ms`.
+fn parse_row(line: &str, interval_ms: i64) -> Result<KlineResponse, Error> {
+    let fields: Vec<&str> = line.split(',').collect();
+    if fields.len() < 6 {
+        return Err(Error::ParseError(format!(
+            "Invalid row: expected at least 6 columns, got {}",
+            fields.len()
+        )));
+    }
+    let open_time = parse_time(fields[0])?;
+    let optional_float = |index: usize| -> Result<f64, Error> {
+        match fields.get(index) {
+            Some(field) => parse_float(field),
+            None => Ok(0.0),
+        }
+    };
+
+    Ok(KlineResponse {
+        open_time,
+        open_price: parse_float(fields[1])?,
+        high_price: parse_float(fields[2])?,
+        low_price: parse_float(fields[3])?,
+        close_price: parse_float(fields[4])?,
+        volume: parse_float(fields[5])?,
+        close_time: match fields.get(6) {
+            Some(field) => parse_time(field)?,
+            None => open_time + chrono::Duration::milliseconds(interval_ms - 1),
+        },
+        quote_asset_volume: optional_float(7)?,
+        number_of_trades: match fields.get(8) {
+            Some(field) => field
+                .trim()
+                .parse()
+                .map_err(|_| Error::ParseError(format!("Invalid number_of_trades: {}", field)))?,
+            None => 0,
+        },
+        taker_buy_base_volume: optional_float(9)?,
+        taker_buy_quote_volume: optional_float(10)?,
+    })
+}
+
+fn read_rows<R: BufRead>(reader: R, interval_ms: i64) -> Result<Vec<KlineResponse>, Error> {
+    let mut candles = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        // Skip a header row, if there is one
+        let first = line.split(',').next().unwrap_or_default();
+        if index == 0 && first.trim().parse::<i64>().is_err() {
+            continue;
+        }
+        let candle = parse_row(line, interval_ms)
+            .map_err(|e| Error::ParseError(format!("line {}: {}", index + 1, e)))?;
+        candles.push(candle);
+    }
+    Ok(candles)
+}
+
+fn finish(symbol: &str, interval_ms: i64, candles: Vec<KlineResponse>) -> PriceHistory {
+    let (history, issues) = PriceHistory::from_candles(symbol, interval_ms, candles);
+    for issue in &issues {
+        log::warn!("{} data issue: {:?}", symbol, issue);
+    }
+    history
+}
+
+// CSV in Binance kline column order (open_time, open, high, low, close, volume, ...),
+// with or without a header row
+pub fn load_csv<P: AsRef<Path>>(
+    path: P,
+    symbol: &str,
+    interval_ms: i64,
+) -> Result<PriceHistory, Error> {
+    let file = File::open(path)?;
+    let candles = read_rows(BufReader::new(file), interval_ms)?;
+    Ok(finish(symbol, interval_ms, candles))
+}
+
+// Monthly/daily kline dump from data.binance.vision, e.g. BTCUSDT-1m-2024-01.zip
+pub fn load_binance_zip<P: AsRef<Path>>(
+    path: P,
+    symbol: &str,
+    interval_ms: i64,
+) -> Result<PriceHistory, Error> {
+    let file = File::open(path)?;
+    let mut archive =
+        zip::ZipArchive::new(file).map_err(|e| Error::ParseError(format!("{}", e)))?;
+
+    let mut candles = Vec::new();
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|e| Error::ParseError(format!("{}", e)))?;
+        if !entry.name().ends_with(".csv") {
+            continue;
+        }
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents)?;
+        candles.extend(read_rows(contents.as_bytes(), interval_ms)?);
+    }
+    Ok(finish(symbol, interval_ms, candles))
+}
+
+// Several dumps at once, e.g. a year of monthly files
+pub fn load_binance_zips<P: AsRef<Path>>(
+    paths: &[P],
+    symbol: &str,
+    interval_ms: i64,
+) -> Result<PriceHistory, Error> {
+    let mut candles = Vec::new();
+    for path in paths {
+        candles.extend(load_binance_zip(path, symbol, interval_ms)?.candles);
+    }
+    Ok(finish(symbol, interval_ms, candles))
+}
+
+// Local candle cache: a JSON-serialized `PriceHistory`
+pub fn load_cache<P: AsRef<Path>>(path: P) -> Result<PriceHistory, Error> {
+    let file = File::open(path)?;
+    let cached: PriceHistory = serde_json::from_reader(BufReader::new(file))?;
+    Ok(finish(&cached.symbol, cached.interval_ms, cached.candles))
+}
+
+pub fn save_cache<P: AsRef<Path>>(path: P, history: &PriceHistory) -> Result<(), Error> {
+    let file = File::create(path)?;
+    serde_json::to_writer(file, history)?;
+    Ok(())
+}
+
+// Picks the loader from the extension: .csv, .zip (Binance dump) or .json (cache)
+pub fn load_path<P: AsRef<Path>>(
+    path: P,
+    symbol: &str,
+    interval_ms: i64,
+) -> Result<PriceHistory, Error> {
+    let path = path.as_ref();
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => load_csv(path, symbol, interval_ms),
+        Some("zip") => load_binance_zip(path, symbol, interval_ms),
+        Some("json") => load_cache(path),
+        _ => Err(Error::ParseError(format!(
+            "Unsupported data file: {}",
+            path.display()
+        ))),
+    }
+}
+
+// Several files for one symbol, merged and de-duplicated
+pub fn load_paths<P: AsRef<Path>>(
+    paths: &[P],
+    symbol: &str,
+    interval_ms: i64,
+) -> Result<PriceHistory, Error> {
+    let zip = |path: &P| path.as_ref().extension().and_then(|ext| ext.to_str()) == Some("zip");
+    match paths {
+        [path] => load_path(path, symbol, interval_ms),
+        _ if paths.iter().all(zip) => load_binance_zips(paths, symbol, interval_ms),
+        _ => {
+            let mut candles = Vec::new();
+            for path in paths {
+                candles.extend(load_path(path, symbol, interval_ms)?.candles);
+            }
+            Ok(finish(symbol, interval_ms, candles))
+        }
+    }
+}
+
+// Page through the public klines endpoint for candles opened in [from, to)
+pub async fn download_klines(
+    symbol: &str,
+    interval: Interval,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<PriceHistory, Error> {
+    let interval_ms = interval.millis();
+    let client = BinanceHttpClient::default();
+    let end = to.timestamp_millis();
+    let mut start = from.timestamp_millis();
+    let mut candles = Vec::new();
+
+    while start < end {
+        let request = market::klines(symbol, interval.kline_interval())
+            .start_time(start as u64)
+            .end_time((end - 1) as u64)
+            .limit(KLINES_PAGE_LIMIT);
+        let body = client
+            .send(request)
+            .await
+            .map_err(|e| Error::RequestError(format!("{:?}", e)))?
+            .into_body_str()
+            .await
+            .map_err(|e| Error::HttpError(format!("{:?}", e)))?;
+        let page = dto::parse_klines(&body)?;
+        let last_open = match page.last() {
+            Some(last) => last.open_time.timestamp_millis(),
+            None => break,
+        };
+        log::debug!("Downloaded {} {} candles", page.len(), symbol);
+        candles.extend(page);
+        start = last_open + interval_ms;
+    }
+    Ok(finish(symbol, interval_ms, candles))
+}
+
+// Candles from the local store, downloading and saving only the spans it lacks.
+// Candles that haven't closed yet are never stored.
+pub async fn cached_klines(
+    store: &CandleStore,
+    symbol: &str,
+    interval: Interval,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<PriceHistory, Error> {
+    let interval_ms = interval.millis();
+    for (start, end) in store.missing_ranges(symbol, interval_ms, from, to)? {
+        let mut fetched = download_klines(symbol, interval, start, end).await?;
+        let now = Utc::now();
+        fetched.candles.retain(|c| c.close_time < now);
+        log::info!(
+            "Backfilled {} {} {} candles from {} to {}",
+            fetched.len(),
+            symbol,
+            interval,
+            start,
+            end
+        );
+        store.upsert(&fetched)?;
+    }
+    store.range(symbol, interval_ms, from, to)
+}
@@ -0,0 +1,117 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use chrono::DateTime;
+use serde::Serialize;
+
+use super::{BacktestReport, BacktestResult};
+use crate::dto::Error;
+use crate::executor::Trade;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EquityPoint {
+    pub time: i64,
+    pub equity: f64,
+    // Distance below the running peak, in percent
+    pub drawdown_pct: f64,
+}
+
+#[derive(Serialize)]
+struct BacktestExport<'a> {
+    report: BacktestReport,
+    equity_curve: Vec<EquityPoint>,
+    trades: &'a [Trade],
+}
+
+pub fn equity_points(result: &BacktestResult) -> Vec<EquityPoint> {
+    let mut peak = f64::MIN;
+    result
+        .equity_curve
+        .iter()
+        .map(|(time, equity)| {
+            peak = peak.max(*equity);
+            let drawdown_pct = if peak > 0.0 {
+                (peak - equity) / peak * 100.0
+            } else {
+                0.0
+            };
+            EquityPoint {
+                time: *time,
+                equity: *equity,
+                drawdown_pct,
+            }
+        })
+        .collect()
+}
+
+pub fn format_time(time: i64) -> String {
+    DateTime::from_timestamp(time, 0)
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_else(|| time.to_string())
+}
+
+pub fn write_equity_csv<P: AsRef<Path>>(path: P, result: &BacktestResult) -> Result<(), Error> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "time,equity,drawdown_pct")?;
+    for point in equity_points(result) {
+        writeln!(
+            out,
+            "{},{:.8},{:.4}",
+            format_time(point.time),
+            point.equity,
+            point.drawdown_pct
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+pub fn write_trades_csv<P: AsRef<Path>>(path: P, trades: &[Trade]) -> Result<(), Error> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(
+        out,
+        "symbol,strategy,side,quantity,entry_price,exit_price,fees,pnl,opened_at,closed_at"
+    )?;
+    for trade in trades {
+        writeln!(
+            out,
+            "{},{},{:?},{:.8},{:.8},{:.8},{:.8},{:.8},{},{}",
+            trade.symbol,
+            trade.strategy,
+            trade.side,
+            trade.quantity,
+            trade.entry_price,
+            trade.exit_price,
+            trade.fees,
+            trade.pnl,
+            format_time(trade.opened_at),
+            format_time(trade.closed_at)
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// Report, equity/drawdown series and trades in one document
+pub fn write_json<P: AsRef<Path>>(path: P, result: &BacktestResult) -> Result<(), Error> {
+    let export = BacktestExport {
+        report: result.report(),
+        equity_curve: equity_points(result),
+        trades: &result.trades,
+    };
+    let out = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(out, &export)?;
+    Ok(())
+}
+
+// Writes equity.csv, trades.csv, backtest.json and report.html into `dir`
+pub fn export_all<P: AsRef<Path>>(dir: P, result: &BacktestResult) -> Result<(), Error> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    write_equity_csv(dir.join("equity.csv"), result)?;
+    write_trades_csv(dir.join("trades.csv"), &result.trades)?;
+    write_json(dir.join("backtest.json"), result)?;
+    super::html::write_html(dir.join("report.html"), result)?;
+    Ok(())
+}
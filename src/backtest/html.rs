@@ -0,0 +1,194 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde_json::json;
+
+use super::export::{equity_points, format_time};
+use super::BacktestResult;
+use crate::dto::Error;
+
+const STYLE: &str = r##"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; margin: 2em; color: #222; }
+h1 { font-size: 1.4em; }
+h2 { font-size: 1.1em; margin-top: 2em; }
+table { border-collapse: collapse; font-size: 0.9em; }
+th, td { padding: 4px 10px; border-bottom: 1px solid #ddd; text-align: right; }
+th:first-child, td:first-child { text-align: left; }
+.win { color: #1a7f37; }
+.loss { color: #cf222e; }
+canvas { width: 100%; height: 360px; border: 1px solid #ddd; }
+"##;
+
+// Draws the equity curve with entry (green) and exit (red) markers onto #equity
+const CHART_SCRIPT: &str = r##"
+(function () {
+  var canvas = document.getElementById("equity");
+  var ctx = canvas.getContext("2d");
+  var ratio = window.devicePixelRatio || 1;
+  canvas.width = canvas.clientWidth * ratio;
+  canvas.height = canvas.clientHeight * ratio;
+  ctx.scale(ratio, ratio);
+  var w = canvas.clientWidth, h = canvas.clientHeight, pad = 50;
+  var points = DATA.equity;
+  if (points.length < 2) { return; }
+  var t0 = points[0][0], t1 = points[points.length - 1][0];
+  var lo = Infinity, hi = -Infinity;
+  points.forEach(function (p) { lo = Math.min(lo, p[1]); hi = Math.max(hi, p[1]); });
+  if (hi === lo) { hi = lo + 1; }
+  function x(t) { return pad + (t - t0) / Math.max(t1 - t0, 1) * (w - 2 * pad); }
+  function y(v) { return h - pad + (lo - v) / (hi - lo) * (h - 2 * pad); }
+  function equityAt(t) {
+    var best = points[0];
+    for (var i = 0; i < points.length && points[i][0] <= t; i++) { best = points[i]; }
+    return best[1];
+  }
+
+  ctx.strokeStyle = "#999";
+  ctx.fillStyle = "#555";
+  ctx.font = "11px sans-serif";
+  [lo, (lo + hi) / 2, hi].forEach(function (v) {
+    ctx.fillText(v.toFixed(2), 2, y(v) + 4);
+  });
+  [t0, t1].forEach(function (t, i) {
+    var label = new Date(t * 1000).toISOString().slice(0, 10);
+    ctx.fillText(label, i === 0 ? pad : w - pad - 60, h - pad + 16);
+  });
+
+  ctx.strokeStyle = "#0969da";
+  ctx.lineWidth = 1.5;
+  ctx.beginPath();
+  points.forEach(function (p, i) {
+    if (i === 0) { ctx.moveTo(x(p[0]), y(p[1])); } else { ctx.lineTo(x(p[0]), y(p[1])); }
+  });
+  ctx.stroke();
+
+  DATA.trades.forEach(function (trade) {
+    [[trade.opened_at, "#1a7f37"], [trade.closed_at, "#cf222e"]].forEach(function (m) {
+      ctx.fillStyle = m[1];
+      ctx.beginPath();
+      ctx.arc(x(m[0]), y(equityAt(m[0])), 3, 0, 2 * Math.PI);
+      ctx.fill();
+    });
+  });
+})();
+"##;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Standalone HTML page with the report statistics, an equity chart and the trades
+pub fn render_html(result: &BacktestResult) -> String {
+    let report = result.report();
+    let stats = [
+        ("Initial capital", format!("{:.2}", report.initial_capital)),
+        ("Final equity", format!("{:.2}", report.final_equity)),
+        ("Total return", format!("{:.2}%", report.total_return_pct)),
+        (
+            "Annualized return",
+            format!("{:.2}%", report.annualized_return_pct),
+        ),
+        ("Sharpe ratio", format!("{:.2}", report.sharpe_ratio)),
+        ("Sortino ratio", format!("{:.2}", report.sortino_ratio)),
+        ("Calmar ratio", format!("{:.2}", report.calmar_ratio)),
+        ("Max drawdown", format!("{:.2}%", report.max_drawdown_pct)),
+        ("Profit factor", format!("{:.2}", report.profit_factor)),
+        ("Win rate", format!("{:.1}%", report.win_rate_pct)),
+        ("Trades", report.total_trades.to_string()),
+        ("Fees", format!("{:.2}", report.total_fees)),
+        (
+            "Avg trade duration",
+            format!("{:.1}h", report.avg_trade_duration_secs / 3600.0),
+        ),
+        ("Exposure", format!("{:.1}%", report.exposure_pct)),
+        (
+            "Buy and hold",
+            format!("{:.2}%", report.buy_and_hold_return_pct),
+        ),
+        (
+            "Benchmark",
+            format!(
+                "{} {:.2}%",
+                escape(&report.benchmark),
+                report.benchmark_return_pct
+            ),
+        ),
+        ("Alpha", format!("{:.2}%", report.alpha_pct)),
+        ("Beta", format!("{:.2}", report.beta)),
+        (
+            "Seed",
+            report
+                .seed
+                .map(|seed| seed.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+        ),
+    ];
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!(
+        "<title>Backtest {} {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(&report.symbol),
+        escape(&report.strategy),
+        STYLE
+    ));
+    html.push_str(&format!(
+        "<h1>{} &middot; {}</h1>\n",
+        escape(&report.symbol),
+        escape(&report.strategy)
+    ));
+
+    html.push_str("<table>\n");
+    for (label, value) in &stats {
+        html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", label, value));
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Equity</h2>\n<canvas id=\"equity\"></canvas>\n");
+
+    html.push_str("<h2>Trades</h2>\n<table>\n<tr><th>Opened</th><th>Closed</th><th>Quantity</th><th>Entry</th><th>Exit</th><th>Fees</th><th>PnL</th></tr>\n");
+    for trade in &result.trades {
+        html.push_str(&format!(
+            "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{:.6}</td><td>{:.4}</td><td>{:.4}</td><td>{:.4}</td><td>{:.4}</td></tr>\n",
+            if trade.pnl >= 0.0 { "win" } else { "loss" },
+            format_time(trade.opened_at),
+            format_time(trade.closed_at),
+            trade.quantity,
+            trade.entry_price,
+            trade.exit_price,
+            trade.fees,
+            trade.pnl
+        ));
+    }
+    html.push_str("</table>\n");
+
+    let data = json!({
+        "equity": equity_points(result)
+            .iter()
+            .map(|p| (p.time, p.equity))
+            .collect::<Vec<_>>(),
+        "trades": result
+            .trades
+            .iter()
+            .map(|t| json!({ "opened_at": t.opened_at, "closed_at": t.closed_at }))
+            .collect::<Vec<_>>(),
+    });
+    // Keep the payload from closing the script element
+    let data = data.to_string().replace("</", "<\\/");
+    html.push_str(&format!(
+        "<script>\nvar DATA = {};\n{}</script>\n</body>\n</html>\n",
+        data, CHART_SCRIPT
+    ));
+    html
+}
+
+pub fn write_html<P: AsRef<Path>>(path: P, result: &BacktestResult) -> Result<(), Error> {
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(render_html(result).as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
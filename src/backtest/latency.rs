@@ -0,0 +1,50 @@
+use rayon::prelude::*;
+
+use super::{BacktestReport, Backtester, PriceHistory};
+use crate::domain::TradeAction;
+use crate::dto::KlineResponse;
+use crate::strategy::Strategy;
+
+/// Market order waiting out the simulated signal-to-fill delay
+#[derive(Debug, Clone)]
+pub struct PendingOrder {
+    pub action: TradeAction,
+    pub strategy: String,
+    pub quantity: f64,
+    pub execute_at_ms: i64,
+}
+
+// Price assumed to trade at `time_ms` within `candle`, moving linearly from the
+// open to the close
+pub fn price_at(candle: &KlineResponse, time_ms: i64) -> f64 {
+    let open = candle.open_time.timestamp_millis();
+    let close = candle.close_time.timestamp_millis();
+    if close <= open || time_ms <= open {
+        return candle.open_price;
+    }
+    let progress = ((time_ms - open) as f64 / (close - open) as f64).min(1.0);
+    candle.open_price + (candle.close_price - candle.open_price) * progress
+}
+
+/// Re-runs the backtest once per latency (milliseconds) to show how much of the
+/// result depends on filling right at the signal candle's close
+pub fn latency_sensitivity<F>(
+    backtester: &Backtester,
+    history: &PriceHistory,
+    make_strategy: F,
+    latencies_ms: &[i64],
+) -> Vec<(i64, BacktestReport)>
+where
+    F: Fn() -> Box<dyn Strategy> + Sync,
+{
+    latencies_ms
+        .par_iter()
+        .map(|latency_ms| {
+            let mut backtester = backtester.clone();
+            backtester.latency_ms = *latency_ms;
+            let mut strategy = make_strategy();
+            let report = backtester.run(strategy.as_mut(), history).report();
+            (*latency_ms, report)
+        })
+        .collect()
+}
@@ -0,0 +1,205 @@
+use serde::Serialize;
+
+use super::{benchmark, BacktestResult};
+
+const SECONDS_PER_YEAR: f64 = 365.0 * 24.0 * 60.0 * 60.0;
+
+/// Summary statistics of a backtest run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BacktestReport {
+    pub symbol: String,
+    pub strategy: String,
+    pub initial_capital: f64,
+    pub final_equity: f64,
+    pub total_return_pct: f64,
+    pub annualized_return_pct: f64,
+    // Annualized, risk-free rate of zero
+    pub sharpe_ratio: f64,
+    pub sortino_ratio: f64,
+    pub calmar_ratio: f64,
+    pub max_drawdown_pct: f64,
+    pub profit_factor: f64,
+    pub win_rate_pct: f64,
+    pub total_trades: usize,
+    pub total_fees: f64,
+    pub avg_trade_duration_secs: f64,
+    // Share of bars with an open position
+    pub exposure_pct: f64,
+    pub buy_and_hold_return_pct: f64,
+    // Benchmark symbol, or "buy_and_hold" when none was configured
+    pub benchmark: String,
+    pub benchmark_return_pct: f64,
+    // Annualized, relative to the benchmark
+    pub alpha_pct: f64,
+    pub beta: f64,
+    // Seed of the randomized search that produced this run, if any
+    pub seed: Option<u64>,
+}
+
+impl BacktestReport {
+    pub fn from_result(result: &BacktestResult) -> Self {
+        let equity: Vec<f64> = result.equity_curve.iter().map(|(_, e)| *e).collect();
+        let returns = period_returns(&equity);
+        let periods_per_year = periods_per_year(&result.equity_curve);
+
+        let total_return = if result.initial_capital > 0.0 {
+            result.final_equity / result.initial_capital - 1.0
+        } else {
+            0.0
+        };
+        let years = match (result.equity_curve.first(), result.equity_curve.last()) {
+            (Some((start, _)), Some((end, _))) => (end - start) as f64 / SECONDS_PER_YEAR,
+            _ => 0.0,
+        };
+        let annualized_return = if years > 0.0 && total_return > -1.0 {
+            (1.0 + total_return).powf(1.0 / years) - 1.0
+        } else {
+            total_return
+        };
+        let max_drawdown = max_drawdown_pct(&equity);
+
+        let (benchmark_name, benchmark_series) = match &result.benchmark {
+            Some((symbol, series)) => (symbol.clone(), series.as_slice()),
+            None => ("buy_and_hold".to_string(), result.buy_and_hold.as_slice()),
+        };
+        let benchmark_values: Vec<f64> = benchmark_series.iter().map(|(_, v)| *v).collect();
+        let (alpha_pct, beta) = benchmark::alpha_beta(&equity, &benchmark_values, periods_per_year);
+
+        let wins = result.trades.iter().filter(|t| t.pnl > 0.0).count();
+        let gross_profit: f64 = result
+            .trades
+            .iter()
+            .filter(|t| t.pnl > 0.0)
+            .map(|t| t.pnl)
+            .sum();
+        let gross_loss: f64 = result
+            .trades
+            .iter()
+            .filter(|t| t.pnl < 0.0)
+            .map(|t| -t.pnl)
+            .sum();
+        let total_trades = result.trades.len();
+        let total_duration: i64 = result
+            .trades
+            .iter()
+            .map(|t| t.closed_at - t.opened_at)
+            .sum();
+
+        BacktestReport {
+            symbol: result.symbol.clone(),
+            strategy: result.strategy.clone(),
+            initial_capital: result.initial_capital,
+            final_equity: result.final_equity,
+            total_return_pct: total_return * 100.0,
+            annualized_return_pct: annualized_return * 100.0,
+            sharpe_ratio: sharpe_ratio(&returns, periods_per_year),
+            sortino_ratio: sortino_ratio(&returns, periods_per_year),
+            calmar_ratio: if max_drawdown > 0.0 {
+                annualized_return * 100.0 / max_drawdown
+            } else {
+                0.0
+            },
+            max_drawdown_pct: max_drawdown,
+            profit_factor: if gross_loss > 0.0 {
+                gross_profit / gross_loss
+            } else if gross_profit > 0.0 {
+                f64::INFINITY
+            } else {
+                0.0
+            },
+            win_rate_pct: if total_trades > 0 {
+                wins as f64 / total_trades as f64 * 100.0
+            } else {
+                0.0
+            },
+            total_trades,
+            total_fees: result.trades.iter().map(|t| t.fees).sum(),
+            avg_trade_duration_secs: if total_trades > 0 {
+                total_duration as f64 / total_trades as f64
+            } else {
+                0.0
+            },
+            exposure_pct: if result.equity_curve.is_empty() {
+                0.0
+            } else {
+                result.bars_in_market as f64 / result.equity_curve.len() as f64 * 100.0
+            },
+            buy_and_hold_return_pct: benchmark::total_return_pct(&result.buy_and_hold),
+            benchmark: benchmark_name,
+            benchmark_return_pct: benchmark::total_return_pct(benchmark_series),
+            alpha_pct,
+            beta,
+            seed: None,
+        }
+    }
+}
+
+// Simple returns between consecutive equity points
+pub fn period_returns(equity: &[f64]) -> Vec<f64> {
+    equity
+        .windows(2)
+        .filter(|pair| pair[0] > 0.0)
+        .map(|pair| pair[1] / pair[0] - 1.0)
+        .collect()
+}
+
+// Bars per year, from the typical spacing of the curve's timestamps (seconds)
+fn periods_per_year(curve: &[(i64, f64)]) -> f64 {
+    let mut spacing: Vec<i64> = curve
+        .windows(2)
+        .map(|pair| pair[1].0 - pair[0].0)
+        .filter(|diff| *diff > 0)
+        .collect();
+    if spacing.is_empty() {
+        return 0.0;
+    }
+    spacing.sort_unstable();
+    SECONDS_PER_YEAR / spacing[spacing.len() / 2] as f64
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+pub fn sharpe_ratio(returns: &[f64], periods_per_year: f64) -> f64 {
+    if returns.len() < 2 {
+        return 0.0;
+    }
+    let avg = mean(returns);
+    let variance =
+        returns.iter().map(|r| (r - avg).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+    let std_dev = variance.sqrt();
+    if std_dev == 0.0 {
+        return 0.0;
+    }
+    avg / std_dev * periods_per_year.sqrt()
+}
+
+pub fn sortino_ratio(returns: &[f64], periods_per_year: f64) -> f64 {
+    if returns.is_empty() {
+        return 0.0;
+    }
+    let avg = mean(returns);
+    let downside = returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / returns.len() as f64;
+    let downside_dev = downside.sqrt();
+    if downside_dev == 0.0 {
+        return 0.0;
+    }
+    avg / downside_dev * periods_per_year.sqrt()
+}
+
+// Largest peak-to-trough decline, in percent
+pub fn max_drawdown_pct(equity: &[f64]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut max_drawdown = 0.0;
+    for value in equity {
+        peak = peak.max(*value);
+        if peak > 0.0 {
+            max_drawdown = f64::max(max_drawdown, (peak - value) / peak * 100.0);
+        }
+    }
+    max_drawdown
+}
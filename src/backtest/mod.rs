@@ -0,0 +1,390 @@
+pub mod benchmark;
+pub mod broker;
+pub mod data;
+pub mod export;
+pub mod html;
+pub mod latency;
+pub mod metrics;
+pub mod optimizer;
+pub mod portfolio;
+pub mod replay;
+pub mod split;
+pub mod walk_forward;
+
+pub use broker::{FeeSchedule, FillModel, LimitFillPolicy, SimulatedBroker, SlippageModel};
+pub use data::PriceHistory;
+pub use latency::PendingOrder;
+pub use metrics::BacktestReport;
+
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+
+use crate::domain::*;
+use crate::dto::KlineResponse;
+use crate::engine::SignalEngine;
+use crate::executor::ExecutionSettings;
+use crate::executor::Trade;
+use crate::strategy::Strategy;
+
+#[derive(Debug, Clone)]
+pub struct BacktestResult {
+    pub symbol: String,
+    pub strategy: String,
+    pub trades: Vec<Trade>,
+    // (close time in seconds, equity) after every candle
+    pub equity_curve: Vec<(i64, f64)>,
+    pub initial_capital: f64,
+    pub final_equity: f64,
+    // Candles that closed with a position open
+    pub bars_in_market: usize,
+    // Value of holding the traded symbol(s) over the same times, starting at 1
+    pub buy_and_hold: Vec<(i64, f64)>,
+    // Configured benchmark's closes at the same times, if any
+    pub benchmark: Option<(String, Vec<(i64, f64)>)>,
+}
+
+impl BacktestResult {
+    pub fn report(&self) -> BacktestReport {
+        BacktestReport::from_result(self)
+    }
+}
+
+/// Replays a `PriceHistory` through a strategy, filling at candle closes
+#[derive(Debug, Clone)]
+pub struct Backtester {
+    pub initial_capital: f64,
+    pub risk: RiskParameters,
+    pub fill_model: FillModel,
+    // Same entry settings as the live executor; limit entries rest on the
+    // simulated book
+    pub execution: ExecutionSettings,
+    // Delay between a signal at candle close and its market order reaching the
+    // book; 0 fills at the close itself
+    pub latency_ms: i64,
+    // Compared against in the report; buy-and-hold of the traded symbol otherwise
+    pub benchmark: Option<PriceHistory>,
+    // Lower-timeframe candles per symbol, used to tell which bracket leg a bar hit
+    // first instead of assuming the stop
+    pub magnifier: HashMap<String, PriceHistory>,
+}
+
+impl Backtester {
+    pub fn new(initial_capital: f64, risk: RiskParameters) -> Self {
+        Backtester {
+            initial_capital,
+            risk,
+            fill_model: FillModel::default(),
+            execution: ExecutionSettings::default(),
+            latency_ms: 0,
+            benchmark: None,
+            magnifier: HashMap::new(),
+        }
+    }
+
+    pub fn run(&self, strategy: &mut dyn Strategy, history: &PriceHistory) -> BacktestResult {
+        self.run_from(strategy, history, 0)
+    }
+
+    // Candles before `start` only warm the strategy up; trading and the equity
+    // curve begin at `start`
+    pub fn run_from(
+        &self,
+        strategy: &mut dyn Strategy,
+        history: &PriceHistory,
+        start: usize,
+    ) -> BacktestResult {
+        let symbol = history.symbol.as_str();
+        let strategy_name = strategy.name().to_string();
+        let mut engine = SignalEngine::new(symbol, strategy);
+        let mut broker = SimulatedBroker::new(self.initial_capital, self.fill_model.clone());
+        let mut trades = Vec::new();
+        let mut equity_curve = Vec::with_capacity(history.len());
+        let mut bars_in_market = 0;
+        let mut pending: Vec<PendingOrder> = Vec::new();
+
+        for (i, candle) in history.candles.iter().enumerate() {
+            let time = candle.close_time.timestamp();
+            let signal = engine.on_close(time, candle.close_price);
+            if i < start {
+                continue;
+            }
+
+            self.process_candle(&mut broker, &signal, candle, &mut pending, &mut trades);
+
+            if broker.position(symbol).is_some() {
+                bars_in_market += 1;
+            }
+            let equity = broker.cash() + broker.position_value(symbol, candle.close_price);
+            equity_curve.push((time, equity));
+        }
+
+        let final_equity = equity_curve
+            .last()
+            .map(|(_, equity)| *equity)
+            .unwrap_or(self.initial_capital);
+        let times: Vec<i64> = equity_curve.iter().map(|(time, _)| *time).collect();
+        BacktestResult {
+            symbol: history.symbol.clone(),
+            strategy: strategy_name,
+            trades,
+            equity_curve,
+            initial_capital: self.initial_capital,
+            final_equity,
+            bars_in_market,
+            buy_and_hold: benchmark::buy_and_hold(std::slice::from_ref(history), &times),
+            benchmark: self.benchmark_series(&times),
+        }
+    }
+
+    pub(crate) fn benchmark_series(&self, times: &[i64]) -> Option<(String, Vec<(i64, f64)>)> {
+        self.benchmark.as_ref().map(|history| {
+            (
+                history.symbol.clone(),
+                benchmark::aligned_closes(history, times),
+            )
+        })
+    }
+
+    // One closed candle for `signal.symbol`: bracket exits, resting and delayed
+    // orders, then the new signal
+    pub(crate) fn process_candle(
+        &self,
+        broker: &mut SimulatedBroker,
+        signal: &TradingSignal,
+        candle: &KlineResponse,
+        pending: &mut Vec<PendingOrder>,
+        trades: &mut Vec<Trade>,
+    ) {
+        let symbol = signal.symbol.as_str();
+        let time = signal.timestamp;
+        let exit = match self.magnified(symbol, candle) {
+            // Walk the bar's sub-candles in order; the first leg reached wins
+            Some(sub_candles) => sub_candles
+                .iter()
+                .find_map(|sub| broker.check_bracket(symbol, sub, sub.close_time.timestamp())),
+            None => broker.check_bracket(symbol, candle, time),
+        };
+        if let Some(trade) = exit {
+            trades.push(trade);
+        }
+        broker.process_limit_order(symbol, candle, &self.risk, time);
+
+        // Market orders whose delay ran out during this candle
+        let close_ms = candle.close_time.timestamp_millis();
+        while pending.first().is_some_and(|o| o.execute_at_ms <= close_ms) {
+            let order = pending.remove(0);
+            let price = latency::price_at(candle, order.execute_at_ms);
+            match order.action {
+                TradeAction::Buy if self.has_capacity(broker) => {
+                    broker.market_buy(
+                        symbol,
+                        &order.strategy,
+                        order.quantity,
+                        price,
+                        candle,
+                        &self.risk,
+                        time,
+                    );
+                }
+                TradeAction::Sell => {
+                    if let Some(trade) = broker.market_sell(symbol, price, candle, time) {
+                        trades.push(trade);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        match signal.action {
+            TradeAction::Buy if self.has_capacity(broker) => {
+                let quantity = from_decimal(self.risk.order_quantity(signal.price));
+                match self.execution.limit_entry_offset_pct {
+                    Some(offset) => {
+                        broker.place_limit_buy(
+                            symbol,
+                            &signal.strategy,
+                            quantity,
+                            from_decimal(
+                                signal.price * (Decimal::ONE - offset / Decimal::ONE_HUNDRED),
+                            ),
+                            candle,
+                            self.execution.order_ttl_secs,
+                            time,
+                        );
+                    }
+                    None if self.latency_ms > 0 => pending.push(PendingOrder {
+                        action: TradeAction::Buy,
+                        strategy: signal.strategy.clone(),
+                        quantity,
+                        execute_at_ms: close_ms + self.latency_ms,
+                    }),
+                    None => {
+                        broker.market_buy(
+                            symbol,
+                            &signal.strategy,
+                            quantity,
+                            candle.close_price,
+                            candle,
+                            &self.risk,
+                            time,
+                        );
+                    }
+                }
+            }
+            TradeAction::Sell => {
+                broker.cancel_limit_order(symbol);
+                if self.latency_ms > 0 {
+                    pending.push(PendingOrder {
+                        action: TradeAction::Sell,
+                        strategy: signal.strategy.clone(),
+                        quantity: 0.0,
+                        execute_at_ms: close_ms + self.latency_ms,
+                    });
+                } else if let Some(trade) =
+                    broker.market_sell(symbol, candle.close_price, candle, time)
+                {
+                    trades.push(trade);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn magnified(&self, symbol: &str, candle: &KlineResponse) -> Option<&[KlineResponse]> {
+        let sub_candles = self
+            .magnifier
+            .get(symbol)?
+            .candles_within(candle.open_time, candle.close_time);
+        if sub_candles.is_empty() {
+            None
+        } else {
+            Some(sub_candles)
+        }
+    }
+
+    // Whether the cross-symbol position limit allows another entry
+    fn has_capacity(&self, broker: &SimulatedBroker) -> bool {
+        broker.open_positions() < self.risk.max_open_positions
+    }
+}
+
+// Fixture series and a strategy with a known best parameter, shared by the
+// optimizer tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use chrono::{DateTime, Duration, Utc};
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    use super::PriceHistory;
+    use crate::domain::{RiskParameters, TradeAction, TradingError};
+    use crate::dto::KlineResponse;
+    use crate::strategy::{ParameterRange, ParameterValue, Strategy, StrategyParameter};
+
+    pub const INTERVAL_MS: i64 = 60_000;
+
+    // One-minute candles from the epoch, flat within each bar
+    pub fn history(closes: &[f64]) -> PriceHistory {
+        let start = DateTime::<Utc>::UNIX_EPOCH;
+        let candles = closes
+            .iter()
+            .enumerate()
+            .map(|(i, close)| {
+                let open_time = start + Duration::milliseconds(i as i64 * INTERVAL_MS);
+                KlineResponse {
+                    open_time,
+                    open_price: *close,
+                    high_price: *close,
+                    low_price: *close,
+                    close_price: *close,
+                    volume: 1_000.0,
+                    close_time: open_time + Duration::milliseconds(INTERVAL_MS - 1),
+                    quote_asset_volume: close * 1_000.0,
+                    number_of_trades: 1,
+                    taker_buy_base_volume: 500.0,
+                    taker_buy_quote_volume: close * 500.0,
+                }
+            })
+            .collect();
+        PriceHistory {
+            symbol: "BTCUSDT".to_string(),
+            interval_ms: INTERVAL_MS,
+            candles,
+        }
+    }
+
+    // Up from 100 to a peak of 105 at the sixth candle, then back down
+    pub fn peak() -> PriceHistory {
+        history(&[
+            100.0, 101.0, 102.0, 103.0, 104.0, 105.0, 104.0, 103.0, 102.0, 101.0, 100.0,
+        ])
+    }
+
+    // Brackets too wide to be reached by the fixtures
+    pub fn risk() -> RiskParameters {
+        RiskParameters {
+            max_position_size: Decimal::ONE_HUNDRED,
+            stop_loss_pct: dec!(50),
+            take_profit_pct: dec!(50),
+            max_open_positions: 1,
+        }
+    }
+
+    /// Buys on the first candle it sees and sells `bars` candles later, so on
+    /// `peak` the best `bars` is 5
+    pub struct HoldFor {
+        pub bars: i64,
+    }
+
+    const BARS: ParameterRange = ParameterRange::Int {
+        min: 1,
+        max: 6,
+        step: 1,
+    };
+
+    impl Strategy for HoldFor {
+        fn name(&self) -> &str {
+            "hold_for"
+        }
+
+        fn analyze(&mut self, closes: &[f64]) -> TradeAction {
+            if closes.len() == 1 {
+                TradeAction::Buy
+            } else if closes.len() as i64 == self.bars + 1 {
+                TradeAction::Sell
+            } else {
+                TradeAction::Hold
+            }
+        }
+
+        fn parameters(&self) -> Vec<StrategyParameter> {
+            vec![StrategyParameter {
+                name: "bars".to_string(),
+                value: ParameterValue::Int(self.bars),
+                range: BARS,
+            }]
+        }
+
+        fn update_parameter(
+            &mut self,
+            name: &str,
+            value: ParameterValue,
+        ) -> Result<(), TradingError> {
+            match (name, value) {
+                ("bars", ParameterValue::Int(bars)) if BARS.contains(&value) => {
+                    self.bars = bars;
+                    Ok(())
+                }
+                _ => Err(TradingError::InvalidParameter(format!(
+                    "{} = {:?}",
+                    name, value
+                ))),
+            }
+        }
+    }
+
+    pub fn hold_for() -> Box<dyn Strategy> {
+        Box::new(HoldFor { bars: 1 })
+    }
+}
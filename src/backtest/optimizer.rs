@@ -0,0 +1,508 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use super::{BacktestReport, Backtester, PriceHistory};
+use crate::domain::TradingError;
+use crate::strategy::{ParameterValue, Strategy, StrategyParameter};
+
+// Refuse sweeps that would take forever; narrow the ranges instead
+const MAX_COMBINATIONS: usize = 250_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Objective {
+    SharpeRatio,
+    SortinoRatio,
+    CalmarRatio,
+    TotalReturn,
+    ProfitFactor,
+}
+
+impl Objective {
+    // Higher is better
+    pub fn score(&self, report: &BacktestReport) -> f64 {
+        let score = match self {
+            Objective::SharpeRatio => report.sharpe_ratio,
+            Objective::SortinoRatio => report.sortino_ratio,
+            Objective::CalmarRatio => report.calmar_ratio,
+            Objective::TotalReturn => report.total_return_pct,
+            Objective::ProfitFactor => report.profit_factor,
+        };
+        if score.is_nan() {
+            f64::MIN
+        } else {
+            score
+        }
+    }
+}
+
+impl FromStr for Objective {
+    type Err = TradingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sharpe" => Ok(Objective::SharpeRatio),
+            "sortino" => Ok(Objective::SortinoRatio),
+            "calmar" => Ok(Objective::CalmarRatio),
+            "return" => Ok(Objective::TotalReturn),
+            "profit-factor" => Ok(Objective::ProfitFactor),
+            _ => Err(TradingError::InvalidParameter(format!(
+                "Unknown objective {:?}",
+                s
+            ))),
+        }
+    }
+}
+
+pub type ParameterSet = Vec<(String, ParameterValue)>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OptimizationResult {
+    pub parameters: ParameterSet,
+    pub score: f64,
+    pub report: BacktestReport,
+}
+
+// Build a strategy from the factory and apply `parameters` to it
+pub fn configure<F>(
+    make_strategy: &F,
+    parameters: &ParameterSet,
+) -> Result<Box<dyn Strategy>, TradingError>
+where
+    F: Fn() -> Box<dyn Strategy>,
+{
+    let mut strategy = make_strategy();
+    for (name, value) in parameters {
+        strategy.update_parameter(name, *value)?;
+    }
+    Ok(strategy)
+}
+
+// Backtest one parameter set; `None` if the strategy rejected it
+pub fn evaluate<F>(
+    backtester: &Backtester,
+    history: &PriceHistory,
+    make_strategy: &F,
+    parameters: &ParameterSet,
+    objective: Objective,
+) -> Option<OptimizationResult>
+where
+    F: Fn() -> Box<dyn Strategy>,
+{
+    let mut strategy = match configure(make_strategy, parameters) {
+        Ok(strategy) => strategy,
+        Err(e) => {
+            log::debug!("Skipping {:?}: {}", parameters, e);
+            return None;
+        }
+    };
+    let report = backtester.run(strategy.as_mut(), history).report();
+    Some(OptimizationResult {
+        parameters: parameters.clone(),
+        score: objective.score(&report),
+        report,
+    })
+}
+
+// Cartesian product of every declared parameter range
+fn parameter_grid(strategy: &dyn Strategy) -> Vec<ParameterSet> {
+    let mut grid: Vec<ParameterSet> = vec![Vec::new()];
+    for parameter in strategy.parameters() {
+        let values = parameter.range.values();
+        let name = &parameter.name;
+        grid = grid
+            .iter()
+            .flat_map(|set| {
+                values.iter().map(move |value| {
+                    let mut set = set.clone();
+                    set.push((name.clone(), *value));
+                    set
+                })
+            })
+            .collect();
+    }
+    grid
+}
+
+/// Backtests every combination of the strategy's parameter ranges in parallel and
+/// returns them best first
+pub fn grid_search<F>(
+    backtester: &Backtester,
+    history: &PriceHistory,
+    make_strategy: F,
+    objective: Objective,
+) -> Result<Vec<OptimizationResult>, TradingError>
+where
+    F: Fn() -> Box<dyn Strategy> + Sync,
+{
+    let template = make_strategy();
+    let combinations: usize = template
+        .parameters()
+        .iter()
+        .map(|p| p.range.values().len().max(1))
+        .product();
+    if combinations > MAX_COMBINATIONS {
+        return Err(TradingError::InvalidParameter(format!(
+            "{} parameter combinations exceed the limit of {}",
+            combinations, MAX_COMBINATIONS
+        )));
+    }
+    let grid = parameter_grid(template.as_ref());
+    log::info!(
+        "Grid search over {} combinations for {}",
+        grid.len(),
+        template.name()
+    );
+
+    let mut results: Vec<OptimizationResult> = grid
+        .par_iter()
+        .filter_map(|parameters| {
+            evaluate(backtester, history, &make_strategy, parameters, objective)
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(results)
+}
+
+/// Settings of the evolutionary search
+#[derive(Debug, Clone)]
+pub struct EvolutionSettings {
+    pub population_size: usize,
+    pub max_generations: usize,
+    // Chance for each gene of a child to mutate
+    pub mutation_rate: f64,
+    // Best individuals copied unchanged into the next generation
+    pub elite_count: usize,
+    pub tournament_size: usize,
+    // Stop after this many generations without a better best score
+    pub patience: usize,
+    // Fixed seed to reproduce a run; a random one is drawn (and reported) otherwise
+    pub seed: Option<u64>,
+}
+
+impl Default for EvolutionSettings {
+    fn default() -> Self {
+        EvolutionSettings {
+            population_size: 40,
+            max_generations: 50,
+            mutation_rate: 0.2,
+            elite_count: 4,
+            tournament_size: 3,
+            patience: 8,
+            seed: None,
+        }
+    }
+}
+
+// An individual is one index into every parameter's grid of values
+type Genome = Vec<usize>;
+
+struct Gene {
+    name: String,
+    values: Vec<ParameterValue>,
+}
+
+impl Gene {
+    fn from_parameter(parameter: &StrategyParameter) -> Self {
+        let mut values = parameter.range.values();
+        if values.is_empty() {
+            values.push(parameter.value);
+        }
+        Gene {
+            name: parameter.name.clone(),
+            values,
+        }
+    }
+
+    // Grid point closest to the parameter's current value
+    fn index_of(&self, value: &ParameterValue) -> usize {
+        let target = value.as_f64();
+        self.values
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                (a.as_f64() - target)
+                    .abs()
+                    .total_cmp(&(b.as_f64() - target).abs())
+            })
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    fn mutate<R: Rng>(&self, index: usize, rng: &mut R) -> usize {
+        let last = self.values.len() - 1;
+        if rng.gen_bool(0.5) {
+            // Small step to a neighbouring value
+            if rng.gen_bool(0.5) {
+                index.saturating_sub(1)
+            } else {
+                (index + 1).min(last)
+            }
+        } else {
+            rng.gen_range(0..=last)
+        }
+    }
+}
+
+fn decode(genes: &[Gene], genome: &Genome) -> ParameterSet {
+    genes
+        .iter()
+        .zip(genome)
+        .map(|(gene, index)| (gene.name.clone(), gene.values[*index]))
+        .collect()
+}
+
+fn random_genome<R: Rng>(genes: &[Gene], rng: &mut R) -> Genome {
+    genes
+        .iter()
+        .map(|gene| rng.gen_range(0..gene.values.len()))
+        .collect()
+}
+
+// Uniform crossover followed by per-gene mutation
+fn breed<R: Rng>(
+    genes: &[Gene],
+    a: &Genome,
+    b: &Genome,
+    mutation_rate: f64,
+    rng: &mut R,
+) -> Genome {
+    genes
+        .iter()
+        .enumerate()
+        .map(|(i, gene)| {
+            let index = if rng.gen_bool(0.5) { a[i] } else { b[i] };
+            if rng.gen_bool(mutation_rate.clamp(0.0, 1.0)) {
+                gene.mutate(index, rng)
+            } else {
+                index
+            }
+        })
+        .collect()
+}
+
+// Best of `size` randomly drawn individuals; `ranked` is sorted best first
+fn tournament<'a, R: Rng>(ranked: &'a [(Genome, f64)], size: usize, rng: &mut R) -> &'a Genome {
+    (0..size.max(1))
+        .map(|_| rng.gen_range(0..ranked.len()))
+        .min()
+        .map(|i| &ranked[i].0)
+        .unwrap_or(&ranked[0].0)
+}
+
+/// Evolves parameter sets through selection, crossover and mutation. Cheaper than
+/// `grid_search` when the grid is large; returns every evaluated set, best first.
+pub fn evolve<F>(
+    backtester: &Backtester,
+    history: &PriceHistory,
+    make_strategy: F,
+    objective: Objective,
+    settings: &EvolutionSettings,
+) -> Result<Vec<OptimizationResult>, TradingError>
+where
+    F: Fn() -> Box<dyn Strategy> + Sync,
+{
+    let template = make_strategy();
+    let parameters = template.parameters();
+    if parameters.is_empty() {
+        return Err(TradingError::InvalidParameter(format!(
+            "{} has no tunable parameters",
+            template.name()
+        )));
+    }
+    if settings.population_size < 2 {
+        return Err(TradingError::InvalidParameter(
+            "Population size must be at least 2".to_string(),
+        ));
+    }
+    let genes: Vec<Gene> = parameters.iter().map(Gene::from_parameter).collect();
+    let seed = settings.seed.unwrap_or_else(rand::random);
+    log::info!("Evolving {} with seed {}", template.name(), seed);
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    // Seed with the strategy's current parameters plus random individuals
+    let mut population: Vec<Genome> = vec![genes
+        .iter()
+        .zip(&parameters)
+        .map(|(gene, parameter)| gene.index_of(&parameter.value))
+        .collect()];
+    while population.len() < settings.population_size {
+        population.push(random_genome(&genes, &mut rng));
+    }
+
+    // Scores by genome, so survivors and repeats are not backtested again. Ordered,
+    // so equal scores rank the same way on every run with the same seed.
+    let mut evaluated: BTreeMap<Genome, Option<OptimizationResult>> = BTreeMap::new();
+    let mut best_score = f64::MIN;
+    let mut stale_generations = 0;
+
+    for generation in 0..settings.max_generations {
+        let pending: Vec<Genome> = population
+            .iter()
+            .filter(|genome| !evaluated.contains_key(*genome))
+            .cloned()
+            .collect();
+        let results: Vec<(Genome, Option<OptimizationResult>)> = pending
+            .into_par_iter()
+            .map(|genome| {
+                let parameters = decode(&genes, &genome);
+                let result = evaluate(backtester, history, &make_strategy, &parameters, objective)
+                    .map(|mut result| {
+                        result.report.seed = Some(seed);
+                        result
+                    });
+                (genome, result)
+            })
+            .collect();
+        evaluated.extend(results);
+
+        // Rejected parameter sets drop out of selection
+        let mut ranked: Vec<(Genome, f64)> = population
+            .iter()
+            .filter_map(|genome| {
+                let score = evaluated.get(genome)?.as_ref()?.score;
+                Some((genome.clone(), score))
+            })
+            .collect();
+        if ranked.is_empty() {
+            population = (0..settings.population_size)
+                .map(|_| random_genome(&genes, &mut rng))
+                .collect();
+            continue;
+        }
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranked.dedup_by(|a, b| a.0 == b.0);
+
+        if ranked[0].1 > best_score {
+            best_score = ranked[0].1;
+            stale_generations = 0;
+        } else {
+            stale_generations += 1;
+        }
+        log::info!(
+            "Generation {}: best {:.4} ({} evaluated)",
+            generation,
+            best_score,
+            evaluated.len()
+        );
+        if stale_generations >= settings.patience {
+            log::info!(
+                "No improvement for {} generations, stopping",
+                stale_generations
+            );
+            break;
+        }
+
+        let mut next: Vec<Genome> = ranked
+            .iter()
+            .take(settings.elite_count)
+            .map(|(genome, _)| genome.clone())
+            .collect();
+        while next.len() < settings.population_size {
+            let a = tournament(&ranked, settings.tournament_size, &mut rng);
+            let b = tournament(&ranked, settings.tournament_size, &mut rng);
+            next.push(breed(&genes, a, b, settings.mutation_rate, &mut rng));
+        }
+        population = next;
+    }
+
+    let mut results: Vec<OptimizationResult> = evaluated.into_values().flatten().collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::testing::{self, hold_for};
+    use crate::backtest::FillModel;
+
+    fn backtester() -> Backtester {
+        let mut backtester = Backtester::new(1_000.0, testing::risk());
+        backtester.fill_model = FillModel::frictionless();
+        backtester
+    }
+
+    #[test]
+    fn grid_search_ranks_the_peak_exit_first() {
+        let results = grid_search(
+            &backtester(),
+            &testing::peak(),
+            hold_for,
+            Objective::TotalReturn,
+        )
+        .unwrap();
+        assert_eq!(results.len(), 6);
+        assert_eq!(
+            results[0].parameters,
+            vec![("bars".to_string(), ParameterValue::Int(5))]
+        );
+        // One unit bought at 100 and sold at 105 on 1000 of capital
+        assert!((results[0].report.total_return_pct - 0.5).abs() < 1e-9);
+        assert!(results
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+    }
+
+    // Every child mutates, half of them to a random value, so the six values of
+    // `bars` are all tried long before the generations run out
+    fn evolution(seed: u64) -> EvolutionSettings {
+        EvolutionSettings {
+            population_size: 10,
+            max_generations: 20,
+            mutation_rate: 1.0,
+            elite_count: 2,
+            tournament_size: 2,
+            patience: 20,
+            seed: Some(seed),
+        }
+    }
+
+    #[test]
+    fn evolve_finds_the_peak_exit() {
+        let results = evolve(
+            &backtester(),
+            &testing::peak(),
+            hold_for,
+            Objective::TotalReturn,
+            &evolution(7),
+        )
+        .unwrap();
+        assert_eq!(
+            results[0].parameters,
+            vec![("bars".to_string(), ParameterValue::Int(5))]
+        );
+        assert_eq!(results[0].report.seed, Some(7));
+        // Each genome is backtested once
+        assert!(results.len() <= 6);
+    }
+
+    #[test]
+    fn evolve_repeats_with_the_same_seed() {
+        let run = || {
+            evolve(
+                &backtester(),
+                &testing::peak(),
+                hold_for,
+                Objective::TotalReturn,
+                &EvolutionSettings {
+                    seed: Some(42),
+                    ..EvolutionSettings::default()
+                },
+            )
+            .unwrap()
+        };
+        let first = run();
+        let second = run();
+        assert_eq!(first.len(), second.len());
+        for (a, b) in first.iter().zip(&second) {
+            assert_eq!(a.parameters, b.parameters);
+            assert_eq!(a.score, b.score);
+            assert_eq!(a.report.seed, Some(42));
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use super::{benchmark, BacktestResult, Backtester, PendingOrder, PriceHistory, SimulatedBroker};
+use crate::engine::SignalEngine;
+use crate::strategy::Strategy;
+
+impl Backtester {
+    /// Backtests a basket of symbols against one cash pool. Candles are replayed in
+    /// close-time order across symbols, each symbol with its own strategy instance,
+    /// and `risk.max_open_positions` caps positions across the whole basket.
+    pub fn run_portfolio<F>(&self, histories: &[PriceHistory], make_strategy: F) -> BacktestResult
+    where
+        F: Fn() -> Box<dyn Strategy>,
+    {
+        let mut engines: Vec<SignalEngine<Box<dyn Strategy>>> = histories
+            .iter()
+            .map(|history| SignalEngine::new(&history.symbol, make_strategy()))
+            .collect();
+        let mut pending: Vec<Vec<PendingOrder>> = vec![Vec::new(); histories.len()];
+        let mut cursors = vec![0; histories.len()];
+        let mut broker = SimulatedBroker::new(self.initial_capital, self.fill_model.clone());
+        let mut prices: HashMap<String, f64> = HashMap::new();
+        let mut trades = Vec::new();
+        let mut equity_curve = Vec::new();
+        let mut bars_in_market = 0;
+
+        loop {
+            let next_close = histories
+                .iter()
+                .zip(&cursors)
+                .filter_map(|(history, cursor)| history.candles.get(*cursor))
+                .map(|candle| candle.close_time)
+                .min();
+            let next_close = match next_close {
+                Some(next_close) => next_close,
+                None => break,
+            };
+
+            for (k, history) in histories.iter().enumerate() {
+                let candle = match history.candles.get(cursors[k]) {
+                    Some(candle) if candle.close_time == next_close => candle,
+                    _ => continue,
+                };
+                cursors[k] += 1;
+                let signal = engines[k].on_close(next_close.timestamp(), candle.close_price);
+                self.process_candle(&mut broker, &signal, candle, &mut pending[k], &mut trades);
+                prices.insert(history.symbol.clone(), candle.close_price);
+            }
+
+            if broker.open_positions() > 0 {
+                bars_in_market += 1;
+            }
+            equity_curve.push((next_close.timestamp(), broker.equity(&prices)));
+        }
+
+        let final_equity = equity_curve
+            .last()
+            .map(|(_, equity)| *equity)
+            .unwrap_or(self.initial_capital);
+        let times: Vec<i64> = equity_curve.iter().map(|(time, _)| *time).collect();
+        BacktestResult {
+            symbol: histories
+                .iter()
+                .map(|history| history.symbol.as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+            strategy: engines
+                .first()
+                .map(|engine| engine.strategy().name().to_string())
+                .unwrap_or_default(),
+            trades,
+            equity_curve,
+            initial_capital: self.initial_capital,
+            final_equity,
+            bars_in_market,
+            buy_and_hold: benchmark::buy_and_hold(histories, &times),
+            benchmark: self.benchmark_series(&times),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use super::{benchmark, BacktestResult, Backtester, PendingOrder, PriceHistory, SimulatedBroker};
+use crate::dto::{
+    parse_websocket_message, parse_websocket_message_depth, parse_websocket_message_ticker,
+    KlineResponse,
+};
+use crate::engine::SignalEngine;
+use crate::recorder::{RecordedMessage, StreamKind};
+use crate::strategy::Strategy;
+
+// Single-price candle so a ticker update can trigger bracket exits between closes
+fn tick_candle(price: f64, time_ms: i64) -> Option<KlineResponse> {
+    let time = chrono::DateTime::from_timestamp_millis(time_ms)?;
+    Some(KlineResponse {
+        open_time: time,
+        open_price: price,
+        high_price: price,
+        low_price: price,
+        close_price: price,
+        volume: 0.0,
+        close_time: time,
+        quote_asset_volume: 0.0,
+        number_of_trades: 0,
+        taker_buy_base_volume: 0.0,
+        taker_buy_quote_volume: 0.0,
+    })
+}
+
+impl Backtester {
+    /// Backtests from raw recorded websocket messages, parsed by the same code the
+    /// live streams use. Closed klines drive the strategy, ticker updates check
+    /// brackets between closes and depth snapshots set the simulated spread.
+    pub fn run_replay(
+        &self,
+        strategy: &mut dyn Strategy,
+        messages: &[RecordedMessage],
+    ) -> BacktestResult {
+        let strategy_name = strategy.name().to_string();
+        let mut strategy = Some(strategy);
+        let mut engine: Option<SignalEngine<&mut dyn Strategy>> = None;
+        let mut broker = SimulatedBroker::new(self.initial_capital, self.fill_model.clone());
+        let mut pending: Vec<PendingOrder> = Vec::new();
+        let mut trades = Vec::new();
+        let mut equity_curve = Vec::new();
+        let mut candles = Vec::new();
+        let mut prices: HashMap<String, f64> = HashMap::new();
+        let mut symbol = String::new();
+        let mut bars_in_market = 0;
+        let mut skipped = 0;
+
+        for message in messages {
+            match message.kind {
+                StreamKind::Kline => {
+                    let kline = match parse_websocket_message(&message.raw) {
+                        Ok(response) => response.data.kline,
+                        Err(_) => {
+                            skipped += 1;
+                            continue;
+                        }
+                    };
+                    if !kline.is_closed {
+                        continue;
+                    }
+                    let candle = match KlineResponse::from_stream(&kline) {
+                        Ok(candle) => candle,
+                        Err(e) => {
+                            log::warn!("Skipping kline at {}: {}", message.received_at, e);
+                            skipped += 1;
+                            continue;
+                        }
+                    };
+                    // The strategy follows the first symbol seen; one instance can't
+                    // serve several
+                    if let Some(strategy) = strategy.take() {
+                        symbol = kline.symbol.clone();
+                        engine = Some(SignalEngine::new(&symbol, strategy));
+                    }
+                    let engine = match engine.as_mut() {
+                        Some(engine) if kline.symbol == symbol => engine,
+                        _ => continue,
+                    };
+                    let time = candle.close_time.timestamp();
+                    let signal = engine.on_close(time, candle.close_price);
+                    self.process_candle(&mut broker, &signal, &candle, &mut pending, &mut trades);
+                    prices.insert(kline.symbol.clone(), candle.close_price);
+
+                    if broker.open_positions() > 0 {
+                        bars_in_market += 1;
+                    }
+                    equity_curve.push((time, broker.equity(&prices)));
+                    candles.push(candle);
+                }
+                StreamKind::Ticker => {
+                    let ticker = match parse_websocket_message_ticker(&message.raw) {
+                        Ok(response) => response.data,
+                        Err(_) => {
+                            skipped += 1;
+                            continue;
+                        }
+                    };
+                    let price: f64 = ticker.last_price.parse().unwrap_or_default();
+                    if price <= 0.0 {
+                        continue;
+                    }
+                    if let Some(candle) = tick_candle(price, ticker.event_time) {
+                        let time = candle.close_time.timestamp();
+                        if let Some(trade) = broker.check_bracket(&ticker.symbol, &candle, time) {
+                            trades.push(trade);
+                        }
+                    }
+                }
+                StreamKind::Depth => {
+                    let depth = match parse_websocket_message_depth(&message.raw) {
+                        Ok(response) => response.data,
+                        Err(_) => {
+                            skipped += 1;
+                            continue;
+                        }
+                    };
+                    if let (Some(bid), Some(ask)) = (depth.best_bid(), depth.best_ask()) {
+                        let mid = (bid + ask) / 2.0;
+                        if mid > 0.0 && ask >= bid {
+                            broker.fill_model.spread_bps = (ask - bid) / mid * 10_000.0;
+                        }
+                    }
+                }
+            }
+        }
+        if skipped > 0 {
+            log::warn!("Skipped {} unparseable recorded messages", skipped);
+        }
+
+        let final_equity = equity_curve
+            .last()
+            .map(|(_, equity)| *equity)
+            .unwrap_or(self.initial_capital);
+        let times: Vec<i64> = equity_curve.iter().map(|(time, _)| *time).collect();
+        let (history, _) = PriceHistory::from_candles(&symbol, 0, candles);
+        BacktestResult {
+            symbol,
+            strategy: strategy_name,
+            trades,
+            equity_curve,
+            initial_capital: self.initial_capital,
+            final_equity,
+            bars_in_market,
+            buy_and_hold: benchmark::buy_and_hold(std::slice::from_ref(&history), &times),
+            benchmark: self.benchmark_series(&times),
+        }
+    }
+}
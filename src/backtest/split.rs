@@ -0,0 +1,242 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use super::optimizer::{self, Objective, ParameterSet};
+use super::{BacktestReport, Backtester, PriceHistory};
+use crate::domain::TradingError;
+use crate::strategy::Strategy;
+
+/// Train, validation and test segments of one history, in time order
+#[derive(Debug, Clone)]
+pub struct DataSplit {
+    pub train: PriceHistory,
+    pub validation: PriceHistory,
+    pub test: PriceHistory,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HoldoutReport {
+    pub parameters: ParameterSet,
+    pub train_score: f64,
+    pub validation_score: f64,
+    // Only run once, for the parameters that won on validation
+    pub test: BacktestReport,
+}
+
+// Candles opened in [from, to), skipping the first `embargo` of the segment so
+// trades and indicators from the previous segment can't leak into it
+fn segment(
+    history: &PriceHistory,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    embargo: Duration,
+) -> PriceHistory {
+    history.between(from + embargo, to)
+}
+
+/// Split at `validation_start` and `test_start`, dropping `embargo` worth of
+/// candles after each boundary
+pub fn split_by_date(
+    history: &PriceHistory,
+    validation_start: DateTime<Utc>,
+    test_start: DateTime<Utc>,
+    embargo: Duration,
+) -> Result<DataSplit, TradingError> {
+    let (first, last) = match (history.candles.first(), history.candles.last()) {
+        (Some(first), Some(last)) => (first.open_time, last.close_time),
+        _ => {
+            return Err(TradingError::InvalidParameter(
+                "Cannot split an empty history".to_string(),
+            ))
+        }
+    };
+    if validation_start >= test_start {
+        return Err(TradingError::InvalidParameter(format!(
+            "Validation start {} must be before test start {}",
+            validation_start, test_start
+        )));
+    }
+    if embargo < Duration::zero() {
+        return Err(TradingError::InvalidParameter(
+            "Embargo must not be negative".to_string(),
+        ));
+    }
+
+    let split = DataSplit {
+        train: segment(history, first, validation_start, Duration::zero()),
+        validation: segment(history, validation_start, test_start, embargo),
+        test: segment(history, test_start, last, embargo),
+    };
+    for (name, part) in [
+        ("train", &split.train),
+        ("validation", &split.validation),
+        ("test", &split.test),
+    ] {
+        if part.is_empty() {
+            return Err(TradingError::InvalidParameter(format!(
+                "The {} segment is empty",
+                name
+            )));
+        }
+    }
+    log::info!(
+        "Split {} into {} train, {} validation and {} test candles",
+        history.symbol,
+        split.train.len(),
+        split.validation.len(),
+        split.test.len()
+    );
+    Ok(split)
+}
+
+/// Split by share of the covered time span, e.g. 0.6 and 0.2 for 60/20/20
+pub fn split_by_fraction(
+    history: &PriceHistory,
+    train: f64,
+    validation: f64,
+    embargo: Duration,
+) -> Result<DataSplit, TradingError> {
+    if train <= 0.0 || validation <= 0.0 || train + validation >= 1.0 {
+        return Err(TradingError::InvalidParameter(format!(
+            "Invalid split fractions {} and {}",
+            train, validation
+        )));
+    }
+    let (first, last) = match (history.candles.first(), history.candles.last()) {
+        (Some(first), Some(last)) => (first.open_time, last.close_time),
+        _ => {
+            return Err(TradingError::InvalidParameter(
+                "Cannot split an empty history".to_string(),
+            ))
+        }
+    };
+    let span_ms = (last - first).num_milliseconds() as f64;
+    let at = |fraction: f64| first + Duration::milliseconds((span_ms * fraction) as i64);
+    split_by_date(history, at(train), at(train + validation), embargo)
+}
+
+/// Grid-searches the train segment, picks the best of the top `candidates` on
+/// validation and reports that one parameter set on the untouched test segment
+pub fn holdout<F>(
+    backtester: &Backtester,
+    split: &DataSplit,
+    make_strategy: F,
+    objective: Objective,
+    candidates: usize,
+) -> Result<HoldoutReport, TradingError>
+where
+    F: Fn() -> Box<dyn Strategy> + Sync,
+{
+    let ranked = optimizer::grid_search(backtester, &split.train, &make_strategy, objective)?;
+    let best = ranked
+        .into_iter()
+        .take(candidates.max(1))
+        .filter_map(|train| {
+            optimizer::evaluate(
+                backtester,
+                &split.validation,
+                &make_strategy,
+                &train.parameters,
+                objective,
+            )
+            .map(|validation| (train, validation))
+        })
+        .max_by(|a, b| a.1.score.total_cmp(&b.1.score));
+    let (train, validation) = best.ok_or_else(|| {
+        TradingError::InvalidParameter("No parameter set survived validation".to_string())
+    })?;
+
+    let mut strategy = optimizer::configure(&make_strategy, &validation.parameters)?;
+    let test = backtester.run(strategy.as_mut(), &split.test).report();
+    log::info!(
+        "Holdout with {:?}: train {:.4}, validation {:.4}, test {:.4}",
+        validation.parameters,
+        train.score,
+        validation.score,
+        objective.score(&test)
+    );
+    Ok(HoldoutReport {
+        parameters: validation.parameters,
+        train_score: train.score,
+        validation_score: validation.score,
+        test,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::testing::{self, INTERVAL_MS};
+
+    fn open_time(history: &PriceHistory, index: usize) -> DateTime<Utc> {
+        history.candles[index].open_time
+    }
+
+    #[test]
+    fn boundary_candles_start_the_later_segment() {
+        let history = testing::history(&[100.0; 10]);
+        let split = split_by_date(
+            &history,
+            open_time(&history, 4),
+            open_time(&history, 7),
+            Duration::zero(),
+        )
+        .unwrap();
+        assert_eq!(split.train.len(), 4);
+        assert_eq!(split.train.candles[3].open_time, open_time(&history, 3));
+        assert_eq!(
+            split.validation.candles[0].open_time,
+            open_time(&history, 4)
+        );
+        assert_eq!(split.validation.len(), 3);
+        assert_eq!(split.test.candles[0].open_time, open_time(&history, 7));
+        // The last candle closes after the span's end but opened inside it
+        assert_eq!(split.test.len(), 3);
+    }
+
+    #[test]
+    fn embargo_drops_candles_after_each_boundary() {
+        let history = testing::history(&[100.0; 12]);
+        let split = split_by_date(
+            &history,
+            open_time(&history, 4),
+            open_time(&history, 8),
+            Duration::milliseconds(2 * INTERVAL_MS),
+        )
+        .unwrap();
+        assert_eq!(split.train.len(), 4);
+        assert_eq!(
+            split.validation.candles[0].open_time,
+            open_time(&history, 6)
+        );
+        assert_eq!(split.validation.len(), 2);
+        assert_eq!(split.test.candles[0].open_time, open_time(&history, 10));
+        assert_eq!(split.test.len(), 2);
+    }
+
+    #[test]
+    fn fractions_split_the_covered_span() {
+        let history = testing::history(&[100.0; 10]);
+        // The span runs from the first open to the last close, just under ten
+        // minutes, so 60% lands inside candle 5 and 80% inside candle 7
+        let split = split_by_fraction(&history, 0.6, 0.2, Duration::zero()).unwrap();
+        assert_eq!(split.train.len(), 6);
+        assert_eq!(split.validation.len(), 2);
+        assert_eq!(split.test.len(), 2);
+        assert_eq!(split.test.candles[0].open_time, open_time(&history, 8));
+    }
+
+    #[test]
+    fn an_empty_segment_is_an_error() {
+        let history = testing::history(&[100.0; 10]);
+        let embargo = Duration::milliseconds(3 * INTERVAL_MS);
+        assert!(split_by_date(
+            &history,
+            open_time(&history, 4),
+            open_time(&history, 7),
+            embargo
+        )
+        .is_err());
+        assert!(split_by_fraction(&history, 0.6, 0.5, Duration::zero()).is_err());
+    }
+}
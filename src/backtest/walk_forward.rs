@@ -0,0 +1,317 @@
+use serde::Serialize;
+
+use super::optimizer::{self, EvolutionSettings, Objective, OptimizationResult, ParameterSet};
+use super::{BacktestReport, BacktestResult, Backtester, PriceHistory};
+use crate::domain::TradingError;
+use crate::strategy::Strategy;
+
+#[derive(Debug, Clone)]
+pub enum Search {
+    Grid,
+    Evolution(EvolutionSettings),
+}
+
+#[derive(Debug, Clone)]
+pub struct WalkForwardSettings {
+    pub in_sample_bars: usize,
+    pub out_of_sample_bars: usize,
+    // Grow the in-sample window from the first candle instead of rolling it
+    pub anchored: bool,
+    pub objective: Objective,
+    pub search: Search,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WalkForwardWindow {
+    // Close times in seconds
+    pub in_sample_start: i64,
+    pub in_sample_end: i64,
+    pub out_of_sample_start: i64,
+    pub out_of_sample_end: i64,
+    pub parameters: ParameterSet,
+    pub in_sample_score: f64,
+    pub out_of_sample_score: f64,
+    pub out_of_sample: BacktestReport,
+    // Seed the in-sample search ran with, for evolutionary searches
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WalkForwardReport {
+    pub windows: Vec<WalkForwardWindow>,
+    // Out-of-sample windows chained together, compounding from window to window
+    pub combined: BacktestReport,
+    // Mean out-of-sample score over mean in-sample score; well below 1 suggests
+    // the optimizer is fitting noise
+    pub efficiency: f64,
+}
+
+fn optimize<F>(
+    backtester: &Backtester,
+    history: &PriceHistory,
+    make_strategy: &F,
+    settings: &WalkForwardSettings,
+) -> Result<Option<OptimizationResult>, TradingError>
+where
+    F: Fn() -> Box<dyn Strategy> + Sync,
+{
+    let results = match &settings.search {
+        Search::Grid => {
+            optimizer::grid_search(backtester, history, make_strategy, settings.objective)?
+        }
+        Search::Evolution(evolution) => optimizer::evolve(
+            backtester,
+            history,
+            make_strategy,
+            settings.objective,
+            evolution,
+        )?,
+    };
+    Ok(results.into_iter().next())
+}
+
+fn close_time(history: &PriceHistory, index: usize) -> i64 {
+    history.candles[index].close_time.timestamp()
+}
+
+// Join per-window series so each one continues from where the previous ended
+fn chain<'a>(series: impl Iterator<Item = &'a [(i64, f64)]>) -> Vec<(i64, f64)> {
+    let mut chained: Vec<(i64, f64)> = Vec::new();
+    for window in series {
+        let first = match window.first() {
+            Some((_, first)) if *first > 0.0 => *first,
+            _ => continue,
+        };
+        let scale = chained.last().map(|(_, last)| last / first).unwrap_or(1.0);
+        chained.extend(window.iter().map(|(time, value)| (*time, value * scale)));
+    }
+    chained
+}
+
+// Chain out-of-sample runs as if the capital at the end of one window was carried
+// into the next
+fn combine(results: &[BacktestResult], initial_capital: f64) -> BacktestResult {
+    let mut capital = initial_capital;
+    let mut trades = Vec::new();
+    let mut equity_curve = Vec::new();
+    let mut bars_in_market = 0;
+    for result in results {
+        let scale = if result.initial_capital > 0.0 {
+            capital / result.initial_capital
+        } else {
+            1.0
+        };
+        equity_curve.extend(
+            result
+                .equity_curve
+                .iter()
+                .map(|(time, equity)| (*time, equity * scale)),
+        );
+        trades.extend(result.trades.iter().cloned().map(|mut trade| {
+            trade.quantity *= scale;
+            trade.fees *= scale;
+            trade.pnl *= scale;
+            trade
+        }));
+        bars_in_market += result.bars_in_market;
+        capital = result.final_equity * scale;
+    }
+    BacktestResult {
+        symbol: results
+            .first()
+            .map(|r| r.symbol.clone())
+            .unwrap_or_default(),
+        strategy: results
+            .first()
+            .map(|r| r.strategy.clone())
+            .unwrap_or_default(),
+        trades,
+        equity_curve,
+        initial_capital,
+        final_equity: capital,
+        bars_in_market,
+        buy_and_hold: chain(results.iter().map(|r| r.buy_and_hold.as_slice())),
+        benchmark: results
+            .first()
+            .and_then(|r| r.benchmark.as_ref())
+            .map(|(symbol, _)| {
+                let series = chain(
+                    results
+                        .iter()
+                        .filter_map(|r| r.benchmark.as_ref())
+                        .map(|(_, series)| series.as_slice()),
+                );
+                (symbol.clone(), series)
+            }),
+    }
+}
+
+/// Optimizes on each in-sample window and backtests the winner on the window that
+/// follows it
+pub fn walk_forward<F>(
+    backtester: &Backtester,
+    history: &PriceHistory,
+    make_strategy: F,
+    settings: &WalkForwardSettings,
+) -> Result<WalkForwardReport, TradingError>
+where
+    F: Fn() -> Box<dyn Strategy> + Sync,
+{
+    if settings.in_sample_bars == 0 || settings.out_of_sample_bars == 0 {
+        return Err(TradingError::InvalidParameter(
+            "Walk-forward windows must not be empty".to_string(),
+        ));
+    }
+    if history.len() < settings.in_sample_bars + settings.out_of_sample_bars {
+        return Err(TradingError::InvalidParameter(format!(
+            "{} candles are not enough for one {}+{} window",
+            history.len(),
+            settings.in_sample_bars,
+            settings.out_of_sample_bars
+        )));
+    }
+
+    let mut windows = Vec::new();
+    let mut results = Vec::new();
+    let mut in_sample_end = settings.in_sample_bars;
+    while in_sample_end < history.len() {
+        let in_sample_start = if settings.anchored {
+            0
+        } else {
+            in_sample_end - settings.in_sample_bars
+        };
+        let out_of_sample_end = (in_sample_end + settings.out_of_sample_bars).min(history.len());
+
+        let in_sample = history.slice(in_sample_start..in_sample_end);
+        let best = match optimize(backtester, &in_sample, &make_strategy, settings)? {
+            Some(best) => best,
+            None => {
+                log::warn!(
+                    "No valid parameters for window ending {}, skipping",
+                    close_time(history, in_sample_end - 1)
+                );
+                in_sample_end = out_of_sample_end;
+                continue;
+            }
+        };
+
+        // The in-sample candles warm up indicators for the out-of-sample run
+        let mut strategy = optimizer::configure(&make_strategy, &best.parameters)?;
+        let result = backtester.run_from(
+            strategy.as_mut(),
+            &history.slice(in_sample_start..out_of_sample_end),
+            in_sample_end - in_sample_start,
+        );
+        let report = result.report();
+        log::info!(
+            "Walk-forward window {}: in-sample {:.4}, out-of-sample {:.4} with {:?}",
+            windows.len(),
+            best.score,
+            settings.objective.score(&report),
+            best.parameters
+        );
+        windows.push(WalkForwardWindow {
+            in_sample_start: close_time(history, in_sample_start),
+            in_sample_end: close_time(history, in_sample_end - 1),
+            out_of_sample_start: close_time(history, in_sample_end),
+            out_of_sample_end: close_time(history, out_of_sample_end - 1),
+            parameters: best.parameters,
+            in_sample_score: best.score,
+            out_of_sample_score: settings.objective.score(&report),
+            out_of_sample: report,
+            seed: best.report.seed,
+        });
+        results.push(result);
+        in_sample_end = out_of_sample_end;
+    }
+
+    let mean = |scores: Vec<f64>| scores.iter().sum::<f64>() / scores.len().max(1) as f64;
+    let in_sample_mean = mean(windows.iter().map(|w| w.in_sample_score).collect());
+    let out_of_sample_mean = mean(windows.iter().map(|w| w.out_of_sample_score).collect());
+    let efficiency = if in_sample_mean.abs() > f64::EPSILON {
+        out_of_sample_mean / in_sample_mean
+    } else {
+        0.0
+    };
+
+    Ok(WalkForwardReport {
+        combined: combine(&results, backtester.initial_capital).report(),
+        windows,
+        efficiency,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::testing::{self, hold_for};
+    use crate::backtest::FillModel;
+    use crate::strategy::ParameterValue;
+
+    fn backtester() -> Backtester {
+        let mut backtester = Backtester::new(1_000.0, testing::risk());
+        backtester.fill_model = FillModel::frictionless();
+        backtester
+    }
+
+    fn settings(in_sample_bars: usize, out_of_sample_bars: usize) -> WalkForwardSettings {
+        WalkForwardSettings {
+            in_sample_bars,
+            out_of_sample_bars,
+            anchored: false,
+            objective: Objective::TotalReturn,
+            search: Search::Grid,
+        }
+    }
+
+    #[test]
+    fn optimizes_in_sample_and_trades_the_next_window() {
+        let mut closes = testing::peak().closes();
+        closes.extend([100.0; 5]);
+        let history = testing::history(&closes);
+
+        let report = walk_forward(&backtester(), &history, hold_for, &settings(11, 5)).unwrap();
+        assert_eq!(report.windows.len(), 1);
+        let window = &report.windows[0];
+        assert_eq!(
+            window.parameters,
+            vec![("bars".to_string(), ParameterValue::Int(5))]
+        );
+        assert!((window.in_sample_score - 0.5).abs() < 1e-9);
+        assert_eq!(
+            window.out_of_sample_start,
+            history.candles[11].close_time.timestamp()
+        );
+        // The strategy only buys on its first candle, which is warm-up out of sample
+        assert_eq!(window.out_of_sample.total_trades, 0);
+        assert_eq!(report.combined.final_equity, 1_000.0);
+        assert_eq!(report.efficiency, 0.0);
+    }
+
+    #[test]
+    fn rolls_until_the_history_runs_out() {
+        let history = testing::history(&[100.0; 20]);
+        let report = walk_forward(&backtester(), &history, hold_for, &settings(10, 4)).unwrap();
+        // Out of sample from candles 10, 14 and a short last window from 18
+        let starts: Vec<i64> = report
+            .windows
+            .iter()
+            .map(|window| window.out_of_sample_start)
+            .collect();
+        let expected: Vec<i64> = [10, 14, 18]
+            .iter()
+            .map(|i| history.candles[*i].close_time.timestamp())
+            .collect();
+        assert_eq!(starts, expected);
+        assert_eq!(
+            report.windows[2].out_of_sample_end,
+            history.candles[19].close_time.timestamp()
+        );
+    }
+
+    #[test]
+    fn rejects_a_history_shorter_than_one_window() {
+        let history = testing::history(&[100.0; 10]);
+        assert!(walk_forward(&backtester(), &history, hold_for, &settings(8, 4)).is_err());
+    }
+}
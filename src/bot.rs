@@ -0,0 +1,786 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use binance_spot_connector_rust::http::Credentials;
+use tokio::sync::{broadcast, mpsc, watch};
+
+use crate::config::{ConfigSource, Profile};
+use crate::control::{ControlCommand, ControlRequest};
+use crate::domain::*;
+use crate::dto::{Kline, TickerData};
+use crate::engine::SignalEngine;
+#[cfg(feature = "futures")]
+use crate::exchange::binance_futures;
+#[cfg(feature = "coinbase")]
+use crate::exchange::coinbase;
+#[cfg(feature = "kraken")]
+use crate::exchange::kraken;
+use crate::exchange::router::parse_venue;
+use crate::exchange::{self, ExchangeRouter};
+use crate::executor::TradeExecutor;
+#[cfg(feature = "grpc")]
+use crate::grpc;
+use crate::health::{HealthState, SharedHealth};
+use crate::journal::SharedJournal;
+#[cfg(feature = "kafka")]
+use crate::kafka;
+#[cfg(feature = "mqtt")]
+use crate::mqtt;
+use crate::parity::SessionRecorder;
+#[cfg(feature = "redis")]
+use crate::pubsub;
+use crate::recorder::SharedRecorder;
+use crate::snapshot::{SharedStrategySnapshot, Snapshot};
+use crate::strategy::Strategy;
+use crate::streams::StreamRegistry;
+use crate::supervisor::Supervisor;
+use crate::user_stream::{self, UserStreamSettings};
+use crate::{
+    alerts, audit, health, heartbeat, journal, notify, recorder, reload, report, retention,
+    safe_mode, secrets, snapshot, storage, telemetry,
+};
+use crate::{
+    analyze_price_data, get_kline_data, get_ticker_data, process_kline_data, process_ticker_data,
+    process_trading_signals, process_venue_data, shutdown_signal, warm_up_closes,
+};
+#[cfg(feature = "web")]
+use crate::{api, dashboard, push, webhook};
+
+/// Assembles live trading from a profile: the exchange client, the executor
+/// with its journal, audit log and store, and the state resumed from a snapshot
+pub struct TradingBotBuilder {
+    profile: Profile,
+    config_source: ConfigSource,
+    snapshot_path: String,
+    resumed: Option<Snapshot>,
+    // Read with secrets::load_credentials when not given
+    credentials: Option<Credentials>,
+}
+
+impl TradingBotBuilder {
+    pub fn new(profile: Profile) -> Self {
+        TradingBotBuilder {
+            profile,
+            config_source: ConfigSource::default(),
+            snapshot_path: "auto_trade_state.json".to_string(),
+            resumed: None,
+            credentials: None,
+        }
+    }
+
+    // Watched for risk and strategy parameter changes while running
+    pub fn config_source(mut self, source: ConfigSource) -> Self {
+        self.config_source = source;
+        self
+    }
+
+    pub fn snapshot_path(mut self, path: String) -> Self {
+        self.snapshot_path = path;
+        self
+    }
+
+    pub fn resume(mut self, snapshot: Option<Snapshot>) -> Self {
+        self.resumed = snapshot;
+        self
+    }
+
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    pub async fn build(self) -> Result<TradingBot, TradingError> {
+        // Global settings with the traded symbol's own section merged in
+        let profile = self.profile.for_symbol(&self.profile.trading.symbol);
+        let symbol = profile.trading.symbol.to_string();
+        let interval = profile.trading.interval;
+        if let Some(snapshot) = &self.resumed {
+            if snapshot.symbol != symbol {
+                return Err(TradingError::InvalidParameter(format!(
+                    "Snapshot is for {}, not {}",
+                    snapshot.symbol, symbol
+                )));
+            }
+        }
+
+        // The primary exchange streams market data; orders for symbols prefixed
+        // with a venue from trading.venues go to that exchange instead
+        let exchange = profile.trading.exchange.clone();
+        // Binance reports fills and cancels of resting orders on the account stream
+        let user_stream = if exchange == "binance" {
+            let api_key = match &self.credentials {
+                Some(credentials) => credentials.api_key.clone(),
+                None => secrets::load_credentials()?.api_key,
+            };
+            Some(UserStreamSettings {
+                api_key,
+                testnet: profile.trading.testnet,
+            })
+        } else {
+            None
+        };
+        let market_data = Arc::new(Mutex::new(MarketData::default()));
+        let primary = exchange::connect(
+            &exchange,
+            &profile.trading.symbol,
+            &profile,
+            market_data.clone(),
+            self.credentials,
+        )
+        .await?;
+        let mut client = ExchangeRouter::new(&exchange, primary);
+        let mut venues = Vec::new();
+        for venue in &profile.trading.venues {
+            let (name, venue_symbol) = parse_venue(venue)?;
+            // Its symbol's own section over the global settings, not the primary's
+            let venue_profile = self.profile.for_symbol(&venue_symbol);
+            let strategy = venue_profile.build_strategy()?;
+            // Its own prices, so its positions and paper fills never see the primary's
+            let venue_data = Arc::new(Mutex::new(MarketData::default()));
+            let venue_client =
+                exchange::connect(&name, &venue_symbol, &profile, venue_data.clone(), None).await?;
+            client.add(&name, venue_client)?;
+            log::info!("Routing {}:{} orders to {}", name, venue_symbol, name);
+            venues.push(VenueFeed {
+                exchange: name,
+                symbol: venue_symbol,
+                interval: venue_profile.trading.interval,
+                strategy,
+                market_data: venue_data,
+            });
+        }
+        let closes = client
+            .recent_closes(&symbol, interval, 15)
+            .await
+            .unwrap_or_else(|e| {
+                log::error!("Failed to load recent prices: {}", e);
+                VecDeque::new()
+            });
+        let price_data = Arc::new(Mutex::new(closes));
+
+        let risk = self
+            .resumed
+            .as_ref()
+            .map(|snapshot| snapshot.risk.clone())
+            .unwrap_or_else(|| profile.risk.clone());
+        let mut executor = TradeExecutor::new(client, risk);
+        executor.set_execution_settings(profile.trading.execution.clone());
+        // Every decision from market snapshot to fill, for post-mortems
+        let event_journal = journal::from_env();
+        if let Some(journal) = &event_journal {
+            executor.set_journal(journal.clone());
+        }
+        // Hash-chained record of every order submit and cancel and what caused it
+        if let Some(audit_log) = audit::from_env() {
+            executor.set_audit_log(audit_log);
+        }
+        if let Some(store) = storage::from_env().await {
+            executor.set_store(store).await;
+        }
+        // Signals already acted on, so resuming mid-bar doesn't repeat an order
+        if let Some(dedup) = storage::dedup::from_env() {
+            executor.set_signal_dedup(dedup);
+        }
+        if let Some(snapshot) = &self.resumed {
+            log::info!(
+                "Resuming with {} positions and {} working orders from {}",
+                snapshot.executor.positions.len(),
+                snapshot.executor.working_orders.len(),
+                snapshot.saved_at
+            );
+            executor
+                .state()
+                .write()
+                .await
+                .restore(snapshot.executor.clone());
+        }
+
+        Ok(TradingBot {
+            executor,
+            exchange,
+            user_stream,
+            market_data,
+            venues,
+            price_data,
+            symbol,
+            interval,
+            profile,
+            config_source: self.config_source,
+            snapshot_path: self.snapshot_path,
+            resumed: self.resumed,
+            event_journal,
+        })
+    }
+}
+
+// A routed venue, the strategy trading it and the market data its own
+// streams keep current
+struct VenueFeed {
+    exchange: String,
+    symbol: Symbol,
+    interval: Interval,
+    strategy: Box<dyn Strategy>,
+    market_data: Arc<Mutex<MarketData>>,
+}
+
+pub struct TradingBot {
+    executor: TradeExecutor<ExchangeRouter>,
+    // Name from trading.exchange, which decides the market data streams
+    exchange: String,
+    user_stream: Option<UserStreamSettings>,
+    market_data: Arc<Mutex<MarketData>>,
+    venues: Vec<VenueFeed>,
+    price_data: Arc<Mutex<VecDeque<f64>>>,
+    symbol: String,
+    interval: Interval,
+    profile: Profile,
+    config_source: ConfigSource,
+    snapshot_path: String,
+    resumed: Option<Snapshot>,
+    event_journal: Option<SharedJournal>,
+}
+
+impl TradingBot {
+    // Starts every task and runs until one fails for good or a shutdown signal
+    pub async fn run(self) {
+        let TradingBot {
+            mut executor,
+            exchange,
+            user_stream,
+            market_data,
+            venues,
+            price_data,
+            symbol,
+            interval,
+            profile,
+            config_source,
+            snapshot_path,
+            resumed,
+            event_journal,
+        } = self;
+        let (kline_tx, kline_rx) = mpsc::channel(100);
+        let (ticker_tx, ticker_rx) = mpsc::channel(100);
+        let (signal_tx, signal_rx) = mpsc::channel(100); // New channel for trading signals
+                                                         // Mailbox of the analysis task: every kline update with the market it saw
+        let (candle_tx, candle_rx) = mpsc::channel(100);
+        let market_data_kline = market_data.clone();
+        let market_data_ticker = market_data.clone();
+        let health: SharedHealth = Arc::new(Mutex::new(HealthState::default()));
+        tokio::spawn(health::serve(health.clone()));
+        if let Some(settings) = heartbeat::HeartbeatSettings::from_env() {
+            tokio::spawn(heartbeat::run(settings, health.clone()));
+        }
+
+        // Sinks below subscribe here; the executor and market streams publish
+        let events = executor.state().read().await.bus.clone();
+        #[cfg(feature = "redis")]
+        if let Some(settings) = pubsub::RedisSettings::from_env() {
+            let bus_rx = events.subscribe();
+            tokio::spawn(pubsub::run_publisher(settings.clone(), bus_rx));
+            tokio::spawn(pubsub::run_signal_subscriber(settings, signal_tx.clone()));
+        }
+        #[cfg(feature = "web")]
+        if let Some(settings) = webhook::WebhookSettings::from_env() {
+            tokio::spawn(webhook::serve(settings, signal_tx.clone()));
+        }
+        // Operator commands (pause, kill) for the signal task to apply
+        let (control_tx, control_rx) = mpsc::unbounded_channel();
+        // Strategy parameter changes for the analysis task
+        let (tuning_tx, tuning_rx) = mpsc::unbounded_channel();
+        let mut notifiers = Vec::new();
+        #[cfg(feature = "telegram")]
+        if let Some(settings) = notify::telegram::TelegramSettings::from_env() {
+            let (notify_tx, notify_rx) = mpsc::unbounded_channel();
+            notifiers.push(notify_tx);
+            tokio::spawn(notify::run_notifier(
+                notify::telegram::TelegramNotifier::new(settings.clone()),
+                notify_rx,
+            ));
+            tokio::spawn(notify::telegram::run_commands(
+                settings,
+                executor.state(),
+                control_tx.clone(),
+            ));
+        }
+        if let Some(discord) = notify::discord::DiscordNotifier::from_env() {
+            let (notify_tx, notify_rx) = mpsc::unbounded_channel();
+            notifiers.push(notify_tx);
+            tokio::spawn(notify::run_notifier(discord, notify_rx));
+        }
+        if let Some(slack) = notify::slack::SlackNotifier::from_env() {
+            let (notify_tx, notify_rx) = mpsc::unbounded_channel();
+            notifiers.push(notify_tx);
+            tokio::spawn(notify::run_notifier(slack, notify_rx));
+        }
+        #[cfg(feature = "email")]
+        if let Some(email) = notify::email::EmailNotifier::from_env() {
+            let (notify_tx, notify_rx) = mpsc::unbounded_channel();
+            notifiers.push(notify_tx);
+            tokio::spawn(notify::run_notifier(email, notify_rx));
+        }
+        // Status of the websocket subscriptions, kept by the stream tasks
+        let streams = StreamRegistry::new(notifiers.clone());
+        if !profile.alerts.is_empty() {
+            if notifiers.is_empty() {
+                log::warn!("Alert rules configured but no notifier is, they will only be logged");
+            }
+            tokio::spawn(alerts::run_rules(
+                profile.alerts.clone(),
+                market_data.clone(),
+                price_data.clone(),
+                executor.state(),
+                health.clone(),
+                notifiers.clone(),
+            ));
+        }
+        let report_dir = dotenv::var("REPORT_DIR").ok().map(std::path::PathBuf::from);
+        if report_dir.is_some() || !notifiers.is_empty() {
+            tokio::spawn(report::run_daily_reports(
+                executor.state(),
+                report_dir,
+                notifiers.clone(),
+            ));
+        }
+        let (alert_settings_tx, alert_settings_rx) =
+            watch::channel(notify::AlertSettings::from_env());
+        if !notifiers.is_empty() {
+            let bus_rx = events.subscribe();
+            tokio::spawn(notify::run_alerts(
+                alert_settings_rx,
+                bus_rx,
+                executor.state(),
+                notifiers.clone(),
+            ));
+        }
+        // Normalized market data and trading events for analytics pipelines
+        #[cfg(feature = "kafka")]
+        if let Some(settings) = kafka::KafkaSettings::from_env() {
+            let market_rx = events.subscribe_market(crate::events::MARKET_QUEUE);
+            let events_rx = events.subscribe();
+            tokio::spawn(kafka::run_producer(settings, market_rx, events_rx));
+        }
+        #[cfg(feature = "web")]
+        if let Some(settings) = api::ApiSettings::from_env() {
+            let market_rx = events.subscribe_market(crate::events::MARKET_QUEUE);
+            let events_rx = events.subscribe();
+            let push = push::channel();
+            tokio::spawn(push::run(push.clone(), market_rx, events_rx));
+            let dashboard = dashboard::Dashboard::new();
+            tokio::spawn(dashboard::run(
+                dashboard.clone(),
+                push.subscribe(),
+                executor.state(),
+            ));
+            tokio::spawn(api::serve(
+                settings,
+                executor.state(),
+                control_tx.clone(),
+                tuning_tx.clone(),
+                dashboard,
+                push,
+                streams.clone(),
+            ));
+        }
+        // Events and operator commands for home setups built around a broker
+        #[cfg(feature = "mqtt")]
+        if let Some(settings) = mqtt::MqttSettings::from_env() {
+            let events_rx = events.subscribe();
+            tokio::spawn(mqtt::run(settings, events_rx, control_tx.clone()));
+        }
+        // Risk, strategy parameters and the drawdown limit follow the config file
+        tokio::spawn(reload::run(
+            config_source,
+            profile.clone(),
+            control_tx.clone(),
+            tuning_tx.clone(),
+            alert_settings_tx,
+        ));
+        // Typed control methods and event streams for other services
+        #[cfg(feature = "grpc")]
+        if let Some(settings) = grpc::GrpcSettings::from_env() {
+            let market_rx = events.subscribe_market(crate::events::MARKET_QUEUE);
+            let events_rx = events.subscribe();
+            tokio::spawn(grpc::serve(
+                settings,
+                executor.state(),
+                control_tx.clone(),
+                events_rx,
+                market_rx,
+            ));
+        }
+        if let Some(settings) = telemetry::TelemetrySettings::from_env() {
+            tokio::spawn(telemetry::run_telemetry(
+                settings,
+                market_data.clone(),
+                executor.state(),
+            ));
+        }
+        tokio::spawn(safe_mode::run_monitor(
+            safe_mode::SafeModeSettings::from_env(),
+            control_tx.clone(),
+        ));
+        // Every long-running task below; failed ones are restarted where possible
+        let mut supervisor = Supervisor::new(notifiers.clone());
+
+        let strategy = match profile.build_strategy() {
+            Ok(strategy) => strategy,
+            Err(e) => {
+                log::error!("Invalid strategy configuration: {}", e);
+                return;
+            }
+        };
+        executor
+            .state()
+            .write()
+            .await
+            .register_strategy(strategy.name());
+        let mut engine = SignalEngine::new(&symbol, strategy);
+        let restored = match resumed.as_ref().and_then(|s| s.strategy.as_ref()) {
+            Some(strategy) => match engine.restore(strategy) {
+                Ok(()) => true,
+                Err(e) => {
+                    log::error!("Failed to restore strategy state: {}", e);
+                    false
+                }
+            },
+            None => false,
+        };
+        if restored {
+            log::info!("Restored strategy state for {}", symbol);
+        } else if let Ok(path) = dotenv::var("CANDLE_DB_PATH") {
+            match warm_up_closes(&path, &symbol, interval).await {
+                Ok(closes) => {
+                    log::info!("Warmed up {} with {} stored closes", symbol, closes.len());
+                    engine.warm_up(&closes);
+                }
+                Err(e) => log::error!("Failed to warm up from {}: {}", path, e),
+            }
+        }
+        let strategy_snapshot: SharedStrategySnapshot =
+            Arc::new(Mutex::new(Some(engine.snapshot())));
+        {
+            let path = snapshot_path.clone();
+            let symbol = symbol.clone();
+            let state = executor.state();
+            let strategy_snapshot = strategy_snapshot.clone();
+            supervisor.spawn("snapshot", move || {
+                snapshot::run_snapshots(
+                    path.clone(),
+                    symbol.clone(),
+                    state.clone(),
+                    strategy_snapshot.clone(),
+                )
+            });
+        }
+
+        // Recorded sessions can be replayed against the backtest engine (see parity.rs)
+        let recorder = match dotenv::var("SESSION_RECORD_PATH") {
+            Ok(path) => match SessionRecorder::create(&path) {
+                Ok(recorder) => Some(recorder),
+                Err(e) => {
+                    log::error!("Failed to open session record {}: {}", path, e);
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+
+        // Raw messages for event-replay backtests (see backtest/replay.rs)
+        let market_recorder = recorder::from_env();
+        let retention = retention::RetentionPolicy::from_env();
+        if !retention.is_empty() {
+            let candles = match dotenv::var("CANDLE_DB_PATH") {
+                Ok(path) => match storage::CandleStore::open(&path) {
+                    Ok(store) => Some(store),
+                    Err(e) => {
+                        log::error!("Failed to open candle store {}: {}", path, e);
+                        None
+                    }
+                },
+                Err(_) => None,
+            };
+            tokio::spawn(retention::run_pruning(
+                retention,
+                candles,
+                market_recorder.clone(),
+                event_journal.clone(),
+            ));
+        }
+        // Websocket tasks end when their connection drops; restarting reconnects
+        let testnet = profile.trading.testnet;
+        spawn_market_streams(
+            &mut supervisor,
+            StreamTasks::PRIMARY,
+            &exchange,
+            profile.trading.symbol.clone(),
+            interval,
+            kline_tx,
+            ticker_tx,
+            market_recorder.clone(),
+            health.clone(),
+            streams.clone(),
+            testnet,
+        );
+        let (account_tx, account_rx) = broadcast::channel(256);
+        if let Some(settings) = user_stream {
+            let account_tx = account_tx.clone();
+            let streams = streams.clone();
+            supervisor.spawn("user data stream", move || {
+                user_stream::run(settings.clone(), account_tx.clone(), streams.clone())
+            });
+        }
+        // Each routed venue streams its own symbol into its own analysis worker,
+        // so symbols never wait on each other. Recordings stay the primary's, and
+        // its health checks too; the stream list shows the venue's.
+        let mut venue_data = Vec::new();
+        for venue in venues {
+            let (venue_kline_tx, venue_kline_rx) = mpsc::channel(100);
+            let (venue_ticker_tx, venue_ticker_rx) = mpsc::channel(100);
+            let (venue_candle_tx, venue_candle_rx) = mpsc::channel(100);
+            let venue_streams = streams.for_venue(&venue.exchange);
+            let venue_health: SharedHealth = Arc::new(Mutex::new(HealthState::default()));
+            // What its signals, orders and positions go by
+            let venue_symbol = format!("{}:{}", venue.exchange, venue.symbol);
+            spawn_market_streams(
+                &mut supervisor,
+                StreamTasks::VENUE,
+                &venue.exchange,
+                venue.symbol,
+                venue.interval,
+                venue_kline_tx,
+                venue_ticker_tx,
+                None,
+                venue_health.clone(),
+                venue_streams.clone(),
+                testnet,
+            );
+            supervisor.spawn_once(
+                "venue processing",
+                process_venue_data(
+                    venue.exchange,
+                    venue_kline_rx,
+                    venue_ticker_rx,
+                    venue_candle_tx,
+                    venue.market_data.clone(),
+                    events.clone(),
+                    venue_streams,
+                ),
+            );
+            executor
+                .state()
+                .write()
+                .await
+                .register_strategy(venue.strategy.name());
+            // Tuning, session recording and snapshots cover the primary symbol
+            let (_, no_tuning) = mpsc::unbounded_channel();
+            supervisor.spawn_once(
+                "venue analysis",
+                analyze_price_data(
+                    signal_tx.clone(),
+                    venue_candle_rx,
+                    Arc::new(Mutex::new(VecDeque::new())),
+                    SignalEngine::new(&venue_symbol, venue.strategy),
+                    None,
+                    Arc::new(Mutex::new(None)),
+                    event_journal.clone(),
+                    venue_health,
+                    no_tuning,
+                ),
+            );
+            venue_data.push((venue_symbol, venue.market_data));
+        }
+        // The rest own their channel receivers and state, so they can't be rebuilt
+        supervisor.spawn_once(
+            "analysis",
+            analyze_price_data(
+                signal_tx,
+                candle_rx,
+                price_data.clone(),
+                engine,
+                recorder,
+                strategy_snapshot.clone(),
+                event_journal,
+                health.clone(),
+                tuning_rx,
+            ),
+        );
+        supervisor.spawn_once(
+            "kline processing",
+            process_kline_data(
+                kline_rx,
+                candle_tx,
+                market_data_kline,
+                events.clone(),
+                streams.clone(),
+            ),
+        );
+        supervisor.spawn_once(
+            "ticker processing",
+            process_ticker_data(ticker_rx, market_data_ticker, events, streams),
+        );
+        let executor_state = executor.state();
+        supervisor.spawn_once(
+            "signal processing",
+            process_trading_signals(
+                signal_rx,
+                control_rx,
+                executor,
+                std::iter::once((symbol.clone(), market_data.clone()))
+                    .chain(venue_data)
+                    .collect(),
+                account_rx,
+                notifiers,
+                health,
+            ),
+        );
+
+        tokio::select! {
+            _ = supervisor.run() => {}
+            _ = shutdown_signal() => {}
+        }
+
+        log::info!("Shutting down");
+        let flatten = dotenv::var("SHUTDOWN_FLATTEN").is_ok_and(|v| v == "true" || v == "1");
+        let timeout = Duration::from_secs(
+            dotenv::var("SHUTDOWN_TIMEOUT_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(30),
+        );
+        let request = ControlRequest {
+            command: ControlCommand::Shutdown { flatten },
+            requested_by: "shutdown".to_string(),
+            reply: None,
+        };
+        // The signal task cancels orders (and flattens) and then ends by itself
+        if control_tx.send(request).is_ok()
+            && !supervisor.wait_for("signal processing", timeout).await
+        {
+            log::warn!("Order cleanup did not finish within {:?}", timeout);
+        }
+        supervisor.abort_all();
+        match snapshot::save_state(&snapshot_path, &symbol, &executor_state, &strategy_snapshot)
+            .await
+        {
+            Ok(()) => log::info!("Saved final snapshot to {}", snapshot_path),
+            Err(e) => log::error!("Failed to save final snapshot: {}", e),
+        }
+        log::info!("Shutdown complete");
+    }
+}
+
+// Supervisor names of one exchange's market data tasks
+struct StreamTasks {
+    // One connection carrying klines and tickers
+    market: &'static str,
+    // Binance's separate connections
+    kline: &'static str,
+    ticker: &'static str,
+}
+
+impl StreamTasks {
+    const PRIMARY: StreamTasks = StreamTasks {
+        market: "market stream",
+        kline: "kline stream",
+        ticker: "ticker stream",
+    };
+    const VENUE: StreamTasks = StreamTasks {
+        market: "venue market stream",
+        kline: "venue kline stream",
+        ticker: "venue ticker stream",
+    };
+}
+
+// Starts `exchange`'s market data for `symbol`; every exchange `exchange::connect`
+// accepts has streams here
+#[allow(clippy::too_many_arguments)]
+fn spawn_market_streams(
+    supervisor: &mut Supervisor,
+    tasks: StreamTasks,
+    exchange: &str,
+    symbol: Symbol,
+    interval: Interval,
+    kline_tx: mpsc::Sender<Kline>,
+    ticker_tx: mpsc::Sender<TickerData>,
+    recorder: Option<SharedRecorder>,
+    health: SharedHealth,
+    streams: StreamRegistry,
+    testnet: bool,
+) {
+    match exchange {
+        #[cfg(feature = "futures")]
+        "binance-futures" => {
+            supervisor.spawn(tasks.market, move || {
+                binance_futures::get_market_data(
+                    symbol.clone(),
+                    interval,
+                    kline_tx.clone(),
+                    ticker_tx.clone(),
+                    recorder.clone(),
+                    health.clone(),
+                    streams.clone(),
+                    testnet,
+                )
+            });
+        }
+        // One connection carries both channels; recordings stay Binance-only
+        #[cfg(feature = "kraken")]
+        "kraken" => {
+            supervisor.spawn(tasks.market, move || {
+                kraken::get_market_data(
+                    symbol.clone(),
+                    interval,
+                    kline_tx.clone(),
+                    ticker_tx.clone(),
+                    health.clone(),
+                    streams.clone(),
+                )
+            });
+        }
+        #[cfg(feature = "coinbase")]
+        "coinbase" => {
+            supervisor.spawn(tasks.market, move || {
+                coinbase::get_market_data(
+                    symbol.clone(),
+                    interval,
+                    kline_tx.clone(),
+                    ticker_tx.clone(),
+                    health.clone(),
+                    streams.clone(),
+                )
+            });
+        }
+        // Paper trading fills against Binance's live streams
+        "binance" | "paper" => {
+            {
+                let recorder = recorder.clone();
+                let health = health.clone();
+                let streams = streams.clone();
+                let symbol = symbol.clone();
+                supervisor.spawn(tasks.kline, move || {
+                    get_kline_data(
+                        symbol.clone(),
+                        interval,
+                        kline_tx.clone(),
+                        recorder.clone(),
+                        health.clone(),
+                        streams.clone(),
+                        testnet,
+                    )
+                });
+            }
+            supervisor.spawn(tasks.ticker, move || {
+                get_ticker_data(
+                    symbol.clone(),
+                    ticker_tx.clone(),
+                    recorder.clone(),
+                    health.clone(),
+                    streams.clone(),
+                    testnet,
+                )
+            });
+        }
+        _ => log::error!("No market data stream for {}", exchange),
+    }
+}
@@ -0,0 +1,1260 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use binance_spot_connector_rust::http::request::Request;
+use binance_spot_connector_rust::http::Credentials;
+use binance_spot_connector_rust::hyper::BinanceHttpClient;
+use binance_spot_connector_rust::trade;
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use clap::{Args, Parser, Subcommand};
+use hyper::client::HttpConnector;
+use hyper_tls::HttpsConnector;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::audit;
+use crate::backtest::latency;
+use crate::backtest::optimizer::{self, EvolutionSettings, Objective, OptimizationResult};
+use crate::backtest::split::{self, HoldoutReport};
+use crate::backtest::walk_forward::{self, Search, WalkForwardReport, WalkForwardSettings};
+use crate::backtest::{self, data, export, BacktestResult, Backtester, FillModel, PriceHistory};
+use crate::config::{self, ConfigSource, Profile};
+use crate::domain::{Interval, RiskParameters, Symbol, TradingError};
+#[cfg(feature = "mock")]
+use crate::domain::{to_decimal, ExchangeClient, TradeAction};
+use crate::dto::{self, Error};
+#[cfg(feature = "mock")]
+use crate::engine::SignalEngine;
+#[cfg(feature = "mock")]
+use crate::executor::{Trade, TradeExecutor};
+use crate::journal;
+#[cfg(feature = "mock")]
+use crate::mock::{self, FixtureEvent, MockExchange};
+use crate::notify;
+use crate::parity;
+use crate::recorder::{self, StreamKind};
+use crate::secrets::{self, ApiCredentials};
+use crate::snapshot;
+use crate::storage::{self, export as store_export, CandleStore};
+use crate::strategy::{self, ParameterValue, Strategy};
+
+#[derive(Debug, Parser)]
+#[command(name = "auto_trade", about = "Binance spot trading bot")]
+pub struct Cli {
+    // Live trading when no subcommand is given, same as `run`
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Restore positions, orders and strategy state from the last snapshot
+    #[arg(long)]
+    pub resume: bool,
+    /// Where live trading periodically saves its state
+    #[arg(long, env = "SNAPSHOT_PATH", default_value = "auto_trade_state.json")]
+    pub snapshot: String,
+    /// Start from a saved profile (symbol, strategy and its parameters, risk)
+    #[arg(long)]
+    pub profile: Option<String>,
+    /// Save the effective configuration under this profile name at startup
+    #[arg(long)]
+    pub save_profile: Option<String>,
+    #[arg(long, env = "PROFILE_DIR", default_value = "profiles")]
+    pub profile_dir: PathBuf,
+    /// TOML or YAML configuration file, instead of a named profile
+    #[arg(long, env = "AUTO_TRADE_CONFIG", conflicts_with = "profile")]
+    pub config: Option<PathBuf>,
+    /// Overrides the configured symbol
+    #[arg(long)]
+    pub symbol: Option<String>,
+    /// Overrides the configured strategy
+    #[arg(long)]
+    pub strategy: Option<String>,
+}
+
+impl Cli {
+    // Defaults, the profile or config file, AUTO_TRADE_* variables and the
+    // flags above, in that order of precedence
+    pub fn config_source(&self) -> Result<ConfigSource, TradingError> {
+        let file = match (&self.config, &self.profile) {
+            (Some(path), _) => Some(path.clone()),
+            (None, Some(name)) => Some(config::profile_path(&self.profile_dir, name)?),
+            (None, None) => None,
+        };
+        let mut overrides = Vec::new();
+        if let Some(symbol) = &self.symbol {
+            overrides.push(("trading.symbol", symbol.to_uppercase()));
+        }
+        if let Some(strategy) = &self.strategy {
+            overrides.push(("trading.strategy", strategy.clone()));
+        }
+        Ok(ConfigSource { file, overrides })
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Trade live (the default)
+    Run,
+    /// Replay historical candles through a strategy
+    Backtest(BacktestArgs),
+    /// Show non-zero account balances
+    Balances,
+    /// List or cancel open orders on the exchange
+    Orders(OrdersArgs),
+    /// Show the positions in the last state snapshot (--snapshot)
+    Positions,
+    /// Fill the candle store with history for later backtests and warm-up
+    DownloadData(DownloadArgs),
+    /// Check that the configuration loads and its strategy and risk settings are valid
+    ValidateConfig,
+    /// Stop the running bot from opening positions; exits are still managed
+    Pause(RemoteArgs),
+    /// Let the running bot open positions again, also leaving safe mode
+    Resume(RemoteArgs),
+    /// Write trades.csv and orders.csv from the trade store (TRADE_DB_URL or TRADE_DB_PATH)
+    Export(ExportArgs),
+    /// Store API credentials in the OS keyring or an encrypted file
+    Credentials(CredentialsArgs),
+    /// Print the control API's OpenAPI document
+    #[cfg(feature = "web")]
+    Openapi,
+    /// Walk through one day of the event journal, signal by signal, to see why
+    /// a trade happened or didn't
+    Replay(ReplayArgs),
+    /// Trade a recorded market data session (MARKET_RECORD_PATH) against the
+    /// mock exchange, without network access
+    #[cfg(feature = "mock")]
+    Simulate(SimulateArgs),
+    /// Check a recorded live session (SESSION_RECORD_PATH) against a replay:
+    /// the signals, and with the mock feature the trades the executor and the
+    /// backtester make from them
+    Parity(ParityArgs),
+    /// Time the websocket parsers on a recorded session, per message
+    BenchParse(BenchParseArgs),
+    /// Check the hash chain of an order audit log
+    VerifyAudit {
+        #[arg(long, env = "AUDIT_LOG_PATH")]
+        path: PathBuf,
+    },
+}
+
+/// Where the running bot's control API is
+#[derive(Debug, Args)]
+pub struct RemoteArgs {
+    #[arg(long, env = "API_URL", default_value = "http://127.0.0.1:8081")]
+    pub api_url: String,
+    #[arg(long, env = "API_TOKEN", hide_env_values = true)]
+    pub api_token: String,
+}
+
+#[derive(Debug, Args)]
+pub struct OrdersArgs {
+    #[command(subcommand)]
+    pub action: OrdersAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum OrdersAction {
+    List {
+        /// Every symbol when not given
+        #[arg(long)]
+        symbol: Option<String>,
+    },
+    Cancel {
+        #[arg(long)]
+        symbol: String,
+        /// Exchange order id, or the client order id
+        #[arg(long)]
+        order_id: String,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct ReplayArgs {
+    #[arg(long, env = "JOURNAL_PATH")]
+    pub journal: PathBuf,
+    /// Day to show (UTC)
+    #[arg(long)]
+    pub date: NaiveDate,
+    /// Every symbol when not given
+    #[arg(long)]
+    pub symbol: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct DownloadArgs {
+    #[arg(long, default_value = "BTCUSDT")]
+    pub symbol: Symbol,
+    /// First day included (UTC)
+    #[arg(long)]
+    pub from: NaiveDate,
+    /// First day excluded (UTC)
+    #[arg(long)]
+    pub to: NaiveDate,
+    #[arg(long, default_value = "1m")]
+    pub interval: Interval,
+    /// SQLite candle store; only candles missing from it are downloaded
+    #[arg(long, env = "CANDLE_DB_PATH")]
+    pub candle_db: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct CredentialsArgs {
+    #[command(subcommand)]
+    pub target: CredentialsTarget,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CredentialsTarget {
+    /// Save to the OS keyring; start with CREDENTIALS_SOURCE=keyring
+    Keyring,
+    /// Encrypt with a passphrase; start with CREDENTIALS_FILE pointing at it
+    Encrypt {
+        #[arg(long, default_value = "credentials.age")]
+        output: PathBuf,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct BacktestArgs {
+    #[arg(long, default_value = "rsi")]
+    pub strategy: String,
+    /// Several symbols, repeated or comma separated, trade one shared cash pool
+    #[arg(long = "symbol", value_delimiter = ',', default_value = "BTCUSDT")]
+    pub symbols: Vec<Symbol>,
+    /// First day included (UTC)
+    #[arg(long)]
+    pub from: NaiveDate,
+    /// First day excluded (UTC)
+    #[arg(long)]
+    pub to: NaiveDate,
+    #[arg(long, default_value = "1m")]
+    pub interval: Interval,
+    /// TOML file with [parameters] for the strategy and optional [risk] overrides
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    /// Candles from .csv files, Binance .zip dumps or a .json cache instead of the
+    /// API; several files are merged, e.g. a year of monthly dumps
+    #[arg(long, num_args = 1..)]
+    pub data: Vec<PathBuf>,
+    /// Save the loaded candles as a .json cache that --data reads back quickly
+    #[arg(long)]
+    pub save_cache: Option<PathBuf>,
+    /// SQLite candle store; only candles missing from it are downloaded
+    #[arg(long, env = "CANDLE_DB_PATH")]
+    pub candle_db: Option<PathBuf>,
+    #[arg(long, default_value_t = 1000.0)]
+    pub capital: f64,
+    /// How orders fill: default (Binance base fees, 1 bps slippage and spread) or
+    /// frictionless, to see the strategy's edge before costs
+    #[arg(long, default_value = "default")]
+    pub fill_model: FillModel,
+    /// Resolve stop-loss vs take-profit inside each bar using 1m candles
+    #[arg(long)]
+    pub magnify: bool,
+    /// 1m candles for --magnify from a file instead of the API
+    #[arg(long, requires = "magnify")]
+    pub magnifier_data: Option<PathBuf>,
+    /// Directory for equity.csv, trades.csv, backtest.json and report.html
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+    /// Search the strategy's parameter ranges instead of one run: grid, or evolve
+    /// when the grid is too large
+    #[arg(long)]
+    pub optimize: Option<OptimizeMethod>,
+    /// What --optimize ranks runs by: sharpe, sortino, calmar, return or profit-factor
+    #[arg(long, default_value = "sharpe")]
+    pub objective: Objective,
+    /// Parameter sets listed after --optimize
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+    /// Individuals per generation of --optimize evolve
+    #[arg(long, default_value_t = 40)]
+    pub population: usize,
+    /// Generations --optimize evolve runs at most
+    #[arg(long, default_value_t = 50)]
+    pub generations: usize,
+    /// Seed for --optimize evolve, to reproduce an earlier search; drawn at random
+    /// and printed otherwise
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// In-sample/out-of-sample window lengths in candles, e.g. 5000/1000: optimize
+    /// on each window (with --optimize, grid by default) and trade the next
+    #[arg(long)]
+    pub walk_forward: Option<WindowSizes>,
+    /// Grow each --walk-forward in-sample window from the first candle
+    #[arg(long, requires = "walk_forward")]
+    pub anchored: bool,
+    /// Grid-search a train segment, pick on validation and report once on the
+    /// rest: train/validation shares like 0.6/0.2, or the validation and test
+    /// start dates like 2024-03-01/2024-04-01
+    #[arg(long, conflicts_with = "walk_forward")]
+    pub holdout: Option<HoldoutSplit>,
+    /// Candles dropped after each --holdout boundary
+    #[arg(long, default_value_t = 0, requires = "holdout")]
+    pub embargo: i64,
+    /// Re-run with each signal-to-fill delay in milliseconds, e.g. 0,100,500, to
+    /// see how much of the result depends on filling at the signal's close
+    #[arg(long, value_delimiter = ',')]
+    pub latency_sweep: Vec<i64>,
+    /// Best train results tried on the --holdout validation segment
+    #[arg(long, default_value_t = 5, requires = "holdout")]
+    pub candidates: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HoldoutSplit {
+    Fractions {
+        train: f64,
+        validation: f64,
+    },
+    // First day (UTC) of the validation and of the test segment
+    Dates {
+        validation: NaiveDate,
+        test: NaiveDate,
+    },
+}
+
+impl FromStr for HoldoutSplit {
+    type Err = TradingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            TradingError::InvalidParameter(format!(
+                "Invalid holdout {:?}, expected <train>/<validation> shares or \
+                 <validation start>/<test start> dates",
+                s
+            ))
+        };
+        let (first, second) = s.split_once('/').ok_or_else(invalid)?;
+        let (first, second) = (first.trim(), second.trim());
+        if let (Ok(train), Ok(validation)) = (first.parse(), second.parse()) {
+            return Ok(HoldoutSplit::Fractions { train, validation });
+        }
+        Ok(HoldoutSplit::Dates {
+            validation: first.parse().map_err(|_| invalid())?,
+            test: second.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowSizes {
+    pub in_sample: usize,
+    pub out_of_sample: usize,
+}
+
+impl FromStr for WindowSizes {
+    type Err = TradingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            TradingError::InvalidParameter(format!(
+                "Invalid windows {:?}, expected <in-sample>/<out-of-sample> candles",
+                s
+            ))
+        };
+        let (in_sample, out_of_sample) = s.split_once('/').ok_or_else(invalid)?;
+        Ok(WindowSizes {
+            in_sample: in_sample.trim().parse().map_err(|_| invalid())?,
+            out_of_sample: out_of_sample.trim().parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptimizeMethod {
+    Grid,
+    Evolve,
+}
+
+impl FromStr for OptimizeMethod {
+    type Err = TradingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grid" => Ok(OptimizeMethod::Grid),
+            "evolve" => Ok(OptimizeMethod::Evolve),
+            _ => Err(TradingError::InvalidParameter(format!(
+                "Unknown optimizer {:?}, expected grid or evolve",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// First day included, in --timezone
+    #[arg(long)]
+    pub from: NaiveDate,
+    /// First day excluded, in --timezone
+    #[arg(long)]
+    pub to: NaiveDate,
+    /// IANA timezone for the day boundaries and timestamps, e.g. Asia/Bangkok
+    #[arg(long, default_value = "UTC")]
+    pub timezone: Tz,
+    #[arg(long, default_value = ".")]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct BacktestConfig {
+    parameters: BTreeMap<String, ParameterValue>,
+    risk: Option<RiskParameters>,
+}
+
+fn load_config(path: &Path) -> Result<BacktestConfig, Error> {
+    let contents = std::fs::read_to_string(path)?;
+    toml::from_str(&contents).map_err(|e| Error::ParseError(format!("{}: {}", path.display(), e)))
+}
+
+async fn fetch(
+    store: Option<&CandleStore>,
+    symbol: &str,
+    interval: Interval,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<PriceHistory, Error> {
+    match store {
+        Some(store) => data::cached_klines(store, symbol, interval, from, to).await,
+        None => data::download_klines(symbol, interval, from, to).await,
+    }
+}
+
+// Strategy `name` with the config's parameters applied
+fn configured_strategy(
+    name: &str,
+    parameters: &BTreeMap<String, ParameterValue>,
+) -> Result<Box<dyn Strategy>, TradingError> {
+    let mut strategy = strategy::create_strategy(name)?;
+    for (name, value) in parameters {
+        strategy.update_parameter(name, *value)?;
+    }
+    Ok(strategy)
+}
+
+pub async fn run_backtest(args: BacktestArgs) -> Result<(), Error> {
+    let config = match &args.config {
+        Some(path) => load_config(path)?,
+        None => BacktestConfig::default(),
+    };
+
+    let mut strategy = configured_strategy(&args.strategy, &config.parameters)
+        .map_err(|e| Error::ParseError(format!("{}", e)))?;
+    // Searches need fresh instances; the first one proved the config applies
+    let make_strategy =
+        || configured_strategy(&args.strategy, &config.parameters).expect("configured above");
+
+    let from = Utc.from_utc_datetime(&args.from.and_hms_opt(0, 0, 0).unwrap_or_default());
+    let to = Utc.from_utc_datetime(&args.to.and_hms_opt(0, 0, 0).unwrap_or_default());
+    let single_symbol_only = !args.data.is_empty()
+        || args.save_cache.is_some()
+        || args.magnifier_data.is_some()
+        || args.optimize.is_some()
+        || args.walk_forward.is_some()
+        || args.holdout.is_some()
+        || !args.latency_sweep.is_empty();
+    if args.symbols.len() > 1 && single_symbol_only {
+        return Err(Error::ParseError(
+            "--data, --save-cache, --magnifier-data, --optimize, --walk-forward, --holdout \
+             and --latency-sweep take one --symbol"
+                .to_string(),
+        ));
+    }
+    let candle_store = match &args.candle_db {
+        Some(path) => Some(CandleStore::open(path)?),
+        None => None,
+    };
+    let mut histories = Vec::with_capacity(args.symbols.len());
+    for symbol in &args.symbols {
+        let history = if args.data.is_empty() {
+            fetch(
+                candle_store.as_ref(),
+                symbol.as_str(),
+                args.interval,
+                from,
+                to,
+            )
+            .await?
+        } else {
+            data::load_paths(&args.data, symbol.as_str(), args.interval.millis())?.between(from, to)
+        };
+        if history.is_empty() {
+            return Err(Error::ParseError(format!(
+                "No {} candles between {} and {}",
+                symbol, args.from, args.to
+            )));
+        }
+        if let Some(path) = &args.save_cache {
+            data::save_cache(path, &history)?;
+            log::info!("Cached {} candles in {}", history.len(), path.display());
+        }
+        log::info!(
+            "Backtesting {} on {} {} candles",
+            strategy.name(),
+            history.len(),
+            symbol
+        );
+        histories.push(history);
+    }
+
+    let mut backtester = Backtester::new(args.capital, config.risk.unwrap_or_default());
+    backtester.fill_model = args.fill_model.clone();
+    if args.magnify {
+        for symbol in &args.symbols {
+            let lower = match &args.magnifier_data {
+                Some(path) => data::load_path(path, symbol.as_str(), Interval::Minutes1.millis())?
+                    .between(from, to),
+                None => {
+                    fetch(
+                        candle_store.as_ref(),
+                        symbol.as_str(),
+                        Interval::Minutes1,
+                        from,
+                        to,
+                    )
+                    .await?
+                }
+            };
+            log::info!("Magnifying {} bars with {} 1m candles", symbol, lower.len());
+            backtester.magnifier.insert(symbol.to_string(), lower);
+        }
+    }
+
+    if histories.len() > 1 {
+        let result = backtester.run_portfolio(&histories, make_strategy);
+        return report_result(&result, args.output.as_deref());
+    }
+    let history = &histories[0];
+
+    let evolution = EvolutionSettings {
+        population_size: args.population,
+        max_generations: args.generations,
+        seed: args.seed,
+        ..EvolutionSettings::default()
+    };
+    if let Some(windows) = args.walk_forward {
+        let settings = WalkForwardSettings {
+            in_sample_bars: windows.in_sample,
+            out_of_sample_bars: windows.out_of_sample,
+            anchored: args.anchored,
+            objective: args.objective,
+            search: match args.optimize {
+                Some(OptimizeMethod::Evolve) => Search::Evolution(evolution),
+                Some(OptimizeMethod::Grid) | None => Search::Grid,
+            },
+        };
+        let report = walk_forward::walk_forward(&backtester, history, make_strategy, &settings)
+            .map_err(|e| Error::ParseError(format!("{}", e)))?;
+        print_walk_forward(&report);
+        return Ok(());
+    }
+
+    if let Some(holdout) = args.holdout {
+        let embargo = chrono::Duration::milliseconds(args.embargo * args.interval.millis());
+        let report = match holdout {
+            HoldoutSplit::Fractions { train, validation } => {
+                split::split_by_fraction(history, train, validation, embargo)
+            }
+            HoldoutSplit::Dates { validation, test } => split::split_by_date(
+                history,
+                Utc.from_utc_datetime(&validation.and_hms_opt(0, 0, 0).unwrap_or_default()),
+                Utc.from_utc_datetime(&test.and_hms_opt(0, 0, 0).unwrap_or_default()),
+                embargo,
+            ),
+        }
+        .and_then(|segments| {
+            split::holdout(
+                &backtester,
+                &segments,
+                make_strategy,
+                args.objective,
+                args.candidates,
+            )
+        })
+        .map_err(|e| Error::ParseError(format!("{}", e)))?;
+        print_holdout(&report);
+        return Ok(());
+    }
+
+    if !args.latency_sweep.is_empty() {
+        let reports =
+            latency::latency_sensitivity(&backtester, history, make_strategy, &args.latency_sweep);
+        print_latency_sweep(&reports);
+        return Ok(());
+    }
+
+    if let Some(method) = args.optimize {
+        let results = match method {
+            OptimizeMethod::Grid => {
+                optimizer::grid_search(&backtester, history, make_strategy, args.objective)
+            }
+            OptimizeMethod::Evolve => optimizer::evolve(
+                &backtester,
+                history,
+                make_strategy,
+                args.objective,
+                &evolution,
+            ),
+        }
+        .map_err(|e| Error::ParseError(format!("{}", e)))?;
+        let best = results
+            .first()
+            .ok_or_else(|| Error::ParseError("No parameter set could be run".to_string()))?;
+        print_optimization(&results, args.top);
+        strategy = optimizer::configure(&make_strategy, &best.parameters)
+            .map_err(|e| Error::ParseError(format!("{}", e)))?;
+    }
+    let result = backtester.run(strategy.as_mut(), history);
+    report_result(&result, args.output.as_deref())
+}
+
+fn report_result(result: &BacktestResult, output: Option<&Path>) -> Result<(), Error> {
+    print_report(&result.report());
+    if let Some(dir) = output {
+        export::export_all(dir, result)?;
+        println!("Saved report to {}", dir.display());
+    }
+    Ok(())
+}
+
+// Midnight of `date` in `timezone`, in seconds
+fn day_start(date: NaiveDate, timezone: Tz) -> Result<i64, Error> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    timezone
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|time| time.timestamp())
+        .ok_or_else(|| Error::ParseError(format!("{} has no midnight in {}", date, timezone)))
+}
+
+pub async fn run_export(args: ExportArgs) -> Result<(), Error> {
+    let store = storage::from_env()
+        .await
+        .ok_or_else(|| Error::ParseError("Set TRADE_DB_URL or TRADE_DB_PATH".to_string()))?;
+    let from = day_start(args.from, args.timezone)?;
+    let to = day_start(args.to, args.timezone)?;
+    let trades = store
+        .trades_between(from, to)
+        .map_err(|e| Error::DatabaseError(format!("{}", e)))?;
+    let orders = store
+        .orders_between(from, to)
+        .map_err(|e| Error::DatabaseError(format!("{}", e)))?;
+
+    std::fs::create_dir_all(&args.output)?;
+    let trades_path = args.output.join("trades.csv");
+    let orders_path = args.output.join("orders.csv");
+    store_export::write_trades_csv(&trades_path, &trades, args.timezone)?;
+    store_export::write_orders_csv(&orders_path, &orders, args.timezone)?;
+    println!(
+        "Exported {} trades to {} and {} orders to {}",
+        trades.len(),
+        trades_path.display(),
+        orders.len(),
+        orders_path.display()
+    );
+    Ok(())
+}
+
+// Asks for the key and secret so they never appear in shell history
+pub fn run_credentials(args: CredentialsArgs) -> Result<(), TradingError> {
+    let credentials = ApiCredentials {
+        api_key: secrets::prompt("API key")?,
+        api_secret: secrets::prompt("API secret")?,
+    };
+    match args.target {
+        CredentialsTarget::Keyring => {
+            secrets::store_keyring(&credentials)?;
+            println!("Saved credentials to the OS keyring");
+        }
+        CredentialsTarget::Encrypt { output } => {
+            secrets::store_encrypted(&output, &credentials)?;
+            println!("Saved encrypted credentials to {}", output.display());
+        }
+    }
+    Ok(())
+}
+
+pub fn run_replay(args: ReplayArgs) -> Result<(), Error> {
+    let entries = journal::load_journal(&args.journal)?;
+    let lines = journal::timeline(&entries, args.date, args.symbol.as_deref());
+    if lines.is_empty() {
+        println!("Nothing journaled on {}", args.date);
+        return Ok(());
+    }
+    for line in lines {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "mock")]
+#[derive(Debug, Args)]
+pub struct SimulateArgs {
+    /// Recording written by live trading with MARKET_RECORD_PATH set
+    #[arg(long)]
+    pub recording: PathBuf,
+    #[arg(long, default_value = "rsi")]
+    pub strategy: String,
+    /// Starting quote balance of the mock exchange
+    #[arg(long, default_value_t = 1000.0)]
+    pub balance: f64,
+    /// Commission per fill as a fraction of its value
+    #[arg(long, default_value_t = 0.001)]
+    pub fee_rate: f64,
+    /// Delay added to every mock exchange call, in milliseconds
+    #[arg(long, default_value_t = 0)]
+    pub latency_ms: u64,
+}
+
+#[cfg(feature = "mock")]
+pub async fn run_simulate(args: SimulateArgs) -> Result<(), TradingError> {
+    let events = mock::load_fixture(&args.recording)?;
+    let Some(symbol) = events.iter().find_map(|event| match event {
+        FixtureEvent::Candle { symbol, .. } => Some(symbol.clone()),
+        FixtureEvent::Ticker { .. } => None,
+    }) else {
+        println!("No closed candles in {}", args.recording.display());
+        return Ok(());
+    };
+
+    let mut exchange = MockExchange::new(to_decimal(args.balance))
+        .with_fee_rate(to_decimal(args.fee_rate))
+        .with_latency(Duration::from_millis(args.latency_ms));
+    exchange.connect().await?;
+    let mut executor = TradeExecutor::new(exchange.clone(), RiskParameters::default());
+    let state = executor.state();
+    let mut engine = SignalEngine::new(&symbol, strategy::create_strategy(&args.strategy)?);
+    for event in events {
+        match event {
+            FixtureEvent::Candle {
+                symbol: candle_symbol,
+                candle,
+            } => {
+                // Candles are f64 like the backtester's, orders Decimal
+                let close = to_decimal(candle.close_price);
+                exchange.set_price(&candle_symbol, close);
+                executor.mark_price(&candle_symbol, close).await;
+                executor.check_brackets().await;
+                if candle_symbol != symbol {
+                    continue;
+                }
+                let signal = engine.on_close(candle.close_time.timestamp(), candle.close_price);
+                if signal.action != TradeAction::Hold {
+                    if let Err(e) = executor.handle_signal(&signal).await {
+                        log::warn!("Signal at {} not executed: {:?}", candle.close_time, e);
+                    }
+                }
+            }
+            FixtureEvent::Ticker { symbol, price, .. } => {
+                exchange.set_price(&symbol, price);
+                executor.mark_price(&symbol, price).await;
+                executor.check_brackets().await;
+            }
+        }
+    }
+
+    let state = state.read().await;
+    let report = state.pnl_report();
+    println!(
+        "{} on {}: {} orders, {} closed trades",
+        engine.strategy().name(),
+        symbol,
+        exchange.orders().len(),
+        state.trades.len()
+    );
+    println!(
+        "Realized {:.2}, unrealized {:.2}, fees {:.2}, balance {:.2}",
+        report.realized,
+        report.unrealized,
+        report.fees,
+        exchange.balance()
+    );
+    Ok(())
+}
+
+#[derive(Debug, Args)]
+pub struct ParityArgs {
+    /// Session written by live trading with SESSION_RECORD_PATH set
+    #[arg(long, env = "SESSION_RECORD_PATH")]
+    pub session: PathBuf,
+    /// Strategy the session ran, configured as it was live
+    #[arg(long, default_value = "rsi")]
+    pub strategy: String,
+    /// TOML file with [parameters] for the strategy and optional [risk] overrides
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    #[cfg(feature = "mock")]
+    #[arg(long, default_value_t = 1000.0)]
+    pub capital: f64,
+}
+
+pub async fn run_parity(args: ParityArgs) -> Result<(), TradingError> {
+    let config = match &args.config {
+        Some(path) => load_config(path)?,
+        None => BacktestConfig::default(),
+    };
+    let events = parity::load_session(&args.session)?;
+    if events.is_empty() {
+        println!("No candles in {}", args.session.display());
+        return Ok(());
+    }
+
+    let signals = parity::replay(
+        &events,
+        configured_strategy(&args.strategy, &config.parameters)?,
+    );
+    println!(
+        "Signals: {} candles, {} differ",
+        signals.events,
+        signals.mismatches.len()
+    );
+    for mismatch in &signals.mismatches {
+        println!(
+            "  {} live {:?}, replayed {:?}",
+            format_time(mismatch.close_time),
+            mismatch.live,
+            mismatch.replayed
+        );
+    }
+    // The executor side runs against the mock exchange
+    #[cfg(feature = "mock")]
+    run_trade_parity(&args, config, &events).await?;
+    Ok(())
+}
+
+#[cfg(feature = "mock")]
+async fn run_trade_parity(
+    args: &ParityArgs,
+    config: BacktestConfig,
+    events: &[parity::SessionEvent],
+) -> Result<(), TradingError> {
+    let trades = parity::compare_trades(
+        events,
+        configured_strategy(&args.strategy, &config.parameters)?,
+        config.risk.unwrap_or_default(),
+        args.capital,
+    )
+    .await?;
+    println!(
+        "Trades: {} live, {} backtest, {} differ",
+        trades.live.len(),
+        trades.backtest.len(),
+        trades.mismatches.len()
+    );
+    let describe = |trade: &Option<Trade>| match trade {
+        Some(trade) => format!(
+            "{:?} {} @ {} -> {}",
+            trade.side, trade.quantity, trade.entry_price, trade.exit_price
+        ),
+        None => "none".to_string(),
+    };
+    for mismatch in &trades.mismatches {
+        println!(
+            "  #{} live {}, backtest {}",
+            mismatch.index + 1,
+            describe(&mismatch.live),
+            describe(&mismatch.backtest)
+        );
+    }
+    Ok(())
+}
+
+#[derive(Debug, Args)]
+pub struct BenchParseArgs {
+    /// Recording written by live trading with MARKET_RECORD_PATH set
+    #[arg(long)]
+    pub recording: PathBuf,
+    /// Passes over the recording per parser
+    #[arg(long, default_value_t = 20)]
+    pub rounds: u32,
+}
+
+// Mean time per message over every pass, and the messages that failed per pass
+fn time_parser<T, E>(
+    messages: &[&str],
+    rounds: u32,
+    parse: impl Fn(&str) -> Result<T, E>,
+) -> (Duration, usize) {
+    let mut failed = 0;
+    let started = Instant::now();
+    for _ in 0..rounds {
+        for message in messages {
+            if std::hint::black_box(parse(message)).is_err() {
+                failed += 1;
+            }
+        }
+    }
+    let parsed = (messages.len() as u32 * rounds).max(1);
+    (started.elapsed() / parsed, failed / rounds.max(1) as usize)
+}
+
+// The full DTOs (used by replays) next to the parsers the live streams use
+pub fn run_bench_parse(args: BenchParseArgs) -> Result<(), TradingError> {
+    let recording = recorder::load_recording(&args.recording)?;
+    let messages = |kind: StreamKind| -> Vec<&str> {
+        recording
+            .iter()
+            .filter(|message| message.kind == kind)
+            .map(|message| message.raw.as_str())
+            .collect()
+    };
+    let klines = messages(StreamKind::Kline);
+    let tickers = messages(StreamKind::Ticker);
+    let depths = messages(StreamKind::Depth);
+    let rows = [
+        (
+            "kline full",
+            klines.len(),
+            time_parser(&klines, args.rounds, dto::parse_websocket_message),
+        ),
+        (
+            "kline live",
+            klines.len(),
+            time_parser(&klines, args.rounds, dto::parse_kline_update),
+        ),
+        (
+            "ticker full",
+            tickers.len(),
+            time_parser(&tickers, args.rounds, dto::parse_websocket_message_ticker),
+        ),
+        (
+            "ticker live",
+            tickers.len(),
+            time_parser(&tickers, args.rounds, dto::parse_ticker_update),
+        ),
+        (
+            "depth",
+            depths.len(),
+            time_parser(&depths, args.rounds, dto::parse_websocket_message_depth),
+        ),
+    ];
+    println!(
+        "{:<12} {:>9} {:>10} {:>7}",
+        "Parser", "Messages", "ns/msg", "Failed"
+    );
+    for (name, count, (per_message, failed)) in rows {
+        if count == 0 {
+            continue;
+        }
+        println!(
+            "{:<12} {:>9} {:>10} {:>7}",
+            name,
+            count,
+            per_message.as_nanos(),
+            failed
+        );
+    }
+    Ok(())
+}
+
+pub fn run_verify_audit(path: &Path) -> Result<(), TradingError> {
+    let entries = audit::verify(path)?;
+    println!("{}: {} entries, chain intact", path.display(), entries);
+    Ok(())
+}
+
+type AccountClient = BinanceHttpClient<HttpsConnector<HttpConnector>>;
+
+fn account_client() -> Result<AccountClient, TradingError> {
+    let api = secrets::load_credentials()?;
+    Ok(BinanceHttpClient::default()
+        .credentials(Credentials::from_hmac(api.api_key, api.api_secret)))
+}
+
+async fn send<R: Into<Request>>(
+    client: &AccountClient,
+    request: R,
+) -> Result<String, TradingError> {
+    client
+        .send(request)
+        .await
+        .map_err(|e| TradingError::OrderError(format!("{:?}", e)))?
+        .into_body_str()
+        .await
+        .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))
+}
+
+pub async fn run_balances() -> Result<(), TradingError> {
+    let client = account_client()?;
+    let body = send(&client, trade::account()).await?;
+    let account: dto::Account = serde_json::from_str(&body)?;
+    println!("{:<10} {:>18} {:>18}", "Asset", "Free", "Locked");
+    for balance in account.balances {
+        let free: f64 = balance.free.parse().unwrap_or_default();
+        let locked: f64 = balance.locked.parse().unwrap_or_default();
+        if free == 0.0 && locked == 0.0 {
+            continue;
+        }
+        println!(
+            "{:<10} {:>18} {:>18}",
+            balance.asset, balance.free, balance.locked
+        );
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenOrder {
+    symbol: String,
+    order_id: u64,
+    side: String,
+    #[serde(rename = "type")]
+    order_type: String,
+    price: String,
+    orig_qty: String,
+    executed_qty: String,
+    // Milliseconds
+    time: i64,
+}
+
+pub async fn run_orders(args: OrdersArgs) -> Result<(), TradingError> {
+    let client = account_client()?;
+    match args.action {
+        OrdersAction::List { symbol } => {
+            let mut request = trade::open_orders();
+            if let Some(symbol) = &symbol {
+                request = request.symbol(symbol);
+            }
+            let body = send(&client, request).await?;
+            let orders: Vec<OpenOrder> = serde_json::from_str(&body)?;
+            if orders.is_empty() {
+                println!("No open orders");
+            }
+            for order in orders {
+                let placed = Utc
+                    .timestamp_millis_opt(order.time)
+                    .single()
+                    .map(|time| time.to_rfc3339())
+                    .unwrap_or_default();
+                println!(
+                    "{} {} {} {} {} @ {} (filled {}) placed {}",
+                    order.order_id,
+                    order.symbol,
+                    order.side,
+                    order.order_type,
+                    order.orig_qty,
+                    order.price,
+                    order.executed_qty,
+                    placed
+                );
+            }
+        }
+        OrdersAction::Cancel { symbol, order_id } => {
+            // Binance ids are numeric; anything else is treated as a client order id
+            let request = trade::cancel_order(&symbol);
+            let request = match order_id.parse::<u64>() {
+                Ok(id) => request.order_id(id),
+                Err(_) => request.orig_client_order_id(&order_id),
+            };
+            send(&client, request).await?;
+            println!("Cancelled {} on {}", order_id, symbol);
+        }
+    }
+    Ok(())
+}
+
+pub fn run_positions(snapshot_path: &str) -> Result<(), TradingError> {
+    let snapshot = snapshot::load(snapshot_path)?;
+    println!(
+        "Snapshot of {} saved at {}",
+        snapshot.symbol, snapshot.saved_at
+    );
+    if snapshot.executor.positions.is_empty() {
+        println!("No open positions");
+    }
+    for position in &snapshot.executor.positions {
+        println!(
+            "{} {} @ {} ({}), opened {}",
+            position.symbol,
+            position.quantity,
+            position.entry_price,
+            position.strategy,
+            position.opened_at
+        );
+    }
+    for order in &snapshot.executor.working_orders {
+        println!(
+            "Working order {} {:?} {} {} @ {}",
+            order.order_id, order.side, order.symbol, order.quantity, order.price
+        );
+    }
+    Ok(())
+}
+
+pub async fn run_download_data(args: DownloadArgs) -> Result<(), Error> {
+    let from = Utc.from_utc_datetime(&args.from.and_hms_opt(0, 0, 0).unwrap_or_default());
+    let to = Utc.from_utc_datetime(&args.to.and_hms_opt(0, 0, 0).unwrap_or_default());
+    let store = CandleStore::open(&args.candle_db)?;
+    let history =
+        data::cached_klines(&store, args.symbol.as_str(), args.interval, from, to).await?;
+    println!(
+        "{} has {} {} {} candles between {} and {}",
+        args.candle_db.display(),
+        history.len(),
+        args.symbol,
+        args.interval,
+        args.from,
+        args.to
+    );
+    Ok(())
+}
+
+// `command` is the control API path, e.g. "pause"
+pub async fn run_remote(args: RemoteArgs, command: &str) -> Result<(), TradingError> {
+    let url = format!("{}/{}", args.api_url.trim_end_matches('/'), command);
+    notify::post_json_authorized(
+        &notify::https_client(),
+        &url,
+        Some(&args.api_token),
+        &serde_json::json!({}),
+    )
+    .await?;
+    println!("Sent {} to {}", command, args.api_url);
+    Ok(())
+}
+
+pub fn run_validate_config(profile: &Profile) -> Result<(), TradingError> {
+    profile.validate()?;
+    let strategy = profile.build_strategy()?;
+    if profile.trading.execution.order_ttl_secs <= 0 {
+        return Err(TradingError::InvalidParameter(
+            "order_ttl_secs must be positive".to_string(),
+        ));
+    }
+    if let Some(trailing) = &profile.trading.execution.trailing_stop {
+        if trailing.activation_pct < Decimal::ZERO || trailing.callback_rate <= Decimal::ZERO {
+            return Err(TradingError::InvalidParameter(
+                "trailing_stop needs a non-negative activation_pct and a positive callback_rate"
+                    .to_string(),
+            ));
+        }
+    }
+    println!(
+        "{} {} with {} ({} parameters), {} symbol sections, {} alert rules: valid",
+        profile.trading.symbol,
+        profile.trading.interval,
+        strategy.name(),
+        strategy.parameters().len(),
+        profile.trading.symbols.len(),
+        profile.alerts.len()
+    );
+    Ok(())
+}
+
+fn print_optimization(results: &[OptimizationResult], top: usize) {
+    println!(
+        "{:>4} {:>10} {:>10} {:>8}  Parameters",
+        "Rank", "Score", "Return", "Trades"
+    );
+    for (rank, result) in results.iter().take(top).enumerate() {
+        let parameters: Vec<String> = result
+            .parameters
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value.as_f64()))
+            .collect();
+        println!(
+            "{:>4} {:>10.4} {:>9.2}% {:>8}  {}",
+            rank + 1,
+            result.score,
+            result.report.total_return_pct,
+            result.report.total_trades,
+            parameters.join(" ")
+        );
+    }
+    if let Some(seed) = results.first().and_then(|result| result.report.seed) {
+        println!("Seed {}", seed);
+    }
+    println!("{} parameter sets run; the best one:", results.len());
+}
+
+fn print_walk_forward(report: &WalkForwardReport) {
+    println!(
+        "{:<21} {:<21} {:>10} {:>10}  Parameters",
+        "In sample from", "Out of sample from", "In", "Out"
+    );
+    for window in &report.windows {
+        let parameters: Vec<String> = window
+            .parameters
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value.as_f64()))
+            .collect();
+        println!(
+            "{:<21} {:<21} {:>10.4} {:>10.4}  {}",
+            format_time(window.in_sample_start),
+            format_time(window.out_of_sample_start),
+            window.in_sample_score,
+            window.out_of_sample_score,
+            parameters.join(" ")
+        );
+    }
+    println!("Walk-forward efficiency {:.2}", report.efficiency);
+    println!("Out-of-sample windows combined:");
+    print_report(&report.combined);
+}
+
+fn print_latency_sweep(reports: &[(i64, backtest::BacktestReport)]) {
+    println!(
+        "{:>10} {:>10} {:>8} {:>10} {:>8}",
+        "Latency", "Return", "Sharpe", "Drawdown", "Trades"
+    );
+    for (latency_ms, report) in reports {
+        println!(
+            "{:>8}ms {:>9.2}% {:>8.2} {:>9.2}% {:>8}",
+            latency_ms,
+            report.total_return_pct,
+            report.sharpe_ratio,
+            report.max_drawdown_pct,
+            report.total_trades
+        );
+    }
+}
+
+fn print_holdout(report: &HoldoutReport) {
+    let parameters: Vec<String> = report
+        .parameters
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value.as_f64()))
+        .collect();
+    println!("Parameters         {}", parameters.join(" "));
+    println!("  Train score      {:>14.4}", report.train_score);
+    println!("  Validation score {:>14.4}", report.validation_score);
+    println!("Test segment:");
+    print_report(&report.test);
+}
+
+fn format_time(seconds: i64) -> String {
+    Utc.timestamp_opt(seconds, 0)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+fn print_report(report: &backtest::BacktestReport) {
+    println!("{} {}", report.symbol, report.strategy);
+    println!("  Final equity       {:>12.2}", report.final_equity);
+    println!("  Total return       {:>11.2}%", report.total_return_pct);
+    println!(
+        "  Annualized return  {:>11.2}%",
+        report.annualized_return_pct
+    );
+    println!(
+        "  Buy and hold       {:>11.2}%",
+        report.buy_and_hold_return_pct
+    );
+    println!("  Sharpe             {:>12.2}", report.sharpe_ratio);
+    println!("  Sortino            {:>12.2}", report.sortino_ratio);
+    println!("  Max drawdown       {:>11.2}%", report.max_drawdown_pct);
+    println!("  Profit factor      {:>12.2}", report.profit_factor);
+    println!("  Win rate           {:>11.1}%", report.win_rate_pct);
+    println!("  Trades             {:>12}", report.total_trades);
+    println!("  Fees               {:>12.2}", report.total_fees);
+    println!(
+        "  Alpha / beta       {:>6.2}% / {:.2}",
+        report.alpha_pct, report.beta
+    );
+}
@@ -0,0 +1,343 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use figment::providers::{Env, Format, Serialized, Toml, Yaml};
+use figment::Figment;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::alerts::AlertRule;
+use crate::domain::*;
+use crate::exchange;
+use crate::executor::ExecutionSettings;
+use crate::logging::LogSettings;
+use crate::strategy::{self, ParameterValue, Strategy};
+
+/// What the live bot trades and how it enters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TradingConfig {
+    // "binance", "paper" for simulated fills on Binance market data, or
+    // "binance-futures" / "kraken" / "coinbase" when built with their feature
+    pub exchange: String,
+    // Binance spot or futures testnet endpoints, for REST and streams alike
+    pub testnet: bool,
+    pub symbol: Symbol,
+    // Candle interval in Binance notation, e.g. "1m" or "4h"
+    pub interval: Interval,
+    // One of strategy::STRATEGY_NAMES
+    pub strategy: String,
+    pub execution: ExecutionSettings,
+    // Keyed by symbol, e.g. [trading.symbols.ETHUSDT]
+    pub symbols: BTreeMap<Symbol, SymbolConfig>,
+    // Only read with exchange = "binance-futures"
+    pub futures: FuturesSettings,
+    // Only read with exchange = "paper"
+    pub paper: PaperSettings,
+    // Further exchanges to trade, as "exchange:SYMBOL" entries, e.g.
+    // "kraken:XBTUSD". Each symbol gets its own stream and analysis worker, with
+    // its [trading.symbols] section applied; orders for a symbol prefixed with
+    // the exchange go there
+    pub venues: Vec<String>,
+}
+
+/// Virtual account and fill costs of paper trading
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaperSettings {
+    // Starting quote balance
+    pub balance: Decimal,
+    pub maker_fee_pct: f64,
+    pub taker_fee_pct: f64,
+    pub slippage_bps: f64,
+    // Assumed when the ticker carries no bid/ask
+    pub spread_bps: f64,
+    // Let sells borrow base asset that isn't held, as a margin account would
+    pub margin: bool,
+}
+
+impl Default for PaperSettings {
+    fn default() -> Self {
+        PaperSettings {
+            balance: Decimal::from(10_000),
+            maker_fee_pct: 0.1,
+            taker_fee_pct: 0.1,
+            slippage_bps: 1.0,
+            spread_bps: 1.0,
+            margin: false,
+        }
+    }
+}
+
+/// How the perpetual is margined; applied when the futures client connects
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FuturesSettings {
+    pub leverage: u32,
+    pub margin: MarginType,
+    // Separate long and short positions instead of one netted position
+    pub hedge_mode: bool,
+}
+
+impl Default for FuturesSettings {
+    fn default() -> Self {
+        FuturesSettings {
+            leverage: 1,
+            margin: MarginType::Isolated,
+            hedge_mode: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarginType {
+    Isolated,
+    Cross,
+}
+
+/// Settings of one symbol that differ from the global ones; unset fields keep them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SymbolConfig {
+    pub interval: Option<Interval>,
+    pub strategy: Option<String>,
+    // Merged over the global parameter overrides, e.g. RSI thresholds
+    pub parameters: BTreeMap<String, ParameterValue>,
+    pub max_position_size: Option<Decimal>,
+    pub stop_loss_pct: Option<Decimal>,
+    pub take_profit_pct: Option<Decimal>,
+}
+
+impl Default for TradingConfig {
+    fn default() -> Self {
+        TradingConfig {
+            exchange: "binance".to_string(),
+            testnet: false,
+            symbol: "BTCUSDT".parse().expect("valid symbol"),
+            interval: Interval::Minutes1,
+            strategy: "rsi".to_string(),
+            execution: ExecutionSettings::default(),
+            symbols: BTreeMap::new(),
+            futures: FuturesSettings::default(),
+            paper: PaperSettings::default(),
+            venues: Vec::new(),
+        }
+    }
+}
+
+/// The effective configuration, saved to and loaded from a named TOML profile so
+/// setups can be shared and kept under version control
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub trading: TradingConfig,
+    pub risk: RiskParameters,
+    // Strategy parameter overrides; unset ones keep the strategy's defaults
+    pub parameters: BTreeMap<String, ParameterValue>,
+    // Evaluated on live data and sent to the configured notifiers
+    pub alerts: Vec<AlertRule>,
+    pub logging: LogSettings,
+    // Variables for the subsystems configured from the environment, e.g.
+    // TELEGRAM_BOT_TOKEN or SLACK_WEBHOOK_URL; variables actually set win
+    pub environment: BTreeMap<String, String>,
+}
+
+/// Where the configuration is loaded from, kept to load it again on changes
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSource {
+    pub file: Option<PathBuf>,
+    // Command line values as (dotted key, value) pairs
+    pub overrides: Vec<(&'static str, String)>,
+}
+
+impl ConfigSource {
+    pub fn load(&self) -> Result<Profile, TradingError> {
+        Profile::load(self.file.as_deref(), &self.overrides)
+    }
+}
+
+// Every environment variable with this prefix overrides a setting, nested with
+// "__", e.g. AUTO_TRADE_RISK__MAX_POSITION_SIZE=50
+const ENV_PREFIX: &str = "AUTO_TRADE_";
+
+// Names end up in file paths, so keep them to something harmless
+pub fn profile_path(dir: &Path, name: &str) -> Result<PathBuf, TradingError> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(TradingError::InvalidParameter(format!(
+            "Invalid profile name {:?}: use letters, digits, '-' and '_'",
+            name
+        )));
+    }
+    Ok(dir.join(format!("{}.toml", name)))
+}
+
+impl Profile {
+    // Defaults, then the TOML or YAML `file`, then AUTO_TRADE_* variables, then
+    // command line `overrides` as (dotted key, value) pairs
+    pub fn load(file: Option<&Path>, overrides: &[(&str, String)]) -> Result<Self, TradingError> {
+        let mut figment = Figment::from(Serialized::defaults(Profile::default()));
+        if let Some(path) = file {
+            // Missing files would otherwise count as empty
+            if !path.exists() {
+                return Err(TradingError::DataError(format!(
+                    "{}: no such file",
+                    path.display()
+                )));
+            }
+            figment = match path.extension().and_then(|e| e.to_str()) {
+                Some("yaml" | "yml") => figment.merge(Yaml::file(path)),
+                _ => figment.merge(Toml::file(path)),
+            };
+        }
+        figment = figment.merge(Env::prefixed(ENV_PREFIX).split("__"));
+        for (key, value) in overrides {
+            figment = figment.merge(Serialized::default(*key, value));
+        }
+        let profile: Profile = figment
+            .extract()
+            .map_err(|e| TradingError::DataError(format!("{}", e)))?;
+        let mut exchanges = vec![profile.trading.exchange.clone()];
+        for venue in &profile.trading.venues {
+            let (exchange, _) = exchange::router::parse_venue(venue)?;
+            if exchanges.contains(&exchange) {
+                return Err(TradingError::InvalidParameter(format!(
+                    "Exchange {} is already a venue",
+                    exchange
+                )));
+            }
+            exchanges.push(exchange);
+        }
+        for exchange in &exchanges {
+            if !exchange::supported(exchange) {
+                return Err(TradingError::InvalidParameter(format!(
+                    "Unsupported exchange {}, expected binance, paper, binance-futures, kraken or coinbase",
+                    exchange
+                )));
+            }
+            let has_testnet = matches!(exchange.as_str(), "binance" | "paper" | "binance-futures");
+            if profile.trading.testnet && !has_testnet {
+                return Err(TradingError::InvalidParameter(format!(
+                    "No testnet support for {}",
+                    exchange
+                )));
+            }
+        }
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    // Fail at startup rather than on the first candle, for every symbol section
+    // as well as the one traded now
+    pub fn validate(&self) -> Result<(), TradingError> {
+        // Symbols and intervals were already checked while deserializing
+        let mut symbols: Vec<&Symbol> = self.trading.symbols.keys().collect();
+        symbols.push(&self.trading.symbol);
+        for symbol in symbols {
+            let effective = self.for_symbol(symbol);
+            let checked = effective
+                .build_strategy()
+                .and_then(|_| effective.risk.validate());
+            checked.map_err(|e| TradingError::InvalidParameter(format!("{}: {:?}", symbol, e)))?;
+        }
+        if let Some(algo) = &self.trading.execution.algo {
+            algo.build()?;
+        }
+        if let Some(iceberg) = &self.trading.execution.iceberg {
+            iceberg.validate()?;
+        }
+        let exit_fraction = self.trading.execution.exit_fraction;
+        if exit_fraction <= Decimal::ZERO || exit_fraction > Decimal::ONE {
+            return Err(TradingError::InvalidParameter(
+                "exit_fraction must be in (0, 1]".to_string(),
+            ));
+        }
+        if let Some(margin) = &self.trading.execution.margin {
+            if margin.daily_interest_pct < Decimal::ZERO {
+                return Err(TradingError::InvalidParameter(
+                    "Margin daily_interest_pct can't be negative".to_string(),
+                ));
+            }
+        }
+        if self.trading.execution.post_only
+            && self.trading.execution.time_in_force != TimeInForce::Gtc
+        {
+            return Err(TradingError::InvalidParameter(
+                "post_only orders rest on the book, so time_in_force must be GTC".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // The global settings with `symbol`'s section merged over them
+    pub fn for_symbol(&self, symbol: &Symbol) -> Profile {
+        let mut profile = self.clone();
+        profile.trading.symbol = symbol.clone();
+        let Some(section) = self.trading.symbols.get(symbol) else {
+            return profile;
+        };
+        if let Some(interval) = section.interval {
+            profile.trading.interval = interval;
+        }
+        if let Some(strategy) = &section.strategy {
+            // Global parameter overrides belong to the global strategy
+            if *strategy != self.trading.strategy {
+                profile.parameters.clear();
+            }
+            profile.trading.strategy = strategy.clone();
+        }
+        profile.parameters.extend(section.parameters.clone());
+        if let Some(size) = section.max_position_size {
+            profile.risk.max_position_size = size;
+        }
+        if let Some(pct) = section.stop_loss_pct {
+            profile.risk.stop_loss_pct = pct;
+        }
+        if let Some(pct) = section.take_profit_pct {
+            profile.risk.take_profit_pct = pct;
+        }
+        profile
+    }
+
+    // Export `environment` for the subsystems that read it; call before any of
+    // them starts
+    pub fn apply_environment(&self) {
+        for (name, value) in &self.environment {
+            if dotenv::var(name).is_err() {
+                std::env::set_var(name, value);
+            }
+        }
+    }
+
+    pub fn save(&self, dir: &Path, name: &str) -> Result<PathBuf, TradingError> {
+        let path = profile_path(dir, name)?;
+        let contents = toml::to_string_pretty(self)
+            .map_err(|e| TradingError::DataError(format!("{:?}", e)))?;
+        std::fs::create_dir_all(dir)
+            .and_then(|_| std::fs::write(&path, contents))
+            .map_err(|e| TradingError::DataError(format!("{}: {:?}", path.display(), e)))?;
+        Ok(path)
+    }
+
+    pub fn build_strategy(&self) -> Result<Box<dyn Strategy>, TradingError> {
+        let mut strategy = strategy::create_strategy(&self.trading.strategy)?;
+        for (name, value) in &self.parameters {
+            strategy.update_parameter(name, *value)?;
+        }
+        Ok(strategy)
+    }
+
+    // Fill in every strategy parameter, so a saved profile documents the whole setup
+    pub fn with_strategy_parameters(mut self, strategy: &dyn Strategy) -> Self {
+        self.parameters = strategy
+            .parameters()
+            .into_iter()
+            .map(|p| (p.name, p.value))
+            .collect();
+        self
+    }
+}
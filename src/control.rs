@@ -0,0 +1,255 @@
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::audit::AuditActor;
+use crate::domain::*;
+use crate::executor::TradeExecutor;
+use crate::notify::{self, Notification, NotifySender};
+use crate::strategy::{ParameterValue, StrategyParameter};
+
+/// Operator commands, applied by the task that owns the executor
+#[derive(Debug, Clone)]
+pub enum ControlCommand {
+    // Stop opening positions; open ones keep their brackets
+    Pause,
+    Resume,
+    // Pause, cancel working orders and close every position at market
+    Kill,
+    // Cancel working orders, close positions too if `flatten`, then stop the
+    // signal task. Leaves `paused` alone so the final snapshot keeps it.
+    Shutdown {
+        flatten: bool,
+    },
+    // Replace the risk limits used for new entries
+    UpdateRisk(RiskParameters),
+    // Buy or sell by hand, at `price` or the latest known one; a limit entry
+    // rests at `price`
+    ManualOrder {
+        symbol: String,
+        action: TradeAction,
+        price: Option<Decimal>,
+        limit: bool,
+    },
+    // Ask the exchange for the account balance
+    Balance,
+    // Allow or stop entries from one strategy
+    SetStrategyEnabled {
+        name: String,
+        enabled: bool,
+    },
+    // Refuse entries like Pause until a Resume; sent by the safe mode monitor
+    EnterSafeMode {
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlReply {
+    Done,
+    Balance(Decimal),
+}
+
+#[derive(Debug)]
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    // Where the command came from, for the audit log, e.g. "telegram:12345"
+    pub requested_by: String,
+    // Answered once the command was applied, for callers that wait on it
+    pub reply: Option<oneshot::Sender<Result<ControlReply, TradingError>>>,
+}
+
+pub type ControlSender = mpsc::UnboundedSender<ControlRequest>;
+
+#[derive(Debug)]
+pub enum CommandError {
+    // The signal task is gone, e.g. during shutdown
+    NotRunning,
+    // Still being applied when the caller stopped waiting
+    TimedOut,
+    Failed(TradingError),
+}
+
+// Hand a command to the signal task and wait until it was applied
+pub async fn send_and_wait(
+    control: &ControlSender,
+    command: ControlCommand,
+    requested_by: &str,
+    timeout: Duration,
+) -> Result<ControlReply, CommandError> {
+    let (reply_tx, reply_rx) = oneshot::channel();
+    let request = ControlRequest {
+        command,
+        requested_by: requested_by.to_string(),
+        reply: Some(reply_tx),
+    };
+    control
+        .send(request)
+        .map_err(|_| CommandError::NotRunning)?;
+    match tokio::time::timeout(timeout, reply_rx).await {
+        Ok(Ok(result)) => result.map_err(CommandError::Failed),
+        Ok(Err(_)) => Err(CommandError::NotRunning),
+        Err(_) => Err(CommandError::TimedOut),
+    }
+}
+
+/// Live strategy tuning, applied by the analysis task that owns the strategy
+#[derive(Debug, Clone)]
+pub enum TuningCommand {
+    List,
+    // Checked against the parameter's declared range
+    Update { name: String, value: ParameterValue },
+}
+
+#[derive(Debug)]
+pub struct TuningRequest {
+    pub command: TuningCommand,
+    // Answered with every parameter after the command was applied
+    pub reply: oneshot::Sender<Result<Vec<StrategyParameter>, TradingError>>,
+}
+
+pub type TuningSender = mpsc::UnboundedSender<TuningRequest>;
+
+pub async fn tune(
+    tuning: &TuningSender,
+    command: TuningCommand,
+    timeout: Duration,
+) -> Result<Vec<StrategyParameter>, CommandError> {
+    let (reply, reply_rx) = oneshot::channel();
+    tuning
+        .send(TuningRequest { command, reply })
+        .map_err(|_| CommandError::NotRunning)?;
+    match tokio::time::timeout(timeout, reply_rx).await {
+        Ok(Ok(result)) => result.map_err(CommandError::Failed),
+        Ok(Err(_)) => Err(CommandError::NotRunning),
+        Err(_) => Err(CommandError::TimedOut),
+    }
+}
+
+pub async fn apply<E: ExchangeClient>(
+    executor: &mut TradeExecutor<E>,
+    request: ControlRequest,
+    notifiers: &[NotifySender],
+) {
+    log::warn!(
+        "Control command {:?} from {}",
+        request.command,
+        request.requested_by
+    );
+    let result = match request.command {
+        ControlCommand::Pause => {
+            executor.state().write().await.paused = true;
+            Ok(ControlReply::Done)
+        }
+        ControlCommand::Resume => {
+            let state = executor.state();
+            let mut state = state.write().await;
+            state.paused = false;
+            if let Some(reason) = state.safe_mode.take() {
+                log::warn!("Leaving safe mode entered for: {}", reason);
+            }
+            Ok(ControlReply::Done)
+        }
+        ControlCommand::Kill => {
+            executor.state().write().await.paused = true;
+            let actor = AuditActor::KillSwitch {
+                requested_by: request.requested_by,
+            };
+            let (reason, result) = match executor.close_all(actor).await {
+                Ok(()) => (
+                    "Kill command: orders cancelled, positions closed".to_string(),
+                    Ok(ControlReply::Done),
+                ),
+                Err(e) => {
+                    log::error!("Kill left positions open: {}", e);
+                    (
+                        format!("Kill command: some positions failed to close: {}", e),
+                        Err(e),
+                    )
+                }
+            };
+            notify::broadcast(notifiers, Notification::Halted { reason });
+            result
+        }
+        ControlCommand::Shutdown { flatten } => {
+            if flatten {
+                if let Err(e) = executor.close_all(AuditActor::Shutdown).await {
+                    log::error!("Shutting down with positions still open: {}", e);
+                }
+            } else {
+                executor.cancel_working_orders(AuditActor::Shutdown).await;
+            }
+            Ok(ControlReply::Done)
+        }
+        ControlCommand::UpdateRisk(risk) => {
+            executor.set_risk(risk).await;
+            Ok(ControlReply::Done)
+        }
+        ControlCommand::ManualOrder {
+            symbol,
+            action,
+            price,
+            limit,
+        } => executor
+            .manual_order(&symbol, action, price, limit, request.requested_by)
+            .await
+            .map(|()| ControlReply::Done),
+        ControlCommand::Balance => executor.balance().await.map(ControlReply::Balance),
+        ControlCommand::SetStrategyEnabled { name, enabled } => executor
+            .state()
+            .write()
+            .await
+            .set_strategy_enabled(&name, enabled)
+            .map(|()| ControlReply::Done),
+        ControlCommand::EnterSafeMode { reason } => {
+            let state = executor.state();
+            let mut state = state.write().await;
+            if state.safe_mode.is_none() {
+                log::error!("Entering safe mode: {}", reason);
+                state.safe_mode = Some(reason.clone());
+                notify::broadcast(notifiers, Notification::SafeMode { reason });
+            }
+            Ok(ControlReply::Done)
+        }
+    };
+    if let Some(reply) = request.reply {
+        // The caller may have given up waiting
+        let _ = reply.send(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockExchange;
+    use rust_decimal_macros::dec;
+
+    fn request(command: ControlCommand) -> ControlRequest {
+        ControlRequest {
+            command,
+            requested_by: "test".to_string(),
+            reply: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn safe_mode_is_entered_through_the_mailbox_and_left_on_resume() {
+        let mut executor =
+            TradeExecutor::new(MockExchange::new(dec!(1000)), RiskParameters::default());
+        let safe_mode = |reason: &str| {
+            request(ControlCommand::EnterSafeMode {
+                reason: reason.to_string(),
+            })
+        };
+        apply(&mut executor, safe_mode("first"), &[]).await;
+        apply(&mut executor, safe_mode("second"), &[]).await;
+        assert_eq!(
+            executor.state().read().await.safe_mode.as_deref(),
+            Some("first")
+        );
+
+        apply(&mut executor, request(ControlCommand::Resume), &[]).await;
+        assert_eq!(executor.state().read().await.safe_mode, None);
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::ws::{Message, WebSocket};
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::domain::from_decimal;
+use crate::events::{BusMessage, MarketEvent};
+use crate::executor::SharedExecutorState;
+use crate::push::PushEvent;
+use crate::storage::PositionEvent;
+
+// What a newly opened page gets to start from
+const CANDLE_HISTORY: usize = 500;
+const MARKER_HISTORY: usize = 200;
+// A day of one-minute samples
+const EQUITY_HISTORY: usize = 1440;
+const EQUITY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// One candle as the chart wants it; times are seconds
+#[derive(Debug, Clone, Serialize)]
+pub struct Candle {
+    pub time: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Marker {
+    // Seconds
+    pub time: i64,
+    pub symbol: String,
+    pub event: PositionEvent,
+    pub price: f64,
+    pub quantity: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EquityPoint {
+    // Seconds
+    pub time: i64,
+    // Realized plus unrealized PnL, net of fees
+    pub equity: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DashboardEvent {
+    // Sent first on every connection
+    Snapshot {
+        candles: Vec<Candle>,
+        markers: Vec<Marker>,
+        equity: Vec<EquityPoint>,
+    },
+    // New or still-forming candle; replaces one with the same time
+    Candle {
+        candle: Candle,
+    },
+    Marker {
+        marker: Marker,
+    },
+    Equity {
+        point: EquityPoint,
+    },
+}
+
+#[derive(Debug, Default)]
+struct History {
+    candles: VecDeque<Candle>,
+    markers: VecDeque<Marker>,
+    equity: VecDeque<EquityPoint>,
+}
+
+fn push_capped<T>(items: &mut VecDeque<T>, item: T, cap: usize) {
+    items.push_back(item);
+    while items.len() > cap {
+        items.pop_front();
+    }
+}
+
+/// Recent chart data plus a push channel for connected pages
+pub struct Dashboard {
+    history: Mutex<History>,
+    events: broadcast::Sender<DashboardEvent>,
+}
+
+pub type SharedDashboard = Arc<Dashboard>;
+
+impl Dashboard {
+    pub fn new() -> SharedDashboard {
+        let (events, _) = broadcast::channel(1024);
+        Arc::new(Dashboard {
+            history: Mutex::new(History::default()),
+            events,
+        })
+    }
+
+    fn snapshot(&self) -> DashboardEvent {
+        let history = self.history.lock().unwrap();
+        DashboardEvent::Snapshot {
+            candles: history.candles.iter().cloned().collect(),
+            markers: history.markers.iter().cloned().collect(),
+            equity: history.equity.iter().cloned().collect(),
+        }
+    }
+
+    fn push_candle(&self, candle: Candle) {
+        {
+            let mut history = self.history.lock().unwrap();
+            match history.candles.back_mut() {
+                Some(last) if last.time == candle.time => *last = candle.clone(),
+                _ => push_capped(&mut history.candles, candle.clone(), CANDLE_HISTORY),
+            }
+        }
+        // Fails only while no page is connected
+        let _ = self.events.send(DashboardEvent::Candle { candle });
+    }
+
+    fn push_marker(&self, marker: Marker) {
+        push_capped(
+            &mut self.history.lock().unwrap().markers,
+            marker.clone(),
+            MARKER_HISTORY,
+        );
+        let _ = self.events.send(DashboardEvent::Marker { marker });
+    }
+
+    fn push_equity(&self, point: EquityPoint) {
+        push_capped(
+            &mut self.history.lock().unwrap().equity,
+            point.clone(),
+            EQUITY_HISTORY,
+        );
+        let _ = self.events.send(DashboardEvent::Equity { point });
+    }
+}
+
+// Markers for trades already in the executor's history
+async fn seed_markers(dashboard: &Dashboard, state: &SharedExecutorState) {
+    let state = state.read().await;
+    let mut markers = Vec::new();
+    for trade in &state.trades {
+        markers.push(Marker {
+            time: trade.opened_at,
+            symbol: trade.symbol.clone(),
+            event: PositionEvent::Opened,
+            price: trade.entry_price,
+            quantity: trade.quantity,
+        });
+        markers.push(Marker {
+            time: trade.closed_at,
+            symbol: trade.symbol.clone(),
+            event: PositionEvent::Closed,
+            price: trade.exit_price,
+            quantity: trade.quantity,
+        });
+    }
+    for position in state.positions.values() {
+        markers.push(Marker {
+            time: position.opened_at,
+            symbol: position.symbol.clone(),
+            event: PositionEvent::Opened,
+            price: from_decimal(position.entry_price),
+            quantity: from_decimal(position.quantity),
+        });
+    }
+    markers.sort_by_key(|marker| marker.time);
+    let mut history = dashboard.history.lock().unwrap();
+    for marker in markers {
+        push_capped(&mut history.markers, marker, MARKER_HISTORY);
+    }
+}
+
+/// Feeds the dashboard from pushed market data and executor events, plus
+/// periodic equity samples
+pub async fn run(
+    dashboard: SharedDashboard,
+    mut events: broadcast::Receiver<PushEvent>,
+    state: SharedExecutorState,
+) {
+    seed_markers(&dashboard, &state).await;
+    let mut sample = tokio::time::interval(EQUITY_INTERVAL);
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(PushEvent::Market(MarketEvent::Kline { candle, .. })) => {
+                    dashboard.push_candle(Candle {
+                        time: candle.open_time.timestamp(),
+                        open: candle.open_price,
+                        high: candle.high_price,
+                        low: candle.low_price,
+                        close: candle.close_price,
+                        volume: candle.volume,
+                    });
+                }
+                Ok(PushEvent::Trading(BusMessage::Position {
+                    symbol,
+                    event,
+                    quantity,
+                    price,
+                    time,
+                    ..
+                })) => {
+                    dashboard.push_marker(Marker {
+                        time,
+                        symbol,
+                        event,
+                        price: from_decimal(price),
+                        quantity: from_decimal(quantity),
+                    });
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("Dashboard feed lagged, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = sample.tick() => {
+                let equity = state.read().await.pnl_report().total();
+                dashboard.push_equity(EquityPoint {
+                    time: chrono::Utc::now().timestamp(),
+                    equity,
+                });
+            }
+        }
+    }
+}
+
+/// Pushes the snapshot and then every update to one connected page
+pub async fn stream(mut socket: WebSocket, dashboard: SharedDashboard) {
+    // Subscribe before the snapshot so nothing falls in between
+    let mut events = dashboard.events.subscribe();
+    let mut next = Some(dashboard.snapshot());
+    loop {
+        if let Some(event) = next.take() {
+            let text = match serde_json::to_string(&event) {
+                Ok(text) => text,
+                Err(e) => {
+                    log::error!("Failed to encode dashboard event: {:?}", e);
+                    continue;
+                }
+            };
+            if socket.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => next = Some(event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("Dashboard client lagged, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by axum; nothing else is expected
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
@@ -1,20 +1,307 @@
-use std::{error::Error, fmt};
+use binance_spot_connector_rust::market::klines::KlineInterval;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::{Decimal, RoundingStrategy};
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dto;
+
+// Prices, quantities and money are Decimal from the market data on; f64 is
+// left to the indicators, the backtester and wire formats that carry floats.
+// Non-finite values become zero.
+pub fn to_decimal(value: f64) -> Decimal {
+    Decimal::from_f64(value).unwrap_or_default()
+}
+
+pub fn from_decimal(value: Decimal) -> f64 {
+    value.to_f64().unwrap_or_default()
+}
+
+// Quote assets, longest first; plain USD for Kraken and Coinbase pairs
+const QUOTE_ASSETS: [&str; 9] = [
+    "FDUSD", "USDT", "USDC", "BUSD", "TUSD", "BTC", "ETH", "BNB", "USD",
+];
+
+// Base assets that tell apart suffixes ending the same way: DOTUSD is DOT/USD
+// rather than DO/TUSD, BTCTUSD is BTC/TUSD rather than BTCT/USD
+const KNOWN_BASES: [&str; 32] = [
+    "BTC", "XBT", "ETH", "BNB", "SOL", "XRP", "ADA", "DOGE", "XDG", "DOT", "LTC", "TRX", "AVAX",
+    "LINK", "MATIC", "ATOM", "XLM", "BCH", "UNI", "ETC", "FIL", "NEAR", "SHIB", "PEPE", "ARB",
+    "OP", "APT", "SUI", "USDT", "USDC", "DAI", "EUR",
+];
+
+// Quote asset `symbol` ends with. Of the quotes that leave a non-empty base,
+// the longest one whose base is known wins, else the longest.
+fn quote_of(symbol: &str) -> Option<&'static str> {
+    let mut quotes = QUOTE_ASSETS
+        .iter()
+        .copied()
+        .filter(|quote| symbol.len() > quote.len() && symbol.ends_with(quote));
+    let longest = quotes.clone().next()?;
+    Some(
+        quotes
+            .find(|quote| KNOWN_BASES.contains(&&symbol[..symbol.len() - quote.len()]))
+            .unwrap_or(longest),
+    )
+}
+
+/// Exchange symbol such as BTCUSDT; always upper case letters and digits
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Symbol(String);
+
+impl Symbol {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    // Lower case, as websocket stream names use it
+    pub fn stream_name(&self) -> String {
+        self.0.to_lowercase()
+    }
+
+    pub fn quote_asset(&self) -> Option<&str> {
+        quote_of(&self.0).map(|quote| &self.0[self.0.len() - quote.len()..])
+    }
+
+    pub fn base_asset(&self) -> Option<&str> {
+        self.quote_asset()
+            .map(|quote| &self.0[..self.0.len() - quote.len()])
+    }
+}
+
+impl FromStr for Symbol {
+    type Err = TradingError;
+
+    // Accepts "btcusdt" and "BTC/USDT" as well
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let symbol: String = s.trim().replace('/', "").to_uppercase();
+        let valid =
+            (2..=20).contains(&symbol.len()) && symbol.chars().all(|c| c.is_ascii_alphanumeric());
+        if !valid {
+            return Err(TradingError::InvalidParameter(format!(
+                "Invalid symbol {:?}",
+                s
+            )));
+        }
+        Ok(Symbol(symbol))
+    }
+}
+
+impl TryFrom<String> for Symbol {
+    type Error = TradingError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Symbol> for String {
+    fn from(symbol: Symbol) -> Self {
+        symbol.0
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Candle interval, written in Binance notation ("1m", "4h", "1w")
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Interval {
+    #[default]
+    Minutes1,
+    Minutes3,
+    Minutes5,
+    Minutes15,
+    Minutes30,
+    Hours1,
+    Hours2,
+    Hours4,
+    Hours6,
+    Hours8,
+    Hours12,
+    Days1,
+    Days3,
+    Weeks1,
+}
+
+impl Interval {
+    pub const ALL: [Interval; 14] = [
+        Interval::Minutes1,
+        Interval::Minutes3,
+        Interval::Minutes5,
+        Interval::Minutes15,
+        Interval::Minutes30,
+        Interval::Hours1,
+        Interval::Hours2,
+        Interval::Hours4,
+        Interval::Hours6,
+        Interval::Hours8,
+        Interval::Hours12,
+        Interval::Days1,
+        Interval::Days3,
+        Interval::Weeks1,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Interval::Minutes1 => "1m",
+            Interval::Minutes3 => "3m",
+            Interval::Minutes5 => "5m",
+            Interval::Minutes15 => "15m",
+            Interval::Minutes30 => "30m",
+            Interval::Hours1 => "1h",
+            Interval::Hours2 => "2h",
+            Interval::Hours4 => "4h",
+            Interval::Hours6 => "6h",
+            Interval::Hours8 => "8h",
+            Interval::Hours12 => "12h",
+            Interval::Days1 => "1d",
+            Interval::Days3 => "3d",
+            Interval::Weeks1 => "1w",
+        }
+    }
+
+    // Length of one candle
+    pub fn millis(self) -> i64 {
+        const MINUTE: i64 = 60_000;
+        match self {
+            Interval::Minutes1 => MINUTE,
+            Interval::Minutes3 => 3 * MINUTE,
+            Interval::Minutes5 => 5 * MINUTE,
+            Interval::Minutes15 => 15 * MINUTE,
+            Interval::Minutes30 => 30 * MINUTE,
+            Interval::Hours1 => 60 * MINUTE,
+            Interval::Hours2 => 120 * MINUTE,
+            Interval::Hours4 => 240 * MINUTE,
+            Interval::Hours6 => 360 * MINUTE,
+            Interval::Hours8 => 480 * MINUTE,
+            Interval::Hours12 => 720 * MINUTE,
+            Interval::Days1 => 1440 * MINUTE,
+            Interval::Days3 => 3 * 1440 * MINUTE,
+            Interval::Weeks1 => 7 * 1440 * MINUTE,
+        }
+    }
+
+    pub fn kline_interval(self) -> KlineInterval {
+        match self {
+            Interval::Minutes1 => KlineInterval::Minutes1,
+            Interval::Minutes3 => KlineInterval::Minutes3,
+            Interval::Minutes5 => KlineInterval::Minutes5,
+            Interval::Minutes15 => KlineInterval::Minutes15,
+            Interval::Minutes30 => KlineInterval::Minutes30,
+            Interval::Hours1 => KlineInterval::Hours1,
+            Interval::Hours2 => KlineInterval::Hours2,
+            Interval::Hours4 => KlineInterval::Hours4,
+            Interval::Hours6 => KlineInterval::Hours6,
+            Interval::Hours8 => KlineInterval::Hours8,
+            Interval::Hours12 => KlineInterval::Hours12,
+            Interval::Days1 => KlineInterval::Days1,
+            Interval::Days3 => KlineInterval::Days3,
+            Interval::Weeks1 => KlineInterval::Weeks1,
+        }
+    }
+}
+
+impl FromStr for Interval {
+    type Err = TradingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Interval::ALL
+            .into_iter()
+            .find(|interval| interval.as_str() == s)
+            .ok_or_else(|| TradingError::InvalidParameter(format!("Unknown interval {:?}", s)))
+    }
+}
+
+impl TryFrom<String> for Interval {
+    type Error = TradingError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Interval> for String {
+    fn from(interval: Interval) -> Self {
+        interval.as_str().to_string()
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
 
 /// Core Trading Components
 #[derive(Debug, Clone)]
 pub struct Order {
     pub symbol: String,
-    pub quantity: f64,
+    pub quantity: Decimal,
     pub order_type: OrderType,
     pub side: OrderSide,
+    // Sent along where the exchange takes one, so a retried order can be
+    // looked up instead of placed twice
+    pub client_order_id: Option<String>,
+    // Only read for limit orders
+    pub time_in_force: TimeInForce,
+    // Limit orders that would take liquidity are rejected instead of filled
+    pub post_only: bool,
     // Add more fields as needed
 }
 
-#[derive(Debug, Clone)]
+/// How long a limit order may wait for its fill
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TimeInForce {
+    // Good till cancelled
+    #[default]
+    Gtc,
+    // Immediate or cancel; what doesn't fill at once is cancelled
+    Ioc,
+    // Fill or kill; all of it at once or nothing
+    Fok,
+}
+
+impl TimeInForce {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimeInForce::Gtc => "GTC",
+            TimeInForce::Ioc => "IOC",
+            TimeInForce::Fok => "FOK",
+        }
+    }
+}
+
+// Stable id for the order `key` describes, within Binance's 36 characters
+pub fn client_order_id(key: &str) -> String {
+    let digest = sha2::Sha256::digest(key.as_bytes());
+    let hex: String = digest[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("at-{}", hex)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum OrderType {
     Market,
-    Limit(f64),
-    Stop(f64),
+    Limit(Decimal),
+    Stop(Decimal),
+    // Arms once the price reaches `activation_price`, then follows it and
+    // triggers `callback_rate` percent back from the best price since
+    TrailingStop {
+        activation_price: Decimal,
+        callback_rate: Decimal,
+    },
     // Add more order types
 }
 impl fmt::Display for OrderType {
@@ -23,23 +310,67 @@ impl fmt::Display for OrderType {
             OrderType::Market => write!(f, "MARKET"),
             OrderType::Limit(price) => write!(f, "LIMIT {}", price),
             OrderType::Stop(price) => write!(f, "STOP {}", price),
+            OrderType::TrailingStop {
+                activation_price,
+                callback_rate,
+            } => write!(f, "TRAILING_STOP {} {}%", activation_price, callback_rate),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "web", derive(utoipa::ToSchema))]
 pub enum OrderSide {
     Buy,
     Sell,
 }
 
+impl OrderSide {
+    // The side that closes a position entered on this one
+    pub fn opposite(&self) -> OrderSide {
+        match self {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct OrderResponse {
     pub order_id: String,
     pub status: OrderStatus,
+    pub fills: Vec<Fill>,
     // Add more response fields
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fill {
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub commission: Decimal,
+    pub commission_asset: String,
+}
+
+impl OrderResponse {
+    // Volume-weighted fill price, if the exchange reported any fills
+    pub fn average_fill_price(&self) -> Option<Decimal> {
+        let quantity: Decimal = self.fills.iter().map(|f| f.quantity).sum();
+        if quantity <= Decimal::ZERO {
+            return None;
+        }
+        let notional: Decimal = self.fills.iter().map(|f| f.price * f.quantity).sum();
+        Some(notional / quantity)
+    }
+}
+
+// Split an exchange symbol like "BTCUSDT" into ("BTC", "USDT")
+pub fn split_symbol(symbol: &str) -> (&str, &str) {
+    match quote_of(symbol) {
+        Some(quote) => (&symbol[..symbol.len() - quote.len()], quote),
+        None => (symbol, ""),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum OrderStatus {
     Filled,
@@ -48,63 +379,322 @@ pub enum OrderStatus {
     Rejected,
     Pending,
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TradingSignal {
     pub symbol: String,
+    // Name of the strategy that produced the signal
+    pub strategy: String,
     pub action: TradeAction,
-    pub price: f64,
+    pub price: Decimal,
     pub timestamp: i64,
+    // Optional exit levels suggested by the strategy
+    pub stop_loss: Option<Decimal>,
+    pub take_profit: Option<Decimal>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TradeAction {
     Buy,
     Sell,
     Hold,
+    // Exit whatever is held on the symbol, long or short; nothing when flat
+    Close,
+}
+/// Risk Management
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "web", derive(utoipa::ToSchema))]
+#[serde(default)]
+pub struct RiskParameters {
+    // Quote currency amount committed per entry
+    pub max_position_size: Decimal,
+    pub stop_loss_pct: Decimal,
+    pub take_profit_pct: Decimal,
+    // Positions (including resting entry orders) allowed across all symbols
+    pub max_open_positions: usize,
+}
+
+impl RiskParameters {
+    pub fn validate(&self) -> Result<(), TradingError> {
+        let valid = self.max_position_size > Decimal::ZERO
+            && self.stop_loss_pct > Decimal::ZERO
+            && self.take_profit_pct > Decimal::ZERO
+            && self.max_open_positions > 0;
+        if !valid {
+            return Err(TradingError::InvalidParameter(
+                "Sizes, percentages and position limit must be positive".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // Base quantity for one entry at `price`, truncated to 5 decimals when the
+    // exchange's step size isn't known
+    pub fn order_quantity(&self, price: Decimal) -> Decimal {
+        if price <= Decimal::ZERO {
+            return Decimal::ZERO;
+        }
+        (self.max_position_size / price).trunc_with_scale(5)
+    }
+}
+
+impl Default for RiskParameters {
+    fn default() -> Self {
+        RiskParameters {
+            max_position_size: dec!(100),
+            stop_loss_pct: dec!(2),
+            take_profit_pct: dec!(4),
+            max_open_positions: 5,
+        }
+    }
+}
+
+// A multiple of `step`, in decimal so 0.1 steps don't drift; no step leaves it alone
+fn to_step(value: Decimal, step: Decimal, strategy: RoundingStrategy) -> Decimal {
+    if step <= Decimal::ZERO {
+        return value;
+    }
+    (value / step).round_dp_with_strategy(0, strategy) * step
 }
+
+/// The exchange's filters on one symbol's orders: quantity and price steps
+/// and the smallest order it accepts
+#[derive(Debug, Clone, Default)]
+pub struct SymbolRules {
+    pub step_size: Decimal,
+    pub tick_size: Decimal,
+    pub min_quantity: Decimal,
+    pub min_notional: Decimal,
+}
+
+impl SymbolRules {
+    // Down, so an order never asks for more than was sized
+    pub fn round_quantity(&self, quantity: Decimal) -> Decimal {
+        to_step(quantity, self.step_size, RoundingStrategy::ToZero)
+    }
+
+    pub fn round_price(&self, price: Decimal) -> Decimal {
+        to_step(price, self.tick_size, RoundingStrategy::MidpointNearestEven)
+    }
+
+    pub fn check(&self, quantity: Decimal, price: Decimal) -> Result<(), String> {
+        if quantity < self.min_quantity {
+            return Err(format!(
+                "quantity {} below the minimum {}",
+                quantity, self.min_quantity
+            ));
+        }
+        if quantity * price < self.min_notional {
+            return Err(format!(
+                "order value {} below the minimum notional {}",
+                quantity * price,
+                self.min_notional
+            ));
+        }
+        Ok(())
+    }
+
+    // Base quantity worth `value` at `price`, on the step size
+    pub fn order_quantity(&self, value: Decimal, price: Decimal) -> Result<Decimal, String> {
+        if price <= Decimal::ZERO {
+            return Ok(Decimal::ZERO);
+        }
+        let quantity = self.round_quantity(value / price);
+        self.check(quantity, price)?;
+        Ok(quantity)
+    }
+}
+
+/// Stop-loss / take-profit pair protecting an open position
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bracket {
+    pub stop_loss: Decimal,
+    pub take_profit: Decimal,
+}
+
+impl Bracket {
+    pub fn from_signal(signal: &TradingSignal) -> Option<Self> {
+        match (signal.stop_loss, signal.take_profit) {
+            (Some(stop_loss), Some(take_profit)) => Some(Bracket {
+                stop_loss,
+                take_profit,
+            }),
+            _ => None,
+        }
+    }
+
+    // Derive exit levels from the entry price; `side` is the side of the entry order
+    pub fn from_risk(entry_price: Decimal, side: &OrderSide, risk: &RiskParameters) -> Self {
+        let stop = entry_price * risk.stop_loss_pct / Decimal::ONE_HUNDRED;
+        let target = entry_price * risk.take_profit_pct / Decimal::ONE_HUNDRED;
+        match side {
+            OrderSide::Buy => Bracket {
+                stop_loss: entry_price - stop,
+                take_profit: entry_price + target,
+            },
+            OrderSide::Sell => Bracket {
+                stop_loss: entry_price + stop,
+                take_profit: entry_price - target,
+            },
+        }
+    }
+
+    // Whether `price` has reached either leg for a position opened with `side`
+    pub fn is_triggered(&self, price: Decimal, side: &OrderSide) -> bool {
+        match side {
+            OrderSide::Buy => price <= self.stop_loss || price >= self.take_profit,
+            OrderSide::Sell => price >= self.stop_loss || price <= self.take_profit,
+        }
+    }
+
+    // Price of the leg `price` has reached, if any, for a position opened with `side`
+    pub fn reached_leg(&self, price: Decimal, side: &OrderSide) -> Option<Decimal> {
+        let (take_profit, stop_loss) = match side {
+            OrderSide::Buy => (price >= self.take_profit, price <= self.stop_loss),
+            OrderSide::Sell => (price <= self.take_profit, price >= self.stop_loss),
+        };
+        if take_profit {
+            Some(self.take_profit)
+        } else if stop_loss {
+            Some(self.stop_loss)
+        } else {
+            None
+        }
+    }
+}
+
 /// Market Data Structures
 #[derive(Debug, Clone, Default)]
 pub struct MarketData {
     pub symbol: String,
     pub timestamp: u64,
-    pub volume: f64,
-    pub last_price: f64,
-    pub open_price: f64,
-    pub close_price: f64,
-    pub high_price: f64,
-    pub low_price: f64,
+    pub volume: Decimal,
+    pub last_price: Decimal,
+    pub open_price: Decimal,
+    pub close_price: Decimal,
+    pub high_price: Decimal,
+    pub low_price: Decimal,
+    // Best bid and ask from the ticker; zero until one carried them
+    pub bid_price: Decimal,
+    pub ask_price: Decimal,
 }
 
-/// Error Handling
-#[derive(Debug)]
+/// Error Handling. Everything the bot reports ends up as one of these; lower
+/// level errors (dto::Error, serde, io) convert with `?`.
+#[derive(Debug, thiserror::Error)]
 pub enum TradingError {
+    #[error("Connection Error: {0}")]
     ConnectionError(String),
+    #[error("Authentication Error: {0}")]
     AuthenticationError(String),
+    #[error("Order Error: {0}")]
     OrderError(String),
+    #[error("Data Error: {0}")]
     DataError(String),
+    #[error("Network Error: {0}")]
     NetworkError(String),
-    // Add more error variants
+    #[error("Invalid Parameter: {0}")]
+    InvalidParameter(String),
+    // The exchange has no order under that id, so it never got there
+    #[error("Unknown Order: {0}")]
+    UnknownOrder(String),
 }
 
-impl fmt::Display for TradingError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl TradingError {
+    // Stable identifier for API responses and alerts
+    pub fn code(&self) -> &'static str {
         match self {
-            TradingError::ConnectionError(msg) => write!(f, "Connection Error: {}", msg),
-            // Implement other variants
-            _ => write!(f, "Generic trading error"),
+            TradingError::ConnectionError(_) => "connection",
+            TradingError::AuthenticationError(_) => "authentication",
+            TradingError::OrderError(_) => "order",
+            TradingError::DataError(_) => "data",
+            TradingError::NetworkError(_) => "network",
+            TradingError::InvalidParameter(_) => "invalid_parameter",
+            TradingError::UnknownOrder(_) => "unknown_order",
+        }
+    }
+
+    // Whether the same call may succeed if tried again later
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            TradingError::ConnectionError(_) | TradingError::NetworkError(_)
+        )
+    }
+}
+
+impl From<dto::Error> for TradingError {
+    fn from(e: dto::Error) -> Self {
+        match e {
+            dto::Error::ApiError(_) | dto::Error::RequestError(_) | dto::Error::HttpError(_) => {
+                TradingError::NetworkError(e.to_string())
+            }
+            dto::Error::ParseError(_)
+            | dto::Error::NumberParseError(_)
+            | dto::Error::DecimalParseError(_)
+            | dto::Error::JsonError(_)
+            | dto::Error::IoError(_)
+            | dto::Error::DatabaseError(_) => TradingError::DataError(e.to_string()),
         }
     }
 }
 
-impl Error for TradingError {}
+impl From<serde_json::Error> for TradingError {
+    fn from(e: serde_json::Error) -> Self {
+        TradingError::DataError(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for TradingError {
+    fn from(e: std::io::Error) -> Self {
+        TradingError::DataError(e.to_string())
+    }
+}
 
 /// Core Trading Traits
+// Futures need not be Send: the bot only spawns executors over concrete clients
+#[allow(async_fn_in_trait)]
 pub trait ExchangeClient {
     async fn connect(&mut self) -> Result<(), TradingError>;
     async fn disconnect(&mut self) -> Result<(), TradingError>;
-    async fn get_balance(&self) -> Result<f64, TradingError>;
+    async fn get_balance(&self) -> Result<Decimal, TradingError>;
     async fn send_order(&mut self, order: &Order) -> Result<OrderResponse, TradingError>;
-    async fn cancel_order(&mut self, order_id: &str) -> Result<(), TradingError>;
+    // `symbol` is the one the order was placed on
+    async fn cancel_order(&mut self, symbol: &str, order_id: &str) -> Result<(), TradingError>;
+    // Orders on `symbol` still working on the exchange
+    async fn get_open_orders(&self, symbol: &str) -> Result<Vec<OrderResponse>, TradingError>;
+    // `symbol` is the one the order was placed on
+    async fn get_order_status(
+        &self,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse, TradingError>;
+    // Every leg of the bracket `order_id` was returned for by place_oco_order,
+    // so a leg that ended unfilled can be told apart from one that ended because
+    // the other executed. Venues that track a bracket as one order have just it.
+    async fn bracket_legs(
+        &self,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<Vec<OrderResponse>, TradingError> {
+        Ok(vec![self.get_order_status(symbol, order_id).await?])
+    }
+    // Order filters the exchange enforces on `symbol`, if they were loaded
+    fn symbol_rules(&self, _symbol: &str) -> Option<SymbolRules> {
+        None
+    }
+    // Whether selling `symbol` without holding it opens a short rather than
+    // being refused
+    fn supports_short(&self, _symbol: &str) -> bool {
+        false
+    }
+    // Exchange-side one-cancels-the-other exit; `side` is the closing side
+    async fn place_oco_order(
+        &mut self,
+        symbol: &str,
+        side: OrderSide,
+        quantity: Decimal,
+        bracket: &Bracket,
+    ) -> Result<OrderResponse, TradingError>;
     // Add more exchange methods
 }
 
@@ -119,3 +709,86 @@ pub trait RiskManager {
     fn validate_order(&self, order: &Order) -> Result<(), TradingError>;
     // Add risk management methods
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbols_split_on_their_quote_asset() {
+        assert_eq!(split_symbol("BTCUSDT"), ("BTC", "USDT"));
+        assert_eq!(split_symbol("BTCFDUSD"), ("BTC", "FDUSD"));
+        assert_eq!(split_symbol("BTCTUSD"), ("BTC", "TUSD"));
+        assert_eq!(split_symbol("ETHBTC"), ("ETH", "BTC"));
+        assert_eq!(split_symbol("BTCUSD"), ("BTC", "USD"));
+        // USD pairs whose base ends in T
+        assert_eq!(split_symbol("DOTUSD"), ("DOT", "USD"));
+        assert_eq!(split_symbol("XBTUSD"), ("XBT", "USD"));
+        assert_eq!(split_symbol("USDT"), ("USDT", ""));
+    }
+
+    #[test]
+    fn symbol_assets_agree_with_split_symbol() {
+        let symbol: Symbol = "DOTUSD".parse().unwrap();
+        assert_eq!(symbol.base_asset(), Some("DOT"));
+        assert_eq!(symbol.quote_asset(), Some("USD"));
+        let symbol: Symbol = "SOLTUSD".parse().unwrap();
+        assert_eq!(symbol.base_asset(), Some("SOL"));
+        assert_eq!(symbol.quote_asset(), Some("TUSD"));
+    }
+
+    fn rules() -> SymbolRules {
+        SymbolRules {
+            step_size: dec!(0.001),
+            tick_size: dec!(0.01),
+            min_quantity: dec!(0.001),
+            min_notional: dec!(10),
+        }
+    }
+
+    #[test]
+    fn quantities_round_down_to_the_step() {
+        let rules = rules();
+        assert_eq!(rules.round_quantity(dec!(0.12349)), dec!(0.123));
+        assert_eq!(rules.round_quantity(dec!(0.1239999)), dec!(0.123));
+        assert_eq!(rules.round_quantity(dec!(0.123)), dec!(0.123));
+        // Binary float noise doesn't lose a step
+        assert_eq!(rules.round_quantity(dec!(0.1) + dec!(0.2)), dec!(0.3));
+        assert_eq!(rules.round_quantity(dec!(0.0009)), Decimal::ZERO);
+    }
+
+    #[test]
+    fn prices_round_to_the_nearest_tick() {
+        let rules = rules();
+        assert_eq!(rules.round_price(dec!(100.014)), dec!(100.01));
+        assert_eq!(rules.round_price(dec!(100.016)), dec!(100.02));
+        // Halfway goes to the even tick
+        assert_eq!(rules.round_price(dec!(100.125)), dec!(100.12));
+        assert_eq!(rules.round_price(dec!(100.135)), dec!(100.14));
+    }
+
+    #[test]
+    fn a_zero_step_leaves_values_alone() {
+        let rules = SymbolRules::default();
+        assert_eq!(rules.round_quantity(dec!(0.123456789)), dec!(0.123456789));
+        assert_eq!(rules.round_price(dec!(100.123456)), dec!(100.123456));
+    }
+
+    #[test]
+    fn orders_below_the_minimums_are_refused() {
+        let rules = rules();
+        assert!(rules.check(dec!(0.0005), dec!(100_000)).is_err());
+        assert!(rules.check(dec!(0.09), Decimal::ONE_HUNDRED).is_err());
+        assert!(rules.check(dec!(0.1), Decimal::ONE_HUNDRED).is_ok());
+        // Rounding down to the step can take an order under the minimum notional
+        assert!(rules.order_quantity(dec!(10.5), dec!(3000)).is_err());
+        assert_eq!(
+            rules.order_quantity(dec!(25), Decimal::ONE_HUNDRED),
+            Ok(dec!(0.25))
+        );
+        assert_eq!(
+            rules.order_quantity(dec!(25), Decimal::ZERO),
+            Ok(Decimal::ZERO)
+        );
+    }
+}
@@ -1,5 +1,8 @@
+use crate::domain::{split_symbol, Fill, OrderResponse, OrderStatus, SymbolRules};
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer, Serialize};
 use std::error::Error as StdError;
 use std::num::ParseFloatError;
 use thiserror::Error;
@@ -63,6 +66,22 @@ pub fn parse_websocket_message(message: &str) -> Result<WebSocketResponse, serde
     serde_json::from_str(message)
 }
 
+// Live path: only the kline, without allocating the envelope's strings
+#[derive(Deserialize)]
+struct KlineFrame {
+    data: KlineFrameData,
+}
+
+#[derive(Deserialize)]
+struct KlineFrameData {
+    #[serde(rename = "k")]
+    kline: Kline,
+}
+
+pub fn parse_kline_update(message: &str) -> Result<Kline, serde_json::Error> {
+    serde_json::from_str::<KlineFrame>(message).map(|frame| frame.data.kline)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebSocketMessage {
     pub stream: String,
@@ -163,6 +182,103 @@ pub struct TickerData {
     #[serde(rename = "n")]
     pub total_trades: i64,
 }
+#[derive(Debug, Clone, Deserialize)]
+pub struct DepthMessage {
+    pub stream: String,
+    pub data: DepthSnapshot,
+}
+
+// Partial book depth stream (<symbol>@depth<levels>), best levels first
+#[derive(Debug, Clone, Deserialize)]
+pub struct DepthSnapshot {
+    #[serde(rename = "lastUpdateId")]
+    pub last_update_id: u64,
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+}
+
+impl DepthSnapshot {
+    pub fn best_bid(&self) -> Option<f64> {
+        self.bids.first().map(|level| level.price)
+    }
+
+    pub fn best_ask(&self) -> Option<f64> {
+        self.asks.first().map(|level| level.price)
+    }
+}
+
+/// A book level, sent as `["price", "quantity"]` and parsed in place so a deep
+/// book doesn't allocate two strings per level
+#[derive(Debug, Clone, Copy)]
+pub struct PriceLevel {
+    pub price: f64,
+    pub quantity: f64,
+}
+
+impl<'de> Deserialize<'de> for PriceLevel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (price, quantity): (&str, &str) = Deserialize::deserialize(deserializer)?;
+        Ok(PriceLevel {
+            price: price.parse().map_err(serde::de::Error::custom)?,
+            quantity: quantity.parse().map_err(serde::de::Error::custom)?,
+        })
+    }
+}
+
+// Malformed and non-positive prices are errors rather than zero
+pub fn parse_price(value: &str, field: &str) -> Result<Decimal, Error> {
+    let price: Decimal = value
+        .parse()
+        .map_err(|_| Error::ParseError(format!("Invalid {}: {:?}", field, value)))?;
+    if price <= Decimal::ZERO {
+        return Err(Error::ParseError(format!("Invalid {}: {:?}", field, value)));
+    }
+    Ok(price)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct KlinePrices {
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+}
+
+impl Kline {
+    pub fn prices(&self) -> Result<KlinePrices, Error> {
+        let prices = KlinePrices {
+            open: parse_price(&self.open_price, "open_price")?,
+            high: parse_price(&self.high_price, "high_price")?,
+            low: parse_price(&self.low_price, "low_price")?,
+            close: parse_price(&self.close_price, "close_price")?,
+        };
+        if prices.low > prices.high {
+            return Err(Error::ParseError(format!(
+                "Low {} above high {}",
+                prices.low, prices.high
+            )));
+        }
+        Ok(prices)
+    }
+}
+
+impl TickerData {
+    pub fn price(&self) -> Result<Decimal, Error> {
+        parse_price(&self.last_price, "last_price")
+    }
+
+    // Best bid and ask, when the stream carries a valid pair
+    pub fn quote(&self) -> Option<(Decimal, Decimal)> {
+        let bid = parse_price(&self.bid_price, "bid_price").ok()?;
+        let ask = parse_price(&self.ask_price, "ask_price").ok()?;
+        (bid <= ask).then_some((bid, ask))
+    }
+}
+
+pub fn parse_websocket_message_depth(message: &str) -> Result<DepthMessage, serde_json::Error> {
+    serde_json::from_str(message)
+}
+
 pub fn parse_websocket_message_ticker(
     message: &str,
 ) -> Result<WebSocketMessage, serde_json::Error> {
@@ -170,6 +286,259 @@ pub fn parse_websocket_message_ticker(
     serde_json::from_str(message)
 }
 
+// Live path: the fields the bot uses, borrowed from the frame instead of the
+// twenty-odd strings of a full TickerData
+#[derive(Deserialize)]
+struct TickerFrame<'a> {
+    #[serde(borrow)]
+    data: TickerUpdate<'a>,
+}
+
+#[derive(Deserialize)]
+struct TickerUpdate<'a> {
+    #[serde(rename = "E")]
+    event_time: i64,
+    #[serde(rename = "s")]
+    symbol: &'a str,
+    #[serde(rename = "c")]
+    last_price: &'a str,
+    #[serde(rename = "v")]
+    volume: &'a str,
+    // Missing from the futures ticker
+    #[serde(rename = "b", default)]
+    bid_price: &'a str,
+    #[serde(rename = "a", default)]
+    ask_price: &'a str,
+}
+
+pub fn parse_ticker_update(message: &str) -> Result<TickerData, serde_json::Error> {
+    let update = serde_json::from_str::<TickerFrame>(message)?.data;
+    Ok(TickerData {
+        event_time: update.event_time,
+        symbol: update.symbol.to_string(),
+        last_price: update.last_price.to_string(),
+        volume: update.volume.to_string(),
+        bid_price: update.bid_price.to_string(),
+        ask_price: update.ask_price.to_string(),
+        ..TickerData::default()
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OcoOrderResponse {
+    #[serde(rename = "orderListId")]
+    pub order_list_id: i64,
+    #[serde(rename = "symbol")]
+    pub symbol: String,
+    #[serde(rename = "orders")]
+    pub orders: Vec<OcoOrderLeg>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OcoOrderLeg {
+    #[serde(rename = "symbol")]
+    pub symbol: String,
+    #[serde(rename = "orderId")]
+    pub order_id: u64,
+    #[serde(rename = "clientOrderId")]
+    pub client_order_id: String,
+}
+
+/// Reply to a new order placed with newOrderRespType=FULL
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewOrderResponse {
+    #[serde(rename = "symbol")]
+    pub symbol: String,
+    #[serde(rename = "orderId")]
+    pub order_id: u64,
+    #[serde(rename = "clientOrderId")]
+    pub client_order_id: String,
+    #[serde(rename = "status")]
+    pub status: String,
+    #[serde(rename = "executedQty")]
+    pub executed_qty: String,
+    #[serde(rename = "cummulativeQuoteQty")]
+    pub cummulative_quote_qty: String,
+    // Only in FULL replies
+    #[serde(rename = "fills", default)]
+    pub fills: Vec<NewOrderFill>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewOrderFill {
+    #[serde(rename = "price")]
+    pub price: String,
+    #[serde(rename = "qty")]
+    pub qty: String,
+    #[serde(rename = "commission")]
+    pub commission: String,
+    #[serde(rename = "commissionAsset")]
+    pub commission_asset: String,
+}
+
+impl NewOrderResponse {
+    pub fn to_order_response(&self) -> Result<OrderResponse, Error> {
+        let mut fills = Vec::with_capacity(self.fills.len());
+        for fill in &self.fills {
+            fills.push(Fill {
+                price: fill.price.parse()?,
+                quantity: fill.qty.parse()?,
+                commission: fill.commission.parse()?,
+                commission_asset: fill.commission_asset.clone(),
+            });
+        }
+        let filled: Decimal = self.executed_qty.parse()?;
+        if fills.is_empty() && filled > Decimal::ZERO {
+            // Shorter reply types only carry totals
+            let quote: Decimal = self.cummulative_quote_qty.parse()?;
+            fills.push(Fill {
+                price: quote / filled,
+                quantity: filled,
+                commission: Decimal::ZERO,
+                commission_asset: split_symbol(&self.symbol).1.to_string(),
+            });
+        }
+        Ok(OrderResponse {
+            order_id: self.order_id.to_string(),
+            status: parse_order_status(&self.status),
+            fills,
+        })
+    }
+}
+
+/// An order as the order query and open orders endpoints return it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderQueryResponse {
+    #[serde(rename = "symbol")]
+    pub symbol: String,
+    #[serde(rename = "orderId")]
+    pub order_id: u64,
+    #[serde(rename = "status")]
+    pub status: String,
+    #[serde(rename = "executedQty")]
+    pub executed_qty: String,
+    #[serde(rename = "cummulativeQuoteQty")]
+    pub cummulative_quote_qty: String,
+    // The OCO list the order is a leg of; -1 when it isn't
+    #[serde(rename = "orderListId", default)]
+    pub order_list_id: Option<i64>,
+}
+
+// Binance order status names, the same on spot and futures
+pub fn parse_order_status(status: &str) -> OrderStatus {
+    match status {
+        "FILLED" => OrderStatus::Filled,
+        "PARTIALLY_FILLED" => OrderStatus::PartiallyFilled,
+        "CANCELED" | "EXPIRED" | "EXPIRED_IN_MATCH" => OrderStatus::Canceled,
+        "REJECTED" => OrderStatus::Rejected,
+        _ => OrderStatus::Pending,
+    }
+}
+
+impl OrderQueryResponse {
+    // Queries only carry totals, so the fills come back as one at the average
+    // price; commissions aren't included
+    pub fn to_order_response(&self) -> Result<OrderResponse, Error> {
+        let filled: Decimal = self.executed_qty.parse()?;
+        let quote: Decimal = self.cummulative_quote_qty.parse()?;
+        let fills = if filled > Decimal::ZERO {
+            vec![Fill {
+                price: quote / filled,
+                quantity: filled,
+                commission: Decimal::ZERO,
+                commission_asset: split_symbol(&self.symbol).1.to_string(),
+            }]
+        } else {
+            Vec::new()
+        };
+        Ok(OrderResponse {
+            order_id: self.order_id.to_string(),
+            status: parse_order_status(&self.status),
+            fills,
+        })
+    }
+}
+
+/// The account endpoint reply, trimmed to the balances
+#[derive(Debug, Deserialize)]
+pub struct Account {
+    pub balances: Vec<Balance>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Balance {
+    pub asset: String,
+    pub free: String,
+    pub locked: String,
+}
+
+impl Account {
+    // Assets never held aren't listed, which reads as nothing free
+    pub fn free(&self, asset: &str) -> Result<Decimal, Error> {
+        match self.balances.iter().find(|balance| balance.asset == asset) {
+            Some(balance) => Ok(balance.free.parse()?),
+            None => Ok(Decimal::ZERO),
+        }
+    }
+}
+
+/// The exchangeInfo reply, trimmed to the order filters of each symbol
+#[derive(Debug, Deserialize)]
+pub struct ExchangeInfo {
+    pub symbols: Vec<SymbolInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SymbolInfo {
+    pub symbol: String,
+    pub filters: Vec<SymbolFilter>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "filterType")]
+pub enum SymbolFilter {
+    #[serde(rename = "PRICE_FILTER")]
+    Price {
+        #[serde(rename = "tickSize")]
+        tick_size: String,
+    },
+    #[serde(rename = "LOT_SIZE")]
+    LotSize {
+        #[serde(rename = "stepSize")]
+        step_size: String,
+        #[serde(rename = "minQty")]
+        min_qty: String,
+    },
+    // NOTIONAL replaced MIN_NOTIONAL on spot; either may come back
+    #[serde(rename = "NOTIONAL", alias = "MIN_NOTIONAL")]
+    Notional {
+        #[serde(rename = "minNotional")]
+        min_notional: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+impl SymbolInfo {
+    pub fn to_symbol_rules(&self) -> Result<SymbolRules, Error> {
+        let mut rules = SymbolRules::default();
+        for filter in &self.filters {
+            match filter {
+                SymbolFilter::Price { tick_size } => rules.tick_size = tick_size.parse()?,
+                SymbolFilter::LotSize { step_size, min_qty } => {
+                    rules.step_size = step_size.parse()?;
+                    rules.min_quantity = min_qty.parse()?;
+                }
+                SymbolFilter::Notional { min_notional } => {
+                    rules.min_notional = min_notional.parse()?
+                }
+                SymbolFilter::Other => (),
+            }
+        }
+        Ok(rules)
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("API error: {0}")]
@@ -181,6 +550,9 @@ pub enum Error {
     #[error("Number parse error: {0}")]
     NumberParseError(#[from] ParseFloatError),
 
+    #[error("Decimal parse error: {0}")]
+    DecimalParseError(#[from] rust_decimal::Error),
+
     #[error("JSON parse error: {0}")]
     JsonError(#[from] serde_json::Error),
 
@@ -189,6 +561,12 @@ pub enum Error {
 
     #[error("HTTP error: {0}")]
     HttpError(String),
+
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Database error: {0}")]
+    DatabaseError(String),
 }
 
 impl From<hyper::Error> for Error {
@@ -197,7 +575,7 @@ impl From<hyper::Error> for Error {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KlineResponse {
     pub open_time: DateTime<Utc>,
     pub open_price: f64,
@@ -213,47 +591,103 @@ pub struct KlineResponse {
 }
 
 impl KlineResponse {
-    pub fn from_raw_data(data: &[serde_json::Value]) -> Result<Self, Error> {
-        if data.len() < 11 {
-            return Err(Error::ParseError(format!(
-                "Invalid data length: expected 11 elements, got {}",
-                data.len()
-            )));
-        }
-
-        let parse_timestamp =
-            |value: &serde_json::Value, field: &str| -> Result<DateTime<Utc>, Error> {
-                value
-                    .as_i64()
-                    .ok_or_else(|| Error::ParseError(format!("Invalid {} format", field)))
-                    .and_then(|ts| {
-                        DateTime::from_timestamp_millis(ts).ok_or_else(|| {
-                            Error::ParseError(format!("Invalid timestamp for {}: {}", field, ts))
-                        })
-                    })
-            };
-
-        let parse_float = |value: &serde_json::Value, field: &str| -> Result<f64, Error> {
-            value
-                .as_str()
-                .ok_or_else(|| Error::ParseError(format!("Invalid {} format", field)))
-                .and_then(|s| s.parse().map_err(Error::NumberParseError))
+    // Candle from a kline stream event
+    pub fn from_stream(kline: &Kline) -> Result<Self, Error> {
+        let parse_timestamp = |ts: i64, field: &str| -> Result<DateTime<Utc>, Error> {
+            DateTime::from_timestamp_millis(ts).ok_or_else(|| {
+                Error::ParseError(format!("Invalid timestamp for {}: {}", field, ts))
+            })
         };
+        let parse_float =
+            |value: &str| -> Result<f64, Error> { value.parse().map_err(Error::NumberParseError) };
 
         Ok(Self {
-            open_time: parse_timestamp(&data[0], "open_time")?,
-            open_price: parse_float(&data[1], "open_price")?,
-            high_price: parse_float(&data[2], "high_price")?,
-            low_price: parse_float(&data[3], "low_price")?,
-            close_price: parse_float(&data[4], "close_price")?,
-            volume: parse_float(&data[5], "volume")?,
-            close_time: parse_timestamp(&data[6], "close_time")?,
-            quote_asset_volume: parse_float(&data[7], "quote_asset_volume")?,
-            number_of_trades: data[8]
-                .as_u64()
-                .ok_or_else(|| Error::ParseError("Invalid number_of_trades format".to_string()))?,
-            taker_buy_base_volume: parse_float(&data[9], "taker_buy_base_volume")?,
-            taker_buy_quote_volume: parse_float(&data[10], "taker_buy_quote_volume")?,
+            open_time: parse_timestamp(kline.start_time, "start_time")?,
+            open_price: parse_float(&kline.open_price)?,
+            high_price: parse_float(&kline.high_price)?,
+            low_price: parse_float(&kline.low_price)?,
+            close_price: parse_float(&kline.close_price)?,
+            volume: parse_float(&kline.volume)?,
+            close_time: parse_timestamp(kline.end_time, "end_time")?,
+            quote_asset_volume: parse_float(&kline.quote_volume)?,
+            number_of_trades: kline.number_of_trades.max(0) as u64,
+            taker_buy_base_volume: parse_float(&kline.taker_buy_volume)?,
+            taker_buy_quote_volume: parse_float(&kline.taker_buy_quote_volume)?,
         })
     }
 }
+
+// A row of the REST klines endpoint: open time, OHLCV, close time, quote
+// volume, trade count, taker volumes and an unused field. Deserialized straight
+// from the body instead of through serde_json::Value.
+#[derive(Deserialize)]
+struct RawKline<'a>(
+    i64,
+    &'a str,
+    &'a str,
+    &'a str,
+    &'a str,
+    &'a str,
+    i64,
+    &'a str,
+    u64,
+    &'a str,
+    &'a str,
+    IgnoredAny,
+);
+
+impl TryFrom<RawKline<'_>> for KlineResponse {
+    type Error = Error;
+
+    fn try_from(raw: RawKline<'_>) -> Result<Self, Error> {
+        let time = |ts: i64, field: &str| -> Result<DateTime<Utc>, Error> {
+            DateTime::from_timestamp_millis(ts).ok_or_else(|| {
+                Error::ParseError(format!("Invalid timestamp for {}: {}", field, ts))
+            })
+        };
+        let float = |value: &str| -> Result<f64, Error> { Ok(value.parse()?) };
+        Ok(KlineResponse {
+            open_time: time(raw.0, "open_time")?,
+            open_price: float(raw.1)?,
+            high_price: float(raw.2)?,
+            low_price: float(raw.3)?,
+            close_price: float(raw.4)?,
+            volume: float(raw.5)?,
+            close_time: time(raw.6, "close_time")?,
+            quote_asset_volume: float(raw.7)?,
+            number_of_trades: raw.8,
+            taker_buy_base_volume: float(raw.9)?,
+            taker_buy_quote_volume: float(raw.10)?,
+        })
+    }
+}
+
+// Body of a REST klines request
+pub fn parse_klines(body: &str) -> Result<Vec<KlineResponse>, Error> {
+    serde_json::from_str::<Vec<RawKline>>(body)?
+        .into_iter()
+        .map(KlineResponse::try_from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn account_free_balance() {
+        let body = r#"{
+            "makerCommission": 10,
+            "canTrade": true,
+            "balances": [
+                {"asset": "BTC", "free": "0.50000000", "locked": "0.10000000"},
+                {"asset": "USDT", "free": "1234.56000000", "locked": "0.00000000"}
+            ]
+        }"#;
+        let account: Account = serde_json::from_str(body).unwrap();
+        assert_eq!(account.free("USDT").unwrap(), dec!(1234.56));
+        assert_eq!(account.free("BTC").unwrap(), dec!(0.5));
+        assert_eq!(account.free("ETH").unwrap(), Decimal::ZERO);
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::VecDeque;
+
+use crate::domain::*;
+use crate::snapshot::StrategySnapshot;
+use crate::strategy::{ParameterValue, Strategy};
+
+// Closes kept for the strategy; enough for every indicator in `ta`
+pub const MAX_HISTORY: usize = 1000;
+
+/// Turns closed candles into trading signals. Live trading and the backtester both
+/// go through this, so a strategy sees exactly the same input in either mode.
+pub struct SignalEngine<S: Strategy> {
+    symbol: String,
+    strategy: S,
+    closes: VecDeque<f64>,
+}
+
+impl<S: Strategy> SignalEngine<S> {
+    pub fn new(symbol: &str, strategy: S) -> Self {
+        SignalEngine {
+            symbol: symbol.to_string(),
+            strategy,
+            closes: VecDeque::with_capacity(MAX_HISTORY),
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    // Takes effect from the next close; the history is kept
+    pub fn update_parameter(
+        &mut self,
+        name: &str,
+        value: ParameterValue,
+    ) -> Result<(), TradingError> {
+        self.strategy.update_parameter(name, value)
+    }
+
+    pub fn snapshot(&self) -> StrategySnapshot {
+        StrategySnapshot {
+            name: self.strategy.name().to_string(),
+            parameters: self
+                .strategy
+                .parameters()
+                .into_iter()
+                .map(|p| (p.name, p.value))
+                .collect(),
+            closes: self.closes.iter().copied().collect(),
+        }
+    }
+
+    // Parameters and close history from a snapshot of the same strategy
+    pub fn restore(&mut self, snapshot: &StrategySnapshot) -> Result<(), TradingError> {
+        if snapshot.name != self.strategy.name() {
+            return Err(TradingError::InvalidParameter(format!(
+                "Snapshot is for strategy {}, not {}",
+                snapshot.name,
+                self.strategy.name()
+            )));
+        }
+        for (name, value) in &snapshot.parameters {
+            self.strategy.update_parameter(name, *value)?;
+        }
+        self.closes.clear();
+        self.warm_up(&snapshot.closes);
+        Ok(())
+    }
+
+    // Seed the close history without asking the strategy for signals
+    pub fn warm_up(&mut self, closes: &[f64]) {
+        for close in closes {
+            if self.closes.len() == MAX_HISTORY {
+                self.closes.pop_front();
+            }
+            self.closes.push_back(*close);
+        }
+    }
+
+    // Feed the close of a finished candle; `close_time` in seconds
+    pub fn on_close(&mut self, close_time: i64, close: f64) -> TradingSignal {
+        if self.closes.len() == MAX_HISTORY {
+            self.closes.pop_front();
+        }
+        self.closes.push_back(close);
+        let action = self.strategy.analyze(self.closes.make_contiguous());
+        TradingSignal {
+            symbol: self.symbol.clone(),
+            strategy: self.strategy.name().to_string(),
+            action,
+            price: to_decimal(close),
+            timestamp: close_time,
+            stop_loss: None,
+            take_profit: None,
+        }
+    }
+}
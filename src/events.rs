@@ -0,0 +1,261 @@
+use std::sync::{Arc, Mutex};
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::domain::*;
+use crate::dto::{Kline, KlineResponse, TickerData};
+use crate::executor::Position;
+use crate::storage::PositionEvent;
+
+// Per-subscriber queues. Publishers never wait: an event that doesn't fit is
+// dropped for that subscriber and counted, rather than stalling the executor
+// or the websocket readers behind a slow sink.
+pub const TRADING_QUEUE: usize = 1_000;
+pub const MARKET_QUEUE: usize = 10_000;
+
+/// Market data in one shape regardless of the stream it came from
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MarketEvent {
+    Kline {
+        symbol: String,
+        interval: String,
+        // Whether the candle is final
+        closed: bool,
+        candle: KlineResponse,
+    },
+    Ticker {
+        symbol: String,
+        // Milliseconds
+        time: i64,
+        last_price: f64,
+        volume: f64,
+    },
+}
+
+impl MarketEvent {
+    pub fn from_kline(kline: &Kline) -> Option<Self> {
+        let candle = KlineResponse::from_stream(kline).ok()?;
+        Some(MarketEvent::Kline {
+            symbol: kline.symbol.clone(),
+            interval: kline.interval.clone(),
+            closed: kline.is_closed,
+            candle,
+        })
+    }
+
+    pub fn from_ticker(ticker: &TickerData) -> Option<Self> {
+        Some(MarketEvent::Ticker {
+            symbol: ticker.symbol.clone(),
+            time: ticker.event_time,
+            last_price: ticker.last_price.parse().ok()?,
+            volume: ticker.volume.parse().ok()?,
+        })
+    }
+
+    pub fn symbol(&self) -> &str {
+        match self {
+            MarketEvent::Kline { symbol, .. } | MarketEvent::Ticker { symbol, .. } => symbol,
+        }
+    }
+}
+
+/// Trading activity published for other services
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BusMessage {
+    Signal {
+        signal: TradingSignal,
+    },
+    // An order was acknowledged by the exchange or cancelled
+    Order {
+        symbol: String,
+        order_id: String,
+        status: String,
+        // Known for submitted orders, not for cancels
+        #[serde(skip_serializing_if = "Option::is_none")]
+        side: Option<OrderSide>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        quantity: Option<Decimal>,
+    },
+    Fill {
+        symbol: String,
+        order_id: String,
+        side: OrderSide,
+        price: Decimal,
+        quantity: Decimal,
+        commission: Decimal,
+        commission_asset: String,
+    },
+    // An entry refused by a risk check or operator setting
+    Risk {
+        symbol: String,
+        reason: String,
+    },
+    Position {
+        symbol: String,
+        event: PositionEvent,
+        // Buy for longs, Sell for shorts
+        side: OrderSide,
+        quantity: Decimal,
+        entry_price: Decimal,
+        price: Decimal,
+        // Seconds
+        time: i64,
+    },
+}
+
+impl BusMessage {
+    pub fn position(event: PositionEvent, position: &Position, price: Decimal) -> Self {
+        BusMessage::Position {
+            symbol: position.symbol.clone(),
+            event,
+            side: position.side.clone(),
+            quantity: position.quantity,
+            entry_price: position.entry_price,
+            price,
+            time: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        match self {
+            BusMessage::Signal { signal } => &signal.symbol,
+            BusMessage::Order { symbol, .. }
+            | BusMessage::Fill { symbol, .. }
+            | BusMessage::Risk { symbol, .. }
+            | BusMessage::Position { symbol, .. } => symbol,
+        }
+    }
+
+    pub fn channel(&self) -> &'static str {
+        match self {
+            BusMessage::Signal { .. } => "signals",
+            BusMessage::Order { .. } => "orders",
+            BusMessage::Fill { .. } => "fills",
+            BusMessage::Risk { .. } => "risk",
+            BusMessage::Position { .. } => "positions",
+        }
+    }
+}
+
+/// Events dropped so far because a subscriber's queue was full
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DroppedEvents {
+    pub trading: u64,
+    pub market: u64,
+}
+
+#[derive(Default)]
+struct Subscribers {
+    trading: Vec<mpsc::Sender<BusMessage>>,
+    market: Vec<mpsc::Sender<MarketEvent>>,
+    dropped: DroppedEvents,
+}
+
+// Queue without blocking; false once the subscriber is gone
+fn offer<T>(sender: &mpsc::Sender<T>, event: T, dropped: &mut u64, kind: &str) -> bool {
+    match sender.try_send(event) {
+        Ok(()) => true,
+        Err(mpsc::error::TrySendError::Full(_)) => {
+            *dropped += 1;
+            log::warn!("{} queue full, dropping event ({} so far)", kind, dropped);
+            true
+        }
+        Err(mpsc::error::TrySendError::Closed(_)) => false,
+    }
+}
+
+/// Typed fan-out of what happens in the bot: market events from the streams, and
+/// signal, order, fill, position and risk events from the executor. Sinks such
+/// as persistence, metrics and notifiers subscribe at any time and get their own
+/// queue, so publishers never need to know about them.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Subscribers>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&self) -> mpsc::Receiver<BusMessage> {
+        let (sender, receiver) = mpsc::channel(TRADING_QUEUE);
+        self.subscribers.lock().unwrap().trading.push(sender);
+        receiver
+    }
+
+    pub fn subscribe_market(&self, queue: usize) -> mpsc::Receiver<MarketEvent> {
+        let (sender, receiver) = mpsc::channel(queue);
+        self.subscribers.lock().unwrap().market.push(sender);
+        receiver
+    }
+
+    pub fn publish(&self, message: BusMessage) {
+        let subscribers = &mut *self.subscribers.lock().unwrap();
+        let dropped = &mut subscribers.dropped.trading;
+        subscribers
+            .trading
+            .retain(|sender| offer(sender, message.clone(), dropped, "Trading"));
+    }
+
+    pub fn publish_market(&self, event: Option<MarketEvent>) {
+        let Some(event) = event else {
+            return;
+        };
+        let subscribers = &mut *self.subscribers.lock().unwrap();
+        let dropped = &mut subscribers.dropped.market;
+        subscribers
+            .market
+            .retain(|sender| offer(sender, event.clone(), dropped, "Market"));
+    }
+
+    pub fn dropped(&self) -> DroppedEvents {
+        self.subscribers.lock().unwrap().dropped
+    }
+}
+
+impl std::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let subscribers = self.subscribers.lock().unwrap();
+        f.debug_struct("EventBus")
+            .field("trading", &subscribers.trading.len())
+            .field("market", &subscribers.market.len())
+            .field("dropped", &subscribers.dropped)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn risk(reason: &str) -> BusMessage {
+        BusMessage::Risk {
+            symbol: "BTCUSDT".to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    #[test]
+    fn a_full_queue_drops_and_counts_instead_of_blocking() {
+        let bus = EventBus::default();
+        let mut slow = bus.subscribe();
+        for _ in 0..TRADING_QUEUE + 2 {
+            bus.publish(risk("limit"));
+        }
+        assert_eq!(bus.dropped().trading, 2);
+        assert!(slow.try_recv().is_ok());
+        // Room again once the subscriber catches up
+        bus.publish(risk("limit"));
+        assert_eq!(bus.dropped().trading, 2);
+    }
+
+    #[test]
+    fn closed_subscribers_are_removed_not_counted() {
+        let bus = EventBus::default();
+        drop(bus.subscribe());
+        bus.publish(risk("limit"));
+        assert_eq!(bus.dropped(), DroppedEvents::default());
+        assert_eq!(bus.subscribers.lock().unwrap().trading.len(), 0);
+    }
+}
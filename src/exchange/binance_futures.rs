@@ -0,0 +1,635 @@
+use std::collections::VecDeque;
+
+use futures_util::StreamExt;
+use hmac::{Hmac, Mac};
+use hyper::{Body, Method, Request};
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::config::{FuturesSettings, MarginType};
+use crate::domain::*;
+use crate::dto::{self, parse_kline_update, parse_ticker_update, Kline, TickerData};
+use crate::health::{self, SharedHealth};
+use crate::notify::{https_client, HttpsClient};
+use crate::recorder::{self, SharedRecorder, StreamKind};
+use crate::secrets::ApiCredentials;
+use crate::streams::StreamRegistry;
+
+const REST_URL: &str = "https://fapi.binance.com";
+const WS_URL: &str = "wss://fstream.binance.com/stream";
+const TESTNET_REST_URL: &str = "https://testnet.binancefuture.com";
+const TESTNET_WS_URL: &str = "wss://stream.binancefuture.com/stream";
+
+// Binance answers these when the setting already has the requested value
+const NO_MARGIN_CHANGE: i64 = -4046;
+const NO_POSITION_MODE_CHANGE: i64 = -4059;
+const ORDER_DOES_NOT_EXIST: i64 = -2013;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PositionSide {
+    Long,
+    Short,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    code: i64,
+    msg: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Balance {
+    asset: String,
+    available_balance: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PositionRisk {
+    position_amt: String,
+    position_side: String,
+}
+
+// As placing, querying and listing orders return them
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OrderInfo {
+    order_id: i64,
+    status: String,
+    executed_qty: String,
+    avg_price: String,
+}
+
+/// Funding of a perpetual as of now
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FundingRate {
+    #[serde(rename = "lastFundingRate")]
+    pub rate: String,
+    pub mark_price: String,
+    // Milliseconds
+    pub next_funding_time: i64,
+}
+
+/// Binance USDT-M perpetual futures client for one symbol. Spot-style signals
+/// are netted against the open position: a sell without a long opens a short,
+/// a buy against a short closes it.
+pub struct BinanceFuturesClient {
+    http: HttpsClient,
+    credentials: ApiCredentials,
+    symbol: Symbol,
+    settings: FuturesSettings,
+    rest_url: &'static str,
+    connected: bool,
+}
+
+impl BinanceFuturesClient {
+    pub fn new(
+        credentials: ApiCredentials,
+        symbol: Symbol,
+        settings: FuturesSettings,
+        testnet: bool,
+    ) -> Self {
+        BinanceFuturesClient {
+            http: https_client(),
+            credentials,
+            symbol,
+            settings,
+            rest_url: if testnet { TESTNET_REST_URL } else { REST_URL },
+            connected: false,
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        self.symbol.as_str()
+    }
+
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        params: &[(&str, String)],
+        signed: bool,
+    ) -> Result<T, TradingError> {
+        let mut query: String = params
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("&");
+        if signed {
+            if !query.is_empty() {
+                query.push('&');
+            }
+            query.push_str(&format!(
+                "recvWindow=5000&timestamp={}",
+                chrono::Utc::now().timestamp_millis()
+            ));
+            let mut mac = Hmac::<Sha256>::new_from_slice(self.credentials.api_secret.as_bytes())
+                .map_err(|e| TradingError::AuthenticationError(format!("{:?}", e)))?;
+            mac.update(query.as_bytes());
+            let signature: String = mac
+                .finalize()
+                .into_bytes()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            query.push_str(&format!("&signature={}", signature));
+        }
+        let request = Request::builder()
+            .method(method)
+            .uri(format!("{}{}?{}", self.rest_url, path, query))
+            .header("X-MBX-APIKEY", &self.credentials.api_key)
+            .body(Body::empty())
+            .map_err(|e| TradingError::DataError(format!("{:?}", e)))?;
+        let response = self
+            .http
+            .request(request)
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+        if status.is_success() {
+            return Ok(serde_json::from_slice(&body)?);
+        }
+        let error: ApiError = serde_json::from_slice(&body)?;
+        Err(match (status.as_u16(), error.code) {
+            (401, _) | (_, -2014 | -2015) => TradingError::AuthenticationError(error.msg),
+            (429 | 418 | 500..=599, _) => TradingError::NetworkError(error.msg),
+            (_, ORDER_DOES_NOT_EXIST) => TradingError::UnknownOrder(error.msg),
+            (_, code) => TradingError::OrderError(format!("{} ({})", error.msg, code)),
+        })
+    }
+
+    // Answers carrying one of `unchanged` codes mean nothing needed changing
+    async fn configure(
+        &self,
+        path: &str,
+        params: &[(&str, String)],
+        unchanged: Option<i64>,
+    ) -> Result<(), TradingError> {
+        match self
+            .request::<serde_json::Value>(Method::POST, path, params, true)
+            .await
+        {
+            Err(TradingError::OrderError(message))
+                if unchanged.is_some_and(|code| message.ends_with(&format!("({})", code))) =>
+            {
+                Ok(())
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
+    pub async fn set_leverage(&self, leverage: u32) -> Result<(), TradingError> {
+        let params = [
+            ("symbol", self.symbol.to_string()),
+            ("leverage", leverage.to_string()),
+        ];
+        self.configure("/fapi/v1/leverage", &params, None).await
+    }
+
+    pub async fn set_margin_type(&self, margin: MarginType) -> Result<(), TradingError> {
+        let margin = match margin {
+            MarginType::Isolated => "ISOLATED",
+            MarginType::Cross => "CROSSED",
+        };
+        let params = [
+            ("symbol", self.symbol.to_string()),
+            ("marginType", margin.to_string()),
+        ];
+        self.configure("/fapi/v1/marginType", &params, Some(NO_MARGIN_CHANGE))
+            .await
+    }
+
+    // Hedge mode holds a long and a short side at once; applies to the whole account
+    pub async fn set_position_mode(&self, hedge: bool) -> Result<(), TradingError> {
+        let params = [("dualSidePosition", hedge.to_string())];
+        self.configure(
+            "/fapi/v1/positionSide/dual",
+            &params,
+            Some(NO_POSITION_MODE_CHANGE),
+        )
+        .await
+    }
+
+    pub async fn funding_rate(&self) -> Result<FundingRate, TradingError> {
+        let params = [("symbol", self.symbol.to_string())];
+        self.request(Method::GET, "/fapi/v1/premiumIndex", &params, false)
+            .await
+    }
+
+    // Open (long, short) quantities of the symbol, both positive
+    pub async fn positions(&self) -> Result<(Decimal, Decimal), TradingError> {
+        let params = [("symbol", self.symbol.to_string())];
+        let risks: Vec<PositionRisk> = self
+            .request(Method::GET, "/fapi/v2/positionRisk", &params, true)
+            .await?;
+        let (mut long, mut short) = (Decimal::ZERO, Decimal::ZERO);
+        for risk in risks {
+            let amount: Decimal = risk.position_amt.parse().unwrap_or_default();
+            match risk.position_side.as_str() {
+                "LONG" => long += amount.abs(),
+                "SHORT" => short += amount.abs(),
+                // One-way mode: the sign is the side
+                _ if amount > Decimal::ZERO => long += amount,
+                _ => short -= amount,
+            }
+        }
+        Ok((long, short))
+    }
+
+    // `order` gives the type and client order id; its side and quantity follow
+    // from the position
+    pub async fn open_position(
+        &mut self,
+        side: PositionSide,
+        quantity: Decimal,
+        order: &Order,
+    ) -> Result<OrderResponse, TradingError> {
+        let order_side = match side {
+            PositionSide::Long => OrderSide::Buy,
+            PositionSide::Short => OrderSide::Sell,
+        };
+        self.submit(side, order_side, quantity, order, false).await
+    }
+
+    pub async fn close_position(
+        &mut self,
+        side: PositionSide,
+        quantity: Decimal,
+        order: &Order,
+    ) -> Result<OrderResponse, TradingError> {
+        let order_side = match side {
+            PositionSide::Long => OrderSide::Sell,
+            PositionSide::Short => OrderSide::Buy,
+        };
+        self.submit(side, order_side, quantity, order, true).await
+    }
+
+    async fn submit(
+        &mut self,
+        position: PositionSide,
+        side: OrderSide,
+        quantity: Decimal,
+        order: &Order,
+        reduce: bool,
+    ) -> Result<OrderResponse, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let mut params = vec![
+            ("symbol", self.symbol.to_string()),
+            (
+                "side",
+                match side {
+                    OrderSide::Buy => "BUY",
+                    OrderSide::Sell => "SELL",
+                }
+                .to_string(),
+            ),
+            ("quantity", quantity.to_string()),
+            ("newOrderRespType", "RESULT".to_string()),
+        ];
+        if let Some(id) = &order.client_order_id {
+            params.push(("newClientOrderId", id.clone()));
+        }
+        match &order.order_type {
+            OrderType::Market => params.push(("type", "MARKET".to_string())),
+            OrderType::Limit(price) => {
+                params.push(("type", "LIMIT".to_string()));
+                params.push(("price", price.to_string()));
+                // GTX is futures' post-only
+                let time_in_force = if order.post_only {
+                    "GTX"
+                } else {
+                    order.time_in_force.as_str()
+                };
+                params.push(("timeInForce", time_in_force.to_string()));
+            }
+            OrderType::Stop(price) => {
+                params.push(("type", "STOP_MARKET".to_string()));
+                params.push(("stopPrice", price.to_string()));
+            }
+            // Binance takes callbackRate in percent, 0.1 to 10
+            OrderType::TrailingStop {
+                activation_price,
+                callback_rate,
+            } => {
+                params.push(("type", "TRAILING_STOP_MARKET".to_string()));
+                params.push(("activationPrice", activation_price.to_string()));
+                params.push(("callbackRate", callback_rate.to_string()));
+            }
+        }
+        if self.settings.hedge_mode {
+            let position = match position {
+                PositionSide::Long => "LONG",
+                PositionSide::Short => "SHORT",
+            };
+            params.push(("positionSide", position.to_string()));
+        } else if reduce {
+            // Hedge mode rejects reduceOnly; the position side says it there
+            params.push(("reduceOnly", "true".to_string()));
+        }
+        let placed: OrderInfo = self
+            .request(Method::POST, "/fapi/v1/order", &params, true)
+            .await?;
+        Ok(self.order_response(placed))
+    }
+
+    fn order_response(&self, order: OrderInfo) -> OrderResponse {
+        let filled: Decimal = order.executed_qty.parse().unwrap_or_default();
+        let fills = if filled > Decimal::ZERO {
+            // Commissions only come with the user data stream's trade events
+            vec![Fill {
+                price: order.avg_price.parse().unwrap_or_default(),
+                quantity: filled,
+                commission: Decimal::ZERO,
+                commission_asset: self.symbol.quote_asset().unwrap_or_default().to_string(),
+            }]
+        } else {
+            Vec::new()
+        };
+        OrderResponse {
+            order_id: order.order_id.to_string(),
+            status: dto::parse_order_status(&order.status),
+            fills,
+        }
+    }
+
+    // Last `count` closed candles' closes, like BinanceExchangeClient::start
+    pub async fn recent_closes(
+        &self,
+        interval: Interval,
+        count: usize,
+    ) -> Result<VecDeque<f64>, TradingError> {
+        let params = [
+            ("symbol", self.symbol.to_string()),
+            ("interval", interval.to_string()),
+            ("limit", (count + 1).to_string()),
+        ];
+        let rows: serde_json::Value = self
+            .request(Method::GET, "/fapi/v1/klines", &params, false)
+            .await?;
+        // Same rows as the spot API
+        let mut closes: VecDeque<f64> = dto::parse_klines(&rows.to_string())?
+            .iter()
+            .map(|candle| candle.close_price)
+            .collect();
+        closes.pop_back();
+        Ok(closes)
+    }
+}
+
+impl ExchangeClient for BinanceFuturesClient {
+    async fn connect(&mut self) -> Result<(), TradingError> {
+        let settings = self.settings.clone();
+        let configured = async {
+            self.set_position_mode(settings.hedge_mode).await?;
+            self.set_margin_type(settings.margin).await?;
+            self.set_leverage(settings.leverage).await
+        };
+        if let Err(e) = configured.await {
+            log::error!("Failed to configure {} futures: {}", self.symbol, e);
+            return Err(match e {
+                TradingError::NetworkError(_) => {
+                    TradingError::ConnectionError("Failed to connect".into())
+                }
+                e => e,
+            });
+        }
+        self.connected = true;
+        log::info!(
+            "Connected to Binance futures: {} at {}x, {:?} margin",
+            self.symbol,
+            settings.leverage,
+            settings.margin
+        );
+        match self.funding_rate().await {
+            Ok(funding) => log::info!(
+                "{} funding rate {} (mark {}), next at {}",
+                self.symbol,
+                funding.rate,
+                funding.mark_price,
+                funding.next_funding_time
+            ),
+            Err(e) => log::warn!("Failed to read the funding rate: {}", e),
+        }
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<(), TradingError> {
+        self.connected = false;
+        Ok(())
+    }
+
+    // Available margin in the symbol's quote asset
+    async fn get_balance(&self) -> Result<Decimal, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let quote = self.symbol.quote_asset().unwrap_or_default();
+        let balances: Vec<Balance> = self
+            .request(Method::GET, "/fapi/v2/balance", &[], true)
+            .await?;
+        balances
+            .iter()
+            .find(|balance| balance.asset == quote)
+            .map_or(Ok(Decimal::ZERO), |balance| {
+                balance.available_balance.parse()
+            })
+            .map_err(|_| TradingError::DataError(format!("Invalid {} balance", quote)))
+    }
+
+    async fn send_order(&mut self, order: &Order) -> Result<OrderResponse, TradingError> {
+        let (long, short) = self.positions().await?;
+        match order.side {
+            OrderSide::Buy if short > Decimal::ZERO => {
+                self.close_position(PositionSide::Short, order.quantity.min(short), order)
+                    .await
+            }
+            OrderSide::Buy => {
+                self.open_position(PositionSide::Long, order.quantity, order)
+                    .await
+            }
+            OrderSide::Sell if long > Decimal::ZERO => {
+                self.close_position(PositionSide::Long, order.quantity.min(long), order)
+                    .await
+            }
+            OrderSide::Sell => {
+                self.open_position(PositionSide::Short, order.quantity, order)
+                    .await
+            }
+        }
+    }
+
+    async fn cancel_order(&mut self, symbol: &str, order_id: &str) -> Result<(), TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let params = [
+            ("symbol", symbol.to_string()),
+            ("orderId", order_id.to_string()),
+        ];
+        let _: serde_json::Value = self
+            .request(Method::DELETE, "/fapi/v1/order", &params, true)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_open_orders(&self, symbol: &str) -> Result<Vec<OrderResponse>, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let params = [("symbol", symbol.to_string())];
+        let orders: Vec<OrderInfo> = self
+            .request(Method::GET, "/fapi/v1/openOrders", &params, true)
+            .await?;
+        Ok(orders
+            .into_iter()
+            .map(|order| self.order_response(order))
+            .collect())
+    }
+
+    async fn get_order_status(
+        &self,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        // Exchange ids are numeric; anything else is a client order id
+        let id_param = match order_id.parse::<u64>() {
+            Ok(_) => "orderId",
+            Err(_) => "origClientOrderId",
+        };
+        let params = [
+            ("symbol", symbol.to_string()),
+            (id_param, order_id.to_string()),
+        ];
+        let order: OrderInfo = self
+            .request(Method::GET, "/fapi/v1/order", &params, true)
+            .await?;
+        Ok(self.order_response(order))
+    }
+
+    // A sell without a long opens a short, see send_order
+    fn supports_short(&self, _symbol: &str) -> bool {
+        true
+    }
+
+    // USDT-M futures have no OCO order lists
+    async fn place_oco_order(
+        &mut self,
+        symbol: &str,
+        _side: OrderSide,
+        _quantity: Decimal,
+        _bracket: &Bracket,
+    ) -> Result<OrderResponse, TradingError> {
+        Err(TradingError::OrderError(format!(
+            "Binance futures have no OCO orders, {} bracket not placed",
+            symbol
+        )))
+    }
+}
+
+#[derive(Deserialize)]
+struct StreamName<'a> {
+    stream: &'a str,
+}
+
+// Kline and ticker streams of the perpetual on one combined connection. The
+// frames match the spot streams, so they're parsed and recorded the same way.
+pub async fn get_market_data(
+    symbol: Symbol,
+    interval: Interval,
+    klines: mpsc::Sender<Kline>,
+    tickers: mpsc::Sender<TickerData>,
+    recorder: Option<SharedRecorder>,
+    health: SharedHealth,
+    streams: StreamRegistry,
+    testnet: bool,
+) {
+    let name = symbol.stream_name();
+    let kline_stream = format!("{}@kline_{}", name, interval);
+    let ticker_stream = format!("{}@ticker", name);
+    let base = if testnet { TESTNET_WS_URL } else { WS_URL };
+    let url = format!("{}?streams={}/{}", base, kline_stream, ticker_stream);
+    let mut resubscribe = streams.register("kline", kline_stream);
+    streams.register("ticker", ticker_stream);
+    loop {
+        streams.connecting("kline");
+        streams.connecting("ticker");
+        let (mut conn, _) = match tokio_tungstenite::connect_async(url.as_str()).await {
+            Ok(conn) => conn,
+            Err(e) => {
+                streams.disconnected("kline", format!("{:?}", e));
+                streams.disconnected("ticker", format!("{:?}", e));
+                return;
+            }
+        };
+        streams.connected("kline");
+        streams.connected("ticker");
+        let dropped = loop {
+            let message = tokio::select! {
+                message = conn.next() => message,
+                Ok(()) = resubscribe.changed() => break None,
+            };
+            let text = match message {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => break Some(format!("{:?}", e)),
+                None => break Some("closed by the exchange".to_string()),
+            };
+            let is_ticker = match serde_json::from_str::<StreamName>(&text) {
+                Ok(frame) => frame.stream.ends_with("@ticker"),
+                Err(e) => {
+                    streams.parse_error("kline", format!("{} raw data: {}", e, text));
+                    continue;
+                }
+            };
+            if is_ticker {
+                recorder::record(&recorder, StreamKind::Ticker, &text);
+                health::mark_message(&health, "ticker");
+                streams.message("ticker");
+                match parse_ticker_update(&text) {
+                    Ok(ticker) => {
+                        if let Err(e) = tickers.send(ticker).await {
+                            log::error!("Failed to send ticker data: {}", e);
+                        }
+                    }
+                    Err(e) => streams.parse_error("ticker", format!("{} raw data: {}", e, text)),
+                }
+            } else {
+                recorder::record(&recorder, StreamKind::Kline, &text);
+                health::mark_message(&health, "kline");
+                streams.message("kline");
+                match parse_kline_update(&text) {
+                    Ok(kline) => {
+                        if let Err(e) = klines.send(kline).await {
+                            log::error!("Failed to send kline data: {}", e);
+                        }
+                    }
+                    Err(e) => streams.parse_error("kline", format!("{} raw data: {}", e, text)),
+                }
+            }
+        };
+        if let Err(e) = conn.close(None).await {
+            log::warn!("Failed to close futures stream: {:?}", e);
+        }
+        if let Some(reason) = dropped {
+            // The supervisor restarts the task
+            streams.disconnected("kline", reason.clone());
+            streams.disconnected("ticker", reason);
+            return;
+        }
+        log::info!("Resubscribing futures streams");
+    }
+}
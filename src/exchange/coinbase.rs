@@ -0,0 +1,786 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL;
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use hyper::{Body, Method, Request};
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey};
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::domain::*;
+use crate::dto::{Kline, KlineResponse, TickerData};
+use crate::health::{self, SharedHealth};
+use crate::notify::{https_client, HttpsClient};
+use crate::secrets::ApiCredentials;
+use crate::streams::StreamRegistry;
+
+const REST_HOST: &str = "api.coinbase.com";
+const REST_PATH: &str = "/api/v3/brokerage";
+const WS_URL: &str = "wss://advanced-trade-ws.coinbase.com";
+
+// BTCUSD -> BTC-USD, BTCUSDC -> BTC-USDC
+pub fn product_id(symbol: &Symbol) -> Result<String, TradingError> {
+    match (symbol.base_asset(), symbol.quote_asset()) {
+        (Some(base), Some(quote)) => Ok(format!("{}-{}", base, quote)),
+        _ => Err(TradingError::InvalidParameter(format!(
+            "Cannot tell the quote asset of {}",
+            symbol
+        ))),
+    }
+}
+
+// BTC-USDT -> BTCUSDT
+pub fn symbol_from_product(product: &str) -> Result<Symbol, TradingError> {
+    product.replace('-', "").parse()
+}
+
+// Intervals both the REST candles and the five-minute stream can serve
+fn granularity(interval: Interval) -> Result<&'static str, TradingError> {
+    match interval {
+        Interval::Minutes5 => Ok("FIVE_MINUTE"),
+        Interval::Minutes15 => Ok("FIFTEEN_MINUTE"),
+        Interval::Minutes30 => Ok("THIRTY_MINUTE"),
+        Interval::Hours1 => Ok("ONE_HOUR"),
+        Interval::Hours2 => Ok("TWO_HOUR"),
+        Interval::Hours6 => Ok("SIX_HOUR"),
+        Interval::Days1 => Ok("ONE_DAY"),
+        _ => Err(TradingError::InvalidParameter(format!(
+            "Coinbase has no {} candles",
+            interval
+        ))),
+    }
+}
+
+// COINBASE_API_KEY is the key name ("organizations/.../apiKeys/...") and
+// COINBASE_API_SECRET its EC private key in PEM
+pub fn credentials_from_env() -> Result<ApiCredentials, TradingError> {
+    let var = |name: &str| {
+        dotenv::var(name)
+            .map_err(|_| TradingError::AuthenticationError(format!("{} must be set", name)))
+    };
+    Ok(ApiCredentials {
+        api_key: var("COINBASE_API_KEY")?,
+        // .env files usually carry the PEM on one line
+        api_secret: var("COINBASE_API_SECRET")?.replace("\\n", "\n"),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct Accounts {
+    accounts: Vec<Account>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Account {
+    currency: String,
+    available_balance: Amount,
+}
+
+#[derive(Debug, Deserialize)]
+struct Amount {
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateOrder {
+    success: bool,
+    success_response: Option<CreatedOrder>,
+    error_response: Option<OrderFailure>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreatedOrder {
+    order_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrderFailure {
+    error: String,
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoricalOrder {
+    order: OrderInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoricalOrders {
+    orders: Vec<OrderInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrderInfo {
+    order_id: String,
+    status: String,
+    filled_size: String,
+    average_filled_price: String,
+    total_fees: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CancelResults {
+    results: Vec<CancelResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CancelResult {
+    success: bool,
+    #[serde(default)]
+    failure_reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Candles {
+    candles: Vec<RestCandle>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RestCandle {
+    start: String,
+    low: String,
+    high: String,
+    open: String,
+    close: String,
+    volume: String,
+}
+
+fn float(value: &str) -> Result<f64, TradingError> {
+    value
+        .parse()
+        .map_err(|_| TradingError::DataError(format!("Invalid number {:?}", value)))
+}
+
+fn amount(value: &str) -> Result<Decimal, TradingError> {
+    value
+        .parse()
+        .map_err(|_| TradingError::DataError(format!("Invalid amount {:?}", value)))
+}
+
+fn order_response(info: OrderInfo, symbol: &Symbol) -> OrderResponse {
+    let filled = amount(&info.filled_size).unwrap_or_default();
+    let status = match info.status.as_str() {
+        "FILLED" => OrderStatus::Filled,
+        "CANCELLED" | "EXPIRED" => OrderStatus::Canceled,
+        "FAILED" => OrderStatus::Rejected,
+        _ if filled > Decimal::ZERO => OrderStatus::PartiallyFilled,
+        _ => OrderStatus::Pending,
+    };
+    let fills = if filled > Decimal::ZERO {
+        vec![Fill {
+            price: amount(&info.average_filled_price).unwrap_or_default(),
+            quantity: filled,
+            commission: amount(&info.total_fees).unwrap_or_default(),
+            commission_asset: symbol.quote_asset().unwrap_or_default().to_string(),
+        }]
+    } else {
+        Vec::new()
+    };
+    OrderResponse {
+        order_id: info.order_id,
+        status,
+        fills,
+    }
+}
+
+/// Coinbase Advanced Trade client for one symbol, the Coinbase counterpart of
+/// the Binance and Kraken clients. Requests are signed with a short-lived
+/// ES256 JWT made from a CDP API key.
+pub struct CoinbaseExchangeClient {
+    http: HttpsClient,
+    key_name: String,
+    key: SigningKey,
+    symbol: Symbol,
+    connected: bool,
+}
+
+impl CoinbaseExchangeClient {
+    pub fn new(credentials: ApiCredentials, symbol: Symbol) -> Result<Self, TradingError> {
+        let key = p256::SecretKey::from_sec1_pem(&credentials.api_secret)
+            .map_err(|e| TradingError::AuthenticationError(format!("{:?}", e)))?;
+        Ok(CoinbaseExchangeClient {
+            http: https_client(),
+            key_name: credentials.api_key,
+            key: SigningKey::from(key),
+            symbol,
+            connected: false,
+        })
+    }
+
+    pub fn symbol(&self) -> &str {
+        self.symbol.as_str()
+    }
+
+    // Bearer token for one request, valid for two minutes
+    fn jwt(&self, method: &Method, path: &str) -> String {
+        let now = chrono::Utc::now().timestamp();
+        let header = serde_json::json!({
+            "alg": "ES256",
+            "typ": "JWT",
+            "kid": self.key_name,
+            "nonce": format!("{:032x}", rand::random::<u128>()),
+        });
+        let claims = serde_json::json!({
+            "sub": self.key_name,
+            "iss": "cdp",
+            "nbf": now,
+            "exp": now + 120,
+            "uri": format!("{} {}{}", method, REST_HOST, path),
+        });
+        let message = format!(
+            "{}.{}",
+            BASE64URL.encode(header.to_string()),
+            BASE64URL.encode(claims.to_string())
+        );
+        let signature: Signature = self.key.sign(message.as_bytes());
+        format!("{}.{}", message, BASE64URL.encode(signature.to_bytes()))
+    }
+
+    // `endpoint` is relative to the brokerage API and may carry a query
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        endpoint: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<T, TradingError> {
+        let path = format!("{}{}", REST_PATH, endpoint);
+        // The token covers the path without its query
+        let signed_path = path.split('?').next().unwrap_or(&path);
+        let request = Request::builder()
+            .method(method.clone())
+            .uri(format!("https://{}{}", REST_HOST, path))
+            .header(
+                "Authorization",
+                format!("Bearer {}", self.jwt(&method, signed_path)),
+            )
+            .header("Content-Type", "application/json")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .map_err(|e| TradingError::DataError(format!("{:?}", e)))?;
+        let response = self
+            .http
+            .request(request)
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+        let text = String::from_utf8_lossy(&body);
+        match status.as_u16() {
+            200..=299 => Ok(serde_json::from_slice(&body)?),
+            401 | 403 => Err(TradingError::AuthenticationError(text.into_owned())),
+            429 | 500..=599 => Err(TradingError::NetworkError(text.into_owned())),
+            _ => Err(TradingError::OrderError(text.into_owned())),
+        }
+    }
+
+    // Closed candles, oldest first, ending now
+    pub async fn get_klines(
+        &self,
+        interval: Interval,
+        count: usize,
+    ) -> Result<Vec<KlineResponse>, TradingError> {
+        let granularity = granularity(interval)?;
+        let interval_ms = interval.millis();
+        let end = chrono::Utc::now().timestamp();
+        // One extra for the candle still forming; Coinbase serves at most 350
+        let span = (count.min(349) as i64 + 1) * interval_ms / 1000;
+        let endpoint = format!(
+            "/market/products/{}/candles?start={}&end={}&granularity={}",
+            product_id(&self.symbol)?,
+            end - span,
+            end,
+            granularity
+        );
+        let candles: Candles = self.request(Method::GET, &endpoint, None).await?;
+        let mut klines = Vec::with_capacity(candles.candles.len());
+        // Newest first
+        for candle in candles.candles.iter().rev() {
+            let start: i64 = candle
+                .start
+                .parse()
+                .map_err(|_| TradingError::DataError(format!("Invalid time {}", candle.start)))?;
+            let open_time = chrono::DateTime::from_timestamp(start, 0)
+                .ok_or_else(|| TradingError::DataError(format!("Invalid time {}", start)))?;
+            let close_price = float(&candle.close)?;
+            let volume = float(&candle.volume)?;
+            klines.push(KlineResponse {
+                open_time,
+                open_price: float(&candle.open)?,
+                high_price: float(&candle.high)?,
+                low_price: float(&candle.low)?,
+                close_price,
+                volume,
+                close_time: open_time + chrono::Duration::milliseconds(interval_ms - 1),
+                // Coinbase reports no quote volume; estimated from the close
+                quote_asset_volume: volume * close_price,
+                number_of_trades: 0,
+                taker_buy_base_volume: 0.0,
+                taker_buy_quote_volume: 0.0,
+            });
+        }
+        if klines
+            .last()
+            .is_some_and(|k| k.close_time.timestamp() >= end)
+        {
+            klines.pop();
+        }
+        Ok(klines)
+    }
+
+    // Last `count` closes for the analysis task, like BinanceExchangeClient::start
+    pub async fn recent_closes(
+        &self,
+        interval: Interval,
+        count: usize,
+    ) -> Result<VecDeque<f64>, TradingError> {
+        Ok(self
+            .get_klines(interval, count)
+            .await?
+            .iter()
+            .map(|candle| candle.close_price)
+            .collect())
+    }
+}
+
+impl ExchangeClient for CoinbaseExchangeClient {
+    async fn connect(&mut self) -> Result<(), TradingError> {
+        // An authenticated call checks the key as well as the connection
+        let _: Accounts = self
+            .request(Method::GET, "/accounts?limit=1", None)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to connect to Coinbase: {}", e);
+                TradingError::ConnectionError("Failed to connect".into())
+            })?;
+        self.connected = true;
+        log::info!("Connected to Coinbase");
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<(), TradingError> {
+        self.connected = false;
+        Ok(())
+    }
+
+    // Available balance of the symbol's quote asset
+    async fn get_balance(&self) -> Result<Decimal, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let quote = self.symbol.quote_asset().unwrap_or_default();
+        let accounts: Accounts = self
+            .request(Method::GET, "/accounts?limit=250", None)
+            .await?;
+        accounts
+            .accounts
+            .iter()
+            .find(|account| account.currency == quote)
+            .map_or(Ok(Decimal::ZERO), |account| {
+                amount(&account.available_balance.value)
+            })
+    }
+
+    async fn send_order(&mut self, order: &Order) -> Result<OrderResponse, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let symbol: Symbol = order.symbol.parse()?;
+        let size = order.quantity.to_string();
+        let configuration = match order.order_type {
+            OrderType::Market => serde_json::json!({
+                "market_market_ioc": {"base_size": size},
+            }),
+            OrderType::Limit(price) => match (order.time_in_force, order.post_only) {
+                (TimeInForce::Gtc, post_only) => serde_json::json!({
+                    "limit_limit_gtc": {
+                        "base_size": size,
+                        "limit_price": price.to_string(),
+                        "post_only": post_only,
+                    },
+                }),
+                (TimeInForce::Ioc, false) => serde_json::json!({
+                    "sor_limit_ioc": {
+                        "base_size": size,
+                        "limit_price": price.to_string(),
+                    },
+                }),
+                (TimeInForce::Fok, false) => serde_json::json!({
+                    "limit_limit_fok": {
+                        "base_size": size,
+                        "limit_price": price.to_string(),
+                    },
+                }),
+                (_, true) => {
+                    return Err(TradingError::OrderError(
+                        "Coinbase post-only orders are good till cancelled only".into(),
+                    ))
+                }
+            },
+            // Coinbase only has stop-limits; limit at the stop price
+            OrderType::Stop(price) => serde_json::json!({
+                "stop_limit_stop_limit_gtc": {
+                    "base_size": size,
+                    "limit_price": price.to_string(),
+                    "stop_price": price.to_string(),
+                    "stop_direction": match order.side {
+                        OrderSide::Buy => "STOP_DIRECTION_STOP_UP",
+                        OrderSide::Sell => "STOP_DIRECTION_STOP_DOWN",
+                    },
+                },
+            }),
+            OrderType::TrailingStop { .. } => {
+                return Err(TradingError::OrderError(
+                    "Coinbase has no trailing stop orders".into(),
+                ))
+            }
+        };
+        let body = serde_json::json!({
+            // Coinbase answers a reused client order id with the existing order
+            "client_order_id": order
+                .client_order_id
+                .clone()
+                .unwrap_or_else(|| format!("{:032x}", rand::random::<u128>())),
+            "product_id": product_id(&symbol)?,
+            "side": match order.side {
+                OrderSide::Buy => "BUY",
+                OrderSide::Sell => "SELL",
+            },
+            "order_configuration": configuration,
+        });
+        let created: CreateOrder = self.request(Method::POST, "/orders", Some(body)).await?;
+        let order_id = match (
+            created.success,
+            created.success_response,
+            created.error_response,
+        ) {
+            (true, Some(response), _) => response.order_id,
+            (_, _, Some(failure)) => {
+                return Err(TradingError::OrderError(format!(
+                    "{}: {}",
+                    failure.error, failure.message
+                )))
+            }
+            _ => return Err(TradingError::OrderError("Order was not accepted".into())),
+        };
+
+        // The create call only acknowledges; the fill is read back from the order
+        self.get_order_status(&order.symbol, &order_id).await
+    }
+
+    async fn cancel_order(&mut self, _symbol: &str, order_id: &str) -> Result<(), TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let body = serde_json::json!({ "order_ids": [order_id] });
+        let cancelled: CancelResults = self
+            .request(Method::POST, "/orders/batch_cancel", Some(body))
+            .await?;
+        match cancelled.results.first() {
+            Some(result) if result.success => Ok(()),
+            Some(result) => Err(TradingError::OrderError(result.failure_reason.clone())),
+            None => Err(TradingError::OrderError(format!(
+                "No cancel result for {}",
+                order_id
+            ))),
+        }
+    }
+
+    async fn get_open_orders(&self, symbol: &str) -> Result<Vec<OrderResponse>, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let symbol: Symbol = symbol.parse()?;
+        let endpoint = format!(
+            "/orders/historical/batch?product_ids={}&order_status=OPEN",
+            product_id(&symbol)?
+        );
+        let orders: HistoricalOrders = self.request(Method::GET, &endpoint, None).await?;
+        Ok(orders
+            .orders
+            .into_iter()
+            .map(|info| order_response(info, &symbol))
+            .collect())
+    }
+
+    async fn get_order_status(
+        &self,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let symbol: Symbol = symbol.parse()?;
+        let historical: HistoricalOrder = self
+            .request(
+                Method::GET,
+                &format!("/orders/historical/{}", order_id),
+                None,
+            )
+            .await?;
+        Ok(order_response(historical.order, &symbol))
+    }
+
+    // Advanced Trade has no one-cancels-the-other orders
+    async fn place_oco_order(
+        &mut self,
+        symbol: &str,
+        _side: OrderSide,
+        _quantity: Decimal,
+        _bracket: &Bracket,
+    ) -> Result<OrderResponse, TradingError> {
+        Err(TradingError::OrderError(format!(
+            "Coinbase has no OCO orders, {} bracket not placed",
+            symbol
+        )))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WsMessage {
+    channel: String,
+    #[serde(default)]
+    events: Vec<WsEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WsEvent {
+    #[serde(default)]
+    candles: Vec<WsCandle>,
+    #[serde(default)]
+    tickers: Vec<WsTicker>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct WsCandle {
+    start: String,
+    high: String,
+    low: String,
+    open: String,
+    close: String,
+    volume: String,
+    product_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WsTicker {
+    product_id: String,
+    price: String,
+    volume_24_h: String,
+}
+
+// Five-minute candles of one bar of the configured interval, by start time
+#[derive(Default)]
+struct Bar {
+    start: i64,
+    pieces: BTreeMap<i64, WsCandle>,
+}
+
+impl Bar {
+    fn kline(&self, interval: Interval, closed: bool) -> Result<Kline, TradingError> {
+        let mut pieces = self.pieces.values();
+        let first = pieces
+            .next()
+            .ok_or_else(|| TradingError::DataError("Empty candle".into()))?;
+        let (mut high, mut low) = (float(&first.high)?, float(&first.low)?);
+        let mut volume = float(&first.volume)?;
+        let mut last = first;
+        for piece in pieces {
+            high = high.max(float(&piece.high)?);
+            low = low.min(float(&piece.low)?);
+            volume += float(&piece.volume)?;
+            last = piece;
+        }
+        Ok(Kline {
+            start_time: self.start,
+            end_time: self.start + interval.millis() - 1,
+            symbol: symbol_from_product(&first.product_id)?.to_string(),
+            interval: interval.to_string(),
+            open_price: first.open.clone(),
+            close_price: last.close.clone(),
+            high_price: high.to_string(),
+            low_price: low.to_string(),
+            volume: volume.to_string(),
+            is_closed: closed,
+            ..Kline::default()
+        })
+    }
+}
+
+// Candles and ticker channels of one product, converted to the Binance stream
+// DTOs the rest of the bot reads. The feed only has five-minute candles, so
+// longer intervals are assembled from them; a bar is passed on as closed once
+// a candle of the next bar arrives.
+pub async fn get_market_data(
+    symbol: Symbol,
+    interval: Interval,
+    klines: mpsc::Sender<Kline>,
+    tickers: mpsc::Sender<TickerData>,
+    health: SharedHealth,
+    streams: StreamRegistry,
+) {
+    let product = match (product_id(&symbol), granularity(interval)) {
+        (Ok(product), Ok(_)) => product,
+        (Err(e), _) | (_, Err(e)) => {
+            log::error!("Cannot stream {} from Coinbase: {}", symbol, e);
+            return;
+        }
+    };
+    let mut resubscribe = streams.register("kline", format!("candles:{}", product));
+    streams.register("ticker", format!("ticker:{}", product));
+    // Heartbeats keep the connection open through quiet markets
+    let subscriptions = ["candles", "ticker", "heartbeats"].map(|channel| {
+        serde_json::json!({
+            "type": "subscribe",
+            "product_ids": [product],
+            "channel": channel,
+        })
+    });
+    let interval_ms = interval.millis();
+    loop {
+        streams.connecting("kline");
+        streams.connecting("ticker");
+        let (mut conn, _) = match tokio_tungstenite::connect_async(WS_URL).await {
+            Ok(conn) => conn,
+            Err(e) => {
+                streams.disconnected("kline", format!("{:?}", e));
+                streams.disconnected("ticker", format!("{:?}", e));
+                return;
+            }
+        };
+        for subscription in &subscriptions {
+            if let Err(e) = conn.send(Message::Text(subscription.to_string())).await {
+                streams.disconnected("kline", format!("{:?}", e));
+                streams.disconnected("ticker", format!("{:?}", e));
+                return;
+            }
+        }
+        streams.connected("kline");
+        streams.connected("ticker");
+        let mut bar = Bar::default();
+        let dropped = loop {
+            let message = tokio::select! {
+                message = conn.next() => message,
+                Ok(()) = resubscribe.changed() => break None,
+            };
+            let text = match message {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => break Some(format!("{:?}", e)),
+                None => break Some("closed by the exchange".to_string()),
+            };
+            let message: WsMessage = match serde_json::from_str(&text) {
+                Ok(message) => message,
+                Err(e) => {
+                    streams.parse_error("kline", format!("{} raw data: {}", e, text));
+                    continue;
+                }
+            };
+            match message.channel.as_str() {
+                "candles" => {
+                    health::mark_message(&health, "kline");
+                    streams.message("kline");
+                    for candle in message.events.into_iter().flat_map(|event| event.candles) {
+                        let start = match candle.start.parse::<i64>() {
+                            Ok(start) => start * 1000,
+                            Err(e) => {
+                                streams.parse_error("kline", e.to_string());
+                                continue;
+                            }
+                        };
+                        let bar_start = start - start.rem_euclid(interval_ms);
+                        let mut updates = Vec::with_capacity(2);
+                        if bar_start > bar.start && !bar.pieces.is_empty() {
+                            updates.push(bar.kline(interval, true));
+                            bar.pieces.clear();
+                        }
+                        if bar_start < bar.start {
+                            // Snapshot of an older bar
+                            continue;
+                        }
+                        bar.start = bar_start;
+                        bar.pieces.insert(start, candle);
+                        updates.push(bar.kline(interval, false));
+                        for update in updates {
+                            match update {
+                                Ok(update) => {
+                                    if let Err(e) = klines.send(update).await {
+                                        log::error!("Failed to send kline data: {}", e);
+                                    }
+                                }
+                                Err(e) => streams.parse_error("kline", e.to_string()),
+                            }
+                        }
+                    }
+                }
+                "ticker" => {
+                    health::mark_message(&health, "ticker");
+                    streams.message("ticker");
+                    for ticker in message.events.into_iter().flat_map(|event| event.tickers) {
+                        let symbol = match symbol_from_product(&ticker.product_id) {
+                            Ok(symbol) => symbol,
+                            Err(e) => {
+                                streams.parse_error("ticker", e.to_string());
+                                continue;
+                            }
+                        };
+                        let update = TickerData {
+                            event_time: chrono::Utc::now().timestamp_millis(),
+                            symbol: symbol.to_string(),
+                            last_price: ticker.price,
+                            volume: ticker.volume_24_h,
+                            ..TickerData::default()
+                        };
+                        if let Err(e) = tickers.send(update).await {
+                            log::error!("Failed to send ticker data: {}", e);
+                        }
+                    }
+                }
+                // Heartbeats and subscription acknowledgements
+                _ => {}
+            }
+        };
+        if let Err(e) = conn.close(None).await {
+            log::warn!("Failed to close Coinbase stream: {:?}", e);
+        }
+        if let Some(reason) = dropped {
+            // The supervisor restarts the task
+            streams.disconnected("kline", reason.clone());
+            streams.disconnected("ticker", reason);
+            return;
+        }
+        log::info!("Resubscribing Coinbase streams");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(s: &str) -> Symbol {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn symbols_map_to_product_ids() {
+        assert_eq!(product_id(&symbol("BTCUSD")).unwrap(), "BTC-USD");
+        assert_eq!(product_id(&symbol("DOTUSD")).unwrap(), "DOT-USD");
+        assert_eq!(product_id(&symbol("ETHUSDC")).unwrap(), "ETH-USDC");
+        assert_eq!(product_id(&symbol("SOLUSDT")).unwrap(), "SOL-USDT");
+        assert_eq!(product_id(&symbol("ETHBTC")).unwrap(), "ETH-BTC");
+        assert!(product_id(&symbol("BTCEUR")).is_err());
+    }
+
+    #[test]
+    fn product_ids_map_back_to_symbols() {
+        assert_eq!(symbol_from_product("BTC-USD").unwrap(), symbol("BTCUSD"));
+        assert_eq!(symbol_from_product("DOT-USD").unwrap(), symbol("DOTUSD"));
+    }
+}
@@ -0,0 +1,703 @@
+use std::collections::{HashMap, VecDeque};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::DateTime;
+use futures_util::{SinkExt, StreamExt};
+use hmac::{Hmac, Mac};
+use hyper::{Body, Method, Request};
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sha2::{Digest, Sha256, Sha512};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::domain::*;
+use crate::dto::{Kline, KlineResponse, TickerData};
+use crate::health::{self, SharedHealth};
+use crate::notify::{https_client, HttpsClient};
+use crate::secrets::ApiCredentials;
+use crate::streams::StreamRegistry;
+
+const REST_URL: &str = "https://api.kraken.com";
+const WS_URL: &str = "wss://ws.kraken.com/v2";
+
+// Kraken's names for assets whose usual ticker it doesn't use
+const ASSET_ALIASES: [(&str, &str); 2] = [("BTC", "XBT"), ("DOGE", "XDG")];
+
+fn kraken_asset(asset: &str) -> &str {
+    ASSET_ALIASES
+        .iter()
+        .find(|(common, _)| *common == asset)
+        .map_or(asset, |(_, kraken)| kraken)
+}
+
+fn common_asset(asset: &str) -> &str {
+    ASSET_ALIASES
+        .iter()
+        .find(|(_, kraken)| *kraken == asset)
+        .map_or(asset, |(common, _)| common)
+}
+
+fn split(symbol: &Symbol) -> Result<(&str, &str), TradingError> {
+    match (symbol.base_asset(), symbol.quote_asset()) {
+        (Some(base), Some(quote)) => Ok((base, quote)),
+        _ => Err(TradingError::InvalidParameter(format!(
+            "Cannot tell the quote asset of {}",
+            symbol
+        ))),
+    }
+}
+
+// BTCUSDT or XBTUSDT -> XBTUSDT, the pair name of the REST API
+pub fn rest_pair(symbol: &Symbol) -> Result<String, TradingError> {
+    let (base, quote) = split(symbol)?;
+    Ok(format!(
+        "{}{}",
+        kraken_asset(common_asset(base)),
+        kraken_asset(common_asset(quote))
+    ))
+}
+
+// BTCUSDT or XBTUSDT -> BTC/USDT, the pair name of the websocket API
+pub fn ws_pair(symbol: &Symbol) -> Result<String, TradingError> {
+    let (base, quote) = split(symbol)?;
+    Ok(format!("{}/{}", common_asset(base), common_asset(quote)))
+}
+
+// BTC/USDT or XBT/USDT -> BTCUSDT
+pub fn symbol_from_pair(pair: &str) -> Result<Symbol, TradingError> {
+    let (base, quote) = pair
+        .split_once('/')
+        .ok_or_else(|| TradingError::DataError(format!("Unexpected Kraken pair {}", pair)))?;
+    format!("{}{}", common_asset(base), common_asset(quote)).parse()
+}
+
+// Kraken candles are given in minutes and only come in these sizes
+fn interval_minutes(interval: Interval) -> Result<u32, TradingError> {
+    match interval {
+        Interval::Minutes1 => Ok(1),
+        Interval::Minutes5 => Ok(5),
+        Interval::Minutes15 => Ok(15),
+        Interval::Minutes30 => Ok(30),
+        Interval::Hours1 => Ok(60),
+        Interval::Hours4 => Ok(240),
+        Interval::Days1 => Ok(1440),
+        Interval::Weeks1 => Ok(10080),
+        _ => Err(TradingError::InvalidParameter(format!(
+            "Kraken has no {} candles",
+            interval
+        ))),
+    }
+}
+
+// KRAKEN_API_KEY and KRAKEN_API_SECRET (base64, as Kraken hands it out)
+pub fn credentials_from_env() -> Result<ApiCredentials, TradingError> {
+    let var = |name: &str| {
+        dotenv::var(name)
+            .map_err(|_| TradingError::AuthenticationError(format!("{} must be set", name)))
+    };
+    Ok(ApiCredentials {
+        api_key: var("KRAKEN_API_KEY")?,
+        api_secret: var("KRAKEN_API_SECRET")?,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct Envelope<T> {
+    error: Vec<String>,
+    result: Option<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddOrderResult {
+    txid: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenOrders {
+    open: HashMap<String, OrderInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrderDescription {
+    // REST pair name, e.g. XBTUSDT
+    pair: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrderInfo {
+    descr: OrderDescription,
+    status: String,
+    vol_exec: String,
+    // Average fill price
+    price: String,
+    // In the quote currency
+    fee: String,
+}
+
+fn order_response(
+    order_id: String,
+    info: OrderInfo,
+    symbol: &Symbol,
+) -> Result<OrderResponse, TradingError> {
+    let filled: Decimal = info.vol_exec.parse().unwrap_or_default();
+    let status = match info.status.as_str() {
+        "closed" => OrderStatus::Filled,
+        "canceled" | "expired" => OrderStatus::Canceled,
+        _ if filled > Decimal::ZERO => OrderStatus::PartiallyFilled,
+        _ => OrderStatus::Pending,
+    };
+    let fills = if filled > Decimal::ZERO {
+        vec![Fill {
+            price: info.price.parse().unwrap_or_default(),
+            quantity: filled,
+            commission: info.fee.parse().unwrap_or_default(),
+            commission_asset: kraken_asset(split(symbol)?.1).to_string(),
+        }]
+    } else {
+        Vec::new()
+    };
+    Ok(OrderResponse {
+        order_id,
+        status,
+        fills,
+    })
+}
+
+/// Kraken spot client for one symbol. The same bot runs on it as on Binance;
+/// symbols stay in Binance notation and are mapped to Kraken pairs here.
+pub struct KrakenExchangeClient {
+    http: HttpsClient,
+    credentials: ApiCredentials,
+    symbol: Symbol,
+    connected: bool,
+}
+
+impl KrakenExchangeClient {
+    pub fn new(credentials: ApiCredentials, symbol: Symbol) -> Self {
+        KrakenExchangeClient {
+            http: https_client(),
+            credentials,
+            symbol,
+            connected: false,
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        self.symbol.as_str()
+    }
+
+    async fn read<T: DeserializeOwned>(&self, request: Request<Body>) -> Result<T, TradingError> {
+        let response = self
+            .http
+            .request(request)
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+        let envelope: Envelope<T> = serde_json::from_slice(&body)?;
+        if !envelope.error.is_empty() {
+            let message = envelope.error.join(", ");
+            return Err(if message.starts_with("EAPI") {
+                TradingError::AuthenticationError(message)
+            } else {
+                TradingError::OrderError(message)
+            });
+        }
+        envelope
+            .result
+            .ok_or_else(|| TradingError::DataError("Kraken answered without a result".into()))
+    }
+
+    // Signed POST to /0/private/<method>
+    async fn private<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &[(&str, String)],
+    ) -> Result<T, TradingError> {
+        let path = format!("/0/private/{}", method);
+        let nonce = chrono::Utc::now().timestamp_millis().to_string();
+        let mut body = format!("nonce={}", nonce);
+        for (name, value) in params {
+            body.push_str(&format!("&{}={}", name, value));
+        }
+        // HMAC-SHA512 of the path and SHA256(nonce + body), keyed with the decoded secret
+        let secret = BASE64
+            .decode(&self.credentials.api_secret)
+            .map_err(|e| TradingError::AuthenticationError(format!("{:?}", e)))?;
+        let mut mac = Hmac::<Sha512>::new_from_slice(&secret)
+            .map_err(|e| TradingError::AuthenticationError(format!("{:?}", e)))?;
+        mac.update(path.as_bytes());
+        mac.update(&Sha256::digest(format!("{}{}", nonce, body).as_bytes()));
+        let signature = BASE64.encode(mac.finalize().into_bytes());
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(format!("{}{}", REST_URL, path))
+            .header("API-Key", &self.credentials.api_key)
+            .header("API-Sign", signature)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(Body::from(body))
+            .map_err(|e| TradingError::DataError(format!("{:?}", e)))?;
+        self.read(request).await
+    }
+
+    // Closed candles, oldest first, starting after `since` (seconds) when given
+    pub async fn get_klines(
+        &self,
+        interval: Interval,
+        since: Option<i64>,
+    ) -> Result<Vec<KlineResponse>, TradingError> {
+        let minutes = interval_minutes(interval)?;
+        let mut uri = format!(
+            "{}/0/public/OHLC?pair={}&interval={}",
+            REST_URL,
+            rest_pair(&self.symbol)?,
+            minutes
+        );
+        if let Some(since) = since {
+            uri.push_str(&format!("&since={}", since));
+        }
+        let request = Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .map_err(|e| TradingError::DataError(format!("{:?}", e)))?;
+        // Rows are keyed by Kraken's own pair name, next to a "last" cursor
+        let result: HashMap<String, serde_json::Value> = self.read(request).await?;
+        let rows = result
+            .into_iter()
+            .find(|(key, _)| key != "last")
+            .map(|(_, rows)| rows)
+            .unwrap_or_default();
+        let rows: Vec<(i64, String, String, String, String, String, String, u64)> =
+            serde_json::from_value(rows)?;
+        let interval_ms = interval.millis();
+        let mut candles = Vec::with_capacity(rows.len());
+        for (time, open, high, low, close, vwap, volume, trades) in rows {
+            let open_time = DateTime::from_timestamp(time, 0)
+                .ok_or_else(|| TradingError::DataError(format!("Invalid time {}", time)))?;
+            let float = |value: &str| -> Result<f64, TradingError> {
+                value
+                    .parse()
+                    .map_err(|_| TradingError::DataError(format!("Invalid number {:?}", value)))
+            };
+            let volume = float(&volume)?;
+            candles.push(KlineResponse {
+                open_time,
+                open_price: float(&open)?,
+                high_price: float(&high)?,
+                low_price: float(&low)?,
+                close_price: float(&close)?,
+                volume,
+                close_time: open_time + chrono::Duration::milliseconds(interval_ms - 1),
+                quote_asset_volume: volume * float(&vwap)?,
+                number_of_trades: trades,
+                taker_buy_base_volume: 0.0,
+                taker_buy_quote_volume: 0.0,
+            });
+        }
+        // The last row is the candle still forming
+        candles.pop();
+        Ok(candles)
+    }
+
+    // Last `count` closes for the analysis task, like BinanceExchangeClient::start
+    pub async fn recent_closes(
+        &self,
+        interval: Interval,
+        count: usize,
+    ) -> Result<VecDeque<f64>, TradingError> {
+        let candles = self.get_klines(interval, None).await?;
+        Ok(candles[candles.len().saturating_sub(count)..]
+            .iter()
+            .map(|candle| candle.close_price)
+            .collect())
+    }
+
+    async fn query_order(&self, txid: &str) -> Result<OrderInfo, TradingError> {
+        let mut orders: HashMap<String, OrderInfo> = self
+            .private("QueryOrders", &[("txid", txid.to_string())])
+            .await?;
+        orders
+            .remove(txid)
+            .ok_or_else(|| TradingError::OrderError(format!("Kraken doesn't know order {}", txid)))
+    }
+}
+
+impl ExchangeClient for KrakenExchangeClient {
+    async fn connect(&mut self) -> Result<(), TradingError> {
+        // A private call checks the key as well as the connection
+        let _: HashMap<String, String> = self.private("Balance", &[]).await.map_err(|e| {
+            log::error!("Failed to connect to Kraken: {}", e);
+            TradingError::ConnectionError("Failed to connect".into())
+        })?;
+        self.connected = true;
+        log::info!("Connected to Kraken");
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<(), TradingError> {
+        self.connected = false;
+        Ok(())
+    }
+
+    // Free balance of the symbol's quote asset
+    async fn get_balance(&self) -> Result<Decimal, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let (_, quote) = split(&self.symbol)?;
+        let quote = kraken_asset(quote);
+        let balances: HashMap<String, String> = self.private("Balance", &[]).await?;
+        // Older assets carry an X or Z prefix, e.g. ZUSD and XXBT
+        let balance = [
+            quote.to_string(),
+            format!("Z{}", quote),
+            format!("X{}", quote),
+        ]
+        .iter()
+        .find_map(|asset| balances.get(asset))
+        .map_or(Ok(Decimal::ZERO), |balance| balance.parse())
+        .map_err(|_| TradingError::DataError(format!("Invalid {} balance", quote)))?;
+        Ok(balance)
+    }
+
+    async fn send_order(&mut self, order: &Order) -> Result<OrderResponse, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let symbol: Symbol = order.symbol.parse()?;
+        let side = match order.side {
+            OrderSide::Buy => "buy",
+            OrderSide::Sell => "sell",
+        };
+        let mut params = vec![
+            ("pair", rest_pair(&symbol)?),
+            ("type", side.to_string()),
+            ("volume", order.quantity.to_string()),
+        ];
+        match order.order_type {
+            OrderType::Market => params.push(("ordertype", "market".to_string())),
+            OrderType::Limit(price) => {
+                params.push(("ordertype", "limit".to_string()));
+                params.push(("price", price.to_string()));
+                match order.time_in_force {
+                    TimeInForce::Gtc => (),
+                    TimeInForce::Ioc => params.push(("timeinforce", "IOC".to_string())),
+                    TimeInForce::Fok => {
+                        return Err(TradingError::OrderError(
+                            "Kraken has no fill-or-kill orders".into(),
+                        ))
+                    }
+                }
+                if order.post_only {
+                    params.push(("oflags", "post".to_string()));
+                }
+            }
+            OrderType::Stop(price) => {
+                params.push(("ordertype", "stop-loss".to_string()));
+                params.push(("price", price.to_string()));
+            }
+            // Kraken's trailing stops have no activation price
+            OrderType::TrailingStop { .. } => {
+                return Err(TradingError::OrderError(
+                    "Kraken trailing stops are kept by the executor".into(),
+                ))
+            }
+        }
+        let result: AddOrderResult = self.private("AddOrder", &params).await?;
+        let order_id = result
+            .txid
+            .into_iter()
+            .next()
+            .ok_or_else(|| TradingError::OrderError("AddOrder returned no txid".into()))?;
+
+        // AddOrder only acknowledges; the fill is read back from the order
+        let info = self.query_order(&order_id).await?;
+        order_response(order_id, info, &symbol)
+    }
+
+    async fn cancel_order(&mut self, _symbol: &str, order_id: &str) -> Result<(), TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let _: serde_json::Value = self
+            .private("CancelOrder", &[("txid", order_id.to_string())])
+            .await?;
+        Ok(())
+    }
+
+    async fn get_open_orders(&self, symbol: &str) -> Result<Vec<OrderResponse>, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let symbol: Symbol = symbol.parse()?;
+        let pair = rest_pair(&symbol)?;
+        let orders: OpenOrders = self.private("OpenOrders", &[]).await?;
+        orders
+            .open
+            .into_iter()
+            .filter(|(_, info)| info.descr.pair == pair)
+            .map(|(txid, info)| order_response(txid, info, &symbol))
+            .collect()
+    }
+
+    async fn get_order_status(
+        &self,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let symbol: Symbol = symbol.parse()?;
+        let info = self.query_order(order_id).await?;
+        order_response(order_id.to_string(), info, &symbol)
+    }
+
+    // Kraken spot has no one-cancels-the-other orders
+    async fn place_oco_order(
+        &mut self,
+        symbol: &str,
+        _side: OrderSide,
+        _quantity: Decimal,
+        _bracket: &Bracket,
+    ) -> Result<OrderResponse, TradingError> {
+        Err(TradingError::OrderError(format!(
+            "Kraken has no OCO orders, {} bracket not placed",
+            symbol
+        )))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WsMessage {
+    channel: Option<String>,
+    #[serde(default)]
+    data: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WsCandle {
+    symbol: String,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+    trades: i64,
+    interval_begin: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WsTicker {
+    symbol: String,
+    last: f64,
+    volume: f64,
+}
+
+fn kline(candle: &WsCandle, interval: Interval, closed: bool) -> Result<Kline, TradingError> {
+    let begin = DateTime::parse_from_rfc3339(&candle.interval_begin)
+        .map_err(|e| TradingError::DataError(format!("{:?}", e)))?
+        .timestamp_millis();
+    Ok(Kline {
+        start_time: begin,
+        end_time: begin + interval.millis() - 1,
+        symbol: symbol_from_pair(&candle.symbol)?.to_string(),
+        interval: interval.to_string(),
+        open_price: candle.open.to_string(),
+        close_price: candle.close.to_string(),
+        high_price: candle.high.to_string(),
+        low_price: candle.low.to_string(),
+        volume: candle.volume.to_string(),
+        number_of_trades: candle.trades,
+        is_closed: closed,
+        ..Kline::default()
+    })
+}
+
+// OHLC and ticker channels of one symbol, converted to the Binance stream DTOs
+// the rest of the bot reads. Kraken has no "closed" flag: a candle is passed on
+// as closed once an update for the next one arrives.
+pub async fn get_market_data(
+    symbol: Symbol,
+    interval: Interval,
+    klines: mpsc::Sender<Kline>,
+    tickers: mpsc::Sender<TickerData>,
+    health: SharedHealth,
+    streams: StreamRegistry,
+) {
+    let (pair, minutes) = match (ws_pair(&symbol), interval_minutes(interval)) {
+        (Ok(pair), Ok(minutes)) => (pair, minutes),
+        (Err(e), _) | (_, Err(e)) => {
+            log::error!("Cannot stream {} from Kraken: {}", symbol, e);
+            return;
+        }
+    };
+    let mut resubscribe = streams.register("kline", format!("ohlc:{}:{}", pair, minutes));
+    streams.register("ticker", format!("ticker:{}", pair));
+    let subscriptions = [
+        serde_json::json!({
+            "method": "subscribe",
+            "params": {"channel": "ohlc", "symbol": [pair], "interval": minutes},
+        }),
+        serde_json::json!({
+            "method": "subscribe",
+            "params": {"channel": "ticker", "symbol": [pair]},
+        }),
+    ];
+    loop {
+        streams.connecting("kline");
+        streams.connecting("ticker");
+        let (mut conn, _) = match tokio_tungstenite::connect_async(WS_URL).await {
+            Ok(conn) => conn,
+            Err(e) => {
+                streams.disconnected("kline", format!("{:?}", e));
+                streams.disconnected("ticker", format!("{:?}", e));
+                return;
+            }
+        };
+        for subscription in &subscriptions {
+            if let Err(e) = conn.send(Message::Text(subscription.to_string())).await {
+                streams.disconnected("kline", format!("{:?}", e));
+                streams.disconnected("ticker", format!("{:?}", e));
+                return;
+            }
+        }
+        streams.connected("kline");
+        streams.connected("ticker");
+        // Latest update of the candle still forming
+        let mut forming: Option<WsCandle> = None;
+        let dropped = loop {
+            let message = tokio::select! {
+                message = conn.next() => message,
+                Ok(()) = resubscribe.changed() => break None,
+            };
+            let text = match message {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => break Some(format!("{:?}", e)),
+                None => break Some("closed by the exchange".to_string()),
+            };
+            let message: WsMessage = match serde_json::from_str(&text) {
+                Ok(message) => message,
+                Err(e) => {
+                    streams.parse_error("kline", format!("{} raw data: {}", e, text));
+                    continue;
+                }
+            };
+            match message.channel.as_deref() {
+                Some("ohlc") => {
+                    health::mark_message(&health, "kline");
+                    streams.message("kline");
+                    for value in message.data {
+                        let candle: WsCandle = match serde_json::from_value(value) {
+                            Ok(candle) => candle,
+                            Err(e) => {
+                                streams.parse_error("kline", e.to_string());
+                                continue;
+                            }
+                        };
+                        let mut updates = Vec::with_capacity(2);
+                        if let Some(previous) = &forming {
+                            if previous.interval_begin != candle.interval_begin {
+                                updates.push(kline(previous, interval, true));
+                            }
+                        }
+                        updates.push(kline(&candle, interval, false));
+                        forming = Some(candle);
+                        for update in updates {
+                            match update {
+                                Ok(update) => {
+                                    if let Err(e) = klines.send(update).await {
+                                        log::error!("Failed to send kline data: {}", e);
+                                    }
+                                }
+                                Err(e) => streams.parse_error("kline", e.to_string()),
+                            }
+                        }
+                    }
+                }
+                Some("ticker") => {
+                    health::mark_message(&health, "ticker");
+                    streams.message("ticker");
+                    for value in message.data {
+                        let ticker: WsTicker = match serde_json::from_value(value) {
+                            Ok(ticker) => ticker,
+                            Err(e) => {
+                                streams.parse_error("ticker", e.to_string());
+                                continue;
+                            }
+                        };
+                        let symbol = match symbol_from_pair(&ticker.symbol) {
+                            Ok(symbol) => symbol,
+                            Err(e) => {
+                                streams.parse_error("ticker", e.to_string());
+                                continue;
+                            }
+                        };
+                        let update = TickerData {
+                            event_time: chrono::Utc::now().timestamp_millis(),
+                            symbol: symbol.to_string(),
+                            last_price: ticker.last.to_string(),
+                            volume: ticker.volume.to_string(),
+                            ..TickerData::default()
+                        };
+                        if let Err(e) = tickers.send(update).await {
+                            log::error!("Failed to send ticker data: {}", e);
+                        }
+                    }
+                }
+                // Heartbeats, status and subscription acknowledgements
+                _ => {}
+            }
+        };
+        if let Err(e) = conn.close(None).await {
+            log::warn!("Failed to close Kraken stream: {:?}", e);
+        }
+        if let Some(reason) = dropped {
+            // The supervisor restarts the task
+            streams.disconnected("kline", reason.clone());
+            streams.disconnected("ticker", reason);
+            return;
+        }
+        log::info!("Resubscribing Kraken streams");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(s: &str) -> Symbol {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn usd_pairs_map_to_kraken_names() {
+        assert_eq!(rest_pair(&symbol("BTCUSD")).unwrap(), "XBTUSD");
+        assert_eq!(rest_pair(&symbol("XBTUSD")).unwrap(), "XBTUSD");
+        assert_eq!(rest_pair(&symbol("DOTUSD")).unwrap(), "DOTUSD");
+        assert_eq!(ws_pair(&symbol("BTCUSD")).unwrap(), "BTC/USD");
+        assert_eq!(ws_pair(&symbol("XBTUSD")).unwrap(), "BTC/USD");
+        assert_eq!(ws_pair(&symbol("DOTUSD")).unwrap(), "DOT/USD");
+        assert_eq!(ws_pair(&symbol("DOGEUSD")).unwrap(), "DOGE/USD");
+    }
+
+    #[test]
+    fn stablecoin_pairs_map_to_kraken_names() {
+        assert_eq!(rest_pair(&symbol("BTCUSDT")).unwrap(), "XBTUSDT");
+        assert_eq!(ws_pair(&symbol("ETHUSDC")).unwrap(), "ETH/USDC");
+        assert_eq!(ws_pair(&symbol("BTCTUSD")).unwrap(), "BTC/TUSD");
+    }
+
+    #[test]
+    fn pairs_map_back_to_symbols() {
+        assert_eq!(symbol_from_pair("XBT/USD").unwrap(), symbol("BTCUSD"));
+        assert_eq!(symbol_from_pair("DOT/USD").unwrap(), symbol("DOTUSD"));
+        assert!(symbol_from_pair("XBTUSD").is_err());
+    }
+}
@@ -0,0 +1,365 @@
+#[cfg(feature = "futures")]
+pub mod binance_futures;
+#[cfg(feature = "coinbase")]
+pub mod coinbase;
+#[cfg(feature = "kraken")]
+pub mod kraken;
+pub mod paper;
+pub mod router;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use binance_spot_connector_rust::http::Credentials;
+use rust_decimal::Decimal;
+
+use crate::config::Profile;
+use crate::domain::*;
+use crate::secrets;
+use crate::BinanceExchangeClient;
+
+pub use router::ExchangeRouter;
+
+// Exchange names `trading.exchange` and venues accept in this build; each has
+// market data streams, which a venue's positions are marked from
+pub fn supported(exchange: &str) -> bool {
+    match exchange {
+        "binance" | "paper" => true,
+        "binance-futures" => cfg!(feature = "futures"),
+        "kraken" => cfg!(feature = "kraken"),
+        "coinbase" => cfg!(feature = "coinbase"),
+        _ => false,
+    }
+}
+
+async fn binance_client(
+    credentials: Credentials,
+    symbol: &Symbol,
+    profile: &Profile,
+) -> BinanceExchangeClient {
+    let mut client = if profile.trading.testnet {
+        log::info!("Using the Binance spot testnet");
+        BinanceExchangeClient::new_testnet(credentials)
+    } else {
+        BinanceExchangeClient::new(credentials)
+    };
+    client.set_symbol(symbol.to_string()).await;
+    client
+}
+
+// Closed candles only, like BinanceExchangeClient::start
+async fn binance_closes(
+    client: &BinanceExchangeClient,
+    interval: Interval,
+    count: usize,
+) -> Result<VecDeque<f64>, TradingError> {
+    let mut closes: VecDeque<f64> = client
+        .get_klines(interval, count + 1)
+        .await?
+        .iter()
+        .map(|candle| candle.close_price)
+        .collect();
+    closes.pop_back();
+    Ok(closes)
+}
+
+// Connected client for `exchange` trading `symbol`. Paper fills read
+// `market_data`, which the bot's streams keep current; Binance uses
+// `credentials` when given instead of loading them.
+pub async fn connect(
+    exchange: &str,
+    symbol: &Symbol,
+    profile: &Profile,
+    market_data: Arc<Mutex<MarketData>>,
+    credentials: Option<Credentials>,
+) -> Result<LiveExchange, TradingError> {
+    let client = match exchange {
+        "binance" => {
+            let credentials = match credentials {
+                Some(credentials) => credentials,
+                None => {
+                    let api = secrets::load_credentials()?;
+                    Credentials::from_hmac(api.api_key, api.api_secret)
+                }
+            };
+            let mut client = binance_client(credentials, symbol, profile).await;
+            client.connect().await?;
+            LiveExchange::Binance(client)
+        }
+        "paper" => {
+            // Binance's public market data, so no keys needed
+            let public = Credentials::from_hmac(String::new(), String::new());
+            let mut history = binance_client(public, symbol, profile).await;
+            // Paper orders are held to the real exchange's filters
+            if let Err(e) = history.load_symbol_rules().await {
+                log::warn!("Failed to load order rules for {}: {}", symbol, e);
+            }
+            let mut paper =
+                paper::PaperExchangeClient::new(market_data, &profile.trading.paper, history);
+            paper.connect().await?;
+            // Resting orders fill as the streams move the price
+            tokio::spawn(paper.clone().run_fills());
+            LiveExchange::Paper(paper)
+        }
+        #[cfg(feature = "futures")]
+        "binance-futures" => {
+            let mut client = binance_futures::BinanceFuturesClient::new(
+                secrets::load_credentials()?,
+                symbol.clone(),
+                profile.trading.futures.clone(),
+                profile.trading.testnet,
+            );
+            client.connect().await?;
+            LiveExchange::BinanceFutures(client)
+        }
+        #[cfg(feature = "kraken")]
+        "kraken" => {
+            let mut client =
+                kraken::KrakenExchangeClient::new(kraken::credentials_from_env()?, symbol.clone());
+            client.connect().await?;
+            LiveExchange::Kraken(client)
+        }
+        #[cfg(feature = "coinbase")]
+        "coinbase" => {
+            let mut client = coinbase::CoinbaseExchangeClient::new(
+                coinbase::credentials_from_env()?,
+                symbol.clone(),
+            )?;
+            client.connect().await?;
+            LiveExchange::Coinbase(client)
+        }
+        _ => {
+            return Err(TradingError::InvalidParameter(format!(
+                "Unsupported exchange {}",
+                exchange
+            )))
+        }
+    };
+    Ok(client)
+}
+
+/// The exchange a live bot trades on, picked by `trading.exchange`. A concrete
+/// type rather than a generic keeps the executor's futures spawnable.
+pub enum LiveExchange {
+    Binance(BinanceExchangeClient),
+    Paper(paper::PaperExchangeClient),
+    #[cfg(feature = "futures")]
+    BinanceFutures(binance_futures::BinanceFuturesClient),
+    #[cfg(feature = "kraken")]
+    Kraken(kraken::KrakenExchangeClient),
+    #[cfg(feature = "coinbase")]
+    Coinbase(coinbase::CoinbaseExchangeClient),
+}
+
+impl LiveExchange {
+    // The symbol the client was connected for
+    pub fn symbol(&self) -> &str {
+        match self {
+            LiveExchange::Binance(client) => client.symbol(),
+            LiveExchange::Paper(client) => client.history().symbol(),
+            #[cfg(feature = "futures")]
+            LiveExchange::BinanceFutures(client) => client.symbol(),
+            #[cfg(feature = "kraken")]
+            LiveExchange::Kraken(client) => client.symbol(),
+            #[cfg(feature = "coinbase")]
+            LiveExchange::Coinbase(client) => client.symbol(),
+        }
+    }
+
+    // Last `count` closes of the client's symbol, for warming up the analysis
+    pub async fn recent_closes(
+        &self,
+        interval: Interval,
+        count: usize,
+    ) -> Result<VecDeque<f64>, TradingError> {
+        match self {
+            LiveExchange::Binance(client) => binance_closes(client, interval, count).await,
+            LiveExchange::Paper(client) => binance_closes(client.history(), interval, count).await,
+            #[cfg(feature = "futures")]
+            LiveExchange::BinanceFutures(client) => client.recent_closes(interval, count).await,
+            #[cfg(feature = "kraken")]
+            LiveExchange::Kraken(client) => client.recent_closes(interval, count).await,
+            #[cfg(feature = "coinbase")]
+            LiveExchange::Coinbase(client) => client.recent_closes(interval, count).await,
+        }
+    }
+}
+
+impl ExchangeClient for LiveExchange {
+    async fn connect(&mut self) -> Result<(), TradingError> {
+        match self {
+            LiveExchange::Binance(client) => client.connect().await,
+            LiveExchange::Paper(client) => client.connect().await,
+            #[cfg(feature = "futures")]
+            LiveExchange::BinanceFutures(client) => client.connect().await,
+            #[cfg(feature = "kraken")]
+            LiveExchange::Kraken(client) => client.connect().await,
+            #[cfg(feature = "coinbase")]
+            LiveExchange::Coinbase(client) => client.connect().await,
+        }
+    }
+
+    async fn disconnect(&mut self) -> Result<(), TradingError> {
+        match self {
+            LiveExchange::Binance(client) => client.disconnect().await,
+            LiveExchange::Paper(client) => client.disconnect().await,
+            #[cfg(feature = "futures")]
+            LiveExchange::BinanceFutures(client) => client.disconnect().await,
+            #[cfg(feature = "kraken")]
+            LiveExchange::Kraken(client) => client.disconnect().await,
+            #[cfg(feature = "coinbase")]
+            LiveExchange::Coinbase(client) => client.disconnect().await,
+        }
+    }
+
+    async fn get_balance(&self) -> Result<Decimal, TradingError> {
+        match self {
+            LiveExchange::Binance(client) => client.get_balance().await,
+            LiveExchange::Paper(client) => client.get_balance().await,
+            #[cfg(feature = "futures")]
+            LiveExchange::BinanceFutures(client) => client.get_balance().await,
+            #[cfg(feature = "kraken")]
+            LiveExchange::Kraken(client) => client.get_balance().await,
+            #[cfg(feature = "coinbase")]
+            LiveExchange::Coinbase(client) => client.get_balance().await,
+        }
+    }
+
+    async fn send_order(&mut self, order: &Order) -> Result<OrderResponse, TradingError> {
+        match self {
+            LiveExchange::Binance(client) => client.send_order(order).await,
+            LiveExchange::Paper(client) => client.send_order(order).await,
+            #[cfg(feature = "futures")]
+            LiveExchange::BinanceFutures(client) => client.send_order(order).await,
+            #[cfg(feature = "kraken")]
+            LiveExchange::Kraken(client) => client.send_order(order).await,
+            #[cfg(feature = "coinbase")]
+            LiveExchange::Coinbase(client) => client.send_order(order).await,
+        }
+    }
+
+    async fn cancel_order(&mut self, symbol: &str, order_id: &str) -> Result<(), TradingError> {
+        match self {
+            LiveExchange::Binance(client) => client.cancel_order(symbol, order_id).await,
+            LiveExchange::Paper(client) => client.cancel_order(symbol, order_id).await,
+            #[cfg(feature = "futures")]
+            LiveExchange::BinanceFutures(client) => client.cancel_order(symbol, order_id).await,
+            #[cfg(feature = "kraken")]
+            LiveExchange::Kraken(client) => client.cancel_order(symbol, order_id).await,
+            #[cfg(feature = "coinbase")]
+            LiveExchange::Coinbase(client) => client.cancel_order(symbol, order_id).await,
+        }
+    }
+
+    async fn get_open_orders(&self, symbol: &str) -> Result<Vec<OrderResponse>, TradingError> {
+        match self {
+            LiveExchange::Binance(client) => client.get_open_orders(symbol).await,
+            LiveExchange::Paper(client) => client.get_open_orders(symbol).await,
+            #[cfg(feature = "futures")]
+            LiveExchange::BinanceFutures(client) => client.get_open_orders(symbol).await,
+            #[cfg(feature = "kraken")]
+            LiveExchange::Kraken(client) => client.get_open_orders(symbol).await,
+            #[cfg(feature = "coinbase")]
+            LiveExchange::Coinbase(client) => client.get_open_orders(symbol).await,
+        }
+    }
+
+    async fn get_order_status(
+        &self,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse, TradingError> {
+        match self {
+            LiveExchange::Binance(client) => client.get_order_status(symbol, order_id).await,
+            LiveExchange::Paper(client) => client.get_order_status(symbol, order_id).await,
+            #[cfg(feature = "futures")]
+            LiveExchange::BinanceFutures(client) => client.get_order_status(symbol, order_id).await,
+            #[cfg(feature = "kraken")]
+            LiveExchange::Kraken(client) => client.get_order_status(symbol, order_id).await,
+            #[cfg(feature = "coinbase")]
+            LiveExchange::Coinbase(client) => client.get_order_status(symbol, order_id).await,
+        }
+    }
+
+    async fn bracket_legs(
+        &self,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<Vec<OrderResponse>, TradingError> {
+        match self {
+            LiveExchange::Binance(client) => client.bracket_legs(symbol, order_id).await,
+            LiveExchange::Paper(client) => client.bracket_legs(symbol, order_id).await,
+            #[cfg(feature = "futures")]
+            LiveExchange::BinanceFutures(client) => client.bracket_legs(symbol, order_id).await,
+            #[cfg(feature = "kraken")]
+            LiveExchange::Kraken(client) => client.bracket_legs(symbol, order_id).await,
+            #[cfg(feature = "coinbase")]
+            LiveExchange::Coinbase(client) => client.bracket_legs(symbol, order_id).await,
+        }
+    }
+
+    fn symbol_rules(&self, symbol: &str) -> Option<SymbolRules> {
+        match self {
+            LiveExchange::Binance(client) => client.symbol_rules(symbol),
+            LiveExchange::Paper(client) => client.symbol_rules(symbol),
+            #[cfg(feature = "futures")]
+            LiveExchange::BinanceFutures(client) => client.symbol_rules(symbol),
+            #[cfg(feature = "kraken")]
+            LiveExchange::Kraken(client) => client.symbol_rules(symbol),
+            #[cfg(feature = "coinbase")]
+            LiveExchange::Coinbase(client) => client.symbol_rules(symbol),
+        }
+    }
+
+    fn supports_short(&self, symbol: &str) -> bool {
+        match self {
+            LiveExchange::Binance(client) => client.supports_short(symbol),
+            LiveExchange::Paper(client) => client.supports_short(symbol),
+            #[cfg(feature = "futures")]
+            LiveExchange::BinanceFutures(client) => client.supports_short(symbol),
+            #[cfg(feature = "kraken")]
+            LiveExchange::Kraken(client) => client.supports_short(symbol),
+            #[cfg(feature = "coinbase")]
+            LiveExchange::Coinbase(client) => client.supports_short(symbol),
+        }
+    }
+
+    async fn place_oco_order(
+        &mut self,
+        symbol: &str,
+        side: OrderSide,
+        quantity: Decimal,
+        bracket: &Bracket,
+    ) -> Result<OrderResponse, TradingError> {
+        match self {
+            LiveExchange::Binance(client) => {
+                client
+                    .place_oco_order(symbol, side, quantity, bracket)
+                    .await
+            }
+            LiveExchange::Paper(client) => {
+                client
+                    .place_oco_order(symbol, side, quantity, bracket)
+                    .await
+            }
+            #[cfg(feature = "futures")]
+            LiveExchange::BinanceFutures(client) => {
+                client
+                    .place_oco_order(symbol, side, quantity, bracket)
+                    .await
+            }
+            #[cfg(feature = "kraken")]
+            LiveExchange::Kraken(client) => {
+                client
+                    .place_oco_order(symbol, side, quantity, bracket)
+                    .await
+            }
+            #[cfg(feature = "coinbase")]
+            LiveExchange::Coinbase(client) => {
+                client
+                    .place_oco_order(symbol, side, quantity, bracket)
+                    .await
+            }
+        }
+    }
+}
@@ -0,0 +1,543 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+
+use crate::backtest::broker::Liquidity;
+use crate::backtest::{FeeSchedule, FillModel, LimitFillPolicy, SlippageModel};
+use crate::config::PaperSettings;
+use crate::domain::*;
+use crate::BinanceExchangeClient;
+
+#[derive(Debug)]
+struct RestingOrder {
+    order: Order,
+    // Id of the OCO list the order is a leg of
+    list: Option<String>,
+}
+
+#[derive(Debug)]
+struct PaperState {
+    connected: bool,
+    // Quote balance
+    balance: Decimal,
+    // Base asset -> quantity held; negative when borrowed to sell short
+    holdings: HashMap<String, Decimal>,
+    margin: bool,
+    resting: HashMap<String, RestingOrder>,
+    // Filled and cancelled orders by id, OCO lists included, so status queries
+    // and cancels answer like an exchange's
+    done: HashMap<String, OrderResponse>,
+    next_id: u64,
+}
+
+/// `ExchangeClient` that fills orders against the live market data instead
+/// of sending them: takers cross the streamed bid/ask (or a modelled spread
+/// around the last price) plus slippage, resting orders fill once the last
+/// price trades through them, and fees come off a virtual balance. Clones
+/// share the same account.
+#[derive(Clone)]
+pub struct PaperExchangeClient {
+    market: Arc<Mutex<MarketData>>,
+    model: FillModel,
+    state: Arc<Mutex<PaperState>>,
+    // Public-data client for candle history
+    history: Arc<BinanceExchangeClient>,
+}
+
+impl PaperExchangeClient {
+    // `market` is the bot's live market data, kept current by the streams
+    pub fn new(
+        market: Arc<Mutex<MarketData>>,
+        settings: &PaperSettings,
+        history: BinanceExchangeClient,
+    ) -> Self {
+        PaperExchangeClient {
+            market,
+            history: Arc::new(history),
+            model: FillModel {
+                fees: FeeSchedule {
+                    maker_pct: settings.maker_fee_pct,
+                    taker_pct: settings.taker_fee_pct,
+                },
+                slippage: SlippageModel::FixedBps(settings.slippage_bps),
+                spread_bps: settings.spread_bps,
+                limit_fills: LimitFillPolicy::Conservative,
+            },
+            state: Arc::new(Mutex::new(PaperState {
+                connected: false,
+                balance: settings.balance,
+                holdings: HashMap::new(),
+                margin: settings.margin,
+                resting: HashMap::new(),
+                done: HashMap::new(),
+                next_id: 1,
+            })),
+        }
+    }
+
+    pub fn history(&self) -> &BinanceExchangeClient {
+        &self.history
+    }
+
+    // Checks resting orders against the market every second, on a clone
+    pub async fn run_fills(self) {
+        let mut ticks = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            ticks.tick().await;
+            self.sweep(&mut self.state.lock().unwrap());
+        }
+    }
+
+    fn market(&self, symbol: &str) -> Result<MarketData, TradingError> {
+        let market = self.market.lock().unwrap().clone();
+        if market.last_price <= Decimal::ZERO
+            || (!market.symbol.is_empty() && market.symbol != symbol)
+        {
+            return Err(TradingError::OrderError(format!(
+                "No price for {} yet",
+                symbol
+            )));
+        }
+        Ok(market)
+    }
+
+    // Taker fill: crosses the real spread when the ticker gave one. The fill
+    // model is the backtester's, so it prices in f64.
+    fn take(&self, market: &MarketData, side: &OrderSide, quantity: Decimal) -> (Decimal, Decimal) {
+        let (quantity, volume) = (from_decimal(quantity), from_decimal(market.volume));
+        let fill = if market.bid_price > Decimal::ZERO && market.ask_price >= market.bid_price {
+            let mid = (market.bid_price + market.ask_price) / Decimal::TWO;
+            let model = FillModel {
+                spread_bps: from_decimal(
+                    (market.ask_price - market.bid_price) / mid * Decimal::from(10_000),
+                ),
+                ..self.model.clone()
+            };
+            model.fill(side, from_decimal(mid), quantity, volume, Liquidity::Taker)
+        } else {
+            self.model.fill(
+                side,
+                from_decimal(market.last_price),
+                quantity,
+                volume,
+                Liquidity::Taker,
+            )
+        };
+        (to_decimal(fill.price), to_decimal(fill.fee))
+    }
+
+    fn maker(&self, side: &OrderSide, price: Decimal, quantity: Decimal) -> (Decimal, Decimal) {
+        let fill = self.model.fill(
+            side,
+            from_decimal(price),
+            from_decimal(quantity),
+            0.0,
+            Liquidity::Maker,
+        );
+        (to_decimal(fill.price), to_decimal(fill.fee))
+    }
+
+    // Fill against the market now if the order is marketable
+    fn try_fill(&self, market: &MarketData, order: &Order) -> Option<(Decimal, Decimal)> {
+        let last = market.last_price;
+        match (&order.order_type, &order.side) {
+            (OrderType::Market, side) => Some(self.take(market, side, order.quantity)),
+            (OrderType::Limit(limit), side) => {
+                let (price, fee) = self.take(market, side, order.quantity);
+                match side {
+                    OrderSide::Buy if price <= *limit => Some((price, fee)),
+                    OrderSide::Sell if price >= *limit => Some((price, fee)),
+                    _ => None,
+                }
+            }
+            (OrderType::Stop(stop), side) => {
+                let triggered = match side {
+                    OrderSide::Buy => last >= *stop,
+                    OrderSide::Sell => last <= *stop,
+                };
+                triggered.then(|| self.take(market, side, order.quantity))
+            }
+            // Refused in send_order; the executor trails its own stops
+            (OrderType::TrailingStop { .. }, _) => None,
+        }
+    }
+
+    // Moves the fill through the virtual balances; refuses what they can't cover
+    fn settle(
+        state: &mut PaperState,
+        order: &Order,
+        price: Decimal,
+        fee: Decimal,
+    ) -> Result<(), String> {
+        let (base, _) = split_symbol(&order.symbol);
+        let value = price * order.quantity;
+        let held = state.holdings.get(base).copied().unwrap_or_default();
+        match order.side {
+            OrderSide::Buy => {
+                if value + fee > state.balance {
+                    return Err(format!(
+                        "Insufficient balance: {:.2} needed, {:.2} available",
+                        value + fee,
+                        state.balance
+                    ));
+                }
+                state.balance -= value + fee;
+                state
+                    .holdings
+                    .insert(base.to_string(), held + order.quantity);
+            }
+            OrderSide::Sell => {
+                if !state.margin && order.quantity > held {
+                    return Err(format!(
+                        "Insufficient {}: {} needed, {} held",
+                        base, order.quantity, held
+                    ));
+                }
+                state.balance += value - fee;
+                let left = held - order.quantity;
+                let left = if state.margin {
+                    left
+                } else {
+                    left.max(Decimal::ZERO)
+                };
+                state.holdings.insert(base.to_string(), left);
+            }
+        }
+        Ok(())
+    }
+
+    // Fills resting orders the market has reached since they were placed
+    fn sweep(&self, state: &mut PaperState) {
+        let Ok(market) = self.market.lock().map(|market| market.clone()) else {
+            return;
+        };
+        if market.last_price <= Decimal::ZERO {
+            return;
+        }
+        let mut reached: Vec<(String, (Decimal, Decimal))> = Vec::new();
+        for (id, resting) in &state.resting {
+            let order = &resting.order;
+            if !market.symbol.is_empty() && market.symbol != order.symbol {
+                continue;
+            }
+            let fill = match order.order_type {
+                // Conservative: the price has to trade through the limit
+                OrderType::Limit(limit) => {
+                    let through = match order.side {
+                        OrderSide::Buy => market.last_price < limit,
+                        OrderSide::Sell => market.last_price > limit,
+                    };
+                    through.then(|| self.maker(&order.side, limit, order.quantity))
+                }
+                _ => self.try_fill(&market, order),
+            };
+            if let Some(fill) = fill {
+                reached.push((id.clone(), fill));
+            }
+        }
+        for (id, (price, fee)) in reached {
+            // Gone if it was the other leg of an OCO list that just filled
+            let Some(resting) = state.resting.remove(&id) else {
+                continue;
+            };
+            if let Err(reason) = Self::settle(state, &resting.order, price, fee) {
+                log::warn!("Paper order {} dropped: {}", id, reason);
+                let rejected = OrderResponse {
+                    order_id: id.clone(),
+                    status: OrderStatus::Rejected,
+                    fills: Vec::new(),
+                };
+                state.done.insert(id, rejected);
+                continue;
+            }
+            log::info!(
+                "Paper order {} {:?} {} {} filled at {:.8}, balance {:.2}",
+                id,
+                resting.order.side,
+                resting.order.quantity,
+                resting.order.symbol,
+                price,
+                state.balance
+            );
+            let response = Self::filled(id.clone(), &resting.order, price, fee);
+            // One leg of an OCO list cancels the other
+            if let Some(list) = resting.list {
+                let others: Vec<String> = state
+                    .resting
+                    .iter()
+                    .filter(|(_, other)| other.list.as_ref() == Some(&list))
+                    .map(|(other, _)| other.clone())
+                    .collect();
+                for other in others {
+                    state.resting.remove(&other);
+                    state.done.insert(other.clone(), Self::canceled(other));
+                }
+                state.done.insert(
+                    list.clone(),
+                    OrderResponse {
+                        order_id: list,
+                        ..response.clone()
+                    },
+                );
+            }
+            state.done.insert(id, response);
+        }
+    }
+
+    fn filled(order_id: String, order: &Order, price: Decimal, fee: Decimal) -> OrderResponse {
+        let (_, quote) = split_symbol(&order.symbol);
+        OrderResponse {
+            order_id,
+            status: OrderStatus::Filled,
+            fills: vec![Fill {
+                price,
+                quantity: order.quantity,
+                commission: fee,
+                commission_asset: quote.to_string(),
+            }],
+        }
+    }
+
+    fn canceled(order_id: String) -> OrderResponse {
+        OrderResponse {
+            order_id,
+            status: OrderStatus::Canceled,
+            fills: Vec::new(),
+        }
+    }
+
+    fn pending(order_id: String) -> OrderResponse {
+        OrderResponse {
+            order_id,
+            status: OrderStatus::Pending,
+            fills: Vec::new(),
+        }
+    }
+
+    fn next_id(state: &mut PaperState) -> String {
+        let id = format!("paper-{}", state.next_id);
+        state.next_id += 1;
+        id
+    }
+
+    fn connected(state: &PaperState) -> Result<(), TradingError> {
+        if state.connected {
+            Ok(())
+        } else {
+            Err(TradingError::ConnectionError("Not connected".into()))
+        }
+    }
+}
+
+impl ExchangeClient for PaperExchangeClient {
+    async fn connect(&mut self) -> Result<(), TradingError> {
+        let mut state = self.state.lock().unwrap();
+        state.connected = true;
+        log::info!("Paper trading with a balance of {:.2}", state.balance);
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<(), TradingError> {
+        self.state.lock().unwrap().connected = false;
+        Ok(())
+    }
+
+    async fn get_balance(&self) -> Result<Decimal, TradingError> {
+        let mut state = self.state.lock().unwrap();
+        Self::connected(&state)?;
+        self.sweep(&mut state);
+        Ok(state.balance)
+    }
+
+    async fn send_order(&mut self, order: &Order) -> Result<OrderResponse, TradingError> {
+        let mut state = self.state.lock().unwrap();
+        Self::connected(&state)?;
+        if let OrderType::TrailingStop { .. } = order.order_type {
+            return Err(TradingError::OrderError(
+                "Paper trading has no trailing stop orders".into(),
+            ));
+        }
+        self.sweep(&mut state);
+        let market = self.market(&order.symbol)?;
+        if let Some(rules) = self.history.symbol_rules(&order.symbol) {
+            let price = match order.order_type {
+                OrderType::Limit(price) | OrderType::Stop(price) => price,
+                _ => market.last_price,
+            };
+            rules
+                .check(order.quantity, price)
+                .map_err(TradingError::OrderError)?;
+        }
+        let order_id = Self::next_id(&mut state);
+
+        let fill = self.try_fill(&market, order);
+        if let OrderType::Limit(_) = order.order_type {
+            if order.post_only && fill.is_some() {
+                return Err(TradingError::OrderError(format!(
+                    "Post-only order would take liquidity at {}",
+                    market.last_price
+                )));
+            }
+            // Paper fills are all or nothing, so IOC and FOK behave the same
+            if fill.is_none() && order.time_in_force != TimeInForce::Gtc {
+                let response = Self::canceled(order_id);
+                state
+                    .done
+                    .insert(response.order_id.clone(), response.clone());
+                return Ok(response);
+            }
+        }
+        let Some((price, fee)) = fill else {
+            state.resting.insert(
+                order_id.clone(),
+                RestingOrder {
+                    order: order.clone(),
+                    list: None,
+                },
+            );
+            return Ok(Self::pending(order_id));
+        };
+        if let Err(reason) = Self::settle(&mut state, order, price, fee) {
+            log::warn!("Paper order {} rejected: {}", order_id, reason);
+            let response = OrderResponse {
+                order_id,
+                status: OrderStatus::Rejected,
+                fills: Vec::new(),
+            };
+            state
+                .done
+                .insert(response.order_id.clone(), response.clone());
+            return Ok(response);
+        }
+        log::info!(
+            "Paper order {} {:?} {} {} filled at {:.8}, fee {:.8}, balance {:.2}",
+            order_id,
+            order.side,
+            order.quantity,
+            order.symbol,
+            price,
+            fee,
+            state.balance
+        );
+        let response = Self::filled(order_id, order, price, fee);
+        state
+            .done
+            .insert(response.order_id.clone(), response.clone());
+        Ok(response)
+    }
+
+    async fn cancel_order(&mut self, _symbol: &str, order_id: &str) -> Result<(), TradingError> {
+        let mut state = self.state.lock().unwrap();
+        Self::connected(&state)?;
+        self.sweep(&mut state);
+        if let Some(done) = state.done.get(order_id) {
+            return Err(TradingError::OrderError(format!(
+                "Order {} already {:?}",
+                order_id, done.status
+            )));
+        }
+        let canceled: Vec<String> = state
+            .resting
+            .iter()
+            .filter(|(id, resting)| {
+                id.as_str() == order_id || resting.list.as_deref() == Some(order_id)
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        if canceled.is_empty() {
+            return Err(TradingError::OrderError(format!(
+                "Unknown order id: {}",
+                order_id
+            )));
+        }
+        for id in canceled {
+            state.resting.remove(&id);
+            state.done.insert(id.clone(), Self::canceled(id));
+        }
+        if !state.done.contains_key(order_id) {
+            // An OCO list, cancelled through its legs
+            state
+                .done
+                .insert(order_id.to_string(), Self::canceled(order_id.to_string()));
+        }
+        Ok(())
+    }
+
+    async fn get_open_orders(&self, symbol: &str) -> Result<Vec<OrderResponse>, TradingError> {
+        let mut state = self.state.lock().unwrap();
+        Self::connected(&state)?;
+        self.sweep(&mut state);
+        Ok(state
+            .resting
+            .iter()
+            .filter(|(_, resting)| resting.order.symbol == symbol)
+            .map(|(id, _)| Self::pending(id.clone()))
+            .collect())
+    }
+
+    async fn get_order_status(
+        &self,
+        _symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse, TradingError> {
+        let mut state = self.state.lock().unwrap();
+        Self::connected(&state)?;
+        self.sweep(&mut state);
+        if let Some(done) = state.done.get(order_id) {
+            return Ok(done.clone());
+        }
+        let working = state
+            .resting
+            .iter()
+            .any(|(id, resting)| id == order_id || resting.list.as_deref() == Some(order_id));
+        if working {
+            Ok(Self::pending(order_id.to_string()))
+        } else {
+            Err(TradingError::UnknownOrder(order_id.to_string()))
+        }
+    }
+
+    // Rests a take-profit limit and a stop leg; whichever fills cancels the other
+    fn symbol_rules(&self, symbol: &str) -> Option<SymbolRules> {
+        self.history.symbol_rules(symbol)
+    }
+
+    fn supports_short(&self, _symbol: &str) -> bool {
+        self.state.lock().unwrap().margin
+    }
+
+    async fn place_oco_order(
+        &mut self,
+        symbol: &str,
+        side: OrderSide,
+        quantity: Decimal,
+        bracket: &Bracket,
+    ) -> Result<OrderResponse, TradingError> {
+        let mut state = self.state.lock().unwrap();
+        Self::connected(&state)?;
+        let list = Self::next_id(&mut state);
+        for (leg, order_type) in [
+            ("tp", OrderType::Limit(bracket.take_profit)),
+            ("sl", OrderType::Stop(bracket.stop_loss)),
+        ] {
+            state.resting.insert(
+                format!("{}-{}", list, leg),
+                RestingOrder {
+                    order: Order {
+                        client_order_id: None,
+                        time_in_force: TimeInForce::Gtc,
+                        post_only: false,
+                        symbol: symbol.to_string(),
+                        quantity,
+                        order_type,
+                        side: side.clone(),
+                    },
+                    list: Some(list.clone()),
+                },
+            );
+        }
+        Ok(Self::pending(list))
+    }
+}
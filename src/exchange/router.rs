@@ -0,0 +1,176 @@
+use std::collections::{HashMap, VecDeque};
+
+use rust_decimal::Decimal;
+
+use super::LiveExchange;
+use crate::domain::*;
+
+// Splits a "venue:SYMBOL" entry, e.g. "kraken:XBTUSD"
+pub fn parse_venue(entry: &str) -> Result<(String, Symbol), TradingError> {
+    let (venue, symbol) = entry.split_once(':').ok_or_else(|| {
+        TradingError::InvalidParameter(format!("Venue {} is not in exchange:SYMBOL form", entry))
+    })?;
+    let symbol = symbol
+        .parse()
+        .map_err(|e| TradingError::InvalidParameter(format!("Venue {}: {:?}", entry, e)))?;
+    Ok((venue.to_string(), symbol))
+}
+
+/// `ExchangeClient` over several connected exchanges, so one bot process can
+/// trade more than one venue. Symbols prefixed with a venue name, e.g.
+/// "kraken:XBTUSD", go to that exchange and bare ones to the default. Cancels
+/// and lookups are routed the same way, by the symbol the order was placed on,
+/// so nothing about earlier orders needs remembering across restarts.
+pub struct ExchangeRouter<C = LiveExchange> {
+    default: String,
+    venues: HashMap<String, C>,
+}
+
+impl<C: ExchangeClient> ExchangeRouter<C> {
+    pub fn new(default: &str, client: C) -> Self {
+        ExchangeRouter {
+            default: default.to_string(),
+            venues: HashMap::from([(default.to_string(), client)]),
+        }
+    }
+
+    pub fn add(&mut self, venue: &str, client: C) -> Result<(), TradingError> {
+        if self.venues.contains_key(venue) {
+            return Err(TradingError::InvalidParameter(format!(
+                "Venue {} added twice",
+                venue
+            )));
+        }
+        self.venues.insert(venue.to_string(), client);
+        Ok(())
+    }
+
+    // Venue for `symbol` and the symbol without its prefix
+    fn route<'a>(&self, symbol: &'a str) -> Result<(String, &'a str), TradingError> {
+        let (venue, symbol) = match symbol.split_once(':') {
+            Some((venue, symbol)) => (venue, symbol),
+            None => (self.default.as_str(), symbol),
+        };
+        if !self.venues.contains_key(venue) {
+            return Err(TradingError::InvalidParameter(format!(
+                "No exchange connected for venue {}",
+                venue
+            )));
+        }
+        Ok((venue.to_string(), symbol))
+    }
+
+    fn venue_mut(&mut self, venue: &str) -> &mut C {
+        self.venues.get_mut(venue).expect("routed venue")
+    }
+}
+
+impl ExchangeRouter {
+    // Each venue's client is bound to one symbol; any other is refused rather
+    // than answered with that symbol's closes
+    pub async fn recent_closes(
+        &self,
+        symbol: &str,
+        interval: Interval,
+        count: usize,
+    ) -> Result<VecDeque<f64>, TradingError> {
+        let (venue, symbol) = self.route(symbol)?;
+        let client = &self.venues[&venue];
+        if client.symbol() != symbol {
+            return Err(TradingError::InvalidParameter(format!(
+                "{} is connected for {}, not {}",
+                venue,
+                client.symbol(),
+                symbol
+            )));
+        }
+        client.recent_closes(interval, count).await
+    }
+}
+
+impl<C: ExchangeClient> ExchangeClient for ExchangeRouter<C> {
+    async fn connect(&mut self) -> Result<(), TradingError> {
+        for client in self.venues.values_mut() {
+            client.connect().await?;
+        }
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<(), TradingError> {
+        for (venue, client) in self.venues.iter_mut() {
+            if let Err(e) = client.disconnect().await {
+                log::warn!("Failed to disconnect from {}: {}", venue, e);
+            }
+        }
+        Ok(())
+    }
+
+    // Balances are in different quote assets, so only the default venue's
+    async fn get_balance(&self) -> Result<Decimal, TradingError> {
+        self.venues[&self.default].get_balance().await
+    }
+
+    async fn send_order(&mut self, order: &Order) -> Result<OrderResponse, TradingError> {
+        let (venue, symbol) = self.route(&order.symbol)?;
+        let order = Order {
+            symbol: symbol.to_string(),
+            ..order.clone()
+        };
+        self.venue_mut(&venue).send_order(&order).await
+    }
+
+    async fn cancel_order(&mut self, symbol: &str, order_id: &str) -> Result<(), TradingError> {
+        let (venue, symbol) = self.route(symbol)?;
+        self.venue_mut(&venue).cancel_order(symbol, order_id).await
+    }
+
+    async fn get_open_orders(&self, symbol: &str) -> Result<Vec<OrderResponse>, TradingError> {
+        let (venue, symbol) = self.route(symbol)?;
+        self.venues[&venue].get_open_orders(symbol).await
+    }
+
+    // Orders this process hasn't seen acknowledged, e.g. after a timeout, are
+    // found the same way
+    async fn get_order_status(
+        &self,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse, TradingError> {
+        let (venue, symbol) = self.route(symbol)?;
+        self.venues[&venue].get_order_status(symbol, order_id).await
+    }
+
+    async fn bracket_legs(
+        &self,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<Vec<OrderResponse>, TradingError> {
+        let (venue, symbol) = self.route(symbol)?;
+        self.venues[&venue].bracket_legs(symbol, order_id).await
+    }
+
+    fn symbol_rules(&self, symbol: &str) -> Option<SymbolRules> {
+        let (venue, symbol) = self.route(symbol).ok()?;
+        self.venues[&venue].symbol_rules(symbol)
+    }
+
+    fn supports_short(&self, symbol: &str) -> bool {
+        match self.route(symbol) {
+            Ok((venue, symbol)) => self.venues[&venue].supports_short(symbol),
+            Err(_) => false,
+        }
+    }
+
+    async fn place_oco_order(
+        &mut self,
+        symbol: &str,
+        side: OrderSide,
+        quantity: Decimal,
+        bracket: &Bracket,
+    ) -> Result<OrderResponse, TradingError> {
+        let (venue, symbol) = self.route(symbol)?;
+        self.venue_mut(&venue)
+            .place_oco_order(symbol, side, quantity, bracket)
+            .await
+    }
+}
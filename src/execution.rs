@@ -0,0 +1,289 @@
+use std::collections::VecDeque;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::*;
+
+pub const ALGORITHM_NAMES: &[&str] = &["twap", "vwap"];
+
+// Keeps a tiny visible_pct from posting children forever
+const ICEBERG_SLICE_LIMIT: usize = 1000;
+
+/// One child order of a sliced parent order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Slice {
+    // Milliseconds after the parent order started
+    pub offset_ms: i64,
+    pub quantity: Decimal,
+}
+
+/// Splits a parent order into child slices spread over time, so a large
+/// order doesn't take all of its slippage at once
+pub trait ExecutionAlgorithm: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn schedule(&self, quantity: Decimal) -> Vec<Slice>;
+}
+
+// As many of `slices` as fit `duration_ms` at least `min_interval_ms` apart,
+// which keeps a schedule inside the exchange's order rate limits
+fn slice_count(duration_ms: i64, slices: usize, min_interval_ms: i64) -> usize {
+    let fit = (duration_ms / min_interval_ms.max(1)).max(1) as usize;
+    slices.min(fit).max(1)
+}
+
+/// Equal slices at equal intervals
+pub struct Twap {
+    pub duration_ms: i64,
+    pub slices: usize,
+    pub min_interval_ms: i64,
+}
+
+impl ExecutionAlgorithm for Twap {
+    fn name(&self) -> &'static str {
+        "twap"
+    }
+
+    fn schedule(&self, quantity: Decimal) -> Vec<Slice> {
+        let count = slice_count(self.duration_ms, self.slices, self.min_interval_ms);
+        let step = self.duration_ms / count as i64;
+        (0..count)
+            .map(|i| Slice {
+                offset_ms: step * i as i64,
+                quantity: quantity / Decimal::from(count),
+            })
+            .collect()
+    }
+}
+
+/// Slices sized by the share of volume usually traded in their part of the
+/// window; `volume_profile` is resampled to the slice count
+pub struct Vwap {
+    pub duration_ms: i64,
+    pub slices: usize,
+    pub min_interval_ms: i64,
+    pub volume_profile: Vec<f64>,
+}
+
+impl ExecutionAlgorithm for Vwap {
+    fn name(&self) -> &'static str {
+        "vwap"
+    }
+
+    fn schedule(&self, quantity: Decimal) -> Vec<Slice> {
+        let count = slice_count(self.duration_ms, self.slices, self.min_interval_ms);
+        let step = self.duration_ms / count as i64;
+        let weights: Vec<f64> = (0..count)
+            .map(|i| {
+                self.volume_profile
+                    .get(i * self.volume_profile.len() / count)
+                    .copied()
+                    .unwrap_or(1.0)
+                    .max(0.0)
+            })
+            .collect();
+        let total: f64 = weights.iter().sum();
+        (0..count)
+            .map(|i| Slice {
+                offset_ms: step * i as i64,
+                // A flat profile without volume to go by
+                quantity: if total > 0.0 {
+                    quantity * to_decimal(weights[i] / total)
+                } else {
+                    quantity / Decimal::from(count)
+                },
+            })
+            .collect()
+    }
+}
+
+/// Shows only `visible_pct` of the order at a time; the schedule has no
+/// delays, the next slice goes up once the one before has filled
+pub struct Iceberg {
+    pub visible_pct: f64,
+}
+
+impl ExecutionAlgorithm for Iceberg {
+    fn name(&self) -> &'static str {
+        "iceberg"
+    }
+
+    fn schedule(&self, quantity: Decimal) -> Vec<Slice> {
+        let count = ((100.0 / self.visible_pct).ceil() as usize).clamp(1, ICEBERG_SLICE_LIMIT);
+        (0..count)
+            .map(|_| Slice {
+                offset_ms: 0,
+                quantity: quantity / Decimal::from(count),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IcebergSettings {
+    // Limit entries worth at least this much quote currency are sliced
+    pub min_notional: Decimal,
+    // Share of the order resting on the book at a time, in percent
+    pub visible_pct: f64,
+}
+
+impl IcebergSettings {
+    pub fn validate(&self) -> Result<(), TradingError> {
+        if self.visible_pct <= 0.0 || self.visible_pct > 100.0 {
+            return Err(TradingError::InvalidParameter(
+                "Iceberg visible_pct must be in (0, 100]".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlgoSettings {
+    // One of ALGORITHM_NAMES
+    pub algorithm: String,
+    // Market entries worth at least this much quote currency are sliced
+    pub min_notional: Decimal,
+    pub duration_secs: i64,
+    pub slices: usize,
+    // Floor between child orders, to stay inside exchange rate limits
+    pub min_interval_ms: i64,
+    // Relative volume over the window, e.g. per minute; only read by "vwap"
+    pub volume_profile: Vec<f64>,
+}
+
+impl Default for AlgoSettings {
+    fn default() -> Self {
+        AlgoSettings {
+            algorithm: "twap".to_string(),
+            min_notional: Decimal::from(10_000),
+            duration_secs: 300,
+            slices: 10,
+            min_interval_ms: 1000,
+            volume_profile: Vec::new(),
+        }
+    }
+}
+
+impl AlgoSettings {
+    pub fn build(&self) -> Result<Box<dyn ExecutionAlgorithm>, TradingError> {
+        if self.duration_secs <= 0 || self.slices == 0 {
+            return Err(TradingError::InvalidParameter(
+                "Execution algorithm needs a positive duration_secs and slices".to_string(),
+            ));
+        }
+        let duration_ms = self.duration_secs * 1000;
+        match self.algorithm.as_str() {
+            "twap" => Ok(Box::new(Twap {
+                duration_ms,
+                slices: self.slices,
+                min_interval_ms: self.min_interval_ms,
+            })),
+            "vwap" => Ok(Box::new(Vwap {
+                duration_ms,
+                slices: self.slices,
+                min_interval_ms: self.min_interval_ms,
+                volume_profile: self.volume_profile.clone(),
+            })),
+            name => Err(TradingError::InvalidParameter(format!(
+                "Unknown execution algorithm {}, expected one of {}",
+                name,
+                ALGORITHM_NAMES.join(", ")
+            ))),
+        }
+    }
+}
+
+/// Entry worked slice by slice; becomes a position once its schedule is done
+/// and no child is left resting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParentOrder {
+    pub id: String,
+    pub signal: TradingSignal,
+    pub algorithm: String,
+    pub quantity: Decimal,
+    // Children are limit orders at this price, one resting at a time;
+    // market orders otherwise
+    pub limit: Option<Decimal>,
+    // Resting child and when it was placed, in Unix milliseconds
+    pub working: Option<(String, i64)>,
+    // Unix milliseconds
+    pub started_at: i64,
+    pub remaining: VecDeque<Slice>,
+    pub child_ids: Vec<String>,
+    pub fills: Vec<Fill>,
+}
+
+impl ParentOrder {
+    pub fn new(
+        algorithm: &dyn ExecutionAlgorithm,
+        signal: &TradingSignal,
+        quantity: Decimal,
+        limit: Option<Decimal>,
+        now_ms: i64,
+    ) -> Self {
+        ParentOrder {
+            id: format!("{}-{}-{}", algorithm.name(), signal.symbol, now_ms),
+            signal: signal.clone(),
+            algorithm: algorithm.name().to_string(),
+            quantity,
+            limit,
+            working: None,
+            started_at: now_ms,
+            remaining: algorithm.schedule(quantity).into(),
+            child_ids: Vec::new(),
+            fills: Vec::new(),
+        }
+    }
+
+    // Takes the slices that have come due, as one quantity. Limit children
+    // go one at a time, after the previous one is done.
+    pub fn take_due(&mut self, now_ms: i64) -> Decimal {
+        if self.working.is_some() {
+            return Decimal::ZERO;
+        }
+        let mut quantity = Decimal::ZERO;
+        while let Some(slice) = self.remaining.front() {
+            if self.started_at + slice.offset_ms > now_ms {
+                break;
+            }
+            quantity += slice.quantity;
+            self.remaining.pop_front();
+            if self.limit.is_some() {
+                break;
+            }
+        }
+        quantity
+    }
+
+    // A resting child's fills arrive later, on the account stream or when it's stopped
+    pub fn record(&mut self, child: &OrderResponse, now_ms: i64) {
+        self.child_ids.push(child.order_id.clone());
+        if matches!(
+            child.status,
+            OrderStatus::Pending | OrderStatus::PartiallyFilled
+        ) {
+            self.working = Some((child.order_id.clone(), now_ms));
+        } else {
+            self.fills.extend(child.fills.iter().cloned());
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.remaining.is_empty() && self.working.is_none()
+    }
+
+    pub fn filled_quantity(&self) -> Decimal {
+        self.fills.iter().map(|fill| fill.quantity).sum()
+    }
+
+    // The children's fills as one order
+    pub fn response(&self) -> OrderResponse {
+        OrderResponse {
+            order_id: self.id.clone(),
+            status: OrderStatus::Filled,
+            fills: self.fills.clone(),
+        }
+    }
+}
@@ -0,0 +1,2821 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::audit::{self, AuditAction, AuditActor, SharedAuditLog};
+use crate::domain::*;
+use crate::events::{BusMessage, EventBus};
+use crate::execution::{AlgoSettings, ExecutionAlgorithm, Iceberg, IcebergSettings, ParentOrder};
+use crate::journal::{self, JournalEvent, SharedJournal};
+use crate::latency::{LatencyStage, OrderLatencies};
+use crate::orders::{OrderState, OrderTracker, TrackedOrder};
+use crate::portfolio::{self, PnlReport};
+use crate::snapshot::ExecutorSnapshot;
+use crate::storage::{self, PositionEvent, SignalDedup, Storage, TradeStore};
+use crate::user_stream::{AccountEvent, ExecutionReport};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Position {
+    pub symbol: String,
+    pub strategy: String,
+    pub side: OrderSide,
+    pub quantity: Decimal,
+    pub entry_price: Decimal,
+    pub bracket: Option<Bracket>,
+    // Id of one OCO leg; cancelling it cancels the whole bracket
+    pub bracket_order_id: Option<String>,
+    pub opened_at: i64,
+    // Entry commission, in quote currency
+    pub entry_fees: Decimal,
+    // Client-side `OrderType::TrailingStop` kept alongside the bracket
+    #[serde(default)]
+    pub trailing_stop: Option<OrderType>,
+    // Best price since the trailing stop armed
+    #[serde(default)]
+    pub trailing_peak: Option<Decimal>,
+    // Entries added after the position opened
+    #[serde(default)]
+    pub scale_ins: u32,
+    // Base asset borrowed to sell a short
+    #[serde(default)]
+    pub borrowed: Decimal,
+    // Daily rate charged on the borrowed value, in percent
+    #[serde(default)]
+    pub interest_rate_pct: Decimal,
+    // Borrow interest accrued so far, in quote currency; booked as a fee on exit
+    #[serde(default)]
+    pub interest: Decimal,
+    // Unix seconds interest has been accrued up to
+    #[serde(default)]
+    pub interest_accrued_at: i64,
+}
+
+impl Position {
+    // Moves the trailing stop's best price along once the activation price is reached
+    pub fn follow(&mut self, price: Decimal) {
+        let Some(OrderType::TrailingStop {
+            activation_price, ..
+        }) = self.trailing_stop
+        else {
+            return;
+        };
+        let peak = match (&self.side, self.trailing_peak) {
+            (OrderSide::Buy, Some(peak)) => peak.max(price),
+            (OrderSide::Sell, Some(peak)) => peak.min(price),
+            (OrderSide::Buy, None) if price >= activation_price => price,
+            (OrderSide::Sell, None) if price <= activation_price => price,
+            (_, None) => return,
+        };
+        self.trailing_peak = Some(peak);
+    }
+
+    // None until the trailing stop has armed
+    pub fn trailing_stop_price(&self) -> Option<Decimal> {
+        let Some(OrderType::TrailingStop { callback_rate, .. }) = self.trailing_stop else {
+            return None;
+        };
+        let peak = self.trailing_peak?;
+        let callback = callback_rate / Decimal::ONE_HUNDRED;
+        Some(match self.side {
+            OrderSide::Buy => peak * (Decimal::ONE - callback),
+            OrderSide::Sell => peak * (Decimal::ONE + callback),
+        })
+    }
+
+    // Charges interest on what's borrowed, valued at `price`, up to `now` in
+    // Unix seconds
+    pub fn accrue_interest(&mut self, price: Decimal, now: i64) {
+        if self.borrowed > Decimal::ZERO && now > self.interest_accrued_at {
+            let days = Decimal::from(now - self.interest_accrued_at) / Decimal::from(86_400);
+            self.interest +=
+                self.borrowed * price * self.interest_rate_pct / Decimal::ONE_HUNDRED * days;
+        }
+        self.interest_accrued_at = self.interest_accrued_at.max(now);
+    }
+
+    // Folds another entry into the position at the weighted average price.
+    // Refused when nothing is added.
+    pub fn add(
+        &mut self,
+        quantity: Decimal,
+        price: Decimal,
+        fees: Decimal,
+    ) -> Result<(), TradingError> {
+        if quantity <= Decimal::ZERO {
+            return Err(TradingError::InvalidParameter(format!(
+                "Cannot add {} to the {} position",
+                quantity, self.symbol
+            )));
+        }
+        let total = self.quantity + quantity;
+        self.entry_price = (self.entry_price * self.quantity + price * quantity) / total;
+        self.quantity = total;
+        self.entry_fees += fees;
+        if self.side == OrderSide::Sell {
+            self.borrowed += quantity;
+        }
+        self.scale_ins += 1;
+        Ok(())
+    }
+
+    // Takes `quantity` off as a position of its own with its share of the
+    // entry fees, borrow and interest, so it can be closed and booked as a
+    // separate trade. Taking nothing, or more than is held, is refused.
+    pub fn split(&mut self, quantity: Decimal) -> Result<Position, TradingError> {
+        if quantity <= Decimal::ZERO || quantity > self.quantity {
+            return Err(TradingError::InvalidParameter(format!(
+                "Cannot split {} off the {} {} position",
+                quantity, self.quantity, self.symbol
+            )));
+        }
+        let share = |amount: Decimal| amount * quantity / self.quantity;
+        let (fees, borrowed, interest) = (
+            share(self.entry_fees),
+            share(self.borrowed),
+            share(self.interest),
+        );
+        self.quantity -= quantity;
+        self.entry_fees -= fees;
+        self.borrowed -= borrowed;
+        self.interest -= interest;
+        Ok(Position {
+            quantity,
+            entry_fees: fees,
+            bracket_order_id: None,
+            borrowed,
+            interest,
+            ..self.clone()
+        })
+    }
+
+    pub fn trailing_stop_hit(&self, price: Decimal) -> bool {
+        match (self.trailing_stop_price(), &self.side) {
+            (Some(stop), OrderSide::Buy) => price <= stop,
+            (Some(stop), OrderSide::Sell) => price >= stop,
+            (None, _) => false,
+        }
+    }
+}
+
+// Longs are entered by buy signals, shorts by sell signals
+fn entry_side(signal: &TradingSignal) -> OrderSide {
+    match signal.action {
+        TradeAction::Sell => OrderSide::Sell,
+        TradeAction::Buy | TradeAction::Hold | TradeAction::Close => OrderSide::Buy,
+    }
+}
+
+/// A closed round trip
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "web", derive(utoipa::ToSchema))]
+pub struct Trade {
+    pub symbol: String,
+    pub strategy: String,
+    pub side: OrderSide,
+    pub quantity: f64,
+    pub entry_price: f64,
+    pub exit_price: f64,
+    // Entry plus exit commission and any borrow interest, in quote currency
+    pub fees: f64,
+    // Net of fees
+    pub pnl: f64,
+    pub opened_at: i64,
+    pub closed_at: i64,
+}
+
+/// A resting order we are waiting on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkingOrder {
+    pub order_id: String,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub placed_at: i64,
+    pub expires_at: i64,
+    // Signal that produced the order, kept for re-signalling on expiry
+    pub signal: TradingSignal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecutionSettings {
+    // Enter with a limit order this far below the signal price instead of a market order
+    pub limit_entry_offset_pct: Option<Decimal>,
+    // Unfilled limit orders are cancelled after this many seconds
+    pub order_ttl_secs: i64,
+    // Re-run the original signal at the current price once its order expired
+    pub resignal_on_expiry: bool,
+    // Trail a stop behind each new position as it moves into profit
+    pub trailing_stop: Option<TrailingStopSettings>,
+    // Work large market entries as TWAP or VWAP slices instead of one order
+    pub algo: Option<AlgoSettings>,
+    // Show large limit entries a slice at a time, re-posting as each one fills
+    pub iceberg: Option<IcebergSettings>,
+    pub retry: RetrySettings,
+    // For limit entries and iceberg slices; post-only needs GTC
+    pub time_in_force: TimeInForce,
+    pub post_only: bool,
+    // Buy signals from a position's own strategy add to it at market up to this
+    // many times, each one sized like a new entry
+    pub max_scale_ins: u32,
+    // Share of the position each exit signal closes; the rest keeps a bracket
+    pub exit_fraction: Decimal,
+    // Sell signals without a long open a short where the exchange can sell
+    // what isn't held, through margin or futures
+    pub margin: Option<MarginSettings>,
+}
+
+/// Re-submission of orders that failed on the way to the exchange
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetrySettings {
+    // Including the first
+    pub max_attempts: u32,
+    // Doubled after every attempt
+    pub backoff_ms: u64,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        RetrySettings {
+            max_attempts: 3,
+            backoff_ms: 500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrailingStopSettings {
+    // Arms once the price is this far above the entry, below it for shorts
+    pub activation_pct: Decimal,
+    // Exits once the price moves back this far from its best since arming
+    pub callback_rate: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarginSettings {
+    // Borrow interest on a short's value, in percent a day; 0 on futures,
+    // where funding is paid instead
+    pub daily_interest_pct: Decimal,
+}
+
+impl Default for ExecutionSettings {
+    fn default() -> Self {
+        ExecutionSettings {
+            limit_entry_offset_pct: None,
+            order_ttl_secs: 300,
+            resignal_on_expiry: false,
+            trailing_stop: None,
+            algo: None,
+            iceberg: None,
+            retry: RetrySettings::default(),
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+            max_scale_ins: 0,
+            exit_fraction: Decimal::ONE,
+            margin: None,
+        }
+    }
+}
+
+/// Positions and trade history, written only by the task that owns the executor
+/// (prices, signals and control commands all arrive through its mailboxes) and
+/// read by everything else. Locks on it are only ever held for bookkeeping,
+/// never across exchange calls.
+#[derive(Debug)]
+pub struct ExecutorState {
+    pub positions: HashMap<String, Position>,
+    // Keyed by order id
+    pub working_orders: HashMap<String, WorkingOrder>,
+    // Entries being sliced by an execution algorithm, keyed by symbol
+    pub parent_orders: HashMap<String, ParentOrder>,
+    // Lifecycle of every order placed or reported, for queries
+    pub orders: OrderTracker,
+    pub trades: Vec<Trade>,
+    daily_pnl: f64,
+    pnl_day: NaiveDate,
+    // Latest price per symbol, used to convert commissions paid in a third asset
+    pub last_prices: HashMap<String, Decimal>,
+    // Durable history, when configured
+    pub store: Option<Storage>,
+    // Outgoing signals, orders, fills, risk decisions and position updates
+    pub bus: EventBus,
+    // Signal to submit, submit to ack and ack to fill, per order
+    pub latency: OrderLatencies,
+    // Entries are refused while set; exits still go through
+    pub paused: bool,
+    // Why the bot put itself into safe mode, which refuses entries like `paused`
+    pub safe_mode: Option<String>,
+    // Copy of the executor's risk limits, for snapshots and the control API
+    pub risk: RiskParameters,
+    // Every strategy running live or seen in a signal, and whether it may open
+    // positions; its exits are still handled when disabled
+    pub strategies: BTreeMap<String, bool>,
+}
+
+pub type SharedExecutorState = Arc<RwLock<ExecutorState>>;
+
+impl Default for ExecutorState {
+    fn default() -> Self {
+        ExecutorState {
+            positions: HashMap::new(),
+            working_orders: HashMap::new(),
+            parent_orders: HashMap::new(),
+            orders: OrderTracker::default(),
+            trades: Vec::new(),
+            daily_pnl: 0.0,
+            pnl_day: chrono::Utc::now().date_naive(),
+            last_prices: HashMap::new(),
+            store: None,
+            bus: EventBus::default(),
+            latency: OrderLatencies::default(),
+            paused: false,
+            safe_mode: None,
+            risk: RiskParameters::default(),
+            strategies: BTreeMap::new(),
+        }
+    }
+}
+
+impl ExecutorState {
+    // Realized PnL from closed trades plus unrealized PnL marked at the latest prices
+    pub fn pnl_report(&self) -> PnlReport {
+        portfolio::build_report(&self.trades, &self.positions, &self.last_prices)
+    }
+
+    // Realized PnL for the current UTC day, net of fees
+    pub fn daily_pnl(&self) -> f64 {
+        if chrono::Utc::now().date_naive() != self.pnl_day {
+            return 0.0;
+        }
+        self.daily_pnl
+    }
+
+    // Called on every price update. Positions are only marked here; a bracket
+    // exit is booked once a leg has filled, see TradeExecutor::check_brackets.
+    pub fn on_price(&mut self, symbol: &str, price: Decimal) {
+        self.last_prices.insert(symbol.to_string(), price);
+        if let Some(position) = self.positions.get_mut(symbol) {
+            position.follow(price);
+            position.accrue_interest(price, chrono::Utc::now().timestamp());
+        }
+    }
+
+    pub fn publish(&self, message: BusMessage) {
+        self.bus.publish(message);
+    }
+
+    pub fn position_changed(&self, event: PositionEvent, position: &Position, price: Decimal) {
+        self.persist(|store| store.record_position(event, position, price));
+        self.publish(BusMessage::position(event, position, price));
+    }
+
+    pub fn snapshot(&self) -> ExecutorSnapshot {
+        ExecutorSnapshot {
+            positions: self.positions.values().cloned().collect(),
+            working_orders: self.working_orders.values().cloned().collect(),
+            parent_orders: self.parent_orders.values().cloned().collect(),
+            open_orders: self.orders.snapshot(),
+            trades: self.trades.clone(),
+            daily_pnl: self.daily_pnl,
+            pnl_day: self.pnl_day,
+            paused: self.paused,
+            strategies: self.strategies.clone(),
+        }
+    }
+
+    // Replaces positions, orders and history; last prices and storage are kept
+    pub fn restore(&mut self, snapshot: ExecutorSnapshot) {
+        self.positions = snapshot
+            .positions
+            .into_iter()
+            .map(|position| (position.symbol.clone(), position))
+            .collect();
+        self.working_orders = snapshot
+            .working_orders
+            .into_iter()
+            .map(|order| (order.order_id.clone(), order))
+            .collect();
+        self.parent_orders = snapshot
+            .parent_orders
+            .into_iter()
+            .map(|parent| (parent.signal.symbol.clone(), parent))
+            .collect();
+        self.orders.restore(snapshot.open_orders);
+        self.trades = snapshot.trades;
+        self.daily_pnl = snapshot.daily_pnl;
+        self.pnl_day = snapshot.pnl_day;
+        self.paused = snapshot.paused;
+        self.strategies = snapshot.strategies;
+    }
+
+    pub fn register_strategy(&mut self, name: &str) {
+        self.strategies.entry(name.to_string()).or_insert(true);
+    }
+
+    pub fn strategy_enabled(&self, name: &str) -> bool {
+        self.strategies.get(name).copied().unwrap_or(true)
+    }
+
+    pub fn set_strategy_enabled(&mut self, name: &str, enabled: bool) -> Result<(), TradingError> {
+        match self.strategies.get_mut(name) {
+            Some(flag) => {
+                *flag = enabled;
+                Ok(())
+            }
+            None => Err(TradingError::InvalidParameter(format!(
+                "Unknown strategy {}, expected one of {}",
+                name,
+                self.strategies
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    pub fn persist<F>(&self, write: F)
+    where
+        F: FnOnce(&dyn TradeStore) -> Result<(), TradingError>,
+    {
+        if let Some(store) = &self.store {
+            storage::log_error(write(store.as_ref()));
+        }
+    }
+
+    // Convert the commission of a fill into the symbol's quote currency
+    fn fee_in_quote(&self, symbol: &str, fill: &Fill) -> Decimal {
+        let (base, quote) = split_symbol(symbol);
+        if fill.commission_asset == quote {
+            return fill.commission;
+        }
+        if fill.commission_asset == base {
+            return fill.commission * fill.price;
+        }
+        let pair = format!("{}{}", fill.commission_asset, quote);
+        match self.last_prices.get(&pair) {
+            Some(price) => fill.commission * price,
+            None => {
+                log::warn!(
+                    "No {} price to convert {} {} commission, ignoring it",
+                    pair,
+                    fill.commission,
+                    fill.commission_asset
+                );
+                Decimal::ZERO
+            }
+        }
+    }
+
+    pub fn total_fees(&self, symbol: &str, response: &OrderResponse) -> Decimal {
+        response
+            .fills
+            .iter()
+            .map(|fill| self.fee_in_quote(symbol, fill))
+            .sum()
+    }
+
+    // Trades are booked in f64, which the reports and the backtester share
+    pub fn record_trade(
+        &mut self,
+        mut position: Position,
+        exit_price: Decimal,
+        exit_fees: Decimal,
+    ) {
+        position.accrue_interest(exit_price, chrono::Utc::now().timestamp());
+        let direction = match position.side {
+            OrderSide::Buy => Decimal::ONE,
+            OrderSide::Sell => Decimal::NEGATIVE_ONE,
+        };
+        let fees = position.entry_fees + exit_fees + position.interest;
+        let pnl = (exit_price - position.entry_price) * position.quantity * direction - fees;
+        let (fees, pnl) = (from_decimal(fees), from_decimal(pnl));
+
+        let today = chrono::Utc::now().date_naive();
+        if today != self.pnl_day {
+            self.pnl_day = today;
+            self.daily_pnl = 0.0;
+        }
+        self.daily_pnl = from_decimal(to_decimal(self.daily_pnl) + to_decimal(pnl));
+        log::info!(
+            "Closed {} at {}: pnl {:.4} (fees {:.4}), daily pnl {:.4}",
+            position.symbol,
+            exit_price,
+            pnl,
+            fees,
+            self.daily_pnl
+        );
+
+        let trade = Trade {
+            symbol: position.symbol,
+            strategy: position.strategy,
+            side: position.side,
+            quantity: from_decimal(position.quantity),
+            entry_price: from_decimal(position.entry_price),
+            exit_price: from_decimal(exit_price),
+            fees,
+            pnl,
+            opened_at: position.opened_at,
+            closed_at: chrono::Utc::now().timestamp(),
+        };
+        self.persist(|store| store.record_trade(&trade));
+        self.trades.push(trade);
+    }
+}
+
+/// Turns trading signals into orders and keeps track of open positions
+pub struct TradeExecutor<E: ExchangeClient> {
+    exchange: E,
+    risk: RiskParameters,
+    settings: ExecutionSettings,
+    state: SharedExecutorState,
+    journal: Option<SharedJournal>,
+    // When the signal being handled arrived, until its order is sent
+    signal_received_at: Option<Instant>,
+    audit: Option<SharedAuditLog>,
+    // Recorded with every order action; set by each public entry point
+    actor: AuditActor,
+    dedup: Option<SignalDedup>,
+    // Signal being handled, recorded in `dedup` once its order is acknowledged
+    dedup_signal: Option<TradingSignal>,
+}
+
+impl<E: ExchangeClient> TradeExecutor<E> {
+    pub fn new(exchange: E, risk: RiskParameters) -> Self {
+        let state = ExecutorState {
+            risk: risk.clone(),
+            ..ExecutorState::default()
+        };
+        TradeExecutor {
+            exchange,
+            risk,
+            settings: ExecutionSettings::default(),
+            state: Arc::new(RwLock::new(state)),
+            journal: None,
+            signal_received_at: None,
+            audit: None,
+            actor: AuditActor::Strategy {
+                name: String::new(),
+            },
+            dedup: None,
+            dedup_signal: None,
+        }
+    }
+
+    pub fn set_journal(&mut self, journal: SharedJournal) {
+        self.journal = Some(journal);
+    }
+
+    pub fn set_audit_log(&mut self, audit: SharedAuditLog) {
+        self.audit = Some(audit);
+    }
+
+    pub fn set_signal_dedup(&mut self, dedup: SignalDedup) {
+        self.dedup = Some(dedup);
+    }
+
+    pub fn set_execution_settings(&mut self, settings: ExecutionSettings) {
+        self.settings = settings;
+    }
+
+    // Applies to new entries; open positions keep their brackets
+    pub async fn set_risk(&mut self, risk: RiskParameters) {
+        log::info!("Risk parameters updated: {:?}", risk);
+        self.state.write().await.risk = risk.clone();
+        self.risk = risk;
+    }
+
+    pub async fn balance(&self) -> Result<Decimal, TradingError> {
+        self.exchange.get_balance().await
+    }
+
+    // Read-only handle for reporting, the API and notifiers
+    pub fn state(&self) -> SharedExecutorState {
+        self.state.clone()
+    }
+
+    // Mark open positions for trailing stops, interest and bracket checks
+    pub async fn mark_price(&self, symbol: &str, price: Decimal) {
+        self.state.write().await.on_price(symbol, price);
+    }
+
+    // Persist history from now on, picking up the trades recorded by earlier runs
+    pub async fn set_store(&self, store: Storage) {
+        let mut state = self.state.write().await;
+        match store.load_trades() {
+            Ok(trades) => {
+                log::info!("Loaded {} trades from storage", trades.len());
+                state.trades = trades;
+            }
+            Err(e) => log::error!("Failed to load trade history: {}", e),
+        }
+        state.store = Some(store);
+    }
+
+    pub async fn positions(&self) -> HashMap<String, Position> {
+        self.state.read().await.positions.clone()
+    }
+
+    pub async fn pnl_report(&self) -> PnlReport {
+        self.state.read().await.pnl_report()
+    }
+
+    pub async fn daily_pnl(&self) -> f64 {
+        self.state.read().await.daily_pnl()
+    }
+
+    // On the exchange's step size when its rules are known, refused below its
+    // minimums; truncated to 5 decimals otherwise
+    pub fn calculate_order_size(&self, symbol: &str, price: Decimal) -> Result<Decimal, String> {
+        match self.exchange.symbol_rules(symbol) {
+            Some(rules) => rules.order_quantity(self.risk.max_position_size, price),
+            None => Ok(self.risk.order_quantity(price)),
+        }
+    }
+
+    fn round_price(&self, symbol: &str, price: Decimal) -> Decimal {
+        self.exchange
+            .symbol_rules(symbol)
+            .map_or(price, |rules| rules.round_price(price))
+    }
+
+    fn round_quantity(&self, symbol: &str, quantity: Decimal) -> Decimal {
+        self.exchange
+            .symbol_rules(symbol)
+            .map_or(quantity, |rules| rules.round_quantity(quantity))
+    }
+
+    pub async fn handle_signal(&mut self, signal: &TradingSignal) -> Result<(), TradingError> {
+        self.signal_received_at = Some(Instant::now());
+        self.actor = AuditActor::Strategy {
+            name: signal.strategy.clone(),
+        };
+        if let Some(dedup) = &self.dedup {
+            match dedup.executed(signal) {
+                Ok(Some(order_id)) => {
+                    log::warn!(
+                        "{:?} {} signal from {} at {} already placed order {}, ignoring",
+                        signal.action,
+                        signal.symbol,
+                        signal.strategy,
+                        signal.timestamp,
+                        order_id
+                    );
+                    return Ok(());
+                }
+                Ok(None) => self.dedup_signal = Some(signal.clone()),
+                Err(e) => log::error!("Failed to check signal dedup store: {}", e),
+            }
+        }
+        let result = self.act_on_signal(signal).await;
+        self.dedup_signal = None;
+        result
+    }
+
+    async fn act_on_signal(&mut self, signal: &TradingSignal) -> Result<(), TradingError> {
+        let (held, can_scale_in, has_working_order, open_positions, paused, enabled) = {
+            let mut state = self.state.write().await;
+            state.register_strategy(&signal.strategy);
+            state.persist(|store| store.record_signal(signal));
+            state.publish(BusMessage::Signal {
+                signal: signal.clone(),
+            });
+            let position = state.positions.get(&signal.symbol);
+            (
+                position.map(|position| position.side.clone()),
+                position.is_some_and(|position| {
+                    position.strategy == signal.strategy
+                        && position.scale_ins < self.settings.max_scale_ins
+                }),
+                state
+                    .working_orders
+                    .values()
+                    .any(|order| order.symbol == signal.symbol)
+                    || state.parent_orders.contains_key(&signal.symbol),
+                state.positions.len() + state.working_orders.len() + state.parent_orders.len(),
+                state.paused || state.safe_mode.is_some(),
+                state.strategy_enabled(&signal.strategy),
+            )
+        };
+        let side = match signal.action {
+            TradeAction::Buy => OrderSide::Buy,
+            TradeAction::Sell => OrderSide::Sell,
+            TradeAction::Hold => return Ok(()),
+            TradeAction::Close if held.is_none() => {
+                log::debug!("No position in {}, ignoring close signal", signal.symbol);
+                return Ok(());
+            }
+            TradeAction::Close => return self.close_position(&signal.symbol, signal.price).await,
+        };
+        let action = match side {
+            OrderSide::Buy => "buy",
+            OrderSide::Sell => "sell",
+        };
+
+        // Against the position held: an exit
+        if held.as_ref().is_some_and(|held| *held != side) {
+            if self.settings.exit_fraction < Decimal::ONE {
+                return self
+                    .reduce_position(&signal.symbol, signal.price, self.settings.exit_fraction)
+                    .await;
+            }
+            return self.close_position(&signal.symbol, signal.price).await;
+        }
+        let can_short =
+            self.settings.margin.is_some() && self.exchange.supports_short(&signal.symbol);
+        if held.is_none() && side == OrderSide::Sell && !can_short {
+            log::debug!("No position in {}, ignoring sell signal", signal.symbol);
+            return Ok(());
+        }
+        if (held.is_some() && !can_scale_in) || has_working_order {
+            log::debug!(
+                "Already holding or entering {}, ignoring {} signal",
+                signal.symbol,
+                action
+            );
+            return Ok(());
+        }
+        if paused {
+            log::info!(
+                "Trading paused, ignoring {} signal for {}",
+                action,
+                signal.symbol
+            );
+            self.risk_rejected(&signal.symbol, "trading paused".to_string())
+                .await;
+            return Ok(());
+        }
+        if !enabled {
+            log::info!(
+                "Strategy {} disabled, ignoring {} signal for {}",
+                signal.strategy,
+                action,
+                signal.symbol
+            );
+            self.risk_rejected(
+                &signal.symbol,
+                format!("strategy {} disabled", signal.strategy),
+            )
+            .await;
+            return Ok(());
+        }
+        if held.is_some() {
+            return self.scale_in(signal).await;
+        }
+        if open_positions >= self.risk.max_open_positions {
+            log::info!(
+                "{} positions open, ignoring {} signal for {}",
+                open_positions,
+                action,
+                signal.symbol
+            );
+            self.risk_rejected(
+                &signal.symbol,
+                format!("{} positions already open", open_positions),
+            )
+            .await;
+            return Ok(());
+        }
+        // Below the signal price for longs, above it for shorts
+        let order_type = match (self.settings.limit_entry_offset_pct, side) {
+            (Some(offset), OrderSide::Buy) => OrderType::Limit(self.round_price(
+                &signal.symbol,
+                signal.price * (Decimal::ONE - offset / Decimal::ONE_HUNDRED),
+            )),
+            (Some(offset), OrderSide::Sell) => OrderType::Limit(self.round_price(
+                &signal.symbol,
+                signal.price * (Decimal::ONE + offset / Decimal::ONE_HUNDRED),
+            )),
+            (None, _) => OrderType::Market,
+        };
+        self.open_position(signal, order_type).await
+    }
+
+    // Operator-placed order, refused with a reason wherever a strategy signal
+    // would be ignored. Entries go at market, or as a limit order at `price`
+    // if `limit`; exits always go at market. Without a price the latest known
+    // one is used.
+    pub async fn manual_order(
+        &mut self,
+        symbol: &str,
+        action: TradeAction,
+        price: Option<Decimal>,
+        limit: bool,
+        requested_by: String,
+    ) -> Result<(), TradingError> {
+        let reject = |reason: String| Err(TradingError::InvalidParameter(reason));
+        let (held, has_working_order, open_positions, paused, last_price) = {
+            let state = self.state.read().await;
+            (
+                state
+                    .positions
+                    .get(symbol)
+                    .map(|position| position.side.clone()),
+                state
+                    .working_orders
+                    .values()
+                    .any(|order| order.symbol == symbol)
+                    || state.parent_orders.contains_key(symbol),
+                state.positions.len() + state.working_orders.len() + state.parent_orders.len(),
+                state.paused || state.safe_mode.is_some(),
+                state.last_prices.get(symbol).copied(),
+            )
+        };
+        let side = match action {
+            TradeAction::Buy => OrderSide::Buy,
+            TradeAction::Sell => OrderSide::Sell,
+            TradeAction::Hold | TradeAction::Close => {
+                return reject("Manual orders buy or sell".to_string())
+            }
+        };
+        // Against the position held: an exit, long or short
+        let exit = held.as_ref().is_some_and(|held| *held != side);
+        if exit && limit {
+            return reject("Exits are sent at market".to_string());
+        }
+        if !exit {
+            let can_short = self.settings.margin.is_some() && self.exchange.supports_short(symbol);
+            if held.is_some() || has_working_order {
+                return reject(format!("Already holding or entering {}", symbol));
+            }
+            if side == OrderSide::Sell && !can_short {
+                return reject(format!("No position in {}", symbol));
+            }
+            if paused {
+                return reject("Trading paused".to_string());
+            }
+            if open_positions >= self.risk.max_open_positions {
+                return reject(format!("{} positions already open", open_positions));
+            }
+        }
+        if limit && price.is_none() {
+            return reject("Limit orders need a price".to_string());
+        }
+        let price = match price.or(last_price) {
+            Some(price) if price > Decimal::ZERO => price,
+            _ => return reject(format!("No price known for {}", symbol)),
+        };
+
+        let signal = TradingSignal {
+            symbol: symbol.to_string(),
+            strategy: "manual".to_string(),
+            action,
+            price,
+            timestamp: chrono::Utc::now().timestamp(),
+            stop_loss: None,
+            take_profit: None,
+        };
+        {
+            let state = self.state.read().await;
+            state.persist(|store| store.record_signal(&signal));
+            state.publish(BusMessage::Signal {
+                signal: signal.clone(),
+            });
+        }
+        self.signal_received_at = Some(Instant::now());
+        self.actor = AuditActor::Operator { requested_by };
+        if exit {
+            return self.close_position(symbol, price).await;
+        }
+        let order_type = if limit {
+            OrderType::Limit(self.round_price(symbol, price))
+        } else {
+            OrderType::Market
+        };
+        self.open_position(&signal, order_type).await
+    }
+
+    // Cancel every resting entry order; brackets of open positions stay. Sliced
+    // entries stop, and what they filled so far is tracked on the next slice check.
+    pub async fn cancel_working_orders(&mut self, actor: AuditActor) {
+        self.actor = actor;
+        let children: Vec<(String, String)>;
+        let orders: Vec<WorkingOrder> = {
+            let mut state = self.state.write().await;
+            for parent in state.parent_orders.values_mut() {
+                parent.remaining.clear();
+            }
+            children = state
+                .parent_orders
+                .values()
+                .filter_map(|parent| {
+                    let (child_id, _) = parent.working.as_ref()?;
+                    Some((parent.signal.symbol.clone(), child_id.clone()))
+                })
+                .collect();
+            state
+                .working_orders
+                .drain()
+                .map(|(_, order)| order)
+                .collect()
+        };
+        for order in orders {
+            if let Err(e) = self.cancel_order(&order.symbol, &order.order_id).await {
+                log::warn!("Failed to cancel order {}: {}", order.order_id, e);
+            }
+        }
+        for (symbol, child_id) in children {
+            self.stop_child(&symbol, &child_id).await;
+        }
+    }
+
+    // Cancel every resting order and market out of every position, e.g. on a kill
+    // command. Positions that fail to close stay tracked.
+    pub async fn close_all(&mut self, actor: AuditActor) -> Result<(), TradingError> {
+        self.cancel_working_orders(actor).await;
+        let positions: Vec<(String, Decimal)> = {
+            let state = self.state.read().await;
+            state
+                .positions
+                .values()
+                .map(|position| {
+                    let price = state
+                        .last_prices
+                        .get(&position.symbol)
+                        .copied()
+                        .unwrap_or(position.entry_price);
+                    (position.symbol.clone(), price)
+                })
+                .collect()
+        };
+        let mut result = Ok(());
+        for (symbol, price) in positions {
+            if let Err(e) = self.close_position(&symbol, price).await {
+                log::error!("Failed to close {}: {}", symbol, e);
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    // Cancel resting orders that outlived their TTL. Returns the signals to re-run,
+    // repriced at the latest known price, when re-signalling is enabled.
+    pub async fn expire_orders(&mut self) -> Vec<TradingSignal> {
+        self.actor = AuditActor::OrderExpiry;
+        let now = chrono::Utc::now().timestamp();
+        let expired: Vec<WorkingOrder> = {
+            let mut state = self.state.write().await;
+            let ids: Vec<String> = state
+                .working_orders
+                .values()
+                .filter(|order| order.expires_at <= now)
+                .map(|order| order.order_id.clone())
+                .collect();
+            ids.iter()
+                .filter_map(|id| state.working_orders.remove(id))
+                .collect()
+        };
+
+        let mut resignals = Vec::new();
+        for order in expired {
+            log::info!(
+                "Order {} for {} unfilled after {}s, cancelling",
+                order.order_id,
+                order.symbol,
+                now - order.placed_at
+            );
+            let canceled = self.cancel_order(&order.symbol, &order.order_id).await;
+            // Whatever it filled before the cancel, or since if the cancel
+            // failed because it filled in the meantime, is held now
+            let response = match self
+                .exchange
+                .get_order_status(&order.symbol, &order.order_id)
+                .await
+            {
+                Ok(response) => response,
+                Err(e) => {
+                    log::error!(
+                        "Failed to read fills of expired order {}: {}",
+                        order.order_id,
+                        e
+                    );
+                    if canceled.is_err() {
+                        self.keep_working(order).await;
+                    }
+                    continue;
+                }
+            };
+            if let Err(e) = canceled {
+                if matches!(
+                    response.status,
+                    OrderStatus::Pending | OrderStatus::PartiallyFilled
+                ) {
+                    // Still resting; the next pass tries again
+                    log::warn!("Failed to cancel expired order {}: {}", order.order_id, e);
+                    self.keep_working(order).await;
+                    continue;
+                }
+            }
+            let filled: Decimal = response.fills.iter().map(|fill| fill.quantity).sum();
+            if filled > Decimal::ZERO {
+                log::info!(
+                    "Expired order {} for {} filled {} of {}",
+                    order.order_id,
+                    order.symbol,
+                    filled,
+                    order.quantity
+                );
+                if let Err(e) = self.entry_filled(&order.signal, filled, &response).await {
+                    log::error!("Failed to track filled entry {}: {}", order.order_id, e);
+                }
+                continue;
+            }
+            if self.settings.resignal_on_expiry {
+                let mut signal = order.signal;
+                if let Some(price) = self.state.read().await.last_prices.get(&signal.symbol) {
+                    signal.price = *price;
+                }
+                signal.timestamp = now;
+                resignals.push(signal);
+            }
+        }
+        resignals
+    }
+
+    async fn keep_working(&self, order: WorkingOrder) {
+        self.state
+            .write()
+            .await
+            .working_orders
+            .insert(order.order_id.clone(), order);
+    }
+
+    // Sends the slices of parent orders that have come due. A parent whose
+    // schedule is done becomes a position of whatever its children filled.
+    pub async fn work_parent_orders(&mut self) {
+        let now = chrono::Utc::now().timestamp_millis();
+        // A limit child left unfilled past the TTL ends its parent
+        let stale: Vec<(String, String)> = {
+            let mut state = self.state.write().await;
+            let ttl_ms = self.settings.order_ttl_secs * 1000;
+            state
+                .parent_orders
+                .values_mut()
+                .filter_map(|parent| {
+                    let (child_id, placed_at) = parent.working.clone()?;
+                    if now - placed_at < ttl_ms {
+                        return None;
+                    }
+                    parent.remaining.clear();
+                    Some((parent.signal.symbol.clone(), child_id))
+                })
+                .collect()
+        };
+        for (symbol, child_id) in stale {
+            log::info!(
+                "Child order {} for {} unfilled after the TTL",
+                child_id,
+                symbol
+            );
+            self.stop_child(&symbol, &child_id).await;
+        }
+
+        let due: Vec<(TradingSignal, Decimal, Option<Decimal>, String)> = {
+            let mut state = self.state.write().await;
+            state
+                .parent_orders
+                .values_mut()
+                .filter_map(|parent| {
+                    let quantity = parent.take_due(now);
+                    // Slices left make each child's id unique within the parent
+                    let key = format!("{}:{}", parent.id, parent.remaining.len());
+                    (quantity > Decimal::ZERO)
+                        .then(|| (parent.signal.clone(), quantity, parent.limit, key))
+                })
+                .collect()
+        };
+        for (signal, quantity, limit, key) in due {
+            let quantity = self.round_quantity(&signal.symbol, quantity);
+            if quantity <= Decimal::ZERO {
+                continue;
+            }
+            self.actor = AuditActor::Strategy {
+                name: signal.strategy.clone(),
+            };
+            let order = Order {
+                symbol: signal.symbol.clone(),
+                quantity,
+                order_type: limit.map_or(OrderType::Market, OrderType::Limit),
+                side: entry_side(&signal),
+                client_order_id: Some(client_order_id(&key)),
+                time_in_force: self.settings.time_in_force,
+                post_only: self.settings.post_only,
+            };
+            match self.send_order(&order).await {
+                Ok(response) => {
+                    let mut state = self.state.write().await;
+                    if let Some(parent) = state.parent_orders.get_mut(&signal.symbol) {
+                        parent.record(&response, now);
+                    }
+                }
+                // The slice is dropped; the parent ends up smaller
+                Err(e) => log::error!("Slice of {} entry failed: {}", signal.symbol, e),
+            }
+        }
+
+        let done: Vec<ParentOrder> = {
+            let mut state = self.state.write().await;
+            let symbols: Vec<String> = state
+                .parent_orders
+                .values()
+                .filter(|parent| parent.is_done())
+                .map(|parent| parent.signal.symbol.clone())
+                .collect();
+            symbols
+                .iter()
+                .filter_map(|symbol| state.parent_orders.remove(symbol))
+                .collect()
+        };
+        for parent in done {
+            let filled = parent.filled_quantity();
+            log::info!(
+                "Parent order {} done: {} of {} filled over {} children",
+                parent.id,
+                filled,
+                parent.quantity,
+                parent.child_ids.len()
+            );
+            self.journal(JournalEvent::ParentOrderDone {
+                symbol: parent.signal.symbol.clone(),
+                parent_id: parent.id.clone(),
+                filled,
+                child_ids: parent.child_ids.clone(),
+            });
+            if filled <= Decimal::ZERO {
+                continue;
+            }
+            self.actor = AuditActor::Strategy {
+                name: parent.signal.strategy.clone(),
+            };
+            if let Err(e) = self
+                .entry_filled(&parent.signal, filled, &parent.response())
+                .await
+            {
+                log::error!("Failed to track {} position: {}", parent.signal.symbol, e);
+            }
+        }
+    }
+
+    // Looks up the bracket of every position whose stop loss or take profit the
+    // price has reached, and closes the position once a leg has filled. The
+    // account stream usually books the fill first. A position without a
+    // bracket resting is exited at market instead.
+    pub async fn check_brackets(&mut self) {
+        let reached: Vec<(String, Option<String>, Decimal)> = {
+            let state = self.state.read().await;
+            state
+                .positions
+                .values()
+                .filter_map(|position| {
+                    let price = *state.last_prices.get(&position.symbol)?;
+                    let leg = position
+                        .bracket
+                        .as_ref()?
+                        .reached_leg(price, &position.side)?;
+                    Some((
+                        position.symbol.clone(),
+                        position.bracket_order_id.clone(),
+                        leg,
+                    ))
+                })
+                .collect()
+        };
+        for (symbol, bracket_order_id, leg) in reached {
+            let Some(order_id) = bracket_order_id else {
+                log::warn!(
+                    "{} reached its bracket at {} with none resting, exiting at market",
+                    symbol,
+                    leg
+                );
+                if let Err(e) = self.close_position(&symbol, leg).await {
+                    log::error!("Failed to exit {}: {}", symbol, e);
+                }
+                continue;
+            };
+            match self.exchange.get_order_status(&symbol, &order_id).await {
+                Ok(response) => match response.status {
+                    OrderStatus::Filled => self.bracket_filled(&symbol, &response, None).await,
+                    // Expired with the other leg executing, or cancelled outside
+                    // the bot; the other leg tells which
+                    OrderStatus::Canceled => self.bracket_ended(&symbol, &order_id).await,
+                    OrderStatus::Rejected => {
+                        log::warn!(
+                            "Bracket {} for {} was rejected, exiting at market",
+                            order_id,
+                            symbol
+                        );
+                        if let Err(e) = self.close_position(&symbol, leg).await {
+                            log::error!("Failed to exit {}: {}", symbol, e);
+                        }
+                    }
+                    OrderStatus::Pending | OrderStatus::PartiallyFilled => {}
+                },
+                Err(e) => log::warn!("Failed to check bracket {} for {}: {}", order_id, symbol, e),
+            }
+        }
+    }
+
+    // The tracked leg of a bracket ended unfilled. A filled leg closed the
+    // position; only when every leg ended unfilled is a new bracket placed.
+    async fn bracket_ended(&mut self, symbol: &str, order_id: &str) {
+        let legs = match self.exchange.bracket_legs(symbol, order_id).await {
+            Ok(legs) => legs,
+            Err(e) => {
+                log::warn!("Failed to check bracket {} for {}: {}", order_id, symbol, e);
+                return;
+            }
+        };
+        if let Some(filled) = legs
+            .iter()
+            .find(|leg| matches!(leg.status, OrderStatus::Filled))
+        {
+            self.bracket_filled(symbol, filled, None).await;
+        } else if legs
+            .iter()
+            .all(|leg| matches!(leg.status, OrderStatus::Canceled | OrderStatus::Rejected))
+        {
+            log::warn!(
+                "Bracket {} for {} ended unfilled, placing it again",
+                order_id,
+                symbol
+            );
+            self.rebracket(symbol).await;
+        }
+    }
+
+    // Puts a new bracket up for a position whose bracket is gone
+    async fn rebracket(&mut self, symbol: &str) {
+        let held = self
+            .state
+            .write()
+            .await
+            .positions
+            .get_mut(symbol)
+            .and_then(|position| {
+                position.bracket_order_id = None;
+                Some((position.side.clone(), position.quantity, position.bracket?))
+            });
+        let Some((side, quantity, bracket)) = held else {
+            return;
+        };
+        let (bracket, bracket_order_id) =
+            self.place_bracket(symbol, &side, quantity, &bracket).await;
+        if bracket_order_id.is_none() {
+            log::error!("{} is left without a bracket", symbol);
+        }
+        if let Some(position) = self.state.write().await.positions.get_mut(symbol) {
+            position.bracket = Some(bracket);
+            position.bracket_order_id = bracket_order_id;
+        }
+    }
+
+    // Sells positions whose trailing stop the price has fallen back through
+    pub async fn trail_stops(&mut self) {
+        let hit: Vec<(String, Decimal)> = {
+            let state = self.state.read().await;
+            state
+                .positions
+                .values()
+                .filter_map(|position| {
+                    let price = *state.last_prices.get(&position.symbol)?;
+                    position
+                        .trailing_stop_hit(price)
+                        .then(|| (position.symbol.clone(), price))
+                })
+                .collect()
+        };
+        if hit.is_empty() {
+            return;
+        }
+        self.actor = AuditActor::TrailingStop;
+        for (symbol, price) in hit {
+            log::info!("Trailing stop for {} hit at {}", symbol, price);
+            if let Err(e) = self.close_position(&symbol, price).await {
+                log::error!("Failed to exit {} on its trailing stop: {}", symbol, e);
+            }
+        }
+    }
+
+    // Moves the reported order along in the tracker, then acts on fills and
+    // cancels of resting entry orders, parent children and bracket legs.
+    // Reports that change nothing, e.g. for orders the ack already completed,
+    // are skipped.
+    pub async fn on_account_event(&mut self, event: &AccountEvent) {
+        let report = match event {
+            AccountEvent::Execution(report) => report,
+            AccountEvent::Balance(update) => {
+                log::info!("{} balance changed by {}", update.asset, update.delta);
+                return;
+            }
+            AccountEvent::Other => return,
+        };
+        let Some(tracked) = self.state.write().await.orders.on_report(report) else {
+            return;
+        };
+        let order_id = report.order_id.to_string();
+        let working = self
+            .state
+            .read()
+            .await
+            .working_orders
+            .get(&order_id)
+            .cloned();
+        if let Some(working) = working {
+            self.actor = AuditActor::Strategy {
+                name: working.signal.strategy.clone(),
+            };
+            self.stream_fill(report).await;
+            self.entry_progress(working, &tracked, report.event_time)
+                .await;
+        } else if let Some(symbol) = self.parent_of(&order_id).await {
+            self.stream_fill(report).await;
+            self.child_progress(&symbol, &tracked).await;
+        } else if report.order_list_id >= 0 && self.has_bracket(&report.symbol).await {
+            self.stream_fill(report).await;
+            if tracked.state == OrderState::Filled {
+                self.bracket_filled(&tracked.symbol, &tracked.response(), None)
+                    .await;
+            }
+        }
+    }
+
+    // Symbol of the parent order `order_id` is the resting child of
+    async fn parent_of(&self, order_id: &str) -> Option<String> {
+        self.state
+            .read()
+            .await
+            .parent_orders
+            .values()
+            .find(|parent| {
+                parent
+                    .working
+                    .as_ref()
+                    .is_some_and(|(child_id, _)| child_id == order_id)
+            })
+            .map(|parent| parent.signal.symbol.clone())
+    }
+
+    // A resting child completing puts the parent's next slice up right away
+    async fn child_progress(&mut self, symbol: &str, tracked: &TrackedOrder) {
+        let status = tracked.state;
+        if !status.is_final() {
+            return;
+        }
+        let response = tracked.response();
+        {
+            let mut state = self.state.write().await;
+            let Some(parent) = state.parent_orders.get_mut(symbol) else {
+                return;
+            };
+            parent.fills.extend(response.fills);
+            parent.working = None;
+            // Cancelled or expired by someone else; don't post into that
+            if status != OrderState::Filled {
+                log::info!(
+                    "Child order {} of {} ended unfilled: {:?}",
+                    response.order_id,
+                    parent.id,
+                    status
+                );
+                parent.remaining.clear();
+            }
+        }
+        self.work_parent_orders().await;
+    }
+
+    // Cancels a parent's resting child and books what it filled before that
+    async fn stop_child(&mut self, symbol: &str, child_id: &str) {
+        if let Err(e) = self.cancel_order(symbol, child_id).await {
+            // Most likely it filled in the meantime
+            log::warn!("Failed to cancel child order {}: {}", child_id, e);
+        }
+        let result = self.exchange.get_order_status(symbol, child_id).await;
+        let mut state = self.state.write().await;
+        let Some(parent) = state.parent_orders.get_mut(symbol) else {
+            return;
+        };
+        match result {
+            Ok(response) => parent.fills.extend(response.fills),
+            Err(e) => log::error!("Failed to read fills of child order {}: {}", child_id, e),
+        }
+        parent.working = None;
+    }
+
+    async fn has_bracket(&self, symbol: &str) -> bool {
+        self.state
+            .read()
+            .await
+            .positions
+            .get(symbol)
+            .is_some_and(|position| position.bracket_order_id.is_some())
+    }
+
+    // Journals and publishes an execution the stream reported
+    async fn stream_fill(&mut self, report: &ExecutionReport) {
+        let Some(fill) = report.fill() else {
+            return;
+        };
+        let order_id = report.order_id.to_string();
+        self.journal(JournalEvent::OrderFilled {
+            symbol: report.symbol.clone(),
+            order_id: order_id.clone(),
+            price: fill.price,
+            quantity: fill.quantity,
+            commission: fill.commission,
+            commission_asset: fill.commission_asset.clone(),
+        });
+        self.state.read().await.publish(BusMessage::Fill {
+            symbol: report.symbol.clone(),
+            order_id: order_id.clone(),
+            side: report.order_side(),
+            price: fill.price,
+            quantity: fill.quantity,
+            commission: fill.commission,
+            commission_asset: fill.commission_asset.clone(),
+        });
+    }
+
+    async fn entry_progress(
+        &mut self,
+        working: WorkingOrder,
+        tracked: &TrackedOrder,
+        event_time: i64,
+    ) {
+        let status = tracked.state;
+        if !status.is_final() {
+            return;
+        }
+        let response = tracked.response();
+        {
+            let mut state = self.state.write().await;
+            state.working_orders.remove(&working.order_id);
+            if status == OrderState::Filled {
+                let waited = event_time - working.placed_at * 1000;
+                state.latency.record(
+                    LatencyStage::AckToFill,
+                    Duration::from_millis(waited.max(0) as u64),
+                );
+            }
+            state.publish(BusMessage::Order {
+                symbol: working.symbol.clone(),
+                order_id: working.order_id.clone(),
+                status: format!("{:?}", status),
+                side: Some(working.side.clone()),
+                quantity: Some(working.quantity),
+            });
+        }
+        let quantity = if status == OrderState::Filled {
+            log::info!(
+                "Entry order {} for {} filled",
+                working.order_id,
+                working.symbol
+            );
+            // The tracker's fills, which also give the entry price; the
+            // ordered quantity only if none were seen
+            if tracked.filled_quantity > Decimal::ZERO {
+                tracked.filled_quantity
+            } else {
+                working.quantity
+            }
+        } else if tracked.filled_quantity > Decimal::ZERO {
+            // Cancelled or expired part way; what filled is held all the same
+            log::info!(
+                "Entry order {} for {} ended {:?} with {} of {} filled",
+                working.order_id,
+                working.symbol,
+                status,
+                tracked.filled_quantity,
+                working.quantity
+            );
+            tracked.filled_quantity
+        } else {
+            log::info!(
+                "Entry order {} for {} ended unfilled: {:?}",
+                working.order_id,
+                working.symbol,
+                status
+            );
+            return;
+        };
+        if let Err(e) = self
+            .entry_filled(&working.signal, quantity, &response)
+            .await
+        {
+            log::error!("Failed to track filled entry {}: {}", working.order_id, e);
+        }
+    }
+
+    // A bracket leg filling closed the position on the exchange's side. `price`
+    // stands in for the fill price when the fills aren't known.
+    async fn bracket_filled(
+        &mut self,
+        symbol: &str,
+        response: &OrderResponse,
+        price: Option<Decimal>,
+    ) {
+        let mut state = self.state.write().await;
+        let Some(position) = state.positions.remove(symbol) else {
+            return;
+        };
+        let exit_price = response
+            .average_fill_price()
+            .or(price)
+            .unwrap_or(position.entry_price);
+        log::info!(
+            "Bracket order {} for {} filled at {}, position closed",
+            response.order_id,
+            symbol,
+            exit_price
+        );
+        state.position_changed(PositionEvent::BracketExit, &position, exit_price);
+        let exit_fees = state.total_fees(symbol, response);
+        state.record_trade(position, exit_price, exit_fees);
+    }
+
+    fn journal(&self, event: JournalEvent) {
+        journal::record(&self.journal, event);
+    }
+
+    async fn risk_rejected(&self, symbol: &str, reason: String) {
+        self.state.read().await.publish(BusMessage::Risk {
+            symbol: symbol.to_string(),
+            reason: reason.clone(),
+        });
+        self.journal(JournalEvent::RiskRejected {
+            symbol: symbol.to_string(),
+            reason,
+        });
+    }
+
+    fn audit(&self, action: AuditAction, outcome: Result<&str, &TradingError>) {
+        audit::record(&self.audit, &self.actor, action, outcome);
+    }
+
+    async fn cancel_order(&mut self, symbol: &str, order_id: &str) -> Result<(), TradingError> {
+        let result = self.exchange.cancel_order(symbol, order_id).await;
+        self.audit(
+            AuditAction::Cancel {
+                symbol: symbol.to_string(),
+                order_id: order_id.to_string(),
+            },
+            result.as_ref().map(|_| order_id),
+        );
+        if result.is_ok() {
+            let mut state = self.state.write().await;
+            state.orders.canceled(order_id);
+            state.publish(BusMessage::Order {
+                symbol: symbol.to_string(),
+                order_id: order_id.to_string(),
+                status: format!("{:?}", OrderStatus::Canceled),
+                side: None,
+                quantity: None,
+            });
+        }
+        result
+    }
+
+    // Sends the order, retrying when it failed on the way. Whether a failed
+    // attempt reached the exchange is unknown, so its client order id is looked
+    // up first and the order only sent again once the exchange says it has none.
+    async fn submit(&mut self, order: &Order) -> Result<OrderResponse, TradingError> {
+        let mut result = self.exchange.send_order(order).await;
+        let mut attempt = 1;
+        while let Err(e) = &result {
+            if !e.is_retryable() {
+                break;
+            }
+            let Some(client_id) = &order.client_order_id else {
+                break;
+            };
+            if attempt >= self.settings.retry.max_attempts {
+                break;
+            }
+            let backoff = self.settings.retry.backoff_ms << (attempt - 1).min(16);
+            log::warn!(
+                "Order {} for {} failed ({}), checking it again in {}ms",
+                client_id,
+                order.symbol,
+                e,
+                backoff
+            );
+            tokio::time::sleep(Duration::from_millis(backoff)).await;
+            attempt += 1;
+            // On the order's own venue, where a lost order would be
+            match self
+                .exchange
+                .get_order_status(&order.symbol, client_id)
+                .await
+            {
+                Ok(response) => {
+                    log::info!("Order {} reached the exchange after all", client_id);
+                    result = Ok(response);
+                }
+                // The exchange never saw it, so sending again can't fill twice
+                Err(TradingError::UnknownOrder(_)) => {
+                    log::info!("Resending order {} (attempt {})", client_id, attempt);
+                    result = self.exchange.send_order(order).await;
+                }
+                // Still unknown; look again rather than risk a second fill
+                Err(e) if e.is_retryable() => result = Err(e),
+                // Can't tell whether it got there; leave it unresolved
+                Err(e) => {
+                    log::error!(
+                        "Order {} for {} left unresolved, its lookup failed: {}",
+                        client_id,
+                        order.symbol,
+                        e
+                    );
+                    result = Err(TradingError::NetworkError(format!(
+                        "Outcome of order {} unknown: {}",
+                        client_id, e
+                    )));
+                    break;
+                }
+            }
+        }
+        result
+    }
+
+    // Send an order, journaling and storing it together with the exchange's answer
+    async fn send_order(&mut self, order: &Order) -> Result<OrderResponse, TradingError> {
+        self.journal(JournalEvent::OrderSubmitted {
+            symbol: order.symbol.clone(),
+            side: order.side.clone(),
+            order_type: order.order_type.to_string(),
+            quantity: order.quantity,
+        });
+        let sent_at = Instant::now();
+        let signal_to_submit = self
+            .signal_received_at
+            .take()
+            .map(|received| sent_at - received);
+        let dedup_signal = self.dedup_signal.take();
+        let result = self.submit(order).await;
+        let submit_to_ack = sent_at.elapsed();
+        self.audit(
+            AuditAction::Submit {
+                symbol: order.symbol.clone(),
+                side: order.side.clone(),
+                order_type: order.order_type.to_string(),
+                quantity: order.quantity,
+            },
+            result.as_ref().map(|response| response.order_id.as_str()),
+        );
+        let response = result?;
+        if let (Some(dedup), Some(signal)) = (&self.dedup, dedup_signal) {
+            storage::log_error(dedup.record(&signal, &response.order_id));
+        }
+        self.journal(JournalEvent::OrderAcknowledged {
+            symbol: order.symbol.clone(),
+            order_id: response.order_id.clone(),
+            status: format!("{:?}", response.status),
+        });
+        for fill in &response.fills {
+            self.journal(JournalEvent::OrderFilled {
+                symbol: order.symbol.clone(),
+                order_id: response.order_id.clone(),
+                price: fill.price,
+                quantity: fill.quantity,
+                commission: fill.commission,
+                commission_asset: fill.commission_asset.clone(),
+            });
+        }
+        let mut state = self.state.write().await;
+        if let Some(latency) = signal_to_submit {
+            state.latency.record(LatencyStage::SignalToSubmit, latency);
+        }
+        state
+            .latency
+            .record(LatencyStage::SubmitToAck, submit_to_ack);
+        // Resting orders' fills are timed when the account stream reports them
+        if !response.fills.is_empty() {
+            state
+                .latency
+                .record(LatencyStage::AckToFill, Duration::ZERO);
+        }
+        state.orders.submitted(order, &response);
+        state.persist(|store| store.record_order(order, &response));
+        state.publish(BusMessage::Order {
+            symbol: order.symbol.clone(),
+            order_id: response.order_id.clone(),
+            status: format!("{:?}", response.status),
+            side: Some(order.side.clone()),
+            quantity: Some(order.quantity),
+        });
+        for fill in &response.fills {
+            state.publish(BusMessage::Fill {
+                symbol: order.symbol.clone(),
+                order_id: response.order_id.clone(),
+                side: order.side.clone(),
+                price: fill.price,
+                quantity: fill.quantity,
+                commission: fill.commission,
+                commission_asset: fill.commission_asset.clone(),
+            });
+        }
+        Ok(response)
+    }
+
+    // Refused with a risk rejection when the exchange's minimums aren't met
+    async fn entry_quantity(&self, signal: &TradingSignal) -> Result<Decimal, TradingError> {
+        let sized = self
+            .calculate_order_size(&signal.symbol, signal.price)
+            .and_then(|quantity| {
+                if quantity > Decimal::ZERO {
+                    Ok(quantity)
+                } else {
+                    Err("order size too small".to_string())
+                }
+            });
+        match sized {
+            Ok(quantity) => Ok(quantity),
+            Err(reason) => {
+                let reason = format!("{} at {}: {}", signal.symbol, signal.price, reason);
+                self.risk_rejected(&signal.symbol, reason.clone()).await;
+                Err(TradingError::OrderError(reason))
+            }
+        }
+    }
+
+    async fn open_position(
+        &mut self,
+        signal: &TradingSignal,
+        order_type: OrderType,
+    ) -> Result<(), TradingError> {
+        let quantity = self.entry_quantity(signal).await?;
+        let notional = quantity * signal.price;
+        let algorithm: Option<(Box<dyn ExecutionAlgorithm>, Option<Decimal>)> =
+            match (&order_type, &self.settings.algo, &self.settings.iceberg) {
+                (OrderType::Market, Some(algo), _) if notional >= algo.min_notional => {
+                    Some((algo.build()?, None))
+                }
+                (OrderType::Limit(price), _, Some(iceberg)) if notional >= iceberg.min_notional => {
+                    let iceberg = Iceberg {
+                        visible_pct: iceberg.visible_pct,
+                    };
+                    Some((Box::new(iceberg), Some(*price)))
+                }
+                _ => None,
+            };
+        if let Some((algorithm, limit)) = algorithm {
+            let now = chrono::Utc::now().timestamp_millis();
+            let parent = ParentOrder::new(algorithm.as_ref(), signal, quantity, limit, now);
+            log::info!(
+                "Working {} {} entry as {} {} slices",
+                quantity,
+                signal.symbol,
+                parent.remaining.len(),
+                algorithm.name()
+            );
+            self.journal(JournalEvent::ParentOrderStarted {
+                symbol: signal.symbol.clone(),
+                parent_id: parent.id.clone(),
+                algorithm: parent.algorithm.clone(),
+                quantity,
+            });
+            self.state
+                .write()
+                .await
+                .parent_orders
+                .insert(signal.symbol.clone(), parent);
+            // The first slice is due right away
+            self.work_parent_orders().await;
+            return Ok(());
+        }
+
+        let order = Order {
+            symbol: signal.symbol.clone(),
+            quantity,
+            order_type,
+            side: entry_side(signal),
+            client_order_id: Some(client_order_id(&format!(
+                "entry:{}:{}:{}",
+                signal.strategy, signal.symbol, signal.timestamp
+            ))),
+            time_in_force: self.settings.time_in_force,
+            post_only: self.settings.post_only,
+        };
+        let response = self.send_order(&order).await?;
+        match response.status {
+            OrderStatus::Filled => self.entry_filled(signal, quantity, &response).await,
+            OrderStatus::Pending | OrderStatus::PartiallyFilled => {
+                if let OrderType::Limit(price) = order.order_type {
+                    let now = chrono::Utc::now().timestamp();
+                    self.state.write().await.working_orders.insert(
+                        response.order_id.clone(),
+                        WorkingOrder {
+                            order_id: response.order_id.clone(),
+                            symbol: signal.symbol.clone(),
+                            side: entry_side(signal),
+                            quantity,
+                            price,
+                            placed_at: now,
+                            expires_at: now + self.settings.order_ttl_secs,
+                            signal: signal.clone(),
+                        },
+                    );
+                }
+                Ok(())
+            }
+            // IOC remainder cancelled after a partial fill
+            _ if !response.fills.is_empty() => {
+                let filled = response.fills.iter().map(|fill| fill.quantity).sum();
+                self.entry_filled(signal, filled, &response).await
+            }
+            _ => {
+                log::warn!(
+                    "Entry order {} for {} not filled: {:?}",
+                    response.order_id,
+                    signal.symbol,
+                    response.status
+                );
+                Ok(())
+            }
+        }
+    }
+
+    // Average price, quote currency fees and the quantity actually held after
+    // an entry filled `quantity`
+    async fn entry_fill(
+        &self,
+        symbol: &str,
+        side: &OrderSide,
+        quantity: Decimal,
+        response: &OrderResponse,
+        price: Decimal,
+    ) -> (Decimal, Decimal, Decimal) {
+        let entry_price = response.average_fill_price().unwrap_or(price);
+        let entry_fees = self.state.read().await.total_fees(symbol, response);
+        // Commission taken in the base asset reduces what a long actually holds
+        let (base, _) = split_symbol(symbol);
+        let base_commission: Decimal = response
+            .fills
+            .iter()
+            .filter(|fill| *side == OrderSide::Buy && fill.commission_asset == base)
+            .map(|fill| fill.commission)
+            .sum();
+        // The bracket is refused off the step size
+        let quantity = self.round_quantity(symbol, quantity - base_commission);
+        (entry_price, entry_fees, quantity)
+    }
+
+    // Puts a bracket closing `quantity` of a `side` position on the exchange at
+    // the symbol's tick size. Returns the levels as placed and the order id,
+    // None if it was refused.
+    async fn place_bracket(
+        &mut self,
+        symbol: &str,
+        side: &OrderSide,
+        quantity: Decimal,
+        bracket: &Bracket,
+    ) -> (Bracket, Option<String>) {
+        let bracket = Bracket {
+            stop_loss: self.round_price(symbol, bracket.stop_loss),
+            take_profit: self.round_price(symbol, bracket.take_profit),
+        };
+        let result = self
+            .exchange
+            .place_oco_order(symbol, side.opposite(), quantity, &bracket)
+            .await;
+        self.audit(
+            AuditAction::SubmitBracket {
+                symbol: symbol.to_string(),
+                quantity,
+                stop_loss: bracket.stop_loss,
+                take_profit: bracket.take_profit,
+            },
+            result.as_ref().map(|response| response.order_id.as_str()),
+        );
+        let bracket_order_id = match result {
+            Ok(response) => Some(response.order_id),
+            Err(e) => {
+                log::error!("Failed to place OCO bracket for {}: {}", symbol, e);
+                None
+            }
+        };
+        (bracket, bracket_order_id)
+    }
+
+    fn trailing_stop(&self, entry_price: Decimal, side: &OrderSide) -> Option<OrderType> {
+        let direction = match side {
+            OrderSide::Buy => Decimal::ONE,
+            OrderSide::Sell => Decimal::NEGATIVE_ONE,
+        };
+        self.settings
+            .trailing_stop
+            .as_ref()
+            .map(|trailing| OrderType::TrailingStop {
+                activation_price: entry_price
+                    * (Decimal::ONE + direction * trailing.activation_pct / Decimal::ONE_HUNDRED),
+                callback_rate: trailing.callback_rate,
+            })
+    }
+
+    // The signal's levels when they lie on the right sides of the entry for
+    // a short, else the risk limits'. Strategies set them with longs in mind.
+    fn entry_bracket(
+        &self,
+        signal: &TradingSignal,
+        entry_price: Decimal,
+        side: &OrderSide,
+    ) -> Bracket {
+        match Bracket::from_signal(signal) {
+            Some(bracket)
+                if *side == OrderSide::Buy
+                    || (bracket.take_profit < entry_price && entry_price < bracket.stop_loss) =>
+            {
+                bracket
+            }
+            _ => Bracket::from_risk(entry_price, side, &self.risk),
+        }
+    }
+
+    // Tracks the position an entry order filled and puts its bracket on the exchange
+    async fn entry_filled(
+        &mut self,
+        signal: &TradingSignal,
+        quantity: Decimal,
+        response: &OrderResponse,
+    ) -> Result<(), TradingError> {
+        let side = entry_side(signal);
+        let (entry_price, entry_fees, quantity) = self
+            .entry_fill(&signal.symbol, &side, quantity, response, signal.price)
+            .await;
+        let bracket = self.entry_bracket(signal, entry_price, &side);
+        let (bracket, bracket_order_id) = self
+            .place_bracket(&signal.symbol, &side, quantity, &bracket)
+            .await;
+
+        let short = side == OrderSide::Sell;
+        let position = Position {
+            symbol: signal.symbol.clone(),
+            strategy: signal.strategy.clone(),
+            side: side.clone(),
+            quantity,
+            entry_price,
+            bracket: Some(bracket),
+            bracket_order_id,
+            opened_at: signal.timestamp,
+            entry_fees,
+            trailing_stop: self.trailing_stop(entry_price, &side),
+            trailing_peak: None,
+            scale_ins: 0,
+            borrowed: if short { quantity } else { Decimal::ZERO },
+            interest_rate_pct: self
+                .settings
+                .margin
+                .as_ref()
+                .filter(|_| short)
+                .map_or(Decimal::ZERO, |margin| margin.daily_interest_pct),
+            interest: Decimal::ZERO,
+            interest_accrued_at: chrono::Utc::now().timestamp(),
+        };
+        let mut state = self.state.write().await;
+        state.position_changed(PositionEvent::Opened, &position, entry_price);
+        state.positions.insert(signal.symbol.clone(), position);
+        Ok(())
+    }
+
+    // Adds to an open position at market. The entry price becomes the
+    // weighted average of both entries and the bracket is moved to cover the
+    // combined quantity.
+    async fn scale_in(&mut self, signal: &TradingSignal) -> Result<(), TradingError> {
+        let quantity = self.entry_quantity(signal).await?;
+        let side = entry_side(signal);
+        let order = Order {
+            symbol: signal.symbol.clone(),
+            quantity,
+            order_type: OrderType::Market,
+            side: side.clone(),
+            client_order_id: Some(client_order_id(&format!(
+                "scale-in:{}:{}:{}",
+                signal.strategy, signal.symbol, signal.timestamp
+            ))),
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+        };
+        let response = self.send_order(&order).await?;
+        let filled: Decimal = response.fills.iter().map(|fill| fill.quantity).sum();
+        if filled <= Decimal::ZERO {
+            log::warn!(
+                "Scale-in order {} for {} not filled: {:?}",
+                response.order_id,
+                signal.symbol,
+                response.status
+            );
+            return Ok(());
+        }
+
+        // Taken out while its bracket is replaced, like in close_position
+        let removed = self.state.write().await.positions.remove(&signal.symbol);
+        let Some(mut position) = removed else {
+            // Its bracket closed it meanwhile; what we just got is a new position
+            return self.entry_filled(signal, filled, &response).await;
+        };
+        let (price, fees, filled) = self
+            .entry_fill(&signal.symbol, &side, filled, &response, signal.price)
+            .await;
+        // Interest so far is on the old borrow only
+        position.accrue_interest(price, chrono::Utc::now().timestamp());
+        if let Err(e) = position.add(filled, price, fees) {
+            log::error!(
+                "Failed to add {} to the {} position: {}",
+                filled,
+                signal.symbol,
+                e
+            );
+        }
+        position.quantity = self.round_quantity(&signal.symbol, position.quantity);
+
+        if let Some(order_id) = position.bracket_order_id.take() {
+            if let Err(e) = self.cancel_order(&signal.symbol, &order_id).await {
+                log::warn!(
+                    "Failed to cancel bracket {} for {}: {}",
+                    order_id,
+                    signal.symbol,
+                    e
+                );
+            }
+        }
+        // The new signal's levels, or the risk limits around the new average entry
+        let bracket = self.entry_bracket(signal, position.entry_price, &side);
+        let (bracket, bracket_order_id) = self
+            .place_bracket(&signal.symbol, &side, position.quantity, &bracket)
+            .await;
+        position.bracket = Some(bracket);
+        position.bracket_order_id = bracket_order_id;
+        if position.trailing_peak.is_none() {
+            position.trailing_stop = self.trailing_stop(position.entry_price, &side);
+        }
+        log::info!(
+            "Scaled into {}: {} at {}, now {} at an average {}",
+            signal.symbol,
+            filled,
+            price,
+            position.quantity,
+            position.entry_price
+        );
+
+        let mut state = self.state.write().await;
+        state.position_changed(PositionEvent::Increased, &position, price);
+        state.positions.insert(signal.symbol.clone(), position);
+        Ok(())
+    }
+
+    // Exits `fraction` of a position at market and books that slice as a trade
+    // of its own, with its share of the entry fees and borrow interest. The
+    // rest keeps its average entry and gets its bracket back, resized.
+    async fn reduce_position(
+        &mut self,
+        symbol: &str,
+        price: Decimal,
+        fraction: Decimal,
+    ) -> Result<(), TradingError> {
+        let removed = self.state.write().await.positions.remove(symbol);
+        let Some(mut position) = removed else {
+            return Ok(());
+        };
+        let quantity = self.round_quantity(symbol, position.quantity * fraction);
+        let rest = self.round_quantity(symbol, position.quantity - quantity);
+        // Neither part may fall below the exchange's minimums
+        let splittable = quantity > Decimal::ZERO
+            && rest > Decimal::ZERO
+            && self.exchange.symbol_rules(symbol).is_none_or(|rules| {
+                rules.check(quantity, price).is_ok() && rules.check(rest, price).is_ok()
+            });
+        if !splittable {
+            log::info!("{} position too small to split, closing all of it", symbol);
+            self.state
+                .write()
+                .await
+                .positions
+                .insert(symbol.to_string(), position);
+            return self.close_position(symbol, price).await;
+        }
+
+        if let Some(order_id) = position.bracket_order_id.take() {
+            if let Err(e) = self.cancel_order(symbol, &order_id).await {
+                log::warn!(
+                    "Failed to cancel bracket {} for {}: {}",
+                    order_id,
+                    symbol,
+                    e
+                );
+            }
+        }
+        let order = Order {
+            symbol: symbol.to_string(),
+            quantity,
+            order_type: OrderType::Market,
+            side: position.side.opposite(),
+            // Distinct per slice, since the quantity held differs each time
+            client_order_id: Some(client_order_id(&format!(
+                "reduce:{}:{}:{}:{}",
+                position.strategy, symbol, position.opened_at, position.quantity
+            ))),
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+        };
+        let result = self.send_order(&order).await;
+        if let Ok(response) = &result {
+            let exit_price = response.average_fill_price().unwrap_or(price);
+            match position.split(quantity) {
+                Ok(slice) => {
+                    position.quantity = rest;
+                    let mut state = self.state.write().await;
+                    state.position_changed(PositionEvent::Reduced, &slice, exit_price);
+                    let exit_fees = state.total_fees(symbol, response);
+                    state.record_trade(slice, exit_price, exit_fees);
+                }
+                Err(e) => log::error!("Failed to book the {} exit of {}: {}", quantity, symbol, e),
+            }
+        }
+
+        // Still holding the rest, or all of it if the exit failed
+        if let Some(bracket) = position.bracket {
+            let (bracket, bracket_order_id) = self
+                .place_bracket(symbol, &position.side, position.quantity, &bracket)
+                .await;
+            position.bracket = Some(bracket);
+            position.bracket_order_id = bracket_order_id;
+        }
+        self.state
+            .write()
+            .await
+            .positions
+            .insert(symbol.to_string(), position);
+        result.map(|_| ())
+    }
+
+    async fn close_position(&mut self, symbol: &str, price: Decimal) -> Result<(), TradingError> {
+        // Taken out up front so the monitor can't close it a second time meanwhile
+        let removed = self.state.write().await.positions.remove(symbol);
+        let mut position = match removed {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+
+        // Free the quantity reserved by the bracket before exiting
+        let mut bracket_canceled = false;
+        if let Some(order_id) = position.bracket_order_id.clone() {
+            if let Err(e) = self.cancel_order(symbol, &order_id).await {
+                log::warn!(
+                    "Failed to cancel bracket {} for {}: {}",
+                    order_id,
+                    symbol,
+                    e
+                );
+                // Exiting on top of a bracket that still rests, or that has
+                // closed the position already, would sell twice
+                let status = self.exchange.get_order_status(symbol, &order_id).await;
+                match status {
+                    Ok(response) if matches!(response.status, OrderStatus::Filled) => {
+                        self.state
+                            .write()
+                            .await
+                            .positions
+                            .insert(symbol.to_string(), position);
+                        self.bracket_filled(symbol, &response, None).await;
+                        return Ok(());
+                    }
+                    Ok(response)
+                        if matches!(
+                            response.status,
+                            OrderStatus::Canceled | OrderStatus::Rejected
+                        ) => {}
+                    _ => {
+                        self.state
+                            .write()
+                            .await
+                            .positions
+                            .insert(symbol.to_string(), position);
+                        return Err(e);
+                    }
+                }
+            }
+            bracket_canceled = true;
+        }
+
+        let order = Order {
+            symbol: symbol.to_string(),
+            quantity: position.quantity,
+            order_type: OrderType::Market,
+            side: position.side.opposite(),
+            client_order_id: Some(client_order_id(&format!(
+                "exit:{}:{}:{}",
+                position.strategy, symbol, position.opened_at
+            ))),
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+        };
+        let response = match self.send_order(&order).await {
+            Ok(response) => response,
+            Err(e) => {
+                // Still holding it; keep tracking so the next signal can retry,
+                // protected again by a new bracket. Without one, check_brackets
+                // exits at market once a level is reached.
+                if bracket_canceled {
+                    position.bracket_order_id = None;
+                    if let Some(bracket) = position.bracket {
+                        let (bracket, bracket_order_id) = self
+                            .place_bracket(symbol, &position.side, position.quantity, &bracket)
+                            .await;
+                        position.bracket = Some(bracket);
+                        position.bracket_order_id = bracket_order_id;
+                    }
+                }
+                self.state
+                    .write()
+                    .await
+                    .positions
+                    .insert(symbol.to_string(), position);
+                return Err(e);
+            }
+        };
+        let exit_price = response.average_fill_price().unwrap_or(price);
+        let mut state = self.state.write().await;
+        state.position_changed(PositionEvent::Closed, &position, exit_price);
+        let exit_fees = state.total_fees(symbol, &response);
+        state.record_trade(position, exit_price, exit_fees);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::ExchangeRouter;
+    use crate::mock::{MockExchange, ScriptedFill};
+    use rust_decimal_macros::dec;
+
+    const SYMBOL: &str = "BTCUSDT";
+
+    fn signal(action: TradeAction, price: Decimal) -> TradingSignal {
+        TradingSignal {
+            symbol: SYMBOL.to_string(),
+            strategy: "test".to_string(),
+            action,
+            price,
+            timestamp: 0,
+            stop_loss: None,
+            take_profit: None,
+        }
+    }
+
+    async fn executor(exchange: &MockExchange) -> TradeExecutor<MockExchange> {
+        let mut exchange = exchange.clone();
+        exchange.connect().await.unwrap();
+        let mut executor = TradeExecutor::new(exchange, RiskParameters::default());
+        executor.set_execution_settings(ExecutionSettings {
+            retry: RetrySettings {
+                max_attempts: 3,
+                backoff_ms: 1,
+            },
+            ..ExecutionSettings::default()
+        });
+        executor
+    }
+
+    // Fee-free exchange at 100 with a position bought through `executor`
+    async fn holding() -> (MockExchange, TradeExecutor<MockExchange>) {
+        let exchange = MockExchange::new(dec!(10_000)).with_fee_rate(Decimal::ZERO);
+        exchange.set_price(SYMBOL, Decimal::ONE_HUNDRED);
+        let mut executor = executor(&exchange).await;
+        executor
+            .handle_signal(&signal(TradeAction::Buy, Decimal::ONE_HUNDRED))
+            .await
+            .unwrap();
+        (exchange, executor)
+    }
+
+    fn position(side: OrderSide, quantity: Decimal, entry_price: Decimal) -> Position {
+        Position {
+            symbol: SYMBOL.to_string(),
+            strategy: "test".to_string(),
+            side,
+            quantity,
+            entry_price,
+            bracket: None,
+            bracket_order_id: Some("bracket".to_string()),
+            opened_at: 0,
+            entry_fees: Decimal::ZERO,
+            trailing_stop: None,
+            trailing_peak: None,
+            scale_ins: 0,
+            borrowed: Decimal::ZERO,
+            interest_rate_pct: Decimal::ZERO,
+            interest: Decimal::ZERO,
+            interest_accrued_at: 0,
+        }
+    }
+
+    #[test]
+    fn unusable_amounts_are_refused_by_add_and_split() {
+        let mut held = position(OrderSide::Buy, dec!(2), Decimal::ONE_HUNDRED);
+        assert!(held
+            .add(Decimal::ZERO, Decimal::ONE_HUNDRED, Decimal::ZERO)
+            .is_err());
+        assert!(held
+            .add(dec!(-1), Decimal::ONE_HUNDRED, Decimal::ZERO)
+            .is_err());
+        assert!(held.split(dec!(-1)).is_err());
+        assert!(held.split(dec!(3)).is_err());
+        // Nothing changed on the way
+        assert_eq!(
+            (held.quantity, held.entry_price, held.scale_ins),
+            (dec!(2), Decimal::ONE_HUNDRED, 0)
+        );
+
+        let mut empty = position(OrderSide::Buy, Decimal::ZERO, Decimal::ONE_HUNDRED);
+        assert!(empty.split(Decimal::ZERO).is_err());
+        empty.add(Decimal::ONE, dec!(90), Decimal::ZERO).unwrap();
+        assert_eq!(
+            (empty.quantity, empty.entry_price),
+            (Decimal::ONE, dec!(90))
+        );
+    }
+
+    #[test]
+    fn adding_averages_the_entry_price_and_fees() {
+        let mut long = position(OrderSide::Buy, dec!(2), Decimal::ONE_HUNDRED);
+        long.entry_fees = dec!(0.2);
+        long.add(Decimal::ONE, dec!(130), dec!(0.13)).unwrap();
+        assert_eq!(
+            (
+                long.quantity,
+                long.entry_price,
+                long.entry_fees,
+                long.scale_ins
+            ),
+            (dec!(3), dec!(110), dec!(0.33), 1)
+        );
+        assert_eq!(long.borrowed, Decimal::ZERO);
+
+        // Shorts borrow what they add
+        let mut short = position(OrderSide::Sell, Decimal::ONE, Decimal::ONE_HUNDRED);
+        short.borrowed = Decimal::ONE;
+        short.add(Decimal::ONE, dec!(80), Decimal::ZERO).unwrap();
+        assert_eq!((short.quantity, short.entry_price), (dec!(2), dec!(90)));
+        assert_eq!(short.borrowed, dec!(2));
+    }
+
+    #[test]
+    fn splitting_prorates_fees_borrow_and_interest() {
+        let mut held = position(OrderSide::Sell, dec!(4), Decimal::ONE_HUNDRED);
+        held.entry_fees = dec!(0.4);
+        held.borrowed = dec!(4);
+        held.interest = dec!(0.08);
+        let piece = held.split(Decimal::ONE).unwrap();
+        assert_eq!(
+            (
+                piece.quantity,
+                piece.entry_fees,
+                piece.borrowed,
+                piece.interest
+            ),
+            (Decimal::ONE, dec!(0.1), Decimal::ONE, dec!(0.02))
+        );
+        assert_eq!(
+            (held.quantity, held.entry_fees, held.borrowed, held.interest),
+            (dec!(3), dec!(0.3), dec!(3), dec!(0.06))
+        );
+        assert_eq!(piece.entry_price, Decimal::ONE_HUNDRED);
+        // The bracket still covers what is left
+        assert_eq!(piece.bracket_order_id, None);
+        assert_eq!(held.bracket_order_id.as_deref(), Some("bracket"));
+    }
+
+    #[test]
+    fn splitting_the_whole_position_or_none_of_it() {
+        let mut held = position(OrderSide::Buy, dec!(2), Decimal::ONE_HUNDRED);
+        held.entry_fees = dec!(0.2);
+        assert!(held.split(Decimal::ZERO).is_err());
+        assert_eq!((held.quantity, held.entry_fees), (dec!(2), dec!(0.2)));
+
+        let piece = held.split(dec!(2)).unwrap();
+        assert_eq!((piece.quantity, piece.entry_fees), (dec!(2), dec!(0.2)));
+        assert_eq!(
+            (held.quantity, held.entry_fees),
+            (Decimal::ZERO, Decimal::ZERO)
+        );
+    }
+
+    #[tokio::test]
+    async fn balance_reads_the_exchange() {
+        let exchange = MockExchange::new(dec!(10_000)).with_fee_rate(Decimal::ZERO);
+        exchange.set_price(SYMBOL, Decimal::ONE_HUNDRED);
+        let mut executor = executor(&exchange).await;
+        assert_eq!(executor.balance().await.unwrap(), dec!(10_000));
+
+        executor
+            .handle_signal(&signal(TradeAction::Buy, Decimal::ONE_HUNDRED))
+            .await
+            .unwrap();
+        // The default risk commits 100 of quote per entry
+        assert_eq!(executor.balance().await.unwrap(), dec!(9900));
+    }
+
+    #[tokio::test]
+    async fn an_order_the_exchange_never_saw_is_resent() {
+        let exchange = MockExchange::new(dec!(10_000)).with_fee_rate(Decimal::ZERO);
+        exchange.set_price(SYMBOL, Decimal::ONE_HUNDRED);
+        exchange.fail_next(TradingError::NetworkError("timed out".to_string()));
+        let mut executor = executor(&exchange).await;
+        executor
+            .handle_signal(&signal(TradeAction::Buy, Decimal::ONE_HUNDRED))
+            .await
+            .unwrap();
+
+        // The lookup answered unknown, so the entry went out a second time
+        assert_eq!(exchange.orders().len(), 2);
+        assert!(executor.positions().await.contains_key(SYMBOL));
+        assert_eq!(exchange.balance(), dec!(9900));
+    }
+
+    #[tokio::test]
+    async fn an_order_whose_reply_was_lost_is_not_resent() {
+        let exchange = MockExchange::new(dec!(10_000)).with_fee_rate(Decimal::ZERO);
+        exchange.set_price(SYMBOL, Decimal::ONE_HUNDRED);
+        exchange.push_fill(ScriptedFill::Lost(TradingError::NetworkError(
+            "connection reset".to_string(),
+        )));
+        let mut executor = executor(&exchange).await;
+        executor
+            .handle_signal(&signal(TradeAction::Buy, Decimal::ONE_HUNDRED))
+            .await
+            .unwrap();
+
+        assert_eq!(exchange.orders().len(), 1);
+        assert_eq!(executor.positions().await[SYMBOL].quantity, Decimal::ONE);
+        assert_eq!(exchange.balance(), dec!(9900));
+    }
+
+    #[tokio::test]
+    async fn a_lost_order_is_looked_up_on_its_own_venue() {
+        let binance = MockExchange::new(dec!(10_000)).with_fee_rate(Decimal::ZERO);
+        let kraken = MockExchange::new(dec!(10_000)).with_fee_rate(Decimal::ZERO);
+        kraken.set_price(SYMBOL, Decimal::ONE_HUNDRED);
+        kraken.push_fill(ScriptedFill::Lost(TradingError::NetworkError(
+            "timed out".to_string(),
+        )));
+        let mut router = ExchangeRouter::new("binance", binance.clone());
+        router.add("kraken", kraken.clone()).unwrap();
+        router.connect().await.unwrap();
+        let mut executor = TradeExecutor::new(router, RiskParameters::default());
+        executor.set_execution_settings(ExecutionSettings {
+            retry: RetrySettings {
+                max_attempts: 3,
+                backoff_ms: 1,
+            },
+            ..ExecutionSettings::default()
+        });
+        let signal = TradingSignal {
+            symbol: format!("kraken:{}", SYMBOL),
+            ..signal(TradeAction::Buy, Decimal::ONE_HUNDRED)
+        };
+        executor.handle_signal(&signal).await.unwrap();
+
+        // Kraken had it all along; the default venue was never asked to send it
+        assert_eq!(kraken.orders().len(), 1);
+        assert!(binance.orders().is_empty());
+        assert_eq!(
+            executor.positions().await[&signal.symbol].quantity,
+            Decimal::ONE
+        );
+    }
+
+    #[tokio::test]
+    async fn a_restored_bracket_is_canceled_on_its_own_venue() {
+        let binance = MockExchange::new(dec!(10_000)).with_fee_rate(Decimal::ZERO);
+        let kraken = MockExchange::new(dec!(10_000)).with_fee_rate(Decimal::ZERO);
+        kraken.set_price(SYMBOL, Decimal::ONE_HUNDRED);
+        let router = |binance: &MockExchange, kraken: &MockExchange| {
+            let mut router = ExchangeRouter::new("binance", binance.clone());
+            router.add("kraken", kraken.clone()).unwrap();
+            router
+        };
+        let symbol = format!("kraken:{}", SYMBOL);
+        let buy = TradingSignal {
+            symbol: symbol.clone(),
+            ..signal(TradeAction::Buy, Decimal::ONE_HUNDRED)
+        };
+        let mut executor = TradeExecutor::new(router(&binance, &kraken), RiskParameters::default());
+        executor.exchange.connect().await.unwrap();
+        executor.handle_signal(&buy).await.unwrap();
+        let snapshot = executor.state().read().await.snapshot();
+
+        // A restarted bot only has the snapshot and fresh connections
+        let mut executor = TradeExecutor::new(router(&binance, &kraken), RiskParameters::default());
+        executor.exchange.connect().await.unwrap();
+        executor.state().write().await.restore(snapshot);
+        let sell = TradingSignal {
+            symbol: symbol.clone(),
+            ..signal(TradeAction::Sell, Decimal::ONE_HUNDRED)
+        };
+        executor.handle_signal(&sell).await.unwrap();
+
+        assert!(executor.positions().await.is_empty());
+        assert!(kraken.get_open_orders(SYMBOL).await.unwrap().is_empty());
+        assert!(binance.orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn an_expired_entry_keeps_what_it_filled() {
+        let exchange = MockExchange::new(dec!(10_000)).with_fee_rate(Decimal::ZERO);
+        exchange.set_price(SYMBOL, Decimal::ONE_HUNDRED);
+        let mut executor = executor(&exchange).await;
+        executor.set_execution_settings(ExecutionSettings {
+            limit_entry_offset_pct: Some(Decimal::ONE),
+            order_ttl_secs: 0,
+            resignal_on_expiry: true,
+            ..ExecutionSettings::default()
+        });
+        exchange.push_fill(ScriptedFill::Fraction(dec!(0.4)));
+        executor
+            .handle_signal(&signal(TradeAction::Buy, Decimal::ONE_HUNDRED))
+            .await
+            .unwrap();
+        assert!(executor.positions().await.is_empty());
+
+        let resignals = executor.expire_orders().await;
+        assert!(resignals.is_empty());
+        let position = executor.positions().await[SYMBOL].clone();
+        assert_eq!(position.quantity, dec!(0.4));
+        // Bracketed for the filled part only
+        assert!(position.bracket_order_id.is_some());
+        assert!(executor.state().read().await.working_orders.is_empty());
+    }
+
+    #[tokio::test]
+    async fn sliced_entries_and_open_orders_survive_a_snapshot() {
+        let exchange = MockExchange::new(dec!(10_000)).with_fee_rate(Decimal::ZERO);
+        exchange.set_price(SYMBOL, Decimal::ONE_HUNDRED);
+        let settings = ExecutionSettings {
+            limit_entry_offset_pct: Some(Decimal::ONE),
+            iceberg: Some(IcebergSettings {
+                min_notional: Decimal::ZERO,
+                visible_pct: 50.0,
+            }),
+            ..ExecutionSettings::default()
+        };
+        let mut restarted = executor(&exchange).await;
+        restarted.set_execution_settings(settings.clone());
+        let mut executor = executor(&exchange).await;
+        executor.set_execution_settings(settings);
+        executor
+            .handle_signal(&signal(TradeAction::Buy, Decimal::ONE_HUNDRED))
+            .await
+            .unwrap();
+        let snapshot = executor.state().read().await.snapshot();
+        let (child_id, _) = snapshot.parent_orders[0].working.clone().unwrap();
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        restarted
+            .state()
+            .write()
+            .await
+            .restore(serde_json::from_str(&json).unwrap());
+
+        let state = restarted.state();
+        let state = state.read().await;
+        let parent = &state.parent_orders[SYMBOL];
+        assert_eq!(parent.working.as_ref().unwrap().0, child_id);
+        assert_eq!(parent.remaining.len(), 1);
+        let open = state.orders.open();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].order_id, child_id);
+    }
+
+    #[tokio::test]
+    async fn manual_orders_open_and_close_shorts() {
+        let exchange = MockExchange::new(dec!(10_000))
+            .with_fee_rate(Decimal::ZERO)
+            .with_shorts();
+        exchange.set_price(SYMBOL, Decimal::ONE_HUNDRED);
+        let mut executor = executor(&exchange).await;
+        executor.set_execution_settings(ExecutionSettings {
+            margin: Some(MarginSettings {
+                daily_interest_pct: Decimal::ZERO,
+            }),
+            ..ExecutionSettings::default()
+        });
+        let operator = || "operator".to_string();
+
+        executor
+            .manual_order(
+                SYMBOL,
+                TradeAction::Sell,
+                Some(Decimal::ONE_HUNDRED),
+                false,
+                operator(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(executor.positions().await[SYMBOL].side, OrderSide::Sell);
+        // Selling again would add to the short, not close it
+        assert!(executor
+            .manual_order(
+                SYMBOL,
+                TradeAction::Sell,
+                Some(Decimal::ONE_HUNDRED),
+                false,
+                operator()
+            )
+            .await
+            .is_err());
+
+        executor
+            .manual_order(
+                SYMBOL,
+                TradeAction::Buy,
+                Some(Decimal::ONE_HUNDRED),
+                false,
+                operator(),
+            )
+            .await
+            .unwrap();
+        assert!(executor.positions().await.is_empty());
+        assert_eq!(executor.state().read().await.trades.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn manual_trades_are_stamped_in_seconds() {
+        let exchange = MockExchange::new(dec!(10_000)).with_fee_rate(Decimal::ZERO);
+        exchange.set_price(SYMBOL, Decimal::ONE_HUNDRED);
+        let mut executor = executor(&exchange).await;
+        for action in [TradeAction::Buy, TradeAction::Sell] {
+            executor
+                .manual_order(
+                    SYMBOL,
+                    action,
+                    Some(Decimal::ONE_HUNDRED),
+                    false,
+                    "operator".to_string(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let state = executor.state();
+        let state = state.read().await;
+        let trade = &state.trades[0];
+        let now = chrono::Utc::now().timestamp();
+        assert!((now - trade.opened_at).abs() <= 5);
+        assert!((now - trade.closed_at).abs() <= 5);
+    }
+
+    #[tokio::test]
+    async fn a_close_while_flat_opens_nothing() {
+        let exchange = MockExchange::new(dec!(10_000))
+            .with_fee_rate(Decimal::ZERO)
+            .with_shorts();
+        exchange.set_price(SYMBOL, Decimal::ONE_HUNDRED);
+        let mut executor = executor(&exchange).await;
+        executor.set_execution_settings(ExecutionSettings {
+            margin: Some(MarginSettings {
+                daily_interest_pct: Decimal::ZERO,
+            }),
+            ..ExecutionSettings::default()
+        });
+
+        executor
+            .handle_signal(&signal(TradeAction::Close, Decimal::ONE_HUNDRED))
+            .await
+            .unwrap();
+        assert!(exchange.orders().is_empty());
+        assert!(executor.positions().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_close_exits_the_position_held() {
+        let (exchange, mut executor) = holding().await;
+        exchange.set_price(SYMBOL, dec!(110));
+
+        executor
+            .handle_signal(&signal(TradeAction::Close, dec!(110)))
+            .await
+            .unwrap();
+        assert!(executor.positions().await.is_empty());
+        let state = executor.state();
+        let state = state.read().await;
+        assert_eq!(state.trades.len(), 1);
+        assert_eq!(state.trades[0].exit_price, 110.0);
+    }
+
+    #[tokio::test]
+    async fn a_bracket_that_could_not_be_canceled_blocks_the_exit() {
+        let (exchange, mut executor) = holding().await;
+        exchange.fail_next(TradingError::NetworkError("timed out".to_string()));
+        let sent = exchange.orders().len();
+
+        assert!(executor
+            .handle_signal(&signal(TradeAction::Sell, Decimal::ONE_HUNDRED))
+            .await
+            .is_err());
+        // Still resting, so no market exit went out next to it
+        assert_eq!(exchange.orders().len(), sent);
+        let position = executor.positions().await[SYMBOL].clone();
+        assert!(position.bracket_order_id.is_some());
+    }
+
+    #[tokio::test]
+    async fn a_canceled_bracket_is_replaced_not_booked() {
+        let (exchange, mut executor) = holding().await;
+        let first_bracket = executor.positions().await[SYMBOL]
+            .bracket_order_id
+            .clone()
+            .unwrap();
+        exchange
+            .clone()
+            .cancel_order(SYMBOL, &first_bracket)
+            .await
+            .unwrap();
+
+        executor.state().write().await.on_price(SYMBOL, dec!(97));
+        executor.check_brackets().await;
+        let state = executor.state();
+        let state = state.read().await;
+        assert!(state.trades.is_empty());
+        let bracket_order_id = state.positions[SYMBOL].bracket_order_id.clone().unwrap();
+        assert_ne!(bracket_order_id, first_bracket);
+    }
+
+    #[tokio::test]
+    async fn a_take_profit_fill_is_booked_when_the_stop_expires() {
+        let (exchange, mut executor) = holding().await;
+        let stop_leg = executor.positions().await[SYMBOL]
+            .bracket_order_id
+            .clone()
+            .unwrap();
+
+        // The take-profit at 104 executes and the stop leg expires with it
+        exchange.set_price(SYMBOL, dec!(105));
+        let status = exchange.get_order_status(SYMBOL, &stop_leg).await.unwrap();
+        assert!(matches!(status.status, OrderStatus::Canceled));
+        executor.state().write().await.on_price(SYMBOL, dec!(105));
+        executor.check_brackets().await;
+
+        let state = executor.state();
+        let state = state.read().await;
+        assert!(state.positions.is_empty());
+        assert_eq!(state.trades.len(), 1);
+        assert_eq!(state.trades[0].exit_price, 104.0);
+        // No new bracket went out for the closed position
+        assert!(exchange.get_open_orders(SYMBOL).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_failed_exit_is_bracketed_again() {
+        let (exchange, mut executor) = holding().await;
+        let first_bracket = executor.positions().await[SYMBOL]
+            .bracket_order_id
+            .clone()
+            .unwrap();
+
+        exchange.push_fill(ScriptedFill::FailOrder(TradingError::OrderError(
+            "insufficient balance".to_string(),
+        )));
+        assert!(executor
+            .handle_signal(&signal(TradeAction::Sell, Decimal::ONE_HUNDRED))
+            .await
+            .is_err());
+        let position = executor.positions().await[SYMBOL].clone();
+        let bracket_order_id = position.bracket_order_id.unwrap();
+        assert_ne!(bracket_order_id, first_bracket);
+
+        // The new bracket's stop at 98 protects the position
+        exchange.set_price(SYMBOL, dec!(97));
+        executor.state().write().await.on_price(SYMBOL, dec!(97));
+        executor.check_brackets().await;
+        let state = executor.state();
+        let state = state.read().await;
+        assert!(state.positions.is_empty());
+        assert_eq!(state.trades.len(), 1);
+        assert!((state.trades[0].exit_price - 98.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn exchange_round_trips_are_timed() {
+        let exchange = MockExchange::new(dec!(10_000)).with_latency(Duration::from_millis(30));
+        exchange.set_price(SYMBOL, Decimal::ONE_HUNDRED);
+        let mut executor = executor(&exchange).await;
+        executor
+            .handle_signal(&signal(TradeAction::Buy, Decimal::ONE_HUNDRED))
+            .await
+            .unwrap();
+
+        let state = executor.state();
+        let state = state.read().await;
+        let submit_to_ack = state.latency.get(LatencyStage::SubmitToAck);
+        assert_eq!(submit_to_ack.count, 1);
+        // 30ms lands in the bucket up to 50ms, or a later one on a slow machine
+        assert!(submit_to_ack.quantile(0.5).unwrap() >= 50.0);
+    }
+}
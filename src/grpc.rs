@@ -0,0 +1,409 @@
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures_util::{Stream, StreamExt};
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use crate::auth::{Role, Tokens};
+use crate::control::{self, CommandError, ControlCommand, ControlReply, ControlSender};
+use crate::domain::*;
+use crate::events::{BusMessage, MarketEvent};
+use crate::executor::SharedExecutorState;
+
+#[allow(clippy::derive_partial_eq_without_eq)]
+pub mod proto {
+    tonic::include_proto!("auto_trade");
+}
+
+use proto::control_server::{Control, ControlServer};
+
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+// Per stream subscriber; slower clients skip what they missed
+const STREAM_BUFFER: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct GrpcSettings {
+    pub addr: SocketAddr,
+    // One is required as a bearer token in the authorization metadata
+    pub tokens: Tokens,
+}
+
+impl GrpcSettings {
+    // GRPC_ADDR plus an operator GRPC_TOKEN and/or read-only GRPC_READ_TOKEN;
+    // the service is not started without a token
+    pub fn from_env() -> Option<Self> {
+        let addr = dotenv::var("GRPC_ADDR").ok()?;
+        let tokens = match Tokens::from_env("GRPC_TOKEN", "GRPC_READ_TOKEN") {
+            Ok(Some(tokens)) => tokens,
+            Err(e) => {
+                log::error!("{}, gRPC service disabled", e);
+                return None;
+            }
+            Ok(None) => {
+                log::warn!(
+                    "GRPC_ADDR set without GRPC_TOKEN or GRPC_READ_TOKEN, gRPC service disabled"
+                );
+                return None;
+            }
+        };
+        match addr.parse() {
+            Ok(addr) => Some(GrpcSettings { addr, tokens }),
+            Err(e) => {
+                log::error!("Invalid GRPC_ADDR {}: {}", addr, e);
+                None
+            }
+        }
+    }
+}
+
+impl From<CommandError> for Status {
+    fn from(e: CommandError) -> Self {
+        match e {
+            CommandError::NotRunning => Status::unavailable("Executor is not running"),
+            CommandError::TimedOut => Status::deadline_exceeded("Command still running"),
+            CommandError::Failed(e) => e.into(),
+        }
+    }
+}
+
+impl From<TradingError> for Status {
+    fn from(e: TradingError) -> Self {
+        match e {
+            TradingError::InvalidParameter(message) => Status::invalid_argument(message),
+            e => Status::unavailable(format!("{:?}", e)),
+        }
+    }
+}
+
+impl From<proto::RiskParameters> for RiskParameters {
+    fn from(risk: proto::RiskParameters) -> Self {
+        RiskParameters {
+            max_position_size: to_decimal(risk.max_position_size),
+            stop_loss_pct: to_decimal(risk.stop_loss_pct),
+            take_profit_pct: to_decimal(risk.take_profit_pct),
+            max_open_positions: risk.max_open_positions as usize,
+        }
+    }
+}
+
+impl From<RiskParameters> for proto::RiskParameters {
+    fn from(risk: RiskParameters) -> Self {
+        proto::RiskParameters {
+            max_position_size: from_decimal(risk.max_position_size),
+            stop_loss_pct: from_decimal(risk.stop_loss_pct),
+            take_profit_pct: from_decimal(risk.take_profit_pct),
+            max_open_positions: risk.max_open_positions as u32,
+        }
+    }
+}
+
+fn side(side: &OrderSide) -> proto::Side {
+    match side {
+        OrderSide::Buy => proto::Side::Buy,
+        OrderSide::Sell => proto::Side::Sell,
+    }
+}
+
+fn signal(message: BusMessage) -> Option<proto::Signal> {
+    match message {
+        BusMessage::Signal { signal } => Some(proto::Signal {
+            symbol: signal.symbol,
+            strategy: signal.strategy,
+            action: format!("{:?}", signal.action),
+            price: from_decimal(signal.price),
+            timestamp: signal.timestamp,
+            stop_loss: signal.stop_loss.map(from_decimal),
+            take_profit: signal.take_profit.map(from_decimal),
+        }),
+        _ => None,
+    }
+}
+
+fn fill(message: BusMessage) -> Option<proto::Fill> {
+    match message {
+        BusMessage::Fill {
+            symbol,
+            order_id,
+            side: fill_side,
+            price,
+            quantity,
+            commission,
+            commission_asset,
+        } => Some(proto::Fill {
+            symbol,
+            order_id,
+            side: side(&fill_side) as i32,
+            price: from_decimal(price),
+            quantity: from_decimal(quantity),
+            commission: from_decimal(commission),
+            commission_asset,
+        }),
+        _ => None,
+    }
+}
+
+fn market_update(event: MarketEvent) -> Option<proto::MarketUpdate> {
+    Some(match event {
+        MarketEvent::Kline {
+            symbol,
+            closed,
+            candle,
+            ..
+        } => proto::MarketUpdate {
+            symbol,
+            kind: "kline".to_string(),
+            time: candle.close_time.timestamp_millis(),
+            price: candle.close_price,
+            volume: candle.volume,
+            closed,
+        },
+        MarketEvent::Ticker {
+            symbol,
+            time,
+            last_price,
+            volume,
+        } => proto::MarketUpdate {
+            symbol,
+            kind: "ticker".to_string(),
+            time,
+            price: last_price,
+            volume,
+            closed: true,
+        },
+    })
+}
+
+type EventStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+// Subscribe to `sender`, keeping what `convert` maps for the requested symbol
+fn subscribe<M, T>(
+    sender: &broadcast::Sender<M>,
+    symbol: String,
+    symbol_of: fn(&M) -> &str,
+    convert: fn(M) -> Option<T>,
+) -> EventStream<T>
+where
+    M: Clone + Send + 'static,
+    T: Send + 'static,
+{
+    let symbol = symbol.to_uppercase();
+    let stream = BroadcastStream::new(sender.subscribe()).filter_map(move |message| {
+        let item = match message {
+            Ok(message) if symbol.is_empty() || symbol_of(&message) == symbol => {
+                convert(message).map(Ok)
+            }
+            Ok(_) => None,
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                log::warn!("gRPC stream client lagged, skipped {} messages", skipped);
+                None
+            }
+        };
+        async move { item }
+    });
+    Box::pin(stream)
+}
+
+struct ControlService {
+    executor: SharedExecutorState,
+    control: ControlSender,
+    events: broadcast::Sender<BusMessage>,
+    market: broadcast::Sender<MarketEvent>,
+}
+
+// The role is set on every request by the authenticating interceptor
+fn require_operator<T>(request: &Request<T>) -> Result<(), Status> {
+    match request.extensions().get::<Role>() {
+        Some(Role::Operator) => Ok(()),
+        _ => Err(Status::permission_denied("Needs an operator token")),
+    }
+}
+
+impl ControlService {
+    async fn command(&self, command: ControlCommand) -> Result<ControlReply, Status> {
+        Ok(control::send_and_wait(&self.control, command, "grpc", COMMAND_TIMEOUT).await?)
+    }
+}
+
+#[tonic::async_trait]
+impl Control for ControlService {
+    async fn get_status(
+        &self,
+        _: Request<proto::Empty>,
+    ) -> Result<Response<proto::Status>, Status> {
+        let state = self.executor.read().await;
+        let report = state.pnl_report();
+        Ok(Response::new(proto::Status {
+            paused: state.paused,
+            safe_mode: state.safe_mode.clone(),
+            open_positions: state.positions.len() as u32,
+            working_orders: state.working_orders.len() as u32,
+            daily_pnl: state.daily_pnl(),
+            realized: report.realized,
+            unrealized: report.unrealized,
+            risk: Some(state.risk.clone().into()),
+        }))
+    }
+
+    async fn pause(
+        &self,
+        request: Request<proto::Empty>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        require_operator(&request)?;
+        self.command(ControlCommand::Pause).await?;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn resume(
+        &self,
+        request: Request<proto::Empty>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        require_operator(&request)?;
+        self.command(ControlCommand::Resume).await?;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn kill(&self, request: Request<proto::Empty>) -> Result<Response<proto::Empty>, Status> {
+        require_operator(&request)?;
+        self.command(ControlCommand::Kill).await?;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn update_risk(
+        &self,
+        request: Request<proto::RiskParameters>,
+    ) -> Result<Response<proto::RiskParameters>, Status> {
+        require_operator(&request)?;
+        let risk: RiskParameters = request.into_inner().into();
+        risk.validate()?;
+        self.command(ControlCommand::UpdateRisk(risk.clone()))
+            .await?;
+        Ok(Response::new(risk.into()))
+    }
+
+    async fn place_order(
+        &self,
+        request: Request<proto::ManualOrder>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        require_operator(&request)?;
+        let order = request.into_inner();
+        let action = match order.side() {
+            proto::Side::Buy => TradeAction::Buy,
+            proto::Side::Sell => TradeAction::Sell,
+        };
+        self.command(ControlCommand::ManualOrder {
+            symbol: order.symbol.to_uppercase(),
+            action,
+            price: order.price.map(to_decimal),
+            limit: order.limit,
+        })
+        .await?;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    type StreamSignalsStream = EventStream<proto::Signal>;
+
+    async fn stream_signals(
+        &self,
+        request: Request<proto::StreamRequest>,
+    ) -> Result<Response<Self::StreamSignalsStream>, Status> {
+        Ok(Response::new(subscribe(
+            &self.events,
+            request.into_inner().symbol,
+            BusMessage::symbol,
+            signal,
+        )))
+    }
+
+    type StreamFillsStream = EventStream<proto::Fill>;
+
+    async fn stream_fills(
+        &self,
+        request: Request<proto::StreamRequest>,
+    ) -> Result<Response<Self::StreamFillsStream>, Status> {
+        Ok(Response::new(subscribe(
+            &self.events,
+            request.into_inner().symbol,
+            BusMessage::symbol,
+            fill,
+        )))
+    }
+
+    type StreamMarketDataStream = EventStream<proto::MarketUpdate>;
+
+    async fn stream_market_data(
+        &self,
+        request: Request<proto::StreamRequest>,
+    ) -> Result<Response<Self::StreamMarketDataStream>, Status> {
+        Ok(Response::new(subscribe(
+            &self.market,
+            request.into_inner().symbol,
+            MarketEvent::symbol,
+            market_update,
+        )))
+    }
+}
+
+/// Serves the gRPC control service, fed with executor events and market data
+pub async fn serve(
+    settings: GrpcSettings,
+    executor: SharedExecutorState,
+    control: ControlSender,
+    mut events: mpsc::Receiver<BusMessage>,
+    mut market: mpsc::Receiver<MarketEvent>,
+) {
+    // Fan each queue out to every stream subscriber; sending fails only while
+    // nobody is subscribed
+    let (events_tx, _) = broadcast::channel(STREAM_BUFFER);
+    let (market_tx, _) = broadcast::channel(STREAM_BUFFER);
+    {
+        let events_tx = events_tx.clone();
+        tokio::spawn(async move {
+            while let Some(message) = events.recv().await {
+                let _ = events_tx.send(message);
+            }
+        });
+    }
+    {
+        let market_tx = market_tx.clone();
+        tokio::spawn(async move {
+            while let Some(event) = market.recv().await {
+                let _ = market_tx.send(event);
+            }
+        });
+    }
+
+    let service = ControlService {
+        executor,
+        control,
+        events: events_tx,
+        market: market_tx,
+    };
+    let tokens = settings.tokens;
+    let service = ControlServer::with_interceptor(service, move |mut request: Request<()>| {
+        let role = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| tokens.bearer_role(value));
+        match role {
+            Some(role) => {
+                request.extensions_mut().insert(role);
+                Ok(request)
+            }
+            None => Err(Status::unauthenticated("Missing or wrong bearer token")),
+        }
+    });
+    log::info!("gRPC service listening on {}", settings.addr);
+    if let Err(e) = Server::builder()
+        .add_service(service)
+        .serve(settings.addr)
+        .await
+    {
+        log::error!("gRPC service stopped: {:?}", e);
+    }
+}
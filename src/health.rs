@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use binance_spot_connector_rust::hyper::BinanceHttpClient;
+use binance_spot_connector_rust::market;
+use serde::Serialize;
+
+// A websocket stream with nothing newer than this is considered stale
+const STREAM_MAX_AGE_MS: i64 = 30_000;
+// Two 1m candles missed
+const CANDLE_MAX_AGE_MS: i64 = 150_000;
+// Tasks beat at least this often while they are alive
+const HEARTBEAT_MAX_AGE_MS: i64 = 60_000;
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// What the probes report on; updated by the tasks they describe
+#[derive(Debug, Default)]
+pub struct HealthState {
+    pub exchange_connected: bool,
+    // Stream name -> last message time, ms
+    pub last_message: HashMap<String, i64>,
+    // Symbol -> close time of the last candle the strategy saw, ms
+    pub last_candle: HashMap<String, i64>,
+    // Task name -> last heartbeat, ms
+    pub heartbeats: HashMap<String, i64>,
+}
+
+pub type SharedHealth = Arc<Mutex<HealthState>>;
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+pub fn mark_message(health: &SharedHealth, stream: &str) {
+    health
+        .lock()
+        .unwrap()
+        .last_message
+        .insert(stream.to_string(), now_ms());
+}
+
+pub fn mark_candle(health: &SharedHealth, symbol: &str, close_time_ms: i64) {
+    health
+        .lock()
+        .unwrap()
+        .last_candle
+        .insert(symbol.to_string(), close_time_ms);
+}
+
+pub fn heartbeat(health: &SharedHealth, task: &str) {
+    health
+        .lock()
+        .unwrap()
+        .heartbeats
+        .insert(task.to_string(), now_ms());
+}
+
+#[derive(Debug, Serialize)]
+struct Check {
+    ok: bool,
+    // Milliseconds since the last update, if there was one
+    age_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct Probe {
+    ok: bool,
+    checks: HashMap<String, Check>,
+}
+
+fn age_checks(
+    prefix: &str,
+    times: &HashMap<String, i64>,
+    max_age: i64,
+    now: i64,
+) -> Vec<(String, Check)> {
+    times
+        .iter()
+        .map(|(name, time)| {
+            let age = now - time;
+            (
+                format!("{}:{}", prefix, name),
+                Check {
+                    ok: age <= max_age,
+                    age_ms: Some(age),
+                },
+            )
+        })
+        .collect()
+}
+
+fn respond(checks: Vec<(String, Check)>) -> (StatusCode, Json<Probe>) {
+    let ok = checks.iter().all(|(_, check)| check.ok);
+    let status = if ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(Probe {
+            ok,
+            checks: checks.into_iter().collect(),
+        }),
+    )
+}
+
+fn liveness_checks(state: &HealthState) -> Vec<(String, Check)> {
+    age_checks("task", &state.heartbeats, HEARTBEAT_MAX_AGE_MS, now_ms())
+}
+
+fn readiness_checks(state: &HealthState) -> Vec<(String, Check)> {
+    let now = now_ms();
+    let mut checks = vec![(
+        "exchange".to_string(),
+        Check {
+            ok: state.exchange_connected,
+            age_ms: None,
+        },
+    )];
+    checks.extend(age_checks(
+        "stream",
+        &state.last_message,
+        STREAM_MAX_AGE_MS,
+        now,
+    ));
+    checks.extend(age_checks(
+        "candle",
+        &state.last_candle,
+        CANDLE_MAX_AGE_MS,
+        now,
+    ));
+    if state.last_message.is_empty() {
+        checks.push((
+            "stream".to_string(),
+            Check {
+                ok: false,
+                age_ms: None,
+            },
+        ));
+    }
+    checks
+}
+
+// Names of the liveness and readiness checks currently failing
+pub fn failing_checks(health: &SharedHealth) -> Vec<String> {
+    let state = health.lock().unwrap();
+    let mut checks = liveness_checks(&state);
+    checks.extend(readiness_checks(&state));
+    let mut failing: Vec<String> = checks
+        .into_iter()
+        .filter(|(_, check)| !check.ok)
+        .map(|(name, _)| name)
+        .collect();
+    failing.sort();
+    failing
+}
+
+// Liveness: every background task is still beating
+async fn healthz(State(health): State<SharedHealth>) -> (StatusCode, Json<Probe>) {
+    respond(liveness_checks(&health.lock().unwrap()))
+}
+
+// Readiness: the exchange answers and market data is current
+async fn readyz(State(health): State<SharedHealth>) -> (StatusCode, Json<Probe>) {
+    respond(readiness_checks(&health.lock().unwrap()))
+}
+
+// Keeps `exchange_connected` current with the REST ping endpoint
+async fn ping_exchange(health: SharedHealth) {
+    let client = BinanceHttpClient::default();
+    let mut interval = tokio::time::interval(PING_INTERVAL);
+    loop {
+        interval.tick().await;
+        let connected = client.send(market::ping()).await.is_ok();
+        if !connected {
+            log::warn!("Exchange ping failed");
+        }
+        health.lock().unwrap().exchange_connected = connected;
+    }
+}
+
+/// Serves /healthz and /readyz on HEALTH_ADDR (default 0.0.0.0:8080)
+pub async fn serve(health: SharedHealth) {
+    let addr: SocketAddr = match dotenv::var("HEALTH_ADDR")
+        .unwrap_or_else(|_| "0.0.0.0:8080".to_string())
+        .parse()
+    {
+        Ok(addr) => addr,
+        Err(e) => {
+            log::error!("Invalid HEALTH_ADDR: {}", e);
+            return;
+        }
+    };
+    tokio::spawn(ping_exchange(health.clone()));
+    let app = Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(health);
+    log::info!("Health endpoints listening on {}", addr);
+    if let Err(e) = axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await
+    {
+        log::error!("Health server stopped: {}", e);
+    }
+}
@@ -0,0 +1,191 @@
+use std::time::Duration;
+
+use hyper::{Body, Method, Request};
+#[cfg(feature = "mqtt")]
+use rumqttc::{AsyncClient, LastWill, MqttOptions, QoS};
+#[cfg(feature = "mqtt")]
+use serde_json::json;
+
+use crate::domain::*;
+use crate::health::{self, SharedHealth};
+use crate::notify::{self, HttpsClient};
+
+#[derive(Debug, Clone)]
+pub enum HeartbeatTarget {
+    // Dead-man's switch pinged while healthy; `fail_url`, if set, is pinged
+    // instead while a check fails so the switch alerts without waiting
+    Http {
+        url: String,
+        fail_url: Option<String>,
+    },
+    // Retained status message, with a last will so the broker reports a
+    // dropped connection
+    #[cfg(feature = "mqtt")]
+    Mqtt {
+        host: String,
+        port: u16,
+        topic: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct HeartbeatSettings {
+    pub targets: Vec<HeartbeatTarget>,
+    pub interval: Duration,
+}
+
+impl HeartbeatSettings {
+    // HEARTBEAT_URL (optional HEARTBEAT_FAIL_URL) and/or HEARTBEAT_MQTT_HOST
+    // (optional HEARTBEAT_MQTT_PORT, HEARTBEAT_MQTT_TOPIC), every
+    // HEARTBEAT_INTERVAL_SECS (default 60)
+    pub fn from_env() -> Option<Self> {
+        let mut targets = Vec::new();
+        if let Ok(url) = dotenv::var("HEARTBEAT_URL") {
+            targets.push(HeartbeatTarget::Http {
+                url,
+                fail_url: dotenv::var("HEARTBEAT_FAIL_URL").ok(),
+            });
+        }
+        #[cfg(not(feature = "mqtt"))]
+        if dotenv::var("HEARTBEAT_MQTT_HOST").is_ok() {
+            log::warn!("HEARTBEAT_MQTT_HOST set but built without the mqtt feature");
+        }
+        #[cfg(feature = "mqtt")]
+        if let Ok(host) = dotenv::var("HEARTBEAT_MQTT_HOST") {
+            targets.push(HeartbeatTarget::Mqtt {
+                host,
+                port: dotenv::var("HEARTBEAT_MQTT_PORT")
+                    .ok()
+                    .and_then(|port| port.parse().ok())
+                    .unwrap_or(1883),
+                topic: dotenv::var("HEARTBEAT_MQTT_TOPIC")
+                    .unwrap_or_else(|_| "auto_trade/heartbeat".to_string()),
+            });
+        }
+        if targets.is_empty() {
+            return None;
+        }
+        let interval = dotenv::var("HEARTBEAT_INTERVAL_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .unwrap_or(60);
+        Some(HeartbeatSettings {
+            targets,
+            interval: Duration::from_secs(interval.max(1)),
+        })
+    }
+}
+
+enum Publisher {
+    Http {
+        client: HttpsClient,
+        url: String,
+        fail_url: Option<String>,
+    },
+    #[cfg(feature = "mqtt")]
+    Mqtt { client: AsyncClient, topic: String },
+}
+
+impl Publisher {
+    fn connect(target: HeartbeatTarget) -> Self {
+        match target {
+            HeartbeatTarget::Http { url, fail_url } => Publisher::Http {
+                client: notify::https_client(),
+                url,
+                fail_url,
+            },
+            #[cfg(feature = "mqtt")]
+            HeartbeatTarget::Mqtt { host, port, topic } => {
+                let client_id = format!("auto_trade-{}", std::process::id());
+                let mut options = MqttOptions::new(client_id, host, port);
+                options.set_keep_alive(Duration::from_secs(30));
+                options.set_last_will(LastWill::new(
+                    &topic,
+                    json!({ "ok": false, "failing": ["process"] }).to_string(),
+                    QoS::AtLeastOnce,
+                    true,
+                ));
+                let (client, mut event_loop) = AsyncClient::new(options, 10);
+                // The event loop does the network work and reconnects on the next poll
+                tokio::spawn(async move {
+                    loop {
+                        if let Err(e) = event_loop.poll().await {
+                            log::warn!("MQTT heartbeat connection error: {:?}", e);
+                            tokio::time::sleep(Duration::from_secs(5)).await;
+                        }
+                    }
+                });
+                Publisher::Mqtt { client, topic }
+            }
+        }
+    }
+
+    async fn publish(&self, failing: &[String]) -> Result<(), TradingError> {
+        match self {
+            Publisher::Http {
+                client,
+                url,
+                fail_url,
+            } => {
+                let url = match (failing.is_empty(), fail_url) {
+                    (true, _) => url,
+                    (false, Some(fail_url)) => fail_url,
+                    // Stay silent and let the switch expire
+                    (false, None) => return Ok(()),
+                };
+                let request = Request::builder()
+                    .method(Method::POST)
+                    .uri(url.as_str())
+                    .body(Body::from(failing.join("\n")))
+                    .map_err(|e| TradingError::DataError(format!("{:?}", e)))?;
+                let response = client
+                    .request(request)
+                    .await
+                    .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+                if !response.status().is_success() {
+                    // The url is left out; ping urls often embed a secret
+                    return Err(TradingError::NetworkError(format!(
+                        "Heartbeat ping answered {}",
+                        response.status()
+                    )));
+                }
+                Ok(())
+            }
+            #[cfg(feature = "mqtt")]
+            Publisher::Mqtt { client, topic } => {
+                let payload = json!({
+                    "ok": failing.is_empty(),
+                    "failing": failing,
+                    "time": chrono::Utc::now().timestamp_millis(),
+                });
+                client
+                    .publish(topic, QoS::AtLeastOnce, true, payload.to_string())
+                    .await
+                    .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))
+            }
+        }
+    }
+}
+
+/// Publishes the health checks to external monitoring, so a dead process or
+/// stalled data feed gets noticed from outside
+pub async fn run(settings: HeartbeatSettings, health: SharedHealth) {
+    let publishers: Vec<Publisher> = settings
+        .targets
+        .into_iter()
+        .map(Publisher::connect)
+        .collect();
+    let mut interval = tokio::time::interval(settings.interval);
+    loop {
+        interval.tick().await;
+        let failing = health::failing_checks(&health);
+        if !failing.is_empty() {
+            log::warn!("Heartbeat unhealthy: {}", failing.join(", "));
+        }
+        for publisher in &publishers {
+            if let Err(e) = publisher.publish(&failing).await {
+                log::error!("Failed to publish heartbeat: {}", e);
+            }
+        }
+    }
+}
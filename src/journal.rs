@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::*;
+use crate::dto::Error;
+use crate::retention;
+
+/// Something that happened on the way from market data to a position. Together
+/// they explain every trade decision after the fact.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JournalEvent {
+    // Market state the strategy saw when it signalled
+    MarketSnapshot {
+        symbol: String,
+        close_time: i64,
+        open: Decimal,
+        high: Decimal,
+        low: Decimal,
+        close: Decimal,
+        last_price: Decimal,
+    },
+    Signal {
+        signal: TradingSignal,
+    },
+    OrderSubmitted {
+        symbol: String,
+        side: OrderSide,
+        order_type: String,
+        quantity: Decimal,
+    },
+    OrderAcknowledged {
+        symbol: String,
+        order_id: String,
+        status: String,
+    },
+    OrderFilled {
+        symbol: String,
+        order_id: String,
+        price: Decimal,
+        quantity: Decimal,
+        commission: Decimal,
+        commission_asset: String,
+    },
+    RiskRejected {
+        symbol: String,
+        reason: String,
+    },
+    // An entry worked as several child orders; their submits and fills follow
+    ParentOrderStarted {
+        symbol: String,
+        parent_id: String,
+        algorithm: String,
+        quantity: Decimal,
+    },
+    ParentOrderDone {
+        symbol: String,
+        parent_id: String,
+        filled: Decimal,
+        child_ids: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    // Increases by one per event, also across restarts
+    pub seq: u64,
+    // Milliseconds
+    pub recorded_at: i64,
+    #[serde(flatten)]
+    pub event: JournalEvent,
+}
+
+// Append-only JSON lines file; entries are never rewritten
+pub struct Journal {
+    out: BufWriter<File>,
+    path: PathBuf,
+    next_seq: u64,
+}
+
+impl Journal {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let next_seq = if path.exists() {
+            load_journal(path)?.last().map(|e| e.seq + 1).unwrap_or(0)
+        } else {
+            0
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Journal {
+            out: BufWriter::new(file),
+            path: path.to_path_buf(),
+            next_seq,
+        })
+    }
+
+    // Retention is the one exception to append-only: events recorded before
+    // `cutoff` (ms) are dropped. Sequence numbers carry on where they were.
+    pub fn prune_before(&mut self, cutoff: i64) -> Result<usize, Error> {
+        self.out.flush()?;
+        let removed = retention::prune_json_lines(&self.path, "recorded_at", cutoff)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.out = BufWriter::new(file);
+        Ok(removed)
+    }
+
+    pub fn append(&mut self, event: JournalEvent) -> Result<(), Error> {
+        let entry = JournalEntry {
+            seq: self.next_seq,
+            recorded_at: chrono::Utc::now().timestamp_millis(),
+            event,
+        };
+        serde_json::to_writer(&mut self.out, &entry)?;
+        writeln!(self.out)?;
+        self.out.flush()?;
+        self.next_seq += 1;
+        Ok(())
+    }
+}
+
+pub fn load_journal<P: AsRef<Path>>(path: P) -> Result<Vec<JournalEntry>, Error> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        entries.push(serde_json::from_str(&line)?);
+    }
+    Ok(entries)
+}
+
+impl JournalEvent {
+    pub fn symbol(&self) -> &str {
+        match self {
+            JournalEvent::Signal { signal } => &signal.symbol,
+            JournalEvent::MarketSnapshot { symbol, .. }
+            | JournalEvent::OrderSubmitted { symbol, .. }
+            | JournalEvent::OrderAcknowledged { symbol, .. }
+            | JournalEvent::OrderFilled { symbol, .. }
+            | JournalEvent::RiskRejected { symbol, .. }
+            | JournalEvent::ParentOrderStarted { symbol, .. }
+            | JournalEvent::ParentOrderDone { symbol, .. } => symbol,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            JournalEvent::MarketSnapshot {
+                open,
+                high,
+                low,
+                close,
+                last_price,
+                ..
+            } => format!(
+                "market  O {} H {} L {} C {}, last {}",
+                open, high, low, close, last_price
+            ),
+            JournalEvent::Signal { signal } => {
+                let mut text = format!(
+                    "signal  {:?} @ {} from {}",
+                    signal.action, signal.price, signal.strategy
+                );
+                if let Some(stop_loss) = signal.stop_loss {
+                    text.push_str(&format!(", stop {}", stop_loss));
+                }
+                if let Some(take_profit) = signal.take_profit {
+                    text.push_str(&format!(", target {}", take_profit));
+                }
+                text
+            }
+            JournalEvent::RiskRejected { reason, .. } => format!("risk    rejected: {}", reason),
+            JournalEvent::OrderSubmitted {
+                side,
+                order_type,
+                quantity,
+                ..
+            } => format!("order   submitted {:?} {} {}", side, order_type, quantity),
+            JournalEvent::OrderAcknowledged {
+                order_id, status, ..
+            } => format!("order   {} {}", order_id, status),
+            JournalEvent::OrderFilled {
+                order_id,
+                price,
+                quantity,
+                commission,
+                commission_asset,
+                ..
+            } => format!(
+                "fill    {} {} @ {}, fee {} {}",
+                order_id, quantity, price, commission, commission_asset
+            ),
+            JournalEvent::ParentOrderStarted {
+                parent_id,
+                algorithm,
+                quantity,
+                ..
+            } => format!("parent  {} {} {}", parent_id, algorithm, quantity),
+            JournalEvent::ParentOrderDone {
+                parent_id,
+                filled,
+                child_ids,
+                ..
+            } => format!(
+                "parent  {} done, {} filled over {} children",
+                parent_id,
+                filled,
+                child_ids.len()
+            ),
+        }
+    }
+}
+
+/// The journal of one UTC day as readable decision chains: each signal with the
+/// market it saw, then the risk decision, order and fills that followed
+pub fn timeline(entries: &[JournalEntry], day: NaiveDate, symbol: Option<&str>) -> Vec<String> {
+    let start = Utc
+        .from_utc_datetime(&day.and_hms_opt(0, 0, 0).unwrap_or_default())
+        .timestamp_millis();
+    let end = start + 24 * 60 * 60 * 1000;
+    let mut lines = Vec::new();
+    // Per symbol, whether the latest signal led to anything yet
+    let mut open_chains: HashMap<String, bool> = HashMap::new();
+    let close_chain = |lines: &mut Vec<String>, symbol: &str, decided: bool| {
+        if !decided {
+            lines.push(format!(
+                "{:21} {:<10} ignored: already holding, nothing to close or trading stopped",
+                "", symbol
+            ));
+        }
+    };
+    for entry in entries {
+        if entry.recorded_at < start || entry.recorded_at >= end {
+            continue;
+        }
+        let entry_symbol = entry.event.symbol();
+        if symbol.is_some_and(|symbol| !symbol.eq_ignore_ascii_case(entry_symbol)) {
+            continue;
+        }
+        match &entry.event {
+            // A new decision starts with the market it was made on
+            JournalEvent::MarketSnapshot { .. } => {
+                if let Some(decided) = open_chains.remove(entry_symbol) {
+                    close_chain(&mut lines, entry_symbol, decided);
+                }
+                lines.push(String::new());
+            }
+            JournalEvent::Signal { .. } => {
+                open_chains.insert(entry_symbol.to_string(), false);
+            }
+            JournalEvent::RiskRejected { .. } | JournalEvent::OrderSubmitted { .. } => {
+                if let Some(decided) = open_chains.get_mut(entry_symbol) {
+                    *decided = true;
+                }
+            }
+            _ => {}
+        }
+        let time = Utc
+            .timestamp_millis_opt(entry.recorded_at)
+            .single()
+            .map(|time| time.format("%H:%M:%S%.3f").to_string())
+            .unwrap_or_default();
+        lines.push(format!(
+            "{} #{:<6} {:<10} {}",
+            time,
+            entry.seq,
+            entry_symbol,
+            entry.event.describe()
+        ));
+    }
+    let mut pending: Vec<_> = open_chains.into_iter().collect();
+    pending.sort();
+    for (symbol, decided) in pending {
+        close_chain(&mut lines, &symbol, decided);
+    }
+    lines
+}
+
+pub type SharedJournal = Arc<Mutex<Journal>>;
+
+// Journal at the path in JOURNAL_PATH, if set
+pub fn from_env() -> Option<SharedJournal> {
+    let path = dotenv::var("JOURNAL_PATH").ok()?;
+    match Journal::open(&path) {
+        Ok(journal) => Some(Arc::new(Mutex::new(journal))),
+        Err(e) => {
+            log::error!("Failed to open event journal {}: {}", path, e);
+            None
+        }
+    }
+}
+
+pub fn record(journal: &Option<SharedJournal>, event: JournalEvent) {
+    if let Some(journal) = journal {
+        if let Err(e) = journal.lock().unwrap().append(event) {
+            log::error!("Failed to append to event journal: {}", e);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use futures_util::future::join_all;
+use rdkafka::config::ClientConfig;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use tokio::sync::mpsc;
+
+use crate::events::{BusMessage, MarketEvent};
+
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+pub struct KafkaSettings {
+    pub brokers: String,
+    pub market_topic: String,
+    pub events_topic: String,
+    // Messages per flush
+    pub batch_size: usize,
+    // Longest a message waits for its batch to fill
+    pub linger: Duration,
+    // Further attempts for messages the broker didn't acknowledge
+    pub retries: u32,
+}
+
+impl KafkaSettings {
+    // KAFKA_BROKERS plus optional KAFKA_MARKET_TOPIC and KAFKA_EVENTS_TOPIC, if set
+    pub fn from_env() -> Option<Self> {
+        Some(KafkaSettings {
+            brokers: dotenv::var("KAFKA_BROKERS").ok()?,
+            market_topic: dotenv::var("KAFKA_MARKET_TOPIC")
+                .unwrap_or_else(|_| "auto_trade.market".to_string()),
+            events_topic: dotenv::var("KAFKA_EVENTS_TOPIC")
+                .unwrap_or_else(|_| "auto_trade.events".to_string()),
+            batch_size: 500,
+            linger: Duration::from_millis(200),
+            retries: 3,
+        })
+    }
+}
+
+struct Pending {
+    topic: String,
+    key: String,
+    payload: String,
+}
+
+// Send a batch, retrying whatever wasn't delivered; returns how many were lost
+async fn flush(
+    producer: &FutureProducer,
+    settings: &KafkaSettings,
+    mut batch: Vec<Pending>,
+) -> usize {
+    for attempt in 0..=settings.retries {
+        if batch.is_empty() {
+            return 0;
+        }
+        if attempt > 0 {
+            tokio::time::sleep(RETRY_DELAY * attempt).await;
+        }
+        let deliveries = batch.iter().map(|message| {
+            producer.send(
+                FutureRecord::to(&message.topic)
+                    .key(&message.key)
+                    .payload(&message.payload),
+                Duration::from_secs(5),
+            )
+        });
+        let results = join_all(deliveries).await;
+        let mut failed = Vec::new();
+        for (message, result) in batch.into_iter().zip(results) {
+            if let Err((e, _)) = result {
+                log::debug!("Kafka delivery to {} failed: {}", message.topic, e);
+                failed.push(message);
+            }
+        }
+        batch = failed;
+    }
+    batch.len()
+}
+
+/// Batches market data and trading events onto their topics until both inputs close
+pub async fn run_producer(
+    settings: KafkaSettings,
+    mut market: mpsc::Receiver<MarketEvent>,
+    mut events: mpsc::Receiver<BusMessage>,
+) {
+    let producer: FutureProducer = match ClientConfig::new()
+        .set("bootstrap.servers", &settings.brokers)
+        .set("enable.idempotence", "true")
+        .set("linger.ms", settings.linger.as_millis().to_string())
+        .set("message.timeout.ms", "30000")
+        .create()
+    {
+        Ok(producer) => producer,
+        Err(e) => {
+            log::error!("Failed to create Kafka producer: {}", e);
+            return;
+        }
+    };
+
+    let mut batch: Vec<Pending> = Vec::with_capacity(settings.batch_size);
+    let mut linger = tokio::time::interval(settings.linger);
+    let (mut market_open, mut events_open) = (true, true);
+    while market_open || events_open {
+        let mut linger_due = false;
+        tokio::select! {
+            event = market.recv(), if market_open => match event {
+                Some(event) => match serde_json::to_string(&event) {
+                    Ok(payload) => batch.push(Pending {
+                        topic: settings.market_topic.clone(),
+                        key: event.symbol().to_string(),
+                        payload,
+                    }),
+                    Err(e) => log::error!("Failed to serialize {:?}: {}", event, e),
+                },
+                None => market_open = false,
+            },
+            message = events.recv(), if events_open => match message {
+                Some(message) => match serde_json::to_string(&message) {
+                    Ok(payload) => batch.push(Pending {
+                        topic: settings.events_topic.clone(),
+                        key: message.symbol().to_string(),
+                        payload,
+                    }),
+                    Err(e) => log::error!("Failed to serialize {:?}: {}", message, e),
+                },
+                None => events_open = false,
+            },
+            _ = linger.tick() => linger_due = true,
+        }
+        let closing = !(market_open || events_open);
+        if !batch.is_empty() && (linger_due || closing || batch.len() >= settings.batch_size) {
+            let lost = flush(&producer, &settings, std::mem::take(&mut batch)).await;
+            if lost > 0 {
+                log::error!(
+                    "Dropped {} Kafka messages after {} retries",
+                    lost,
+                    settings.retries
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+// Upper bounds of the histogram buckets, milliseconds; slower samples land in a
+// final overflow bucket
+const BUCKETS_MS: [f64; 12] = [
+    1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+];
+// Short and long exponential averages; the short one running this far above the
+// long one is flagged as a regression
+const FAST_ALPHA: f64 = 0.2;
+const SLOW_ALPHA: f64 = 0.02;
+const REGRESSION_FACTOR: f64 = 2.0;
+// Samples needed before the long average is trusted
+const REGRESSION_MIN_SAMPLES: u64 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LatencyStage {
+    // Executor receiving the signal to the order leaving for the exchange
+    SignalToSubmit,
+    // Exchange round trip
+    SubmitToAck,
+    // Acknowledgement to the fill being known; 0 when the ack carries the fills
+    AckToFill,
+}
+
+impl LatencyStage {
+    pub const ALL: [LatencyStage; 3] = [
+        LatencyStage::SignalToSubmit,
+        LatencyStage::SubmitToAck,
+        LatencyStage::AckToFill,
+    ];
+
+    // Telemetry series: p50, p95 and the regression flag
+    pub fn metrics(self) -> [&'static str; 3] {
+        match self {
+            LatencyStage::SignalToSubmit => [
+                "latency_signal_submit_p50_ms",
+                "latency_signal_submit_p95_ms",
+                "latency_signal_submit_regression",
+            ],
+            LatencyStage::SubmitToAck => [
+                "latency_submit_ack_p50_ms",
+                "latency_submit_ack_p95_ms",
+                "latency_submit_ack_regression",
+            ],
+            LatencyStage::AckToFill => [
+                "latency_ack_fill_p50_ms",
+                "latency_ack_fill_p95_ms",
+                "latency_ack_fill_regression",
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LatencyHistogram {
+    // One count per bucket plus the overflow bucket
+    pub counts: [u64; BUCKETS_MS.len() + 1],
+    pub count: u64,
+    pub sum_ms: f64,
+    pub max_ms: f64,
+    fast_ms: f64,
+    slow_ms: f64,
+    pub regressed: bool,
+}
+
+impl LatencyHistogram {
+    // Returns true when this sample starts a regression
+    pub fn record(&mut self, latency: Duration) -> bool {
+        let ms = latency.as_secs_f64() * 1000.0;
+        let bucket = BUCKETS_MS
+            .iter()
+            .position(|bound| ms <= *bound)
+            .unwrap_or(BUCKETS_MS.len());
+        self.counts[bucket] += 1;
+        self.sum_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+        if self.count == 0 {
+            self.fast_ms = ms;
+            self.slow_ms = ms;
+        } else {
+            self.fast_ms += FAST_ALPHA * (ms - self.fast_ms);
+            self.slow_ms += SLOW_ALPHA * (ms - self.slow_ms);
+        }
+        self.count += 1;
+
+        let regressed =
+            self.count >= REGRESSION_MIN_SAMPLES && self.fast_ms > self.slow_ms * REGRESSION_FACTOR;
+        let started = regressed && !self.regressed;
+        self.regressed = regressed;
+        started
+    }
+
+    // Upper bound of the bucket holding quantile `q`; the maximum for the overflow bucket
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let target = (q * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return Some(BUCKETS_MS.get(i).copied().unwrap_or(self.max_ms));
+            }
+        }
+        Some(self.max_ms)
+    }
+}
+
+/// Per-stage latency histograms over every order the executor has sent
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OrderLatencies {
+    pub signal_to_submit: LatencyHistogram,
+    pub submit_to_ack: LatencyHistogram,
+    pub ack_to_fill: LatencyHistogram,
+}
+
+impl OrderLatencies {
+    pub fn get(&self, stage: LatencyStage) -> &LatencyHistogram {
+        match stage {
+            LatencyStage::SignalToSubmit => &self.signal_to_submit,
+            LatencyStage::SubmitToAck => &self.submit_to_ack,
+            LatencyStage::AckToFill => &self.ack_to_fill,
+        }
+    }
+
+    pub fn record(&mut self, stage: LatencyStage, latency: Duration) {
+        let histogram = match stage {
+            LatencyStage::SignalToSubmit => &mut self.signal_to_submit,
+            LatencyStage::SubmitToAck => &mut self.submit_to_ack,
+            LatencyStage::AckToFill => &mut self.ack_to_fill,
+        };
+        if histogram.record(latency) {
+            log::warn!(
+                "{:?} latency regressed: {:.1}ms recently against {:.1}ms usually",
+                stage,
+                histogram.fast_ms,
+                histogram.slow_ms
+            );
+        }
+    }
+}
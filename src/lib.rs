@@ -0,0 +1,1260 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+mod alerts;
+#[cfg(feature = "web")]
+mod api;
+mod audit;
+mod auth;
+mod bot;
+pub mod domain;
+mod engine;
+mod events;
+mod exchange;
+mod execution;
+#[cfg(feature = "grpc")]
+mod grpc;
+use crate::domain::*;
+use crate::engine::SignalEngine;
+use crate::exchange::ExchangeRouter;
+mod dto;
+use crate::dto::Error as dtoError;
+use crate::dto::*;
+pub mod executor;
+mod health;
+mod heartbeat;
+use crate::health::SharedHealth;
+mod journal;
+#[cfg(feature = "kafka")]
+mod kafka;
+mod latency;
+mod logging;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod notify;
+mod orders;
+use crate::events::{EventBus, MarketEvent};
+use crate::executor::*;
+use crate::journal::{JournalEvent, SharedJournal};
+use crate::notify::{Notification, NotifySender};
+mod backtest;
+mod cli;
+mod config;
+mod control;
+#[cfg(feature = "web")]
+mod dashboard;
+use crate::control::{ControlCommand, ControlRequest, TuningCommand, TuningRequest};
+use clap::Parser;
+mod parity;
+use crate::parity::{SessionEvent, SessionRecorder};
+mod portfolio;
+#[cfg(feature = "redis")]
+mod pubsub;
+#[cfg(feature = "web")]
+mod push;
+mod recorder;
+mod reload;
+mod report;
+mod retention;
+mod safe_mode;
+mod secrets;
+use crate::recorder::{SharedRecorder, StreamKind};
+mod snapshot;
+mod storage;
+mod streams;
+use crate::snapshot::SharedStrategySnapshot;
+use crate::streams::StreamRegistry;
+use crate::user_stream::AccountEvent;
+mod strategy;
+mod supervisor;
+use crate::strategy::Strategy;
+mod ta;
+mod telemetry;
+mod user_stream;
+#[cfg(feature = "web")]
+mod webhook;
+use binance_spot_connector_rust::http::error::{BinanceApiError, ClientError, HttpError};
+use binance_spot_connector_rust::hyper::Error as ConnectorError;
+use binance_spot_connector_rust::market;
+use binance_spot_connector_rust::market::time;
+use binance_spot_connector_rust::market_stream::ticker;
+use binance_spot_connector_rust::market_stream::ticker::TickerStream;
+use binance_spot_connector_rust::trade;
+use binance_spot_connector_rust::trade::order::NewOrderResponseType;
+use binance_spot_connector_rust::trade::order::Side;
+use binance_spot_connector_rust::trade::order::TimeInForce as BinanceTimeInForce;
+use binance_spot_connector_rust::{
+    http::Credentials,
+    hyper::{BinanceHttpClient, Error},
+    market_stream::kline::KlineStream,
+    tokio_tungstenite::BinanceWebSocketClient,
+    wallet::{self, account_status},
+};
+
+use futures_util::StreamExt;
+use hyper::client::connect::Connect;
+use hyper::client::HttpConnector;
+use hyper_tls::HttpsConnector;
+use rust_decimal::Decimal;
+use tokio::sync::{broadcast, mpsc};
+
+// Order lookups answer this when no such order was placed
+const ORDER_DOES_NOT_EXIST: i16 = -2013;
+const BINANCE_WS_URL: &str = "wss://stream.binance.com:9443/stream";
+const BINANCE_TESTNET_REST_URL: &str = "https://testnet.binance.vision";
+const BINANCE_TESTNET_WS_URL: &str = "wss://testnet.binance.vision/stream";
+
+pub struct BinanceExchangeClient {
+    connected: bool,
+    client: BinanceHttpClient<HttpsConnector<HttpConnector>>,
+    symbol: String,
+    // Filled from exchangeInfo on connect
+    rules: HashMap<String, SymbolRules>,
+}
+impl BinanceExchangeClient {
+    pub fn new(credentials: Credentials) -> Self {
+        Self::with_client(BinanceHttpClient::default().credentials(credentials))
+    }
+    // Spot testnet; its keys are issued separately from production ones
+    pub fn new_testnet(credentials: Credentials) -> Self {
+        Self::with_client(
+            BinanceHttpClient::with_url(BINANCE_TESTNET_REST_URL).credentials(credentials),
+        )
+    }
+    fn with_client(client: BinanceHttpClient<HttpsConnector<HttpConnector>>) -> Self {
+        BinanceExchangeClient {
+            connected: false,
+            symbol: String::new(),
+            client,
+            rules: HashMap::new(),
+        }
+    }
+    pub async fn set_symbol(&mut self, symbol: String) {
+        self.symbol = symbol;
+    }
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+    // Lot size, tick size and minimum notional of the bound symbol
+    pub async fn load_symbol_rules(&mut self) -> Result<(), TradingError> {
+        let data = self
+            .client
+            .send(market::exchange_info().symbol(&self.symbol))
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?
+            .into_body_str()
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+        let info: ExchangeInfo = serde_json::from_str(&data)?;
+        for symbol in &info.symbols {
+            let rules = symbol.to_symbol_rules()?;
+            log::info!("{} order rules: {:?}", symbol.symbol, rules);
+            self.rules.insert(symbol.symbol.clone(), rules);
+        }
+        Ok(())
+    }
+    // An order by exchange id, or by client order id when it isn't numeric
+    async fn query_order(
+        &self,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderQueryResponse, TradingError> {
+        let request = trade::get_order(symbol);
+        let request = match order_id.parse::<u64>() {
+            Ok(id) => request.order_id(id),
+            Err(_) => request.orig_client_order_id(order_id),
+        };
+        let data = self
+            .client
+            .send(request)
+            .await
+            .map_err(|e| match e {
+                ConnectorError::Send(_) | ConnectorError::Server(_) => {
+                    TradingError::NetworkError(format!("{:?}", e))
+                }
+                ConnectorError::Client(ClientError::Structured(HttpError {
+                    data: BinanceApiError { code, ref msg },
+                    ..
+                })) if code == ORDER_DOES_NOT_EXIST => {
+                    TradingError::UnknownOrder(format!("{}: {}", order_id, msg))
+                }
+                _ => TradingError::OrderError(format!("{:?}", e)),
+            })?
+            .into_body_str()
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+        Ok(serde_json::from_str(&data)?)
+    }
+    pub async fn account_status(&self) -> Result<String, Error> {
+        let data = self
+            .client
+            .send(wallet::account_status())
+            .await?
+            .into_body_str()
+            .await?;
+        log::info!("{}", data);
+        Ok(data)
+    }
+    pub async fn api_trading_status(&self) -> Result<String, Error> {
+        let data = self
+            .client
+            .send(wallet::api_trading_status())
+            .await?
+            .into_body_str()
+            .await?;
+        log::info!("{}", data);
+        Ok(data)
+    }
+    pub async fn get_klines(
+        &self,
+        timeframe: Interval,
+        window_size: usize,
+    ) -> Result<Vec<KlineResponse>, dtoError> {
+        let request =
+            market::klines(&self.symbol, timeframe.kline_interval()).limit(window_size as u32);
+        let response = self
+            .client
+            .send(request)
+            .await
+            .map_err(|e| dtoError::RequestError(format!("{:?}", e)))?;
+        let data = response
+            .into_body_str()
+            .await
+            .map_err(|e| dtoError::HttpError(format!("{:?}", e)))?;
+
+        parse_klines(&data)
+    }
+    // Places the order and parses the exchange's FULL reply
+    pub async fn place_order(&self, order: &Order) -> Result<NewOrderResponse, TradingError> {
+        let side = match order.side {
+            OrderSide::Buy => Side::Buy,
+            OrderSide::Sell => Side::Sell,
+        };
+        let request = match order.order_type {
+            OrderType::Market => trade::new_order(&order.symbol, side, "MARKET"),
+            // Rejected by the exchange if it would match at once
+            OrderType::Limit(price) if order.post_only => {
+                trade::new_order(&order.symbol, side, "LIMIT_MAKER").price(price)
+            }
+            OrderType::Limit(price) => trade::new_order(&order.symbol, side, "LIMIT")
+                .price(price)
+                .time_in_force(match order.time_in_force {
+                    TimeInForce::Gtc => BinanceTimeInForce::Gtc,
+                    TimeInForce::Ioc => BinanceTimeInForce::Ioc,
+                    TimeInForce::Fok => BinanceTimeInForce::Fok,
+                }),
+            OrderType::Stop(price) => {
+                trade::new_order(&order.symbol, side, "STOP_LOSS").stop_price(price)
+            }
+            OrderType::TrailingStop { .. } => {
+                return Err(TradingError::OrderError(
+                    "Binance spot trailing stops are kept by the executor".into(),
+                ))
+            }
+        }
+        .quantity(order.quantity)
+        .new_order_resp_type(NewOrderResponseType::Full);
+        let request = match &order.client_order_id {
+            Some(id) => request.new_client_order_id(id),
+            None => request,
+        };
+        let data = self
+            .client
+            .send(request)
+            .await
+            .map_err(|e| match e {
+                // Whether the order got there is unknown
+                ConnectorError::Send(_) | ConnectorError::Server(_) => {
+                    TradingError::NetworkError(format!("{:?}", e))
+                }
+                _ => TradingError::OrderError(format!("{:?}", e)),
+            })?
+            .into_body_str()
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+        log::info!("{}", data);
+        Ok(serde_json::from_str(&data)?)
+    }
+}
+// Ctrl-C, or SIGTERM from a container runtime
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(e) => {
+                log::error!("Cannot listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+// Enough recent closes to fill the engine, backfilling what the candle store lacks
+async fn warm_up_closes(
+    path: &str,
+    symbol: &str,
+    interval: Interval,
+) -> Result<Vec<f64>, dtoError> {
+    let store = storage::CandleStore::open(path)?;
+    let to = chrono::Utc::now();
+    let from = to - chrono::Duration::milliseconds(interval.millis() * engine::MAX_HISTORY as i64);
+    let history = backtest::data::cached_klines(&store, symbol, interval, from, to).await?;
+    Ok(history.closes())
+}
+
+// A closed kline and the merged market state at that moment, so the analysis
+// task never has to read the shared MarketData
+struct CandleUpdate {
+    // Milliseconds
+    end_time: i64,
+    data: MarketData,
+}
+
+// Applies a kline to `market_data`; the updated data, unless it didn't parse
+fn apply_kline(
+    kline: &Kline,
+    market_data: &Mutex<MarketData>,
+    streams: &StreamRegistry,
+) -> Option<MarketData> {
+    let prices = match kline.prices() {
+        Ok(prices) => prices,
+        Err(e) => {
+            streams.parse_error("kline", e.to_string());
+            return None;
+        }
+    };
+    streams.parsed("kline");
+    let mut data = market_data.lock().unwrap();
+    // Update market data
+    *data = MarketData {
+        symbol: kline.symbol.clone(),
+        open_price: prices.open,
+        close_price: prices.close,
+        high_price: prices.high,
+        low_price: prices.low,
+        ..*data
+    };
+    Some(data.clone())
+}
+
+async fn process_kline_data(
+    mut receiver: mpsc::Receiver<Kline>,
+    candles: mpsc::Sender<CandleUpdate>,
+    market_data: Arc<Mutex<MarketData>>,
+    events: EventBus,
+    streams: StreamRegistry,
+) {
+    while let Some(kline) = receiver.recv().await {
+        let Some(data) = apply_kline(&kline, &market_data, &streams) else {
+            continue;
+        };
+        events.publish_market(MarketEvent::from_kline(&kline));
+        // Strategies only see finished bars
+        if !kline.is_closed {
+            continue;
+        }
+        let update = CandleUpdate {
+            end_time: kline.end_time,
+            data,
+        };
+        if let Err(e) = candles.send(update).await {
+            log::error!("Failed to send candle update: {}", e);
+        }
+        // Log or do additional processing
+        // log::info!(
+        //     "Kline Update - Symbol: {}, Open: {}, Close: {}",
+        //     kline.symbol,
+        //     kline.open_price,
+        //     kline.close_price
+        // );
+    }
+}
+
+// Applies a ticker to `market_data`; false if it didn't parse
+fn apply_ticker(
+    ticker: &TickerData,
+    market_data: &Mutex<MarketData>,
+    streams: &StreamRegistry,
+) -> bool {
+    let last_price = match ticker.price() {
+        Ok(price) => price,
+        Err(e) => {
+            streams.parse_error("ticker", e.to_string());
+            return false;
+        }
+    };
+    streams.parsed("ticker");
+    let mut data = market_data.lock().unwrap();
+    // Update market data
+    *data = MarketData {
+        symbol: ticker.symbol.clone(),
+        last_price,
+        ..*data
+    };
+    if let Some((bid, ask)) = ticker.quote() {
+        data.bid_price = bid;
+        data.ask_price = ask;
+    }
+    true
+}
+
+// เช่นเดียวกันสำหรับ ticker data
+async fn process_ticker_data(
+    mut receiver: mpsc::Receiver<TickerData>,
+    market_data: Arc<Mutex<MarketData>>,
+    events: EventBus,
+    streams: StreamRegistry,
+) {
+    while let Some(ticker) = receiver.recv().await {
+        if apply_ticker(&ticker, &market_data, &streams) {
+            events.publish_market(MarketEvent::from_ticker(&ticker));
+        }
+
+        // Log or do additional processing
+        // log::info!(
+        //     "Ticker Update - Symbol: {}, Last: {}",
+        //     ticker.symbol,
+        //     ticker.last_price
+        // );
+    }
+}
+
+// A routed venue's streams, feeding its own analysis worker. Market events
+// and candles carry the venue-prefixed symbol, e.g. "kraken:XBTUSD", that its
+// orders go by.
+async fn process_venue_data(
+    venue: String,
+    mut klines: mpsc::Receiver<Kline>,
+    mut tickers: mpsc::Receiver<TickerData>,
+    candles: mpsc::Sender<CandleUpdate>,
+    market_data: Arc<Mutex<MarketData>>,
+    events: EventBus,
+    streams: StreamRegistry,
+) {
+    loop {
+        tokio::select! {
+            Some(mut kline) = klines.recv() => {
+                let Some(data) = apply_kline(&kline, &market_data, &streams) else {
+                    continue;
+                };
+                kline.symbol = format!("{}:{}", venue, kline.symbol);
+                events.publish_market(MarketEvent::from_kline(&kline));
+                // Strategies only see finished bars
+                if !kline.is_closed {
+                    continue;
+                }
+                let update = CandleUpdate {
+                    end_time: kline.end_time,
+                    data: MarketData {
+                        symbol: kline.symbol.clone(),
+                        ..data
+                    },
+                };
+                if let Err(e) = candles.send(update).await {
+                    log::error!("Failed to send candle update: {}", e);
+                }
+            }
+            Some(mut ticker) = tickers.recv() => {
+                if apply_ticker(&ticker, &market_data, &streams) {
+                    ticker.symbol = format!("{}:{}", venue, ticker.symbol);
+                    events.publish_market(MarketEvent::from_ticker(&ticker));
+                }
+            }
+            else => break,
+        }
+    }
+}
+fn stream_url(testnet: bool) -> &'static str {
+    if testnet {
+        BINANCE_TESTNET_WS_URL
+    } else {
+        BINANCE_WS_URL
+    }
+}
+pub async fn get_kline_data(
+    symbol: Symbol,
+    interval: Interval,
+    sender: mpsc::Sender<Kline>,
+    recorder: Option<SharedRecorder>,
+    health: SharedHealth,
+    streams: StreamRegistry,
+    testnet: bool,
+) {
+    let stream = format!("{}@kline_{}", symbol.stream_name(), interval);
+    let mut resubscribe = streams.register("kline", stream);
+    loop {
+        streams.connecting("kline");
+        // Establish connection
+        let (mut conn, _) = match BinanceWebSocketClient::connect_async(stream_url(testnet)).await {
+            Ok(conn) => conn,
+            Err(e) => {
+                streams.disconnected("kline", format!("{:?}", e));
+                return;
+            }
+        };
+        // Subscribe to streams
+        conn.subscribe(vec![&KlineStream::new(
+            symbol.as_str(),
+            interval.kline_interval(),
+        )
+        .into()])
+            .await;
+        streams.connected("kline");
+        // Read messages until the connection drops (Some) or a resubscribe (None)
+        let dropped = loop {
+            let message = tokio::select! {
+                message = conn.as_mut().next() => message,
+                Ok(()) = resubscribe.changed() => break None,
+            };
+            let message = match message {
+                Some(Ok(message)) => message,
+                Some(Err(e)) => break Some(format!("{:?}", e)),
+                None => break Some("closed by the exchange".to_string()),
+            };
+            let binary_data = message.into_data();
+            let data = match std::str::from_utf8(&binary_data) {
+                Ok(data) => data,
+                Err(e) => {
+                    streams.parse_error("kline", format!("{:?}", e));
+                    continue;
+                }
+            };
+            recorder::record(&recorder, StreamKind::Kline, data);
+            health::mark_message(&health, "kline");
+            streams.message("kline");
+            match parse_kline_update(data) {
+                Ok(kline) => {
+                    if let Err(e) = sender.send(kline).await {
+                        log::error!("Failed to send kline data: {}", e);
+                    }
+                }
+                Err(e) => {
+                    // Numeric frames are subscription acknowledgements
+                    if data.trim().parse::<i64>().is_err() {
+                        streams.parse_error("kline", format!("{} raw data: {}", e, data));
+                    }
+                }
+            }
+        };
+        // Disconnect
+        if let Err(e) = conn.close().await {
+            log::warn!("Failed to close kline stream: {:?}", e);
+        }
+        if let Some(reason) = dropped {
+            // The supervisor restarts the task
+            streams.disconnected("kline", reason);
+            return;
+        }
+        log::info!("Resubscribing kline stream");
+    }
+}
+pub async fn get_ticker_data(
+    symbol: Symbol,
+    sender: mpsc::Sender<TickerData>,
+    recorder: Option<SharedRecorder>,
+    health: SharedHealth,
+    streams: StreamRegistry,
+    testnet: bool,
+) {
+    let stream = format!("{}@ticker", symbol.stream_name());
+    let mut resubscribe = streams.register("ticker", stream);
+    loop {
+        streams.connecting("ticker");
+        // Establish connection
+        let (mut conn, _) = match BinanceWebSocketClient::connect_async(stream_url(testnet)).await {
+            Ok(conn) => conn,
+            Err(e) => {
+                streams.disconnected("ticker", format!("{:?}", e));
+                return;
+            }
+        };
+        // Subscribe to streams
+        conn.subscribe(vec![&TickerStream::from_symbol(symbol.as_str()).into()])
+            .await;
+        streams.connected("ticker");
+        // Read messages until the connection drops (Some) or a resubscribe (None)
+        let dropped = loop {
+            let message = tokio::select! {
+                message = conn.as_mut().next() => message,
+                Ok(()) = resubscribe.changed() => break None,
+            };
+            let message = match message {
+                Some(Ok(message)) => message,
+                Some(Err(e)) => break Some(format!("{:?}", e)),
+                None => break Some("closed by the exchange".to_string()),
+            };
+            let binary_data = message.into_data();
+            let data = match std::str::from_utf8(&binary_data) {
+                Ok(data) => data,
+                Err(e) => {
+                    streams.parse_error("ticker", format!("{:?}", e));
+                    continue;
+                }
+            };
+            recorder::record(&recorder, StreamKind::Ticker, data);
+            health::mark_message(&health, "ticker");
+            streams.message("ticker");
+            match parse_ticker_update(data) {
+                Ok(ticker) => {
+                    if let Err(e) = sender.send(ticker).await {
+                        log::error!("Failed to send ticker data: {}", e);
+                    }
+                }
+                Err(e) => {
+                    // Numeric frames are subscription acknowledgements
+                    if data.trim().parse::<i64>().is_err() {
+                        streams.parse_error("ticker", format!("{} raw data: {}", e, data));
+                    }
+                }
+            }
+        };
+        // Disconnect
+        if let Err(e) = conn.close().await {
+            log::warn!("Failed to close ticker stream: {:?}", e);
+        }
+        if let Some(reason) = dropped {
+            // The supervisor restarts the task
+            streams.disconnected("ticker", reason);
+            return;
+        }
+        log::info!("Resubscribing ticker stream");
+    }
+}
+pub async fn update_prices(data: Arc<Mutex<VecDeque<f64>>>, prices: f64) {
+    let mut data = data.lock().unwrap();
+    data.push_back(prices);
+    data.pop_front();
+}
+// Owns the signal engine; everything reaches it as a message, parameter
+// changes included, so nothing here is shared or locked. One runs per traded
+// symbol, woken by that symbol's closed candles only.
+async fn analyze_price_data(
+    signal_sender: mpsc::Sender<TradingSignal>,
+    mut candles: mpsc::Receiver<CandleUpdate>,
+    history_data: Arc<Mutex<VecDeque<f64>>>,
+    mut engine: SignalEngine<Box<dyn Strategy>>,
+    mut recorder: Option<SessionRecorder>,
+    strategy_snapshot: SharedStrategySnapshot,
+    event_journal: Option<SharedJournal>,
+    health: SharedHealth,
+    mut tuning: mpsc::UnboundedReceiver<TuningRequest>,
+) {
+    // Close time of the candle last analysed; a reconnect can repeat a closed kline
+    let mut last_close_time = 0;
+    loop {
+        let CandleUpdate {
+            end_time: current_timestamp_closed,
+            data,
+        } = tokio::select! {
+            Some(request) = tuning.recv() => {
+                let result = match request.command {
+                    TuningCommand::List => Ok(()),
+                    TuningCommand::Update { name, value } => {
+                        log::warn!("Strategy parameter {} set to {:?}", name, value);
+                        engine.update_parameter(&name, value)
+                    }
+                };
+                *strategy_snapshot.lock().unwrap() = Some(engine.snapshot());
+                let _ = request
+                    .reply
+                    .send(result.map(|()| engine.strategy().parameters()));
+                continue;
+            }
+            update = candles.recv() => match update {
+                Some(update) => update,
+                None => break,
+            },
+        };
+        health::heartbeat(&health, "analysis");
+        if current_timestamp_closed > last_close_time {
+            last_close_time = current_timestamp_closed;
+            // Indicators run on f64
+            let close = from_decimal(data.close_price);
+            update_prices(history_data.clone(), close).await;
+            // Same engine the backtester runs, so both see identical signals
+            let close_time = current_timestamp_closed / 1000;
+            let signal = engine.on_close(close_time, close);
+            health::mark_candle(&health, &data.symbol, current_timestamp_closed);
+            *strategy_snapshot.lock().unwrap() = Some(engine.snapshot());
+            if let Some(recorder) = recorder.as_mut() {
+                let event = SessionEvent {
+                    close_time,
+                    close,
+                    signal: signal.clone(),
+                };
+                if let Err(e) = recorder.record(&event) {
+                    log::error!("Failed to record session event: {}", e);
+                }
+            }
+            if signal.action != TradeAction::Hold {
+                journal::record(
+                    &event_journal,
+                    JournalEvent::MarketSnapshot {
+                        symbol: data.symbol.clone(),
+                        close_time,
+                        open: data.open_price,
+                        high: data.high_price,
+                        low: data.low_price,
+                        close: data.close_price,
+                        last_price: data.last_price,
+                    },
+                );
+                journal::record(
+                    &event_journal,
+                    JournalEvent::Signal {
+                        signal: signal.clone(),
+                    },
+                );
+                if let Err(e) = signal_sender.send(signal).await {
+                    log::error!("Failed to send trading signal: {}", e);
+                }
+            }
+        }
+    }
+}
+
+// Process trading signals
+async fn process_trading_signals(
+    mut receiver: mpsc::Receiver<TradingSignal>,
+    mut control: mpsc::UnboundedReceiver<ControlRequest>,
+    mut executor: TradeExecutor<ExchangeRouter>,
+    // By the symbol positions go by, venue-prefixed for a routed venue's
+    market_data: HashMap<String, Arc<Mutex<MarketData>>>,
+    mut account_events: broadcast::Receiver<AccountEvent>,
+    notifiers: Vec<NotifySender>,
+    health: SharedHealth,
+) {
+    let mut expiry_check = tokio::time::interval(Duration::from_secs(5));
+    // Prices are polled every second, so stops can't trail any finer
+    let mut trailing_check = tokio::time::interval(Duration::from_secs(1));
+    let mut slice_check = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        tokio::select! {
+            signal = receiver.recv() => {
+                let signal = match signal {
+                    Some(signal) => signal,
+                    None => break,
+                };
+                execute_signal(&mut executor, &signal, &notifiers).await;
+            }
+            Some(request) = control.recv() => {
+                let shutdown = matches!(request.command, ControlCommand::Shutdown { .. });
+                control::apply(&mut executor, request, &notifiers).await;
+                if shutdown {
+                    break;
+                }
+            }
+            event = account_events.recv() => match event {
+                Ok(event) => executor.on_account_event(&event).await,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    log::warn!("Missed {} account updates", missed);
+                }
+                // The bot keeps the sender for as long as this task runs
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = expiry_check.tick() => {
+                health::heartbeat(&health, "signals");
+                for signal in executor.expire_orders().await {
+                    log::info!("Re-signalling {} after order expiry", signal.symbol);
+                    execute_signal(&mut executor, &signal, &notifiers).await;
+                }
+            }
+            _ = trailing_check.tick() => {
+                // Copy out first so the std mutex is never held across an await;
+                // each venue's positions are marked from its own stream
+                let marks: Vec<(String, Decimal)> = market_data
+                    .iter()
+                    .map(|(symbol, data)| (symbol.clone(), data.lock().unwrap().last_price))
+                    .collect();
+                for (symbol, price) in marks {
+                    if price > Decimal::ZERO {
+                        executor.mark_price(&symbol, price).await;
+                    }
+                }
+                executor.trail_stops().await;
+                executor.check_brackets().await;
+            }
+            _ = slice_check.tick() => executor.work_parent_orders().await,
+        }
+    }
+}
+
+async fn execute_signal(
+    executor: &mut TradeExecutor<ExchangeRouter>,
+    signal: &TradingSignal,
+    notifiers: &[NotifySender],
+) {
+    match signal.action {
+        TradeAction::Buy => {
+            log::info!(
+                "Buy Signal - Symbol: {}, Price: {}",
+                signal.symbol,
+                signal.price
+            );
+            if let Err(e) = executor.handle_signal(signal).await {
+                log::error!("Failed to execute buy signal: {}", e);
+                let message = format!("Buy {} failed ({}): {}", signal.symbol, e.code(), e);
+                safe_mode::report_error(message.clone());
+                notify::broadcast(notifiers, Notification::Error { message });
+            }
+        }
+        TradeAction::Sell => {
+            log::info!(
+                "Sell Signal - Symbol: {}, Price: {}",
+                signal.symbol,
+                signal.price
+            );
+            if let Err(e) = executor.handle_signal(signal).await {
+                log::error!("Failed to execute sell signal: {}", e);
+                let message = format!("Sell {} failed ({}): {}", signal.symbol, e.code(), e);
+                safe_mode::report_error(message.clone());
+                notify::broadcast(notifiers, Notification::Error { message });
+            }
+        }
+        TradeAction::Close => {
+            log::info!(
+                "Close Signal - Symbol: {}, Price: {}",
+                signal.symbol,
+                signal.price
+            );
+            if let Err(e) = executor.handle_signal(signal).await {
+                log::error!("Failed to execute close signal: {}", e);
+                let message = format!("Close {} failed ({}): {}", signal.symbol, e.code(), e);
+                safe_mode::report_error(message.clone());
+                notify::broadcast(notifiers, Notification::Error { message });
+            }
+        }
+        TradeAction::Hold => {
+            log::debug!(
+                "Hold Position - Symbol: {}, Price: {}",
+                signal.symbol,
+                signal.price
+            );
+        }
+    }
+}
+
+impl ExchangeClient for BinanceExchangeClient {
+    async fn connect(&mut self) -> Result<(), TradingError> {
+        match self.account_status().await {
+            Ok(_) => (),
+            Err(e) => {
+                log::error!("Failed to connect: {:?}", e);
+                return Err(TradingError::ConnectionError("Failed to connect".into()));
+            }
+        }
+
+        // Orders still go out without them, with the sizing's own truncation
+        if let Err(e) = self.load_symbol_rules().await {
+            log::warn!("Failed to load order rules for {}: {}", self.symbol, e);
+        }
+
+        match self.api_trading_status().await {
+            Ok(_) => {
+                self.connected = true;
+                log::info!("Connected to Binance");
+                Ok(())
+            }
+            Err(e) => {
+                log::error!("Failed to connect: {:?}", e);
+                Err(TradingError::ConnectionError("Failed to connect".into()))
+            }
+        }
+    }
+
+    async fn disconnect(&mut self) -> Result<(), TradingError> {
+        self.connected = false;
+        Ok(())
+    }
+
+    // Free balance of the traded symbol's quote asset, what new entries spend
+    async fn get_balance(&self) -> Result<Decimal, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let data = self
+            .client
+            .send(trade::account())
+            .await
+            .map_err(|e| TradingError::OrderError(format!("{:?}", e)))?
+            .into_body_str()
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+        let account: Account = serde_json::from_str(&data)?;
+        Ok(account.free(split_symbol(&self.symbol).1)?)
+    }
+
+    async fn send_order(&mut self, order: &Order) -> Result<OrderResponse, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        Ok(self.place_order(order).await?.to_order_response()?)
+    }
+
+    async fn cancel_order(&mut self, symbol: &str, order_id: &str) -> Result<(), TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        // Binance ids are numeric; anything else is treated as a client order id
+        let request = trade::cancel_order(symbol);
+        let request = match order_id.parse::<u64>() {
+            Ok(id) => request.order_id(id),
+            Err(_) => request.orig_client_order_id(order_id),
+        };
+        let data = self
+            .client
+            .send(request)
+            .await
+            .map_err(|e| TradingError::OrderError(format!("{:?}", e)))?
+            .into_body_str()
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+        log::info!("{}", data);
+        Ok(())
+    }
+
+    async fn get_open_orders(&self, symbol: &str) -> Result<Vec<OrderResponse>, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let data = self
+            .client
+            .send(trade::open_orders().symbol(symbol))
+            .await
+            .map_err(|e| TradingError::OrderError(format!("{:?}", e)))?
+            .into_body_str()
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+        let orders: Vec<OrderQueryResponse> = serde_json::from_str(&data)?;
+        orders
+            .iter()
+            .map(|order| order.to_order_response().map_err(TradingError::from))
+            .collect()
+    }
+
+    async fn get_order_status(
+        &self,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        Ok(self
+            .query_order(symbol, order_id)
+            .await?
+            .to_order_response()?)
+    }
+
+    // OCO brackets are placed as a list; the leg not tracked is found through it
+    async fn bracket_legs(
+        &self,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<Vec<OrderResponse>, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let order = self.query_order(symbol, order_id).await?;
+        let Some(list_id) = order.order_list_id.filter(|id| *id >= 0) else {
+            return Ok(vec![order.to_order_response()?]);
+        };
+        let data = self
+            .client
+            .send(trade::get_oco_order().order_list_id(list_id as u64))
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?
+            .into_body_str()
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+        let list: OcoOrderResponse = serde_json::from_str(&data)?;
+        let mut legs = vec![order.to_order_response()?];
+        for leg in list
+            .orders
+            .iter()
+            .filter(|leg| leg.order_id != order.order_id)
+        {
+            let leg = self.query_order(symbol, &leg.order_id.to_string()).await?;
+            legs.push(leg.to_order_response()?);
+        }
+        Ok(legs)
+    }
+
+    fn symbol_rules(&self, symbol: &str) -> Option<SymbolRules> {
+        self.rules.get(symbol).cloned()
+    }
+
+    async fn place_oco_order(
+        &mut self,
+        symbol: &str,
+        side: OrderSide,
+        quantity: Decimal,
+        bracket: &Bracket,
+    ) -> Result<OrderResponse, TradingError> {
+        if !self.connected {
+            return Err(TradingError::ConnectionError("Not connected".into()));
+        }
+        let side = match side {
+            OrderSide::Buy => Side::Buy,
+            OrderSide::Sell => Side::Sell,
+        };
+
+        // Binance cancels the remaining leg itself once either one executes
+        let request = trade::new_oco_order(
+            symbol,
+            side,
+            quantity,
+            bracket.take_profit,
+            bracket.stop_loss,
+        )
+        .stop_limit_price(bracket.stop_loss)
+        .stop_limit_time_in_force(BinanceTimeInForce::Gtc);
+        let data = self
+            .client
+            .send(request)
+            .await
+            .map_err(|e| TradingError::OrderError(format!("{:?}", e)))?
+            .into_body_str()
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+        log::info!("{}", data);
+
+        let response: OcoOrderResponse =
+            serde_json::from_str(&data).map_err(|e| TradingError::DataError(e.to_string()))?;
+        let order_id = response
+            .orders
+            .first()
+            .map(|leg| leg.order_id.to_string())
+            .ok_or_else(|| TradingError::OrderError("OCO response has no orders".into()))?;
+        Ok(OrderResponse {
+            order_id,
+            status: OrderStatus::Pending,
+            fills: Vec::new(),
+        })
+    }
+}
+// The binary's whole run, from parsing the command line to shutdown
+pub async fn run() {
+    let cli = cli::Cli::parse();
+    // Loaded before logging starts, since the configuration sets up the log file
+    let config_source = cli.config_source();
+    let profile = config_source
+        .as_ref()
+        .map_err(|e| TradingError::InvalidParameter(format!("{:?}", e)))
+        .and_then(|source| source.load());
+    logging::init(
+        &profile
+            .as_ref()
+            .map(|profile| profile.logging.clone())
+            .unwrap_or_default(),
+    );
+    match cli.command {
+        Some(cli::Command::Backtest(args)) => {
+            if let Err(e) = cli::run_backtest(args).await {
+                log::error!("Backtest failed: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(cli::Command::Export(args)) => {
+            if let Err(e) = cli::run_export(args).await {
+                log::error!("Export failed: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(cli::Command::Credentials(args)) => {
+            if let Err(e) = cli::run_credentials(args) {
+                log::error!("Storing credentials failed: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(cli::Command::VerifyAudit { path }) => {
+            if let Err(e) = cli::run_verify_audit(&path) {
+                log::error!("Audit log {} failed verification: {:?}", path.display(), e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(cli::Command::Balances) => {
+            if let Err(e) = cli::run_balances().await {
+                log::error!("Fetching balances failed: {:?}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(cli::Command::Orders(args)) => {
+            if let Err(e) = cli::run_orders(args).await {
+                log::error!("Order command failed: {:?}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(cli::Command::Positions) => {
+            if let Err(e) = cli::run_positions(&cli.snapshot) {
+                log::error!("Cannot read positions: {:?}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(cli::Command::DownloadData(args)) => {
+            if let Err(e) = cli::run_download_data(args).await {
+                log::error!("Download failed: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(cli::Command::ValidateConfig) => {
+            let result = match &profile {
+                Ok(profile) => cli::run_validate_config(profile),
+                Err(e) => Err(TradingError::InvalidParameter(format!("{:?}", e))),
+            };
+            if let Err(e) = result {
+                log::error!("Invalid configuration: {:?}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(cli::Command::Pause(args)) => {
+            if let Err(e) = cli::run_remote(args, "pause").await {
+                log::error!("Pause failed: {:?}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(cli::Command::Resume(args)) => {
+            if let Err(e) = cli::run_remote(args, "resume").await {
+                log::error!("Resume failed: {:?}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(cli::Command::Replay(args)) => {
+            if let Err(e) = cli::run_replay(args) {
+                log::error!("Replay failed: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(cli::Command::BenchParse(args)) => {
+            if let Err(e) = cli::run_bench_parse(args) {
+                log::error!("Parser benchmark failed: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        #[cfg(feature = "mock")]
+        Some(cli::Command::Simulate(args)) => {
+            if let Err(e) = cli::run_simulate(args).await {
+                log::error!("Simulation failed: {:?}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(cli::Command::Parity(args)) => {
+            if let Err(e) = cli::run_parity(args).await {
+                log::error!("Parity check failed: {:?}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        #[cfg(feature = "web")]
+        Some(cli::Command::Openapi) => {
+            println!("{}", api::openapi_json());
+            return;
+        }
+        Some(cli::Command::Run) | None => {}
+    }
+    let profile = match profile {
+        Ok(profile) => profile,
+        Err(e) => {
+            log::error!("Cannot load configuration: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+    profile.apply_environment();
+    let resumed = if cli.resume {
+        match snapshot::load(&cli.snapshot) {
+            Ok(snapshot) => Some(snapshot),
+            Err(e) => {
+                log::error!("Cannot resume: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    if let Some(name) = &cli.save_profile {
+        let mut effective = profile.clone();
+        if let Some(snapshot) = &resumed {
+            effective.risk = snapshot.risk.clone();
+        }
+        let saved = profile
+            .build_strategy()
+            .map(|strategy| effective.with_strategy_parameters(strategy.as_ref()))
+            .and_then(|effective| effective.save(&cli.profile_dir, name));
+        match saved {
+            Ok(path) => log::info!("Saved profile {} to {}", name, path.display()),
+            Err(e) => log::error!("Failed to save profile {}: {}", name, e),
+        }
+    }
+    let bot = bot::TradingBotBuilder::new(profile)
+        .config_source(config_source.unwrap_or_default())
+        .snapshot_path(cli.snapshot)
+        .resume(resumed)
+        .build()
+        .await;
+    match bot {
+        Ok(bot) => bot.run().await,
+        Err(e) => {
+            log::error!("Cannot start trading: {:?}", e);
+            std::process::exit(1);
+        }
+    }
+    // client.get_market_data().await;
+
+    // let response = client.send_order(&order).?await.unwrap();;
+    // println!("Order response: {:?}", response);
+
+    // let balance = client.get_balance().?await.unwrap();;
+    // println!("Balance: {}", balance);
+}
+// #[cfg(test)]
+// mod tests {
+//     use super::*;
+
+//     #[test]
+//     fn test_mock_exchange() {
+//         let mut client = BinanceExchangeClient :new();
+//         client.connect().unwrap();
+
+//         let order = Order {
+//             symbol: "BTC/USD".to_string(),
+//             quantity: 1.0,
+//             order_type: OrderType::Market,
+//             side: OrderSide::Buy,
+//         };
+
+//         let response = client.send_order(&order).unwrap();
+//         assert_eq!(response.status, OrderStatus::Filled);
+
+//         let balance = client.get_balance().unwrap();
+//         assert!(balance < 100000.0);
+//     }
+// }
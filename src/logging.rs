@@ -0,0 +1,114 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use env_logger::{Builder, Target};
+use file_rotate::compression::Compression;
+use file_rotate::suffix::{AppendCount, AppendTimestamp, FileLimit};
+use file_rotate::{ContentLimit, FileRotate, TimeFrequency};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rotation {
+    // Once the file reaches `max_size_mb`
+    Size,
+    Hourly,
+    Daily,
+}
+
+/// Optional log file written alongside stdout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    // stdout only when unset
+    pub file: Option<PathBuf>,
+    pub rotation: Rotation,
+    pub max_size_mb: u64,
+    // Rotated files kept before the oldest is deleted
+    pub keep: usize,
+    // Gzip rotated files
+    pub compress: bool,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        LogSettings {
+            file: None,
+            rotation: Rotation::Daily,
+            max_size_mb: 100,
+            keep: 14,
+            compress: true,
+        }
+    }
+}
+
+// Copies every line to stdout and the log file
+struct Tee {
+    file: Box<dyn Write + Send>,
+}
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::stdout().write_all(buf)?;
+        // A full disk shouldn't take stdout logging down with it
+        if let Err(e) = self.file.write_all(buf) {
+            eprintln!("Failed to write log file: {:?}", e);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()?;
+        let _ = self.file.flush();
+        Ok(())
+    }
+}
+
+fn open_file(path: PathBuf, settings: &LogSettings) -> Box<dyn Write + Send> {
+    let compression = if settings.compress {
+        Compression::OnRotate(0)
+    } else {
+        Compression::None
+    };
+    match settings.rotation {
+        Rotation::Size => Box::new(FileRotate::new(
+            path,
+            AppendCount::new(settings.keep),
+            ContentLimit::Bytes(settings.max_size_mb.max(1) as usize * 1024 * 1024),
+            compression,
+            #[cfg(unix)]
+            None,
+        )),
+        Rotation::Hourly | Rotation::Daily => {
+            let frequency = if settings.rotation == Rotation::Hourly {
+                TimeFrequency::Hourly
+            } else {
+                TimeFrequency::Daily
+            };
+            Box::new(FileRotate::new(
+                path,
+                AppendTimestamp::default(FileLimit::MaxFiles(settings.keep)),
+                ContentLimit::Time(frequency),
+                compression,
+                #[cfg(unix)]
+                None,
+            ))
+        }
+    }
+}
+
+pub fn init(settings: &LogSettings) {
+    let mut builder = Builder::from_default_env();
+    builder.filter(None, log::LevelFilter::Debug);
+    if let Some(path) = &settings.file {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                eprintln!("Cannot create log directory {}: {:?}", dir.display(), e);
+            }
+        }
+        builder.target(Target::Pipe(Box::new(Tee {
+            file: open_file(path.clone(), settings),
+        })));
+    }
+    builder.init();
+}
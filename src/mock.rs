@@ -0,0 +1,507 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::domain::*;
+use crate::dto::{parse_websocket_message, parse_websocket_message_ticker, Error, KlineResponse};
+use crate::recorder::{self, StreamKind};
+
+const DEFAULT_FEE_RATE: Decimal = dec!(0.001);
+
+/// What the mock does with the next order it receives
+#[derive(Debug)]
+// Scripted by tests; a plain run fills every order
+#[cfg_attr(not(test), allow(dead_code))]
+pub enum ScriptedFill {
+    // Fill this fraction (0..=1) of the order at the current price
+    Fraction(Decimal),
+    // Fill completely at this price instead of the current one
+    AtPrice(Decimal),
+    // Acknowledge without filling, like a resting order
+    Rest,
+    // Refuse the order
+    Reject(String),
+    // Fail the call itself, e.g. with a NetworkError
+    Error(TradingError),
+    // Fail the next order sent, letting other calls (like a cancel) through
+    FailOrder(TradingError),
+    // Fill the order but fail the call, as if the reply was lost on the way back
+    Lost(TradingError),
+}
+
+#[derive(Debug)]
+struct MockState {
+    connected: bool,
+    // Quote balance
+    balance: Decimal,
+    fee_rate: Decimal,
+    latency: Duration,
+    // Sells of what isn't held are accepted, like on margin
+    shorts: bool,
+    prices: HashMap<String, Decimal>,
+    script: VecDeque<ScriptedFill>,
+    // Every order received, in order
+    orders: Vec<Order>,
+    open_orders: HashMap<String, String>,
+    // Resting OCO brackets by order id: the closing order and its legs
+    brackets: HashMap<String, (Order, Bracket)>,
+    // Order id of each bracket's take-profit leg; the id returned for the
+    // bracket is its stop leg, like the first order of a Binance OCO list
+    take_profit_legs: HashMap<String, String>,
+    // Latest state of every order acknowledged, for status queries
+    responses: HashMap<String, OrderResponse>,
+    // Client order id -> order id, so orders can be looked up by either
+    client_ids: HashMap<String, String>,
+    next_id: u64,
+}
+
+/// In-memory `ExchangeClient` that fills market orders at the price it was
+/// last given, for running the executor without network access. Clones share
+/// the same state, so a clone kept outside the executor can feed prices and
+/// script what the next orders do.
+#[derive(Debug, Clone)]
+pub struct MockExchange {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockExchange {
+    pub fn new(balance: Decimal) -> Self {
+        MockExchange {
+            state: Arc::new(Mutex::new(MockState {
+                connected: false,
+                balance,
+                fee_rate: DEFAULT_FEE_RATE,
+                latency: Duration::ZERO,
+                shorts: false,
+                prices: HashMap::new(),
+                script: VecDeque::new(),
+                orders: Vec::new(),
+                open_orders: HashMap::new(),
+                brackets: HashMap::new(),
+                take_profit_legs: HashMap::new(),
+                responses: HashMap::new(),
+                client_ids: HashMap::new(),
+                next_id: 1,
+            })),
+        }
+    }
+
+    // Commission per fill as a fraction of its value, charged in the quote asset
+    pub fn with_fee_rate(self, fee_rate: Decimal) -> Self {
+        self.state.lock().unwrap().fee_rate = fee_rate;
+        self
+    }
+
+    // Delay added to every call, to exercise latency tracking and timeouts
+    pub fn with_latency(self, latency: Duration) -> Self {
+        self.state.lock().unwrap().latency = latency;
+        self
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn with_shorts(self) -> Self {
+        self.state.lock().unwrap().shorts = true;
+        self
+    }
+
+    // Resting brackets the price reaches fill at that leg's price
+    pub fn set_price(&self, symbol: &str, price: Decimal) {
+        let mut state = self.state.lock().unwrap();
+        state.prices.insert(symbol.to_string(), price);
+        Self::fill_brackets(&mut state);
+    }
+
+    // Applied to the next order; orders without a script fill completely
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn push_fill(&self, fill: ScriptedFill) {
+        self.state.lock().unwrap().script.push_back(fill);
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn fail_next(&self, error: TradingError) {
+        self.push_fill(ScriptedFill::Error(error));
+    }
+
+    pub fn orders(&self) -> Vec<Order> {
+        self.state.lock().unwrap().orders.clone()
+    }
+
+    pub fn balance(&self) -> Decimal {
+        self.state.lock().unwrap().balance
+    }
+
+    async fn delay(&self) {
+        let latency = self.state.lock().unwrap().latency;
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+    }
+
+    fn connected(state: &MockState) -> Result<(), TradingError> {
+        if state.connected {
+            Ok(())
+        } else {
+            Err(TradingError::ConnectionError("Not connected".into()))
+        }
+    }
+
+    fn fill_brackets(state: &mut MockState) {
+        let reached: Vec<(String, Decimal)> = state
+            .brackets
+            .iter()
+            .filter_map(|(order_id, (order, bracket))| {
+                let price = *state.prices.get(&order.symbol)?;
+                // The bracket's order closes the position, opened on the other side
+                let leg = bracket.reached_leg(price, &order.side.opposite())?;
+                Some((order_id.clone(), leg))
+            })
+            .collect();
+        for (order_id, price) in reached {
+            let Some((order, bracket)) = state.brackets.remove(&order_id) else {
+                continue;
+            };
+            state.open_orders.remove(&order_id);
+            // The leg that executed fills, the other expires with it
+            let take_profit = state.take_profit_legs[&order_id].clone();
+            let (order_id, expired) = if price == bracket.take_profit {
+                (take_profit, order_id)
+            } else {
+                (order_id, take_profit)
+            };
+            if let Some(response) = state.responses.get_mut(&expired) {
+                response.status = OrderStatus::Canceled;
+            }
+            let value = price * order.quantity;
+            let commission = value * state.fee_rate;
+            state.balance += match order.side {
+                OrderSide::Buy => -value - commission,
+                OrderSide::Sell => value - commission,
+            };
+            let response = OrderResponse {
+                order_id: order_id.clone(),
+                status: OrderStatus::Filled,
+                fills: vec![Fill {
+                    price,
+                    quantity: order.quantity,
+                    commission,
+                    commission_asset: "QUOTE".to_string(),
+                }],
+            };
+            state.responses.insert(order_id, response);
+        }
+    }
+
+    // Calls other than send_order only consume scripted errors
+    fn scripted_error(state: &mut MockState) -> Result<(), TradingError> {
+        if matches!(state.script.front(), Some(ScriptedFill::Error(_))) {
+            if let Some(ScriptedFill::Error(e)) = state.script.pop_front() {
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ExchangeClient for MockExchange {
+    async fn connect(&mut self) -> Result<(), TradingError> {
+        self.delay().await;
+        self.state.lock().unwrap().connected = true;
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<(), TradingError> {
+        self.state.lock().unwrap().connected = false;
+        Ok(())
+    }
+
+    async fn get_balance(&self) -> Result<Decimal, TradingError> {
+        self.delay().await;
+        let state = self.state.lock().unwrap();
+        Self::connected(&state)?;
+        Ok(state.balance)
+    }
+
+    async fn send_order(&mut self, order: &Order) -> Result<OrderResponse, TradingError> {
+        self.delay().await;
+        let mut state = self.state.lock().unwrap();
+        Self::connected(&state)?;
+        state.orders.push(order.clone());
+        let order_id = format!("mock-{}", state.next_id);
+        state.next_id += 1;
+        if let Some(client_id) = &order.client_order_id {
+            state.client_ids.insert(client_id.clone(), order_id.clone());
+        }
+
+        let market = state.prices.get(&order.symbol).copied();
+        let mut lost = None;
+        let (price, fraction) = match state.script.pop_front() {
+            Some(ScriptedFill::Error(e) | ScriptedFill::FailOrder(e)) => return Err(e),
+            Some(ScriptedFill::Lost(e)) => {
+                lost = Some(e);
+                (market, Decimal::ONE)
+            }
+            Some(ScriptedFill::Reject(reason)) => {
+                log::info!("Mock exchange rejected {}: {}", order_id, reason);
+                let response = OrderResponse {
+                    order_id,
+                    status: OrderStatus::Rejected,
+                    fills: Vec::new(),
+                };
+                state
+                    .responses
+                    .insert(response.order_id.clone(), response.clone());
+                return Ok(response);
+            }
+            Some(ScriptedFill::Rest) => (None, Decimal::ZERO),
+            Some(ScriptedFill::AtPrice(price)) => (Some(price), Decimal::ONE),
+            Some(ScriptedFill::Fraction(fraction)) => {
+                (market, fraction.clamp(Decimal::ZERO, Decimal::ONE))
+            }
+            None => match order.order_type {
+                OrderType::Market => (market, Decimal::ONE),
+                // Limit and stop orders rest until cancelled
+                OrderType::Limit(_) | OrderType::Stop(_) | OrderType::TrailingStop { .. } => {
+                    (None, Decimal::ZERO)
+                }
+            },
+        };
+        let price = match price {
+            _ if fraction <= Decimal::ZERO => {
+                state
+                    .open_orders
+                    .insert(order_id.clone(), order.symbol.clone());
+                let response = OrderResponse {
+                    order_id,
+                    status: OrderStatus::Pending,
+                    fills: Vec::new(),
+                };
+                state
+                    .responses
+                    .insert(response.order_id.clone(), response.clone());
+                return Ok(response);
+            }
+            Some(price) => price,
+            None => {
+                return Err(TradingError::OrderError(format!(
+                    "No price for {} yet",
+                    order.symbol
+                )))
+            }
+        };
+
+        let quantity = order.quantity * fraction;
+        let value = price * quantity;
+        let commission = value * state.fee_rate;
+        state.balance += match order.side {
+            OrderSide::Buy => -value - commission,
+            OrderSide::Sell => value - commission,
+        };
+        let status = if fraction < Decimal::ONE {
+            state
+                .open_orders
+                .insert(order_id.clone(), order.symbol.clone());
+            OrderStatus::PartiallyFilled
+        } else {
+            OrderStatus::Filled
+        };
+        let response = OrderResponse {
+            order_id,
+            status,
+            fills: vec![Fill {
+                price,
+                quantity,
+                commission,
+                commission_asset: "QUOTE".to_string(),
+            }],
+        };
+        state
+            .responses
+            .insert(response.order_id.clone(), response.clone());
+        match lost {
+            Some(e) => Err(e),
+            None => Ok(response),
+        }
+    }
+
+    async fn cancel_order(&mut self, _symbol: &str, order_id: &str) -> Result<(), TradingError> {
+        self.delay().await;
+        let mut state = self.state.lock().unwrap();
+        Self::connected(&state)?;
+        Self::scripted_error(&mut state)?;
+        state
+            .open_orders
+            .remove(order_id)
+            .ok_or_else(|| TradingError::OrderError(format!("Unknown order id: {}", order_id)))?;
+        state.brackets.remove(order_id);
+        let take_profit = state.take_profit_legs.get(order_id).cloned();
+        for order_id in [Some(order_id.to_string()), take_profit]
+            .into_iter()
+            .flatten()
+        {
+            if let Some(response) = state.responses.get_mut(&order_id) {
+                response.status = OrderStatus::Canceled;
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_open_orders(&self, symbol: &str) -> Result<Vec<OrderResponse>, TradingError> {
+        self.delay().await;
+        let mut state = self.state.lock().unwrap();
+        Self::connected(&state)?;
+        Self::scripted_error(&mut state)?;
+        Ok(state
+            .open_orders
+            .iter()
+            .filter(|(_, order_symbol)| *order_symbol == symbol)
+            .filter_map(|(order_id, _)| state.responses.get(order_id).cloned())
+            .collect())
+    }
+
+    async fn get_order_status(
+        &self,
+        _symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse, TradingError> {
+        self.delay().await;
+        let mut state = self.state.lock().unwrap();
+        Self::connected(&state)?;
+        Self::scripted_error(&mut state)?;
+        let order_id = state
+            .client_ids
+            .get(order_id)
+            .map_or(order_id, String::as_str);
+        state
+            .responses
+            .get(order_id)
+            .cloned()
+            .ok_or_else(|| TradingError::UnknownOrder(order_id.to_string()))
+    }
+
+    fn supports_short(&self, _symbol: &str) -> bool {
+        self.state.lock().unwrap().shorts
+    }
+
+    async fn place_oco_order(
+        &mut self,
+        symbol: &str,
+        side: OrderSide,
+        quantity: Decimal,
+        bracket: &Bracket,
+    ) -> Result<OrderResponse, TradingError> {
+        self.delay().await;
+        let mut state = self.state.lock().unwrap();
+        Self::connected(&state)?;
+        Self::scripted_error(&mut state)?;
+        let order_id = format!("mock-{}", state.next_id);
+        state.next_id += 1;
+        state
+            .open_orders
+            .insert(order_id.clone(), symbol.to_string());
+        let order = Order {
+            symbol: symbol.to_string(),
+            quantity,
+            order_type: OrderType::Limit(bracket.take_profit),
+            side,
+            client_order_id: None,
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+        };
+        state.brackets.insert(order_id.clone(), (order, *bracket));
+        let take_profit = format!("mock-{}", state.next_id);
+        state.next_id += 1;
+        state
+            .take_profit_legs
+            .insert(order_id.clone(), take_profit.clone());
+        let response = OrderResponse {
+            order_id,
+            status: OrderStatus::Pending,
+            fills: Vec::new(),
+        };
+        for order_id in [response.order_id.clone(), take_profit] {
+            let leg = OrderResponse {
+                order_id: order_id.clone(),
+                ..response.clone()
+            };
+            state.responses.insert(order_id, leg);
+        }
+        Ok(response)
+    }
+
+    async fn bracket_legs(
+        &self,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<Vec<OrderResponse>, TradingError> {
+        let mut legs = vec![self.get_order_status(symbol, order_id).await?];
+        let state = self.state.lock().unwrap();
+        if let Some(leg) = state
+            .take_profit_legs
+            .get(order_id)
+            .and_then(|id| state.responses.get(id))
+        {
+            legs.push(leg.clone());
+        }
+        Ok(legs)
+    }
+}
+
+/// A recorded market update, parsed by the same code the live streams use
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "mock"), allow(dead_code))]
+pub enum FixtureEvent {
+    // A closed kline
+    Candle {
+        symbol: String,
+        candle: KlineResponse,
+    },
+    Ticker {
+        symbol: String,
+        // Milliseconds
+        time: i64,
+        price: Decimal,
+    },
+}
+
+// Closed klines and ticker prices from a MARKET_RECORD_PATH recording, in the
+// order they arrived; open klines, depth and unreadable messages are skipped
+#[cfg_attr(not(feature = "mock"), allow(dead_code))]
+pub fn load_fixture<P: AsRef<Path>>(path: P) -> Result<Vec<FixtureEvent>, Error> {
+    let mut events = Vec::new();
+    for message in recorder::load_recording(path)? {
+        let event = match message.kind {
+            StreamKind::Kline => {
+                let Ok(response) = parse_websocket_message(&message.raw) else {
+                    continue;
+                };
+                if !response.data.kline.is_closed {
+                    continue;
+                }
+                FixtureEvent::Candle {
+                    symbol: response.data.symbol,
+                    candle: KlineResponse::from_stream(&response.data.kline)?,
+                }
+            }
+            StreamKind::Ticker => {
+                let Ok(response) = parse_websocket_message_ticker(&message.raw) else {
+                    continue;
+                };
+                let Ok(price) = response.data.last_price.parse() else {
+                    continue;
+                };
+                FixtureEvent::Ticker {
+                    symbol: response.data.symbol,
+                    time: message.received_at,
+                    price,
+                }
+            }
+            StreamKind::Depth => continue,
+        };
+        events.push(event);
+    }
+    Ok(events)
+}
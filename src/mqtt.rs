@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use crate::control::{self, ControlCommand, ControlSender};
+use crate::events::BusMessage;
+
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct MqttSettings {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // Events go to <prefix>/signals, /orders, /fills, /risk and /positions
+    pub prefix: String,
+    // Whether pause, resume and kill are taken from <prefix>/command/<name>;
+    // anyone who can publish there can then stop the bot
+    pub commands: bool,
+}
+
+impl MqttSettings {
+    // MQTT_HOST, MQTT_PORT, MQTT_USERNAME, MQTT_PASSWORD, MQTT_TOPIC_PREFIX and
+    // MQTT_COMMANDS, if MQTT_HOST is set
+    pub fn from_env() -> Option<Self> {
+        Some(MqttSettings {
+            host: dotenv::var("MQTT_HOST").ok()?,
+            port: dotenv::var("MQTT_PORT")
+                .ok()
+                .and_then(|port| port.parse().ok())
+                .unwrap_or(1883),
+            username: dotenv::var("MQTT_USERNAME").ok(),
+            password: dotenv::var("MQTT_PASSWORD").ok(),
+            prefix: dotenv::var("MQTT_TOPIC_PREFIX").unwrap_or_else(|_| "auto_trade".to_string()),
+            commands: dotenv::var("MQTT_COMMANDS")
+                .is_ok_and(|value| value == "true" || value == "1"),
+        })
+    }
+}
+
+fn parse_command(name: &str) -> Option<ControlCommand> {
+    match name {
+        "pause" => Some(ControlCommand::Pause),
+        "resume" => Some(ControlCommand::Resume),
+        "kill" => Some(ControlCommand::Kill),
+        _ => None,
+    }
+}
+
+// Applies a command and answers on <prefix>/result/<name>
+async fn run_command(client: AsyncClient, prefix: String, name: String, control: ControlSender) {
+    let result = match parse_command(&name) {
+        Some(command) => control::send_and_wait(&control, command, "mqtt", COMMAND_TIMEOUT)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("{:?}", e)),
+        None => Err(format!("Unknown command {}", name)),
+    };
+    match &result {
+        Ok(()) => log::info!("Applied MQTT command {}", name),
+        Err(e) => log::warn!("MQTT command {} failed: {}", name, e),
+    }
+    let payload = match result {
+        Ok(()) => json!({ "ok": true }),
+        Err(e) => json!({ "ok": false, "error": e }),
+    };
+    let topic = format!("{}/result/{}", prefix, name);
+    if let Err(e) = client
+        .publish(topic, QoS::AtLeastOnce, false, payload.to_string())
+        .await
+    {
+        log::warn!("Failed to answer MQTT command {}: {:?}", name, e);
+    }
+}
+
+/// Publishes trading events to the broker until every sender is dropped, and
+/// takes operator commands from it if enabled
+pub async fn run(
+    settings: MqttSettings,
+    mut receiver: mpsc::Receiver<BusMessage>,
+    control: ControlSender,
+) {
+    let client_id = format!("auto_trade-events-{}", std::process::id());
+    let mut options = MqttOptions::new(client_id, settings.host.clone(), settings.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    if let (Some(username), Some(password)) = (&settings.username, &settings.password) {
+        options.set_credentials(username, password);
+    }
+    let (client, mut event_loop) = AsyncClient::new(options, 100);
+    let command_filter = format!("{}/command/+", settings.prefix);
+    {
+        let client = client.clone();
+        let settings = settings.clone();
+        // The event loop does the network work and reconnects on the next poll
+        tokio::spawn(async move {
+            let command_prefix = format!("{}/command/", settings.prefix);
+            loop {
+                match event_loop.poll().await {
+                    // Subscriptions don't survive a reconnect to a clean session
+                    Ok(Event::Incoming(Packet::ConnAck(_))) if settings.commands => {
+                        if let Err(e) = client.subscribe(&command_filter, QoS::AtLeastOnce).await {
+                            log::error!("Failed to subscribe to {}: {:?}", command_filter, e);
+                        }
+                    }
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        if let Some(name) = publish.topic.strip_prefix(&command_prefix) {
+                            tokio::spawn(run_command(
+                                client.clone(),
+                                settings.prefix.clone(),
+                                name.to_string(),
+                                control.clone(),
+                            ));
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        log::warn!("MQTT connection error: {:?}", e);
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                }
+            }
+        });
+    }
+    if settings.commands {
+        log::info!(
+            "Accepting commands on {}/command/<pause|resume|kill>",
+            settings.prefix
+        );
+    }
+
+    while let Some(message) = receiver.recv().await {
+        let topic = format!("{}/{}", settings.prefix, message.channel());
+        let payload = match serde_json::to_string(&message) {
+            Ok(payload) => payload,
+            Err(e) => {
+                log::error!("Failed to serialize {:?}: {}", message, e);
+                continue;
+            }
+        };
+        if let Err(e) = client
+            .publish(&topic, QoS::AtLeastOnce, false, payload)
+            .await
+        {
+            log::warn!("Failed to publish to {}: {:?}", topic, e);
+        }
+    }
+}
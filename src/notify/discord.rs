@@ -0,0 +1,63 @@
+use rust_decimal::Decimal;
+use serde_json::json;
+
+use super::{HttpsClient, Notification, NotificationKind, Notifier};
+use crate::domain::*;
+
+// Embed side bar colors
+const GREEN: u32 = 0x1a7f37;
+const RED: u32 = 0xcf222e;
+const BLUE: u32 = 0x0969da;
+
+pub struct DiscordNotifier {
+    client: HttpsClient,
+    webhook_url: String,
+}
+
+impl DiscordNotifier {
+    // DISCORD_WEBHOOK_URL, if set
+    pub fn from_env() -> Option<Self> {
+        Some(DiscordNotifier {
+            client: super::https_client(),
+            webhook_url: dotenv::var("DISCORD_WEBHOOK_URL").ok()?,
+        })
+    }
+}
+
+fn color(notification: &Notification) -> u32 {
+    match notification {
+        Notification::Exit { pnl, .. } | Notification::BracketExit { pnl, .. }
+            if *pnl < Decimal::ZERO =>
+        {
+            RED
+        }
+        Notification::DailySummary { report } if report.pnl < 0.0 => RED,
+        _ => match notification.kind() {
+            NotificationKind::Trade => GREEN,
+            NotificationKind::Error => RED,
+            NotificationKind::Report => BLUE,
+        },
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &'static str {
+        "Discord"
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<(), TradingError> {
+        let embed = json!({
+            "title": notification.title(),
+            "description": notification.body(),
+            "color": color(notification),
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+        });
+        super::post_json(
+            &self.client,
+            &self.webhook_url,
+            &json!({ "embeds": [embed] }),
+        )
+        .await
+        .map(|_| ())
+    }
+}
@@ -0,0 +1,131 @@
+use std::path::Path;
+
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use super::{Notification, Notifier};
+use crate::domain::*;
+
+// Placeholders: {{title}}, {{body}}, {{kind}} and {{time}}
+const DEFAULT_TEMPLATE: &str = "{{title}}
+
+{{body}}
+
+--
+auto_trade {{kind}} notification, {{time}}
+";
+
+/// Emails the notifications that are rare and worth interrupting someone for
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    template: String,
+}
+
+fn parse_mailbox(address: &str) -> Option<Mailbox> {
+    match address.trim().parse() {
+        Ok(mailbox) => Some(mailbox),
+        Err(e) => {
+            log::error!("Invalid email address {}: {}", address, e);
+            None
+        }
+    }
+}
+
+impl EmailNotifier {
+    // SMTP_HOST, SMTP_FROM and comma separated SMTP_TO, if set. SMTP_TLS is
+    // starttls (default, port 587), tls (implicit, port 465) or none; SMTP_PORT,
+    // SMTP_USERNAME/SMTP_PASSWORD and SMTP_TEMPLATE_PATH are optional.
+    pub fn from_env() -> Option<Self> {
+        let host = dotenv::var("SMTP_HOST").ok()?;
+        let from = parse_mailbox(&dotenv::var("SMTP_FROM").ok()?)?;
+        let to: Vec<Mailbox> = dotenv::var("SMTP_TO")
+            .ok()?
+            .split(',')
+            .filter_map(parse_mailbox)
+            .collect();
+        if to.is_empty() {
+            return None;
+        }
+
+        let tls = dotenv::var("SMTP_TLS").unwrap_or_else(|_| "starttls".to_string());
+        let builder = match tls.as_str() {
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host),
+            "none" => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                &host,
+            )),
+            _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host),
+        };
+        let mut builder = match builder {
+            Ok(builder) => builder,
+            Err(e) => {
+                log::error!("Invalid SMTP settings for {}: {}", host, e);
+                return None;
+            }
+        };
+        if let Some(port) = dotenv::var("SMTP_PORT").ok().and_then(|p| p.parse().ok()) {
+            builder = builder.port(port);
+        }
+        if let (Ok(username), Ok(password)) =
+            (dotenv::var("SMTP_USERNAME"), dotenv::var("SMTP_PASSWORD"))
+        {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        let template = match dotenv::var("SMTP_TEMPLATE_PATH") {
+            Ok(path) => match std::fs::read_to_string(Path::new(&path)) {
+                Ok(template) => template,
+                Err(e) => {
+                    log::error!("Failed to read email template {}: {}", path, e);
+                    DEFAULT_TEMPLATE.to_string()
+                }
+            },
+            Err(_) => DEFAULT_TEMPLATE.to_string(),
+        };
+
+        Some(EmailNotifier {
+            transport: builder.build(),
+            from,
+            to,
+            template,
+        })
+    }
+
+    fn render(&self, notification: &Notification) -> String {
+        self.template
+            .replace("{{title}}", &notification.title())
+            .replace("{{body}}", &notification.body())
+            .replace("{{kind}}", &format!("{:?}", notification.kind()))
+            .replace("{{time}}", &chrono::Utc::now().to_rfc3339())
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<(), TradingError> {
+        if !notification.is_important() {
+            return Ok(());
+        }
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(format!("[auto_trade] {}", notification.title()))
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let message = message
+            .body(self.render(notification))
+            .map_err(|e| TradingError::DataError(format!("{:?}", e)))?;
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+        Ok(())
+    }
+}
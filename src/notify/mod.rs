@@ -0,0 +1,383 @@
+pub mod discord;
+#[cfg(feature = "email")]
+pub mod email;
+pub mod slack;
+#[cfg(feature = "telegram")]
+pub mod telegram;
+
+use std::future::Future;
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use hyper::body::Bytes;
+use hyper::client::HttpConnector;
+use hyper::{Body, Client, Method, Request};
+use hyper_tls::HttpsConnector;
+use rust_decimal::Decimal;
+use tokio::sync::{mpsc, watch};
+
+use crate::domain::*;
+use crate::events::BusMessage;
+use crate::executor::SharedExecutorState;
+use crate::report::DailyReport;
+use crate::storage::PositionEvent;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    // Entries, exits and fills
+    Trade,
+    Error,
+    // Periodic summaries and limit warnings
+    Report,
+}
+
+/// Something an operator should hear about
+#[derive(Debug, Clone)]
+pub enum Notification {
+    Entry {
+        symbol: String,
+        quantity: Decimal,
+        price: Decimal,
+    },
+    // Closed by a signal
+    Exit {
+        symbol: String,
+        quantity: Decimal,
+        entry_price: Decimal,
+        price: Decimal,
+        // Before fees
+        pnl: Decimal,
+    },
+    Fill {
+        symbol: String,
+        side: OrderSide,
+        quantity: Decimal,
+        price: Decimal,
+    },
+    // A stop loss or take profit closed the position on the exchange
+    BracketExit {
+        symbol: String,
+        quantity: Decimal,
+        entry_price: Decimal,
+        price: Decimal,
+        // Before fees
+        pnl: Decimal,
+    },
+    // The day's realized plus unrealized PnL fell through the configured limit
+    Drawdown {
+        pnl: f64,
+        limit: f64,
+    },
+    Error {
+        message: String,
+    },
+    // Trading was stopped and positions closed, e.g. by a kill command
+    Halted {
+        reason: String,
+    },
+    // Entries stopped after a panic or too many errors; exits still managed
+    SafeMode {
+        reason: String,
+    },
+    // A configured alert rule started to hold
+    Rule {
+        name: String,
+        message: String,
+    },
+    DailySummary {
+        report: DailyReport,
+    },
+}
+
+// Shorts gain as the price falls
+fn gross_pnl(side: &OrderSide, entry_price: Decimal, price: Decimal, quantity: Decimal) -> Decimal {
+    match side {
+        OrderSide::Buy => (price - entry_price) * quantity,
+        OrderSide::Sell => (entry_price - price) * quantity,
+    }
+}
+
+impl Notification {
+    fn from_bus(message: &BusMessage) -> Option<Self> {
+        match message {
+            BusMessage::Fill {
+                symbol,
+                side,
+                price,
+                quantity,
+                ..
+            } => Some(Notification::Fill {
+                symbol: symbol.clone(),
+                side: side.clone(),
+                quantity: *quantity,
+                price: *price,
+            }),
+            BusMessage::Position {
+                symbol,
+                event: PositionEvent::Opened,
+                quantity,
+                price,
+                ..
+            } => Some(Notification::Entry {
+                symbol: symbol.clone(),
+                quantity: *quantity,
+                price: *price,
+            }),
+            BusMessage::Position {
+                symbol,
+                event: PositionEvent::Closed | PositionEvent::Reduced,
+                side,
+                quantity,
+                entry_price,
+                price,
+                ..
+            } => Some(Notification::Exit {
+                symbol: symbol.clone(),
+                quantity: *quantity,
+                entry_price: *entry_price,
+                price: *price,
+                pnl: gross_pnl(side, *entry_price, *price, *quantity),
+            }),
+            BusMessage::Position {
+                symbol,
+                event: PositionEvent::BracketExit,
+                side,
+                quantity,
+                entry_price,
+                price,
+                ..
+            } => Some(Notification::BracketExit {
+                symbol: symbol.clone(),
+                quantity: *quantity,
+                entry_price: *entry_price,
+                price: *price,
+                pnl: gross_pnl(side, *entry_price, *price, *quantity),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> NotificationKind {
+        match self {
+            Notification::Entry { .. }
+            | Notification::Exit { .. }
+            | Notification::Fill { .. }
+            | Notification::BracketExit { .. } => NotificationKind::Trade,
+            Notification::Error { .. }
+            | Notification::Halted { .. }
+            | Notification::SafeMode { .. } => NotificationKind::Error,
+            Notification::Drawdown { .. }
+            | Notification::Rule { .. }
+            | Notification::DailySummary { .. } => NotificationKind::Report,
+        }
+    }
+
+    // Rare enough to be worth an email
+    pub fn is_important(&self) -> bool {
+        matches!(
+            self,
+            Notification::Halted { .. }
+                | Notification::SafeMode { .. }
+                | Notification::Drawdown { .. }
+                | Notification::DailySummary { .. }
+        )
+    }
+
+    pub fn title(&self) -> String {
+        match self {
+            Notification::Entry { symbol, .. } => format!("Entered {}", symbol),
+            Notification::Exit { symbol, .. } => format!("Exited {}", symbol),
+            Notification::Fill { symbol, side, .. } => format!("{:?} {} filled", side, symbol),
+            Notification::BracketExit { symbol, .. } => format!("{} bracket exit", symbol),
+            Notification::Drawdown { .. } => "Daily drawdown limit reached".to_string(),
+            Notification::Error { .. } => "Error".to_string(),
+            Notification::Halted { .. } => "Trading halted".to_string(),
+            Notification::SafeMode { .. } => "Safe mode".to_string(),
+            Notification::Rule { name, .. } => format!("Alert: {}", name),
+            Notification::DailySummary { report } => format!("Summary for {}", report.date),
+        }
+    }
+
+    pub fn body(&self) -> String {
+        match self {
+            Notification::Entry {
+                quantity, price, ..
+            } => format!("{} @ {}", quantity, price),
+            Notification::Exit {
+                quantity,
+                entry_price,
+                price,
+                pnl,
+                ..
+            } => format!(
+                "{} closed at {} (entry {}), pnl {:.4} before fees",
+                quantity, price, entry_price, pnl
+            ),
+            Notification::Fill {
+                quantity, price, ..
+            } => format!("{} @ {}", quantity, price),
+            Notification::BracketExit {
+                quantity,
+                entry_price,
+                price,
+                pnl,
+                ..
+            } => format!(
+                "{} closed at {} (entry {}), pnl {:.4} before fees",
+                quantity, price, entry_price, pnl
+            ),
+            Notification::Drawdown { pnl, limit } => {
+                format!("Today's PnL is {:.2}, limit -{:.2}", pnl, limit)
+            }
+            Notification::Error { message } => message.clone(),
+            Notification::Halted { reason } => reason.clone(),
+            Notification::SafeMode { reason } => {
+                format!("{}\nNo new entries until resumed", reason)
+            }
+            Notification::Rule { message, .. } => message.clone(),
+            Notification::DailySummary { report } => report.summary(),
+        }
+    }
+}
+
+/// A channel notifications are delivered on
+pub trait Notifier: Send + Sync + 'static {
+    fn name(&self) -> &'static str;
+    fn send(
+        &self,
+        notification: &Notification,
+    ) -> impl Future<Output = Result<(), TradingError>> + Send;
+}
+
+// One per running notifier; unbounded so producers never wait on delivery
+pub type NotifySender = mpsc::UnboundedSender<Notification>;
+
+pub fn broadcast(notifiers: &[NotifySender], notification: Notification) {
+    for notifier in notifiers {
+        // Only fails once the notifier task is gone
+        let _ = notifier.send(notification.clone());
+    }
+}
+
+pub async fn run_notifier<N: Notifier>(
+    notifier: N,
+    mut receiver: mpsc::UnboundedReceiver<Notification>,
+) {
+    while let Some(notification) = receiver.recv().await {
+        if let Err(e) = notifier.send(&notification).await {
+            log::error!("Failed to send {} notification: {}", notifier.name(), e);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AlertSettings {
+    // Quote currency loss for the day that triggers a drawdown alert
+    pub drawdown_limit: Option<f64>,
+}
+
+impl AlertSettings {
+    // NOTIFY_DRAWDOWN_LIMIT, if set
+    pub fn from_env() -> Self {
+        AlertSettings::parse(dotenv::var("NOTIFY_DRAWDOWN_LIMIT").ok().as_deref())
+    }
+
+    pub fn parse(drawdown_limit: Option<&str>) -> Self {
+        AlertSettings {
+            drawdown_limit: drawdown_limit.and_then(|limit| limit.parse().ok()),
+        }
+    }
+}
+
+// Replaced when the configuration file changes
+pub type AlertSettingsReceiver = watch::Receiver<AlertSettings>;
+
+/// Turns executor events into notifications and watches the day's PnL
+pub async fn run_alerts(
+    settings: AlertSettingsReceiver,
+    mut bus: mpsc::Receiver<BusMessage>,
+    state: SharedExecutorState,
+    notifiers: Vec<NotifySender>,
+) {
+    let mut check = tokio::time::interval(Duration::from_secs(60));
+    // At most one drawdown alert per day
+    let mut alerted_on: Option<NaiveDate> = None;
+    loop {
+        tokio::select! {
+            message = bus.recv() => {
+                let message = match message {
+                    Some(message) => message,
+                    None => break,
+                };
+                if let Some(notification) = Notification::from_bus(&message) {
+                    broadcast(&notifiers, notification);
+                }
+            }
+            _ = check.tick() => {
+                let limit = match settings.borrow().drawdown_limit {
+                    Some(limit) => limit,
+                    None => continue,
+                };
+                let pnl = {
+                    let state = state.read().await;
+                    state.daily_pnl() + state.pnl_report().unrealized
+                };
+                let today = chrono::Utc::now().date_naive();
+                if pnl <= -limit && alerted_on != Some(today) {
+                    alerted_on = Some(today);
+                    broadcast(&notifiers, Notification::Drawdown { pnl, limit });
+                }
+            }
+        }
+    }
+}
+
+pub(crate) type HttpsClient = Client<HttpsConnector<HttpConnector>>;
+
+pub(crate) fn https_client() -> HttpsClient {
+    Client::builder().build(HttpsConnector::new())
+}
+
+// POST a JSON body, failing on a non-2xx answer; returns the response body
+pub(crate) async fn post_json(
+    client: &HttpsClient,
+    url: &str,
+    body: &serde_json::Value,
+) -> Result<Bytes, TradingError> {
+    post_json_authorized(client, url, None, body).await
+}
+
+pub(crate) async fn post_json_authorized(
+    client: &HttpsClient,
+    url: &str,
+    bearer_token: Option<&str>,
+    body: &serde_json::Value,
+) -> Result<Bytes, TradingError> {
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri(url)
+        .header("Content-Type", "application/json; charset=utf-8");
+    if let Some(token) = bearer_token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    let request = request
+        .body(Body::from(body.to_string()))
+        .map_err(|e| TradingError::DataError(format!("{:?}", e)))?;
+    let response = client
+        .request(request)
+        .await
+        .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body())
+        .await
+        .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+    if !status.is_success() {
+        // The url is left out; bot tokens and webhook urls are credentials
+        return Err(TradingError::NetworkError(format!(
+            "Request answered {}: {}",
+            status,
+            String::from_utf8_lossy(&body)
+        )));
+    }
+    Ok(body)
+}
@@ -0,0 +1,112 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{HttpsClient, Notification, NotificationKind, Notifier};
+use crate::domain::*;
+
+const POST_MESSAGE_URL: &str = "https://slack.com/api/chat.postMessage";
+
+#[derive(Debug, Clone)]
+enum Transport {
+    // Incoming webhooks; each one is bound to a channel, so routes are webhook urls
+    Webhook,
+    // Slack app bot token; routes are channel names or ids
+    App { token: String },
+}
+
+/// Where each kind of notification goes
+#[derive(Debug, Clone)]
+struct Routes {
+    trades: String,
+    errors: String,
+    reports: String,
+}
+
+impl Routes {
+    // <prefix> is the default; <prefix>_TRADES, _ERRORS and _REPORTS override it
+    fn from_env(prefix: &str) -> Option<Self> {
+        let default = dotenv::var(prefix).ok();
+        let route = |suffix: &str| {
+            dotenv::var(format!("{}_{}", prefix, suffix))
+                .ok()
+                .or_else(|| default.clone())
+        };
+        Some(Routes {
+            trades: route("TRADES")?,
+            errors: route("ERRORS")?,
+            reports: route("REPORTS")?,
+        })
+    }
+
+    fn get(&self, kind: NotificationKind) -> &str {
+        match kind {
+            NotificationKind::Trade => &self.trades,
+            NotificationKind::Error => &self.errors,
+            NotificationKind::Report => &self.reports,
+        }
+    }
+}
+
+pub struct SlackNotifier {
+    client: HttpsClient,
+    transport: Transport,
+    routes: Routes,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    ok: bool,
+    error: Option<String>,
+}
+
+impl SlackNotifier {
+    // SLACK_BOT_TOKEN with SLACK_CHANNEL[_TRADES|_ERRORS|_REPORTS], or
+    // SLACK_WEBHOOK_URL[_TRADES|_ERRORS|_REPORTS]
+    pub fn from_env() -> Option<Self> {
+        let (transport, routes) = match dotenv::var("SLACK_BOT_TOKEN") {
+            Ok(token) => (Transport::App { token }, Routes::from_env("SLACK_CHANNEL")?),
+            Err(_) => (Transport::Webhook, Routes::from_env("SLACK_WEBHOOK_URL")?),
+        };
+        Some(SlackNotifier {
+            client: super::https_client(),
+            transport,
+            routes,
+        })
+    }
+}
+
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &'static str {
+        "Slack"
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<(), TradingError> {
+        let text = format!("*{}*\n{}", notification.title(), notification.body());
+        let route = self.routes.get(notification.kind());
+        match &self.transport {
+            Transport::Webhook => {
+                super::post_json(&self.client, route, &json!({ "text": text })).await?;
+            }
+            Transport::App { token } => {
+                let body = super::post_json_authorized(
+                    &self.client,
+                    POST_MESSAGE_URL,
+                    Some(token),
+                    &json!({ "channel": route, "text": text }),
+                )
+                .await?;
+                // The Web API answers 200 even when it refuses the message
+                let response: ApiResponse = serde_json::from_slice(&body)
+                    .map_err(|e| TradingError::DataError(format!("{:?}", e)))?;
+                if !response.ok {
+                    return Err(TradingError::NetworkError(format!(
+                        "Slack refused message for {}: {}",
+                        route,
+                        response.error.unwrap_or_default()
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
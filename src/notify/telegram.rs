@@ -0,0 +1,254 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{HttpsClient, Notification, Notifier};
+use crate::control::{ControlCommand, ControlRequest, ControlSender};
+use crate::domain::*;
+use crate::executor::SharedExecutorState;
+
+#[derive(Debug, Clone)]
+pub struct TelegramSettings {
+    pub token: String,
+    // Alerts go to every listed chat, and only they may send commands
+    pub chat_ids: Vec<i64>,
+}
+
+impl TelegramSettings {
+    // TELEGRAM_BOT_TOKEN and a comma separated TELEGRAM_CHAT_IDS, if both are set
+    pub fn from_env() -> Option<Self> {
+        let token = dotenv::var("TELEGRAM_BOT_TOKEN").ok()?;
+        let chat_ids: Vec<i64> = dotenv::var("TELEGRAM_CHAT_IDS")
+            .ok()?
+            .split(',')
+            .filter_map(|id| id.trim().parse().ok())
+            .collect();
+        if chat_ids.is_empty() {
+            log::warn!("TELEGRAM_CHAT_IDS has no valid chat ids, Telegram disabled");
+            return None;
+        }
+        Some(TelegramSettings { token, chat_ids })
+    }
+
+    fn url(&self, method: &str) -> String {
+        format!("https://api.telegram.org/bot{}/{}", self.token, method)
+    }
+}
+
+pub struct TelegramNotifier {
+    client: HttpsClient,
+    settings: TelegramSettings,
+}
+
+impl TelegramNotifier {
+    pub fn new(settings: TelegramSettings) -> Self {
+        TelegramNotifier {
+            client: super::https_client(),
+            settings,
+        }
+    }
+}
+
+async fn send_message(
+    client: &HttpsClient,
+    settings: &TelegramSettings,
+    chat_id: i64,
+    text: &str,
+) -> Result<(), TradingError> {
+    super::post_json(
+        client,
+        &settings.url("sendMessage"),
+        &json!({ "chat_id": chat_id, "text": text }),
+    )
+    .await
+    .map(|_| ())
+}
+
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &'static str {
+        "Telegram"
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<(), TradingError> {
+        let text = format!("{}\n{}", notification.title(), notification.body());
+        for chat_id in &self.settings.chat_ids {
+            send_message(&self.client, &self.settings, *chat_id, &text).await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Updates {
+    result: Vec<Update>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    chat: Chat,
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+const HELP: &str =
+    "/status, /positions, /strategies, /enable <strategy>, /disable <strategy>, /pause, /resume, /kill";
+
+async fn status(state: &SharedExecutorState) -> String {
+    let state = state.read().await;
+    let report = state.pnl_report();
+    format!(
+        "{}\nOpen positions: {}\nWorking orders: {}\nToday: {:.2}\nRealized: {:.2}\nUnrealized: {:.2}",
+        match &state.safe_mode {
+            Some(reason) => format!("Safe mode: {}", reason),
+            None if state.paused => "Paused".to_string(),
+            None => "Trading".to_string(),
+        },
+        state.positions.len(),
+        state.working_orders.len(),
+        state.daily_pnl(),
+        report.realized,
+        report.unrealized
+    )
+}
+
+async fn positions(state: &SharedExecutorState) -> String {
+    let report = state.read().await.pnl_report();
+    if report.open_positions.is_empty() {
+        return "No open positions".to_string();
+    }
+    report
+        .open_positions
+        .iter()
+        .map(|p| {
+            format!(
+                "{} {} @ {} ({}), mark {}, pnl {:.4}",
+                p.symbol, p.quantity, p.entry_price, p.strategy, p.mark_price, p.unrealized
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+async fn strategies(state: &SharedExecutorState) -> String {
+    let state = state.read().await;
+    if state.strategies.is_empty() {
+        return "No strategies registered".to_string();
+    }
+    state
+        .strategies
+        .iter()
+        .map(|(name, enabled)| {
+            format!(
+                "{}: {}",
+                name,
+                if *enabled { "enabled" } else { "disabled" }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+async fn handle_command(
+    chat_id: i64,
+    text: &str,
+    state: &SharedExecutorState,
+    control: &ControlSender,
+) -> String {
+    // Commands may be addressed as /status@botname in group chats
+    let mut words = text.split_whitespace();
+    let command = words.next().unwrap_or_default();
+    let command = command.split('@').next().unwrap_or_default();
+    let argument = words.next();
+    let (control_command, reply) = match (command, argument) {
+        ("/status", _) => return status(state).await,
+        ("/positions", _) => return positions(state).await,
+        ("/strategies", _) => return strategies(state).await,
+        ("/enable" | "/disable", Some(name)) => {
+            if !state.read().await.strategies.contains_key(name) {
+                return format!("Unknown strategy {}, see /strategies", name);
+            }
+            let enabled = command == "/enable";
+            let command = ControlCommand::SetStrategyEnabled {
+                name: name.to_string(),
+                enabled,
+            };
+            let reply = if enabled {
+                "Enabled, entries allowed again"
+            } else {
+                "Disabled, no new entries; its open positions are still managed"
+            };
+            (command, reply)
+        }
+        ("/pause", _) => (ControlCommand::Pause, "Paused, no new entries"),
+        ("/resume", _) => (ControlCommand::Resume, "Resumed"),
+        ("/kill", _) => (
+            ControlCommand::Kill,
+            "Paused, cancelling orders and closing all positions",
+        ),
+        _ => return HELP.to_string(),
+    };
+    let request = ControlRequest {
+        command: control_command,
+        requested_by: format!("telegram:{}", chat_id),
+        reply: None,
+    };
+    match control.send(request) {
+        Ok(()) => reply.to_string(),
+        Err(_) => "Executor is not running".to_string(),
+    }
+}
+
+/// Long-polls the bot for commands from allowlisted chats
+pub async fn run_commands(
+    settings: TelegramSettings,
+    state: SharedExecutorState,
+    control: ControlSender,
+) {
+    let client = super::https_client();
+    let mut offset = 0;
+    loop {
+        let body = json!({ "offset": offset, "timeout": 30, "allowed_updates": ["message"] });
+        let updates = match super::post_json(&client, &settings.url("getUpdates"), &body).await {
+            Ok(body) => serde_json::from_slice::<Updates>(&body)
+                .map_err(|e| TradingError::DataError(format!("{:?}", e))),
+            Err(e) => Err(e),
+        };
+        let updates = match updates {
+            Ok(updates) => updates.result,
+            Err(e) => {
+                log::error!("Failed to poll Telegram: {}", e);
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+        };
+        for update in updates {
+            offset = update.update_id + 1;
+            let (chat_id, text) = match update.message {
+                Some(Message {
+                    chat,
+                    text: Some(text),
+                }) => (chat.id, text),
+                _ => continue,
+            };
+            if !settings.chat_ids.contains(&chat_id) {
+                log::warn!("Ignoring Telegram message from chat {}", chat_id);
+                continue;
+            }
+            let reply = handle_command(chat_id, &text, &state, &control).await;
+            if let Err(e) = send_message(&client, &settings, chat_id, &reply).await {
+                log::error!("Failed to answer Telegram chat {}: {}", chat_id, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::{HashMap, VecDeque};
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::*;
+use crate::user_stream::ExecutionReport;
+
+// Finished orders kept for queries, oldest dropped first
+const FINISHED_KEPT: usize = 500;
+
+/// Where an order is in its life on the exchange. Orders only move forward:
+/// New, then PartiallyFilled any number of times, then one final state.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "web", derive(utoipa::ToSchema))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderState {
+    New,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Expired,
+    Rejected,
+}
+
+impl OrderState {
+    // Binance status names; PENDING_CANCEL and the like don't move the order
+    pub fn from_exchange(status: &str) -> Option<OrderState> {
+        match status {
+            "NEW" => Some(OrderState::New),
+            "PARTIALLY_FILLED" => Some(OrderState::PartiallyFilled),
+            "FILLED" => Some(OrderState::Filled),
+            "CANCELED" => Some(OrderState::Canceled),
+            "EXPIRED" | "EXPIRED_IN_MATCH" => Some(OrderState::Expired),
+            "REJECTED" => Some(OrderState::Rejected),
+            _ => None,
+        }
+    }
+
+    pub fn from_status(status: &OrderStatus) -> OrderState {
+        match status {
+            OrderStatus::Pending => OrderState::New,
+            OrderStatus::PartiallyFilled => OrderState::PartiallyFilled,
+            OrderStatus::Filled => OrderState::Filled,
+            OrderStatus::Canceled => OrderState::Canceled,
+            OrderStatus::Rejected => OrderState::Rejected,
+        }
+    }
+
+    pub fn to_status(self) -> OrderStatus {
+        match self {
+            OrderState::New => OrderStatus::Pending,
+            OrderState::PartiallyFilled => OrderStatus::PartiallyFilled,
+            OrderState::Filled => OrderStatus::Filled,
+            OrderState::Canceled | OrderState::Expired => OrderStatus::Canceled,
+            OrderState::Rejected => OrderStatus::Rejected,
+        }
+    }
+
+    pub fn is_final(self) -> bool {
+        !matches!(self, OrderState::New | OrderState::PartiallyFilled)
+    }
+
+    // Partial fills may repeat; nothing leaves a final state or goes back to New
+    pub fn can_become(self, next: OrderState) -> bool {
+        match (self, next) {
+            (OrderState::New, _) => true,
+            (OrderState::PartiallyFilled, OrderState::New) => false,
+            (OrderState::PartiallyFilled, _) => true,
+            _ => false,
+        }
+    }
+}
+
+/// One order and the fills it has had so far
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "web", derive(utoipa::ToSchema))]
+pub struct TrackedOrder {
+    pub order_id: String,
+    pub client_order_id: Option<String>,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub quantity: Decimal,
+    pub state: OrderState,
+    pub filled_quantity: Decimal,
+    pub average_price: Option<Decimal>,
+    // Unix milliseconds
+    pub created_at: i64,
+    pub updated_at: i64,
+    #[serde(skip)]
+    fills: Vec<Fill>,
+}
+
+impl TrackedOrder {
+    fn add_fill(&mut self, fill: Fill) {
+        self.fills.push(fill);
+        let quote: Decimal = self.fills.iter().map(|f| f.price * f.quantity).sum();
+        self.filled_quantity = self.fills.iter().map(|f| f.quantity).sum();
+        self.average_price =
+            (self.filled_quantity > Decimal::ZERO).then(|| quote / self.filled_quantity);
+    }
+
+    pub fn response(&self) -> OrderResponse {
+        OrderResponse {
+            order_id: self.order_id.clone(),
+            status: self.state.to_status(),
+            fills: self.fills.clone(),
+        }
+    }
+}
+
+/// Every order the bot placed or saw on the account stream, moved through
+/// its states by acks, stream reports and cancels
+#[derive(Debug, Default)]
+pub struct OrderTracker {
+    orders: HashMap<String, TrackedOrder>,
+    // Final orders by when they finished
+    finished: VecDeque<String>,
+}
+
+impl OrderTracker {
+    // An order the exchange acknowledged, with the fills its answer carried
+    pub fn submitted(&mut self, order: &Order, response: &OrderResponse) {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut tracked = TrackedOrder {
+            order_id: response.order_id.clone(),
+            client_order_id: order.client_order_id.clone(),
+            symbol: order.symbol.clone(),
+            side: order.side.clone(),
+            quantity: order.quantity,
+            state: OrderState::New,
+            filled_quantity: Decimal::ZERO,
+            average_price: None,
+            created_at: now,
+            updated_at: now,
+            fills: Vec::new(),
+        };
+        for fill in &response.fills {
+            tracked.add_fill(fill.clone());
+        }
+        tracked.state = OrderState::from_status(&response.status);
+        self.insert(tracked);
+    }
+
+    // Applies an account stream report. Returns the order as it now stands, or
+    // None when the report changed nothing, e.g. one for an order the ack
+    // already completed or one arriving out of order.
+    pub fn on_report(&mut self, report: &ExecutionReport) -> Option<TrackedOrder> {
+        let order_id = report.order_id.to_string();
+        let next = OrderState::from_exchange(&report.status)?;
+        let Some(tracked) = self.orders.get_mut(&order_id) else {
+            // Placed elsewhere or before a restart; fills before this report weren't seen
+            let mut tracked = TrackedOrder {
+                order_id,
+                client_order_id: (report.execution_type == "NEW")
+                    .then(|| report.client_order_id.clone()),
+                symbol: report.symbol.clone(),
+                side: report.order_side(),
+                quantity: report.quantity.parse().unwrap_or_default(),
+                state: next,
+                filled_quantity: Decimal::ZERO,
+                average_price: None,
+                created_at: report.event_time,
+                updated_at: report.event_time,
+                fills: Vec::new(),
+            };
+            if let Some(fill) = report.cumulative_fill() {
+                tracked.add_fill(fill);
+            }
+            self.insert(tracked.clone());
+            return Some(tracked);
+        };
+
+        if !tracked.state.can_become(next) {
+            log::debug!(
+                "Order {} report {} ignored in state {:?}",
+                order_id,
+                report.status,
+                tracked.state
+            );
+            return None;
+        }
+        // The ack may already have carried this execution
+        let cumulative: Decimal = report.cumulative_quantity.parse().unwrap_or_default();
+        if let Some(fill) = report.fill() {
+            if cumulative > tracked.filled_quantity {
+                tracked.add_fill(fill);
+            }
+        }
+        // Executions whose reports came late or not at all are made up from
+        // the order's totals
+        if let Some(total) = report.cumulative_fill() {
+            let missing = total.quantity - tracked.filled_quantity;
+            if missing > Decimal::ZERO {
+                let quote: Decimal = tracked.fills.iter().map(|f| f.price * f.quantity).sum();
+                tracked.add_fill(Fill {
+                    price: (total.price * total.quantity - quote) / missing,
+                    quantity: missing,
+                    ..total
+                });
+            }
+        }
+        tracked.state = next;
+        tracked.updated_at = report.event_time;
+        let tracked = tracked.clone();
+        if next.is_final() {
+            self.finish(&order_id);
+        }
+        Some(tracked)
+    }
+
+    // Cancel acknowledged; the stream's report for it, if any, is then ignored
+    pub fn canceled(&mut self, order_id: &str) {
+        let Some(tracked) = self.orders.get_mut(order_id) else {
+            return;
+        };
+        if tracked.state.can_become(OrderState::Canceled) {
+            tracked.state = OrderState::Canceled;
+            tracked.updated_at = chrono::Utc::now().timestamp_millis();
+            self.finish(order_id);
+        }
+    }
+
+    pub fn get(&self, order_id: &str) -> Option<&TrackedOrder> {
+        self.orders.get(order_id)
+    }
+
+    // Open orders with their fills so far, which the API leaves out, for the
+    // state snapshot
+    pub fn snapshot(&self) -> Vec<(TrackedOrder, Vec<Fill>)> {
+        self.open()
+            .into_iter()
+            .map(|order| (order.clone(), order.fills.clone()))
+            .collect()
+    }
+
+    // Replaces what's tracked with the open orders of a snapshot
+    pub fn restore(&mut self, open: Vec<(TrackedOrder, Vec<Fill>)>) {
+        self.orders.clear();
+        self.finished.clear();
+        for (mut order, fills) in open {
+            order.fills = fills;
+            self.orders.insert(order.order_id.clone(), order);
+        }
+    }
+
+    // Still New or PartiallyFilled
+    pub fn open(&self) -> Vec<&TrackedOrder> {
+        let mut open: Vec<&TrackedOrder> = self
+            .orders
+            .values()
+            .filter(|order| !order.state.is_final())
+            .collect();
+        open.sort_by_key(|order| order.created_at);
+        open
+    }
+
+    // Most recently updated first
+    pub fn recent(&self, limit: usize) -> Vec<&TrackedOrder> {
+        let mut orders: Vec<&TrackedOrder> = self.orders.values().collect();
+        orders.sort_by_key(|order| std::cmp::Reverse(order.updated_at));
+        orders.truncate(limit);
+        orders
+    }
+
+    fn insert(&mut self, tracked: TrackedOrder) {
+        let order_id = tracked.order_id.clone();
+        let is_final = tracked.state.is_final();
+        self.orders.insert(order_id.clone(), tracked);
+        if is_final {
+            self.finish(&order_id);
+        }
+    }
+
+    fn finish(&mut self, order_id: &str) {
+        self.finished.push_back(order_id.to_string());
+        while self.finished.len() > FINISHED_KEPT {
+            if let Some(oldest) = self.finished.pop_front() {
+                self.orders.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn order(quantity: Decimal) -> Order {
+        Order {
+            symbol: "BTCUSDT".to_string(),
+            quantity,
+            order_type: OrderType::Limit(dec!(100)),
+            side: OrderSide::Buy,
+            client_order_id: Some("entry".to_string()),
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+        }
+    }
+
+    fn ack(status: OrderStatus, fills: Vec<Fill>) -> OrderResponse {
+        OrderResponse {
+            order_id: "1".to_string(),
+            status,
+            fills,
+        }
+    }
+
+    fn fill(price: Decimal, quantity: Decimal) -> Fill {
+        Fill {
+            price,
+            quantity,
+            commission: Decimal::ZERO,
+            commission_asset: "USDT".to_string(),
+        }
+    }
+
+    // A TRADE report of `last` at `price` taking the order to `cumulative`,
+    // `cumulative_quote` worth
+    fn trade(
+        status: &str,
+        last: Decimal,
+        price: Decimal,
+        cumulative: Decimal,
+        cumulative_quote: Decimal,
+    ) -> ExecutionReport {
+        ExecutionReport {
+            event_time: 1,
+            symbol: "BTCUSDT".to_string(),
+            order_id: 1,
+            client_order_id: "entry".to_string(),
+            quantity: "1".to_string(),
+            order_list_id: -1,
+            side: "BUY".to_string(),
+            execution_type: "TRADE".to_string(),
+            status: status.to_string(),
+            last_quantity: last.to_string(),
+            last_price: price.to_string(),
+            commission: "0".to_string(),
+            commission_asset: None,
+            cumulative_quantity: cumulative.to_string(),
+            cumulative_quote: cumulative_quote.to_string(),
+        }
+    }
+
+    #[test]
+    fn states_only_move_forward() {
+        use OrderState::*;
+        assert!(New.can_become(PartiallyFilled));
+        assert!(New.can_become(Canceled));
+        assert!(PartiallyFilled.can_become(PartiallyFilled));
+        assert!(PartiallyFilled.can_become(Filled));
+        assert!(!PartiallyFilled.can_become(New));
+        assert!(!Filled.can_become(PartiallyFilled));
+        assert!(!Filled.can_become(Canceled));
+        assert!(!Canceled.can_become(Filled));
+        assert!(!Expired.can_become(New));
+    }
+
+    #[test]
+    fn fills_the_ack_carried_are_not_counted_twice() {
+        let mut tracker = OrderTracker::default();
+        tracker.submitted(
+            &order(dec!(1)),
+            &ack(
+                OrderStatus::PartiallyFilled,
+                vec![fill(dec!(100), dec!(0.4))],
+            ),
+        );
+
+        let tracked = tracker
+            .on_report(&trade(
+                "PARTIALLY_FILLED",
+                dec!(0.4),
+                dec!(100),
+                dec!(0.4),
+                dec!(40),
+            ))
+            .unwrap();
+        assert_eq!(tracked.filled_quantity, dec!(0.4));
+
+        let tracked = tracker
+            .on_report(&trade("FILLED", dec!(0.6), dec!(101), dec!(1), dec!(100.6)))
+            .unwrap();
+        assert_eq!(tracked.state, OrderState::Filled);
+        assert_eq!(tracked.filled_quantity, dec!(1));
+        assert_eq!(tracked.average_price.unwrap(), dec!(100.6));
+    }
+
+    #[test]
+    fn a_late_report_does_not_move_a_finished_order() {
+        let mut tracker = OrderTracker::default();
+        tracker.submitted(&order(dec!(1)), &ack(OrderStatus::Pending, Vec::new()));
+
+        // The final report overtook the partial fill before it
+        let tracked = tracker
+            .on_report(&trade("FILLED", dec!(0.6), dec!(101), dec!(1), dec!(100.6)))
+            .unwrap();
+        assert_eq!(tracked.filled_quantity, dec!(1));
+        assert_eq!(tracked.average_price.unwrap(), dec!(100.6));
+
+        assert!(tracker
+            .on_report(&trade(
+                "PARTIALLY_FILLED",
+                dec!(0.4),
+                dec!(100),
+                dec!(0.4),
+                dec!(40)
+            ))
+            .is_none());
+        let tracked = tracker.get("1").unwrap();
+        assert_eq!(tracked.state, OrderState::Filled);
+        assert_eq!(tracked.filled_quantity, dec!(1));
+    }
+
+    #[test]
+    fn a_cancel_keeps_the_fills_before_it() {
+        let mut tracker = OrderTracker::default();
+        tracker.submitted(&order(dec!(1)), &ack(OrderStatus::Pending, Vec::new()));
+        let mut canceled = trade("CANCELED", dec!(0), dec!(0), dec!(0.4), dec!(40));
+        canceled.execution_type = "CANCELED".to_string();
+
+        let tracked = tracker.on_report(&canceled).unwrap();
+        assert_eq!(tracked.state, OrderState::Canceled);
+        assert_eq!(tracked.filled_quantity, dec!(0.4));
+        assert!(tracker.open().is_empty());
+    }
+}
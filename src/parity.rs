@@ -0,0 +1,305 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+#[cfg(any(test, feature = "mock"))]
+use chrono::DateTime;
+#[cfg(any(test, feature = "mock"))]
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[cfg(any(test, feature = "mock"))]
+use crate::backtest::{Backtester, FillModel, PriceHistory};
+use crate::domain::*;
+use crate::dto::Error;
+#[cfg(any(test, feature = "mock"))]
+use crate::dto::KlineResponse;
+use crate::engine::SignalEngine;
+#[cfg(any(test, feature = "mock"))]
+use crate::executor::{Trade, TradeExecutor};
+#[cfg(any(test, feature = "mock"))]
+use crate::mock::MockExchange;
+use crate::strategy::Strategy;
+
+#[cfg(any(test, feature = "mock"))]
+// Prices closer than this count as the same fill
+const PRICE_TOLERANCE: f64 = 1e-9;
+
+/// One closed candle of a live session and the signal it produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEvent {
+    pub close_time: i64,
+    pub close: f64,
+    pub signal: TradingSignal,
+}
+
+// Appends session events to a JSON lines file
+pub struct SessionRecorder {
+    out: BufWriter<File>,
+}
+
+impl SessionRecorder {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(SessionRecorder {
+            out: BufWriter::new(file),
+        })
+    }
+
+    pub fn record(&mut self, event: &SessionEvent) -> Result<(), Error> {
+        serde_json::to_writer(&mut self.out, event)?;
+        writeln!(self.out)?;
+        // Flush per event so a crashed session is still replayable
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+pub fn load_session<P: AsRef<Path>>(path: P) -> Result<Vec<SessionEvent>, Error> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut events = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        events.push(serde_json::from_str(&line)?);
+    }
+    Ok(events)
+}
+
+#[derive(Debug, Clone)]
+pub struct ParityMismatch {
+    pub close_time: i64,
+    pub live: TradeAction,
+    pub replayed: TradeAction,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParityReport {
+    pub events: usize,
+    pub mismatches: Vec<ParityMismatch>,
+}
+
+impl ParityReport {
+    pub fn is_match(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Feeds a recorded live session through a fresh `SignalEngine` and reports every
+/// candle where the replayed signal differs from the one produced live. `strategy`
+/// must be configured as it was during the session.
+pub fn replay<S: Strategy>(events: &[SessionEvent], strategy: S) -> ParityReport {
+    let symbol = events
+        .first()
+        .map(|e| e.signal.symbol.as_str())
+        .unwrap_or_default();
+    let mut engine = SignalEngine::new(symbol, strategy);
+    let mut report = ParityReport {
+        events: events.len(),
+        mismatches: Vec::new(),
+    };
+    for event in events {
+        let signal = engine.on_close(event.close_time, event.close);
+        if signal.action != event.signal.action {
+            report.mismatches.push(ParityMismatch {
+                close_time: event.close_time,
+                live: event.signal.action.clone(),
+                replayed: signal.action,
+            });
+        }
+    }
+    if !report.is_match() {
+        log::warn!(
+            "Replay diverged from live on {} of {} candles",
+            report.mismatches.len(),
+            report.events
+        );
+    }
+    report
+}
+
+#[cfg(any(test, feature = "mock"))]
+/// Closed trades at the same position in the live and backtest sequences that
+/// differ, or that only one side has
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "mock"), allow(dead_code))]
+pub struct TradeMismatch {
+    pub index: usize,
+    pub live: Option<Trade>,
+    pub backtest: Option<Trade>,
+}
+
+#[cfg(any(test, feature = "mock"))]
+#[derive(Debug, Clone, Default)]
+pub struct TradeParityReport {
+    pub live: Vec<Trade>,
+    pub backtest: Vec<Trade>,
+    pub mismatches: Vec<TradeMismatch>,
+}
+
+#[cfg(any(test, feature = "mock"))]
+impl TradeParityReport {
+    pub fn is_match(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+#[cfg(any(test, feature = "mock"))]
+fn same_fill(a: f64, b: f64) -> bool {
+    (a - b).abs() <= PRICE_TOLERANCE * a.abs().max(1.0)
+}
+
+#[cfg(any(test, feature = "mock"))]
+fn same_trade(live: &Trade, backtest: &Trade) -> bool {
+    live.side == backtest.side
+        && same_fill(live.quantity, backtest.quantity)
+        && same_fill(live.entry_price, backtest.entry_price)
+        && same_fill(live.exit_price, backtest.exit_price)
+}
+
+#[cfg(any(test, feature = "mock"))]
+// Flat candles at each recorded close; sessions don't keep the rest of the bar
+fn session_history(events: &[SessionEvent]) -> PriceHistory {
+    let symbol = events
+        .first()
+        .map(|e| e.signal.symbol.as_str())
+        .unwrap_or_default();
+    let candles = events
+        .iter()
+        .filter_map(|event| {
+            let time = DateTime::from_timestamp(event.close_time, 0)?;
+            Some(KlineResponse {
+                open_time: time,
+                open_price: event.close,
+                high_price: event.close,
+                low_price: event.close,
+                close_price: event.close,
+                volume: 0.0,
+                close_time: time,
+                quote_asset_volume: 0.0,
+                number_of_trades: 0,
+                taker_buy_base_volume: 0.0,
+                taker_buy_quote_volume: 0.0,
+            })
+        })
+        .collect();
+    PriceHistory::from_candles(symbol, 0, candles).0
+}
+
+#[cfg(any(test, feature = "mock"))]
+/// Executes a recorded session both ways: the signals produced live through the
+/// executor against a mock exchange filling at each close, and `strategy` through
+/// the backtester. Neither side pays fees or slippage, so differing trades point
+/// at the execution paths themselves.
+pub async fn compare_trades<S: Strategy>(
+    events: &[SessionEvent],
+    mut strategy: S,
+    risk: RiskParameters,
+    capital: f64,
+) -> Result<TradeParityReport, TradingError> {
+    let mut exchange = MockExchange::new(to_decimal(capital)).with_fee_rate(Decimal::ZERO);
+    exchange.connect().await?;
+    let mut executor = TradeExecutor::new(exchange.clone(), risk.clone());
+    let state = executor.state();
+    for event in events {
+        let symbol = event.signal.symbol.as_str();
+        let close = to_decimal(event.close);
+        exchange.set_price(symbol, close);
+        executor.mark_price(symbol, close).await;
+        executor.check_brackets().await;
+        if event.signal.action != TradeAction::Hold {
+            if let Err(e) = executor.handle_signal(&event.signal).await {
+                log::warn!("Signal at {} not executed: {:?}", event.close_time, e);
+            }
+        }
+    }
+    let live = state.read().await.trades.clone();
+
+    let mut backtester = Backtester::new(capital, risk);
+    backtester.fill_model = FillModel::frictionless();
+    let backtest = backtester
+        .run(&mut strategy, &session_history(events))
+        .trades;
+
+    let mut report = TradeParityReport {
+        live,
+        backtest,
+        mismatches: Vec::new(),
+    };
+    for index in 0..report.live.len().max(report.backtest.len()) {
+        let live = report.live.get(index);
+        let backtest = report.backtest.get(index);
+        let matches = match (live, backtest) {
+            (Some(live), Some(backtest)) => same_trade(live, backtest),
+            _ => false,
+        };
+        if !matches {
+            report.mismatches.push(TradeMismatch {
+                index,
+                live: live.cloned(),
+                backtest: backtest.cloned(),
+            });
+        }
+    }
+    if !report.is_match() {
+        log::warn!(
+            "Live and backtest execution differ on {} of {} trades",
+            report.mismatches.len(),
+            report.live.len().max(report.backtest.len())
+        );
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::testing::{self, HoldFor};
+
+    // A session as the bot records it, with the signals `bars` produced live
+    fn session(bars: i64) -> Vec<SessionEvent> {
+        let mut engine = SignalEngine::new("BTCUSDT", HoldFor { bars });
+        testing::peak()
+            .candles
+            .iter()
+            .map(|candle| {
+                let close_time = candle.close_time.timestamp();
+                SessionEvent {
+                    close_time,
+                    close: candle.close_price,
+                    signal: engine.on_close(close_time, candle.close_price),
+                }
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn live_and_backtest_trades_match() {
+        let events = session(5);
+        assert!(replay(&events, HoldFor { bars: 5 }).is_match());
+
+        let report = compare_trades(&events, HoldFor { bars: 5 }, testing::risk(), 1_000.0)
+            .await
+            .unwrap();
+        assert!(report.is_match(), "{:?}", report.mismatches);
+        assert_eq!(report.live.len(), 1);
+        assert_eq!(report.live[0].entry_price, 100.0);
+        assert_eq!(report.live[0].exit_price, 105.0);
+    }
+
+    #[tokio::test]
+    async fn a_differently_configured_replay_trades_differently() {
+        let events = session(5);
+        assert!(!replay(&events, HoldFor { bars: 4 }).is_match());
+
+        let report = compare_trades(&events, HoldFor { bars: 4 }, testing::risk(), 1_000.0)
+            .await
+            .unwrap();
+        assert_eq!(report.mismatches.len(), 1);
+        let mismatch = &report.mismatches[0];
+        assert_eq!(mismatch.live.as_ref().unwrap().exit_price, 105.0);
+        assert_eq!(mismatch.backtest.as_ref().unwrap().exit_price, 104.0);
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, NaiveDate};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::domain::*;
+use crate::executor::{Position, Trade};
+
+/// Realized / unrealized PnL breakdown
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "web", derive(utoipa::ToSchema))]
+pub struct PnlReport {
+    // Net of fees
+    pub realized: f64,
+    pub unrealized: f64,
+    pub fees: f64,
+    // UTC day each trade closed on
+    #[cfg_attr(feature = "web", schema(value_type = BTreeMap<String, f64>))]
+    pub realized_by_day: BTreeMap<NaiveDate, f64>,
+    pub realized_by_symbol: BTreeMap<String, f64>,
+    pub realized_by_strategy: BTreeMap<String, f64>,
+    pub open_positions: Vec<PositionPnl>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "web", derive(utoipa::ToSchema))]
+pub struct PositionPnl {
+    pub symbol: String,
+    pub strategy: String,
+    pub quantity: f64,
+    pub entry_price: f64,
+    pub mark_price: f64,
+    // Entry fees already paid and borrow interest accrued so far are included
+    pub unrealized: f64,
+}
+
+impl PnlReport {
+    pub fn total(&self) -> f64 {
+        self.realized + self.unrealized
+    }
+}
+
+pub fn unrealized_pnl(position: &Position, mark_price: Decimal) -> f64 {
+    let direction = match position.side {
+        OrderSide::Buy => Decimal::ONE,
+        OrderSide::Sell => Decimal::NEGATIVE_ONE,
+    };
+    let pnl = (mark_price - position.entry_price) * position.quantity * direction
+        - position.entry_fees
+        - position.interest;
+    from_decimal(pnl)
+}
+
+pub fn build_report(
+    trades: &[Trade],
+    positions: &HashMap<String, Position>,
+    prices: &HashMap<String, Decimal>,
+) -> PnlReport {
+    let mut report = PnlReport::default();
+
+    for trade in trades {
+        let day = DateTime::from_timestamp(trade.closed_at, 0)
+            .map(|time| time.date_naive())
+            .unwrap_or_default();
+        report.realized += trade.pnl;
+        report.fees += trade.fees;
+        *report.realized_by_day.entry(day).or_insert(0.0) += trade.pnl;
+        *report
+            .realized_by_symbol
+            .entry(trade.symbol.clone())
+            .or_insert(0.0) += trade.pnl;
+        *report
+            .realized_by_strategy
+            .entry(trade.strategy.clone())
+            .or_insert(0.0) += trade.pnl;
+    }
+
+    for position in positions.values() {
+        // Without a price yet, mark at entry
+        let mark_price = prices
+            .get(&position.symbol)
+            .copied()
+            .unwrap_or(position.entry_price);
+        let unrealized = unrealized_pnl(position, mark_price);
+        report.unrealized += unrealized;
+        report.fees += from_decimal(position.entry_fees);
+        report.open_positions.push(PositionPnl {
+            symbol: position.symbol.clone(),
+            strategy: position.strategy.clone(),
+            quantity: from_decimal(position.quantity),
+            entry_price: from_decimal(position.entry_price),
+            mark_price: from_decimal(mark_price),
+            unrealized,
+        });
+    }
+    report
+        .open_positions
+        .sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(symbol: &str, strategy: &str, pnl: f64, closed_at: i64) -> Trade {
+        Trade {
+            symbol: symbol.to_string(),
+            strategy: strategy.to_string(),
+            side: OrderSide::Buy,
+            quantity: 1.0,
+            entry_price: 100.0,
+            exit_price: 100.0 + pnl,
+            fees: 0.5,
+            pnl,
+            opened_at: closed_at - 60,
+            closed_at,
+        }
+    }
+
+    #[test]
+    fn realized_pnl_is_broken_down_by_day_symbol_and_strategy() {
+        // 2024-01-01 and 2024-01-02, UTC
+        let (first, second) = (1_704_067_200, 1_704_153_600);
+        let trades = [
+            trade("BTCUSDT", "sma", 10.0, first + 60),
+            trade("ETHUSDT", "sma", -4.0, first + 120),
+            trade("BTCUSDT", "rsi", 3.0, second + 60),
+        ];
+        let report = build_report(&trades, &HashMap::new(), &HashMap::new());
+
+        assert_eq!(report.realized, 9.0);
+        assert_eq!(report.fees, 1.5);
+        let day = |date: &str| report.realized_by_day[&date.parse::<NaiveDate>().unwrap()];
+        assert_eq!(day("2024-01-01"), 6.0);
+        assert_eq!(day("2024-01-02"), 3.0);
+        assert_eq!(report.realized_by_symbol["BTCUSDT"], 13.0);
+        assert_eq!(report.realized_by_symbol["ETHUSDT"], -4.0);
+        assert_eq!(report.realized_by_strategy["sma"], 6.0);
+        assert_eq!(report.realized_by_strategy["rsi"], 3.0);
+    }
+}
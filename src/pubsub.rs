@@ -0,0 +1,108 @@
+use futures_util::StreamExt;
+use redis::AsyncCommands;
+use tokio::sync::mpsc;
+
+use crate::domain::*;
+use crate::events::BusMessage;
+
+#[derive(Debug, Clone)]
+pub struct RedisSettings {
+    pub url: String,
+    // Channels are <prefix>:signals, <prefix>:orders, <prefix>:fills,
+    // <prefix>:risk and <prefix>:positions; external signals are read from <prefix>:signals:in
+    pub prefix: String,
+}
+
+impl RedisSettings {
+    // REDIS_URL and optional REDIS_CHANNEL_PREFIX, if set
+    pub fn from_env() -> Option<Self> {
+        Some(RedisSettings {
+            url: dotenv::var("REDIS_URL").ok()?,
+            prefix: dotenv::var("REDIS_CHANNEL_PREFIX")
+                .unwrap_or_else(|_| "auto_trade".to_string()),
+        })
+    }
+}
+
+/// Forwards published messages to Redis until every sender is dropped
+pub async fn run_publisher(settings: RedisSettings, mut receiver: mpsc::Receiver<BusMessage>) {
+    let client = match redis::Client::open(settings.url.as_str()) {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("Invalid Redis URL: {}", e);
+            return;
+        }
+    };
+    let mut conn = match client.get_multiplexed_tokio_connection().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Failed to connect to Redis: {}", e);
+            return;
+        }
+    };
+    while let Some(message) = receiver.recv().await {
+        let channel = format!("{}:{}", settings.prefix, message.channel());
+        let payload = match serde_json::to_string(&message) {
+            Ok(payload) => payload,
+            Err(e) => {
+                log::error!("Failed to serialize {:?}: {}", message, e);
+                continue;
+            }
+        };
+        let published: redis::RedisResult<i64> = conn.publish(&channel, payload).await;
+        if let Err(e) = published {
+            log::warn!("Failed to publish to {}: {}", channel, e);
+        }
+    }
+}
+
+/// Feeds JSON `TradingSignal`s published by other services into the executor
+pub async fn run_signal_subscriber(settings: RedisSettings, signals: mpsc::Sender<TradingSignal>) {
+    let channel = format!("{}:signals:in", settings.prefix);
+    let client = match redis::Client::open(settings.url.as_str()) {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("Invalid Redis URL: {}", e);
+            return;
+        }
+    };
+    let mut pubsub = match client.get_async_pubsub().await {
+        Ok(pubsub) => pubsub,
+        Err(e) => {
+            log::error!("Failed to connect to Redis: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = pubsub.subscribe(&channel).await {
+        log::error!("Failed to subscribe to {}: {}", channel, e);
+        return;
+    }
+    log::info!("Accepting external signals on {}", channel);
+
+    let mut messages = pubsub.on_message();
+    while let Some(message) = messages.next().await {
+        let payload: String = match message.get_payload() {
+            Ok(payload) => payload,
+            Err(e) => {
+                log::warn!("Unreadable message on {}: {}", channel, e);
+                continue;
+            }
+        };
+        let signal: TradingSignal = match serde_json::from_str(&payload) {
+            Ok(signal) => signal,
+            Err(e) => {
+                log::warn!("Ignoring invalid signal on {}: {}", channel, e);
+                continue;
+            }
+        };
+        log::info!(
+            "External {:?} signal for {} from {}",
+            signal.action,
+            signal.symbol,
+            signal.strategy
+        );
+        if signals.send(signal).await.is_err() {
+            break;
+        }
+    }
+}
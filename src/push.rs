@@ -0,0 +1,133 @@
+use axum::extract::ws::{Message, WebSocket};
+use serde::Serialize;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::events::{BusMessage, MarketEvent};
+
+// Events waiting for slow subscribers before they start skipping
+const PUSH_BUFFER: usize = 4096;
+
+/// Market data and trading activity as pushed to websocket clients; both
+/// already carry a "type" field
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum PushEvent {
+    Market(MarketEvent),
+    Trading(BusMessage),
+}
+
+impl PushEvent {
+    // "market", "signals", "orders", "fills", "risk" or "positions"
+    pub fn topic(&self) -> &'static str {
+        match self {
+            PushEvent::Market(_) => "market",
+            PushEvent::Trading(message) => message.channel(),
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        match self {
+            PushEvent::Market(event) => event.symbol(),
+            PushEvent::Trading(message) => message.symbol(),
+        }
+    }
+}
+
+pub type PushSender = broadcast::Sender<PushEvent>;
+
+pub fn channel() -> PushSender {
+    broadcast::channel(PUSH_BUFFER).0
+}
+
+/// Merges the market and executor feeds into `push` for every subscriber
+pub async fn run(
+    push: PushSender,
+    mut market: mpsc::Receiver<MarketEvent>,
+    mut bus: mpsc::Receiver<BusMessage>,
+) {
+    loop {
+        let event = tokio::select! {
+            Some(event) = market.recv() => PushEvent::Market(event),
+            Some(message) = bus.recv() => PushEvent::Trading(message),
+            else => break,
+        };
+        // Fails only while nobody is subscribed
+        let _ = push.send(event);
+    }
+}
+
+/// Which events a client asked for; empty means everything
+#[derive(Debug, Clone, Default)]
+pub struct Subscription {
+    pub topics: Vec<String>,
+    pub symbols: Vec<String>,
+}
+
+impl Subscription {
+    // From comma separated lists, e.g. topics=signals,fills and symbols=BTCUSDT
+    pub fn parse(topics: Option<&str>, symbols: Option<&str>) -> Self {
+        let list = |value: Option<&str>, upper: bool| -> Vec<String> {
+            value
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| {
+                    if upper {
+                        item.to_uppercase()
+                    } else {
+                        item.to_lowercase()
+                    }
+                })
+                .collect()
+        };
+        Subscription {
+            topics: list(topics, false),
+            symbols: list(symbols, true),
+        }
+    }
+
+    fn wants(&self, event: &PushEvent) -> bool {
+        (self.topics.is_empty() || self.topics.iter().any(|topic| topic == event.topic()))
+            && (self.symbols.is_empty() || self.symbols.iter().any(|s| s == event.symbol()))
+    }
+}
+
+/// Sends every matching event to one websocket client as JSON text
+pub async fn stream(mut socket: WebSocket, push: PushSender, subscription: Subscription) {
+    let mut events = push.subscribe();
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) if subscription.wants(&event) => event,
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!("Websocket client lagged, skipped {} events", skipped);
+                        let notice = serde_json::json!({ "type": "lagged", "skipped": skipped });
+                        if socket.send(Message::Text(notice.to_string())).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let text = match serde_json::to_string(&event) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        log::error!("Failed to encode push event: {:?}", e);
+                        continue;
+                    }
+                };
+                if socket.send(Message::Text(text)).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by axum; nothing else is expected
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
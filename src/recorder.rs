@@ -0,0 +1,101 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::dto::Error;
+use crate::retention;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamKind {
+    Kline,
+    Ticker,
+    Depth,
+}
+
+/// A websocket message exactly as it arrived
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedMessage {
+    // Local receive time, milliseconds
+    pub received_at: i64,
+    pub kind: StreamKind,
+    pub raw: String,
+}
+
+// Appends raw market data messages to a JSON lines file
+pub struct MarketRecorder {
+    out: BufWriter<File>,
+    path: PathBuf,
+}
+
+impl MarketRecorder {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(MarketRecorder {
+            out: BufWriter::new(file),
+            path: path.as_ref().to_path_buf(),
+        })
+    }
+
+    // Drop messages received before `cutoff` (ms) and keep appending to the new file
+    pub fn prune_before(&mut self, cutoff: i64) -> Result<usize, Error> {
+        self.out.flush()?;
+        let removed = retention::prune_json_lines(&self.path, "received_at", cutoff)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.out = BufWriter::new(file);
+        Ok(removed)
+    }
+
+    pub fn record(&mut self, kind: StreamKind, raw: &str) -> Result<(), Error> {
+        let message = RecordedMessage {
+            received_at: chrono::Utc::now().timestamp_millis(),
+            kind,
+            raw: raw.to_string(),
+        };
+        serde_json::to_writer(&mut self.out, &message)?;
+        writeln!(self.out)?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+pub fn load_recording<P: AsRef<Path>>(path: P) -> Result<Vec<RecordedMessage>, Error> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut messages = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        messages.push(serde_json::from_str(&line)?);
+    }
+    Ok(messages)
+}
+
+pub type SharedRecorder = Arc<Mutex<MarketRecorder>>;
+
+// Recorder for the path in MARKET_RECORD_PATH, if set
+pub fn from_env() -> Option<SharedRecorder> {
+    let path = dotenv::var("MARKET_RECORD_PATH").ok()?;
+    match MarketRecorder::create(&path) {
+        Ok(recorder) => Some(Arc::new(Mutex::new(recorder))),
+        Err(e) => {
+            log::error!("Failed to open market recording {}: {}", path, e);
+            None
+        }
+    }
+}
+
+pub fn record(recorder: &Option<SharedRecorder>, kind: StreamKind, raw: &str) {
+    if let Some(recorder) = recorder {
+        if let Err(e) = recorder.lock().unwrap().record(kind, raw) {
+            log::error!("Failed to record {:?} message: {}", kind, e);
+        }
+    }
+}
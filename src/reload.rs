@@ -0,0 +1,291 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::config::{ConfigSource, Profile};
+use crate::control::{self, ControlCommand, ControlSender, TuningCommand, TuningSender};
+use crate::domain::Symbol;
+use crate::notify::AlertSettings;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+// The one environment setting read again on changes
+const DRAWDOWN_LIMIT: &str = "NOTIFY_DRAWDOWN_LIMIT";
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn differs<T: Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() != serde_json::to_value(b).ok()
+}
+
+// Changed settings that only take effect on a restart
+fn restart_required(running: &Profile, loaded: &Profile) -> Vec<String> {
+    let mut changed = Vec::new();
+    let trading = [
+        (
+            "exchange",
+            running.trading.exchange.clone(),
+            loaded.trading.exchange.clone(),
+        ),
+        (
+            "symbol",
+            running.trading.symbol.to_string(),
+            loaded.trading.symbol.to_string(),
+        ),
+        (
+            "interval",
+            running.trading.interval.to_string(),
+            loaded.trading.interval.to_string(),
+        ),
+        (
+            "strategy",
+            running.trading.strategy.clone(),
+            loaded.trading.strategy.clone(),
+        ),
+        (
+            "testnet",
+            running.trading.testnet.to_string(),
+            loaded.trading.testnet.to_string(),
+        ),
+    ];
+    for (name, old, new) in trading {
+        if old != new {
+            changed.push(format!("trading.{} ({} -> {})", name, old, new));
+        }
+    }
+    let sections = [
+        (
+            "execution",
+            differs(&running.trading.execution, &loaded.trading.execution),
+        ),
+        (
+            "futures",
+            differs(&running.trading.futures, &loaded.trading.futures),
+        ),
+        (
+            "paper",
+            differs(&running.trading.paper, &loaded.trading.paper),
+        ),
+        ("venues", running.trading.venues != loaded.trading.venues),
+    ];
+    for (name, changed_section) in sections {
+        if changed_section {
+            changed.push(format!("trading.{}", name));
+        }
+    }
+    // The traded symbol's section is already merged into the settings above,
+    // the risk limits and the parameters; the others only apply to a bot
+    // started on their symbol
+    let mut symbols: Vec<&Symbol> = running
+        .trading
+        .symbols
+        .keys()
+        .chain(loaded.trading.symbols.keys())
+        .filter(|symbol| **symbol != running.trading.symbol)
+        .collect();
+    symbols.sort();
+    symbols.dedup();
+    for symbol in symbols {
+        if differs(
+            &running.trading.symbols.get(symbol),
+            &loaded.trading.symbols.get(symbol),
+        ) {
+            changed.push(format!("trading.symbols.{}", symbol));
+        }
+    }
+    if differs(&running.alerts, &loaded.alerts) {
+        changed.push("alerts".to_string());
+    }
+    if differs(&running.logging, &loaded.logging) {
+        changed.push("logging".to_string());
+    }
+    let mut names: Vec<&String> = running
+        .environment
+        .keys()
+        .chain(loaded.environment.keys())
+        .filter(|name| *name != DRAWDOWN_LIMIT)
+        .collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        if running.environment.get(name) != loaded.environment.get(name) {
+            changed.push(format!("environment.{}", name));
+        }
+    }
+    changed
+}
+
+// Risk limits, strategy parameters and the drawdown alert limit are applied to
+// the running bot; the returned profile is what is in effect afterwards
+async fn apply(
+    mut running: Profile,
+    loaded: &Profile,
+    control: &ControlSender,
+    tuning: &TuningSender,
+    alerts: &watch::Sender<AlertSettings>,
+) -> Profile {
+    if differs(&running.risk, &loaded.risk) {
+        let command = ControlCommand::UpdateRisk(loaded.risk.clone());
+        match control::send_and_wait(control, command, "config", COMMAND_TIMEOUT).await {
+            Ok(_) => {
+                log::warn!("Risk limits reloaded: {:?}", loaded.risk);
+                running.risk = loaded.risk.clone();
+            }
+            Err(e) => log::error!("Failed to apply reloaded risk limits: {:?}", e),
+        }
+    }
+    // Parameters of another strategy only make sense after a restart
+    if running.trading.strategy == loaded.trading.strategy {
+        let (old, new) = match (running.build_strategy(), loaded.build_strategy()) {
+            (Ok(old), Ok(new)) => (old.parameters(), new.parameters()),
+            _ => (Vec::new(), Vec::new()),
+        };
+        let mut applied = true;
+        for parameter in new {
+            let unchanged = old
+                .iter()
+                .any(|p| p.name == parameter.name && p.value == parameter.value);
+            if unchanged {
+                continue;
+            }
+            let command = TuningCommand::Update {
+                name: parameter.name.clone(),
+                value: parameter.value,
+            };
+            if let Err(e) = control::tune(tuning, command, COMMAND_TIMEOUT).await {
+                log::error!("Failed to apply reloaded {}: {:?}", parameter.name, e);
+                applied = false;
+            }
+        }
+        if applied {
+            running.parameters = loaded.parameters.clone();
+        }
+    }
+    let limit = loaded.environment.get(DRAWDOWN_LIMIT);
+    if running.environment.get(DRAWDOWN_LIMIT) != limit {
+        log::warn!("Drawdown alert limit reloaded: {:?}", limit);
+        alerts.send_replace(AlertSettings::parse(limit.map(String::as_str)));
+        match limit {
+            Some(limit) => running
+                .environment
+                .insert(DRAWDOWN_LIMIT.to_string(), limit.clone()),
+            None => running.environment.remove(DRAWDOWN_LIMIT),
+        };
+    }
+    running
+}
+
+/// Watches the configuration file and applies what can change while trading;
+/// anything else is refused with a message until the next restart.
+/// `running` is the effective configuration the bot started with.
+pub async fn run(
+    source: ConfigSource,
+    mut running: Profile,
+    control: ControlSender,
+    tuning: TuningSender,
+    alerts: watch::Sender<AlertSettings>,
+) {
+    let Some(path) = source.file.clone() else {
+        return;
+    };
+    log::info!("Watching {} for configuration changes", path.display());
+    let mut last_modified = modified(&path);
+    let mut poll = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        poll.tick().await;
+        let current = modified(&path);
+        if current == last_modified {
+            continue;
+        }
+        last_modified = current;
+        let loaded = match source.load() {
+            Ok(loaded) => loaded.for_symbol(&loaded.trading.symbol),
+            Err(e) => {
+                log::error!(
+                    "Ignoring changed {}, it is invalid: {:?}",
+                    path.display(),
+                    e
+                );
+                continue;
+            }
+        };
+        for setting in restart_required(&running, &loaded) {
+            log::error!(
+                "Configuration change to {} needs a restart, keeping the running value",
+                setting
+            );
+        }
+        running = apply(running, &loaded, &control, &tuning, &alerts).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SymbolConfig;
+    use rust_decimal_macros::dec;
+
+    fn symbol(s: &str) -> Symbol {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn risk_and_parameter_changes_apply_without_a_restart() {
+        let running = Profile::default();
+        let mut loaded = running.clone();
+        loaded.risk.max_position_size = dec!(250);
+        loaded
+            .environment
+            .insert(DRAWDOWN_LIMIT.to_string(), "50".to_string());
+        assert!(restart_required(&running, &loaded).is_empty());
+    }
+
+    #[test]
+    fn symbol_sections_need_a_restart() {
+        let running = Profile::default();
+        let mut loaded = running.clone();
+        let section = SymbolConfig {
+            max_position_size: Some(dec!(50)),
+            ..SymbolConfig::default()
+        };
+        loaded.trading.symbols.insert(symbol("ETHUSDT"), section);
+        loaded.trading.testnet = true;
+        loaded.trading.venues.push("kraken:XBTUSD".to_string());
+        assert_eq!(
+            restart_required(&running, &loaded),
+            vec![
+                "trading.testnet (false -> true)",
+                "trading.venues",
+                "trading.symbols.ETHUSDT"
+            ]
+        );
+    }
+
+    #[test]
+    fn notifier_settings_need_a_restart() {
+        let mut running = Profile::default();
+        running
+            .environment
+            .insert("TELEGRAM_CHAT_IDS".to_string(), "1".to_string());
+        let mut loaded = running.clone();
+        loaded
+            .environment
+            .insert("TELEGRAM_CHAT_IDS".to_string(), "1,2".to_string());
+        loaded
+            .environment
+            .insert("SLACK_WEBHOOK_URL".to_string(), "https://hooks".to_string());
+        assert_eq!(
+            restart_required(&running, &loaded),
+            vec![
+                "environment.SLACK_WEBHOOK_URL",
+                "environment.TELEGRAM_CHAT_IDS"
+            ]
+        );
+    }
+}
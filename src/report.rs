@@ -0,0 +1,199 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::domain::*;
+use crate::executor::{SharedExecutorState, Trade};
+use crate::notify::{self, Notification, NotifySender};
+use crate::portfolio;
+
+// How many symbols and strategies are listed as top contributors
+const TOP_CONTRIBUTORS: usize = 3;
+
+/// End-of-day summary of the trades closed on one UTC day
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyReport {
+    pub date: NaiveDate,
+    pub trades: usize,
+    pub wins: usize,
+    pub win_rate_pct: f64,
+    // Net of fees
+    pub pnl: f64,
+    pub fees: f64,
+    // Largest peak-to-trough fall of the day's PnL, realized plus unrealized
+    // where the live bot sampled it, quote currency
+    pub max_intraday_drawdown: f64,
+    // Largest absolute PnL first
+    pub top_symbols: Vec<(String, f64)>,
+    pub top_strategies: Vec<(String, f64)>,
+}
+
+fn top(pnl_by_name: BTreeMap<String, f64>) -> Vec<(String, f64)> {
+    let mut contributors: Vec<(String, f64)> = pnl_by_name.into_iter().collect();
+    contributors.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+    contributors.truncate(TOP_CONTRIBUTORS);
+    contributors
+}
+
+impl DailyReport {
+    // `sampled_drawdown` is what was seen live between trades, if anything
+    pub fn build(trades: &[Trade], date: NaiveDate, sampled_drawdown: f64) -> Self {
+        let mut day: Vec<Trade> = trades
+            .iter()
+            .filter(|trade| {
+                chrono::DateTime::from_timestamp(trade.closed_at, 0)
+                    .is_some_and(|closed| closed.date_naive() == date)
+            })
+            .cloned()
+            .collect();
+        day.sort_by_key(|trade| trade.closed_at);
+
+        // Realized PnL curve through the day, starting flat
+        let mut cumulative = 0.0;
+        let mut peak = 0.0_f64;
+        let mut max_drawdown = sampled_drawdown;
+        for trade in &day {
+            cumulative += trade.pnl;
+            peak = peak.max(cumulative);
+            max_drawdown = max_drawdown.max(peak - cumulative);
+        }
+        // Broken down the same way as the control API's /pnl
+        let breakdown = portfolio::build_report(&day, &HashMap::new(), &HashMap::new());
+
+        let wins = day.iter().filter(|trade| trade.pnl > 0.0).count();
+        DailyReport {
+            date,
+            trades: day.len(),
+            wins,
+            win_rate_pct: if day.is_empty() {
+                0.0
+            } else {
+                wins as f64 / day.len() as f64 * 100.0
+            },
+            pnl: breakdown.realized,
+            fees: breakdown.fees,
+            max_intraday_drawdown: max_drawdown,
+            top_symbols: top(breakdown.realized_by_symbol),
+            top_strategies: top(breakdown.realized_by_strategy),
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let contributors = |list: &[(String, f64)]| {
+            list.iter()
+                .map(|(name, pnl)| format!("{} {:+.2}", name, pnl))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut text = format!(
+            "{} trades, {:.1}% winners\nPnL {:.2} after {:.2} fees\nMax intraday drawdown {:.2}",
+            self.trades, self.win_rate_pct, self.pnl, self.fees, self.max_intraday_drawdown
+        );
+        if !self.top_symbols.is_empty() {
+            text.push_str(&format!("\nSymbols: {}", contributors(&self.top_symbols)));
+            text.push_str(&format!(
+                "\nStrategies: {}",
+                contributors(&self.top_strategies)
+            ));
+        }
+        text
+    }
+
+    // <dir>/daily-<date>.json
+    pub fn save(&self, dir: &Path) -> Result<PathBuf, TradingError> {
+        let path = dir.join(format!("daily-{}.json", self.date));
+        let contents = serde_json::to_string_pretty(self)?;
+        std::fs::create_dir_all(dir)
+            .and_then(|_| std::fs::write(&path, contents))
+            .map_err(|e| TradingError::DataError(format!("{}: {:?}", path.display(), e)))?;
+        Ok(path)
+    }
+}
+
+/// Samples the day's PnL for its drawdown and, once the UTC day is over, stores
+/// its report under `report_dir` and sends it to the notifiers
+pub async fn run_daily_reports(
+    state: SharedExecutorState,
+    report_dir: Option<PathBuf>,
+    notifiers: Vec<NotifySender>,
+) {
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+    let mut day = chrono::Utc::now().date_naive();
+    let mut peak = 0.0_f64;
+    let mut max_drawdown = 0.0_f64;
+    loop {
+        interval.tick().await;
+        let today = chrono::Utc::now().date_naive();
+        if today != day {
+            let report = DailyReport::build(&state.read().await.trades, day, max_drawdown);
+            if let Some(dir) = &report_dir {
+                match report.save(dir) {
+                    Ok(path) => log::info!("Saved daily report {}", path.display()),
+                    Err(e) => log::error!("Failed to save daily report: {:?}", e),
+                }
+            }
+            notify::broadcast(&notifiers, Notification::DailySummary { report });
+            day = today;
+            peak = 0.0;
+            max_drawdown = 0.0;
+        }
+
+        let pnl = {
+            let state = state.read().await;
+            state.daily_pnl() + state.pnl_report().unrealized
+        };
+        peak = peak.max(pnl);
+        max_drawdown = max_drawdown.max(peak - pnl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(symbol: &str, strategy: &str, pnl: f64, closed_at: i64) -> Trade {
+        Trade {
+            symbol: symbol.to_string(),
+            strategy: strategy.to_string(),
+            side: OrderSide::Buy,
+            quantity: 1.0,
+            entry_price: 100.0,
+            exit_price: 100.0 + pnl,
+            fees: 0.5,
+            pnl,
+            opened_at: closed_at - 60,
+            closed_at,
+        }
+    }
+
+    #[test]
+    fn the_daily_report_matches_the_pnl_breakdown_for_its_day() {
+        // 2024-01-01 and 2024-01-02, UTC
+        let (first, second) = (1_704_067_200, 1_704_153_600);
+        let trades = [
+            trade("BTCUSDT", "sma", 10.0, first + 60),
+            trade("ETHUSDT", "rsi", -4.0, first + 120),
+            trade("BTCUSDT", "rsi", 3.0, second + 60),
+        ];
+        let date: NaiveDate = "2024-01-01".parse().unwrap();
+        let report = DailyReport::build(&trades, date, 0.0);
+        let breakdown = portfolio::build_report(&trades, &HashMap::new(), &HashMap::new());
+
+        assert_eq!(report.trades, 2);
+        assert_eq!(report.pnl, breakdown.realized_by_day[&date]);
+        assert_eq!(report.fees, 1.0);
+        assert_eq!(
+            report.top_symbols,
+            vec![("BTCUSDT".to_string(), 10.0), ("ETHUSDT".to_string(), -4.0)]
+        );
+        assert_eq!(
+            report.top_strategies,
+            vec![("sma".to_string(), 10.0), ("rsi".to_string(), -4.0)]
+        );
+        // Falls from 10 to 6 after the second trade
+        assert_eq!(report.max_intraday_drawdown, 4.0);
+    }
+}
@@ -0,0 +1,161 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::dto::Error;
+use crate::journal::SharedJournal;
+use crate::recorder::SharedRecorder;
+use crate::storage::CandleStore;
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// How long each kind of data is kept; `None` keeps it forever
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPolicy {
+    pub candles_days: Option<i64>,
+    pub recordings_days: Option<i64>,
+    pub journal_days: Option<i64>,
+    pub logs_days: Option<i64>,
+    // Where the deployment writes log files, if it does
+    pub log_dir: Option<PathBuf>,
+    pub interval: Duration,
+}
+
+fn days(name: &str) -> Option<i64> {
+    dotenv::var(name)
+        .ok()?
+        .parse()
+        .ok()
+        .filter(|days| *days > 0)
+}
+
+impl RetentionPolicy {
+    // RETAIN_CANDLES_DAYS, RETAIN_RECORDINGS_DAYS, RETAIN_JOURNAL_DAYS and
+    // RETAIN_LOGS_DAYS (with LOG_DIR); pruning runs hourly
+    pub fn from_env() -> Self {
+        RetentionPolicy {
+            candles_days: days("RETAIN_CANDLES_DAYS"),
+            recordings_days: days("RETAIN_RECORDINGS_DAYS"),
+            journal_days: days("RETAIN_JOURNAL_DAYS"),
+            logs_days: days("RETAIN_LOGS_DAYS"),
+            log_dir: dotenv::var("LOG_DIR").ok().map(PathBuf::from),
+            interval: Duration::from_secs(3600),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candles_days.is_none()
+            && self.recordings_days.is_none()
+            && self.journal_days.is_none()
+            && self.logs_days.is_none()
+    }
+}
+
+fn cutoff_ms(days: i64) -> i64 {
+    chrono::Utc::now().timestamp_millis() - days * DAY_MS
+}
+
+// Rewrite a JSON lines file without entries whose `time_field` (ms) is before
+// `cutoff`; the caller must keep writers out meanwhile. Returns lines removed.
+pub fn prune_json_lines(path: &Path, time_field: &str, cutoff: i64) -> Result<usize, Error> {
+    let tmp = path.with_extension("pruning");
+    let mut removed = 0;
+    {
+        let reader = BufReader::new(File::open(path)?);
+        let mut out = BufWriter::new(File::create(&tmp)?);
+        for line in reader.lines() {
+            let line = line?;
+            let time = serde_json::from_str::<serde_json::Value>(&line)
+                .ok()
+                .and_then(|value| value.get(time_field).and_then(|t| t.as_i64()));
+            if time.is_some_and(|time| time < cutoff) {
+                removed += 1;
+                continue;
+            }
+            writeln!(out, "{}", line)?;
+        }
+        out.flush()?;
+        out.get_ref().sync_all()?;
+    }
+    fs::rename(&tmp, path)?;
+    Ok(removed)
+}
+
+// Delete files in `dir` last modified more than `days` ago
+fn prune_files(dir: &Path, days: i64) -> Result<usize, Error> {
+    let max_age = Duration::from_millis((days * DAY_MS) as u64);
+    let mut removed = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+        if metadata.is_file() && age.is_some_and(|age| age > max_age) {
+            fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+fn prune_once(
+    policy: &RetentionPolicy,
+    candles: Option<&CandleStore>,
+    recorder: &Option<SharedRecorder>,
+    journal: &Option<SharedJournal>,
+) {
+    if let (Some(days), Some(store)) = (policy.candles_days, candles) {
+        match store.prune_before(cutoff_ms(days)) {
+            Ok(removed) => log::info!("Pruned {} candles older than {} days", removed, days),
+            Err(e) => log::warn!("Failed to prune candles: {}", e),
+        }
+    }
+    if let (Some(days), Some(recorder)) = (policy.recordings_days, recorder) {
+        match recorder.lock().unwrap().prune_before(cutoff_ms(days)) {
+            Ok(removed) => log::info!("Pruned {} recorded messages", removed),
+            Err(e) => log::warn!("Failed to prune market recording: {}", e),
+        }
+    }
+    if let (Some(days), Some(journal)) = (policy.journal_days, journal) {
+        match journal.lock().unwrap().prune_before(cutoff_ms(days)) {
+            Ok(removed) => log::info!("Pruned {} journal events", removed),
+            Err(e) => log::warn!("Failed to prune event journal: {}", e),
+        }
+    }
+    if let (Some(days), Some(dir)) = (policy.logs_days, &policy.log_dir) {
+        match prune_files(dir, days) {
+            Ok(removed) => log::info!("Removed {} old log files", removed),
+            Err(e) => log::warn!("Failed to prune logs in {}: {}", dir.display(), e),
+        }
+    }
+}
+
+/// Applies the policy on every interval so long-running deployments stay bounded
+pub async fn run_pruning(
+    policy: RetentionPolicy,
+    candles: Option<CandleStore>,
+    recorder: Option<SharedRecorder>,
+    journal: Option<SharedJournal>,
+) {
+    let mut interval = tokio::time::interval(policy.interval);
+    loop {
+        interval.tick().await;
+        let (policy, candles, recorder, journal) = (
+            policy.clone(),
+            candles.clone(),
+            recorder.clone(),
+            journal.clone(),
+        );
+        // File rewrites and deletes are blocking work
+        let pruned = tokio::task::spawn_blocking(move || {
+            prune_once(&policy, candles.as_ref(), &recorder, &journal)
+        })
+        .await;
+        if let Err(e) = pruned {
+            log::error!("Pruning task failed: {}", e);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc;
+
+use crate::control::{ControlCommand, ControlRequest, ControlSender};
+
+#[derive(Debug)]
+enum Incident {
+    Panic(String),
+    Error(String),
+}
+
+// Global so the panic hook, which can't capture state, can report to the monitor
+static INCIDENTS: OnceLock<mpsc::UnboundedSender<Incident>> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+pub struct SafeModeSettings {
+    // This many errors within `window` trips safe mode; any panic trips it at once
+    pub max_errors: usize,
+    pub window: Duration,
+}
+
+impl SafeModeSettings {
+    // SAFE_MODE_MAX_ERRORS (default 5) within SAFE_MODE_WINDOW_SECS (default 300)
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: u64| {
+            dotenv::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+        SafeModeSettings {
+            max_errors: var("SAFE_MODE_MAX_ERRORS", 5).max(1) as usize,
+            window: Duration::from_secs(var("SAFE_MODE_WINDOW_SECS", 300)),
+        }
+    }
+}
+
+// Count an unexpected error against the error budget
+pub fn report_error(message: String) {
+    if let Some(incidents) = INCIDENTS.get() {
+        let _ = incidents.send(Incident::Error(message));
+    }
+}
+
+/// Installs the panic hook and watches panics and the error budget, asking the
+/// executor's task to enter safe mode (no new entries, exits still managed) when
+/// either trips. Operators leave safe mode with a resume command.
+pub async fn run_monitor(settings: SafeModeSettings, control: ControlSender) {
+    let (incidents_tx, mut incidents) = mpsc::unbounded_channel();
+    if INCIDENTS.set(incidents_tx).is_err() {
+        log::warn!("Safe mode monitor already running");
+        return;
+    }
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if let Some(incidents) = INCIDENTS.get() {
+            let _ = incidents.send(Incident::Panic(info.to_string()));
+        }
+    }));
+
+    let mut recent_errors: VecDeque<Instant> = VecDeque::new();
+    while let Some(incident) = incidents.recv().await {
+        let reason = match incident {
+            Incident::Panic(message) => format!("Panic: {}", message),
+            Incident::Error(message) => {
+                let now = Instant::now();
+                recent_errors.push_back(now);
+                while recent_errors
+                    .front()
+                    .is_some_and(|time| now.duration_since(*time) > settings.window)
+                {
+                    recent_errors.pop_front();
+                }
+                if recent_errors.len() < settings.max_errors {
+                    continue;
+                }
+                recent_errors.clear();
+                format!(
+                    "{} errors within {:?}, last: {}",
+                    settings.max_errors, settings.window, message
+                )
+            }
+        };
+
+        // Already being in safe mode is checked where the command is applied
+        let request = ControlRequest {
+            command: ControlCommand::EnterSafeMode {
+                reason: reason.clone(),
+            },
+            requested_by: "safe_mode".to_string(),
+            reply: None,
+        };
+        if control.send(request).is_err() {
+            log::error!("Signal task gone, can't enter safe mode: {}", reason);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::io::{Read, Write};
+use std::path::Path;
+
+use age::secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::TradingError;
+
+const KEYRING_API_KEY: &str = "api_key";
+const KEYRING_API_SECRET: &str = "api_secret";
+
+/// Exchange API credentials, wherever they were loaded from
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ApiCredentials {
+    pub api_key: String,
+    pub api_secret: String,
+}
+
+// Never print the secret by accident
+impl std::fmt::Debug for ApiCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ApiCredentials")
+            .field("api_key", &self.api_key)
+            .finish_non_exhaustive()
+    }
+}
+
+fn secret_error<E: std::fmt::Debug>(e: E) -> TradingError {
+    TradingError::AuthenticationError(format!("{:?}", e))
+}
+
+fn keyring_service() -> String {
+    dotenv::var("KEYRING_SERVICE").unwrap_or_else(|_| "auto_trade".to_string())
+}
+
+// CREDENTIALS_PASSPHRASE if set, otherwise asked for on the terminal
+fn passphrase(prompt: &str) -> Result<String, TradingError> {
+    match dotenv::var("CREDENTIALS_PASSPHRASE") {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => rpassword::prompt_password(prompt).map_err(secret_error),
+    }
+}
+
+pub fn prompt(label: &str) -> Result<String, TradingError> {
+    rpassword::prompt_password(format!("{}: ", label)).map_err(secret_error)
+}
+
+pub fn load_keyring() -> Result<ApiCredentials, TradingError> {
+    let service = keyring_service();
+    let get = |user: &str| {
+        keyring::Entry::new(&service, user)
+            .and_then(|entry| entry.get_password())
+            .map_err(secret_error)
+    };
+    Ok(ApiCredentials {
+        api_key: get(KEYRING_API_KEY)?,
+        api_secret: get(KEYRING_API_SECRET)?,
+    })
+}
+
+pub fn store_keyring(credentials: &ApiCredentials) -> Result<(), TradingError> {
+    let service = keyring_service();
+    let set = |user: &str, value: &str| {
+        keyring::Entry::new(&service, user)
+            .and_then(|entry| entry.set_password(value))
+            .map_err(secret_error)
+    };
+    set(KEYRING_API_KEY, &credentials.api_key)?;
+    set(KEYRING_API_SECRET, &credentials.api_secret)
+}
+
+// age file encrypted with a passphrase (scrypt), holding the credentials as JSON
+pub fn load_encrypted(path: &Path) -> Result<ApiCredentials, TradingError> {
+    let encrypted = std::fs::read(path)
+        .map_err(|e| TradingError::DataError(format!("{}: {:?}", path.display(), e)))?;
+    let decryptor = match age::Decryptor::new(&encrypted[..]).map_err(secret_error)? {
+        age::Decryptor::Passphrase(decryptor) => decryptor,
+        _ => {
+            return Err(TradingError::AuthenticationError(format!(
+                "{} is not passphrase-encrypted",
+                path.display()
+            )))
+        }
+    };
+    let passphrase = passphrase(&format!("Passphrase for {}: ", path.display()))?;
+    let mut plaintext = Vec::new();
+    decryptor
+        .decrypt(&Secret::new(passphrase), None)
+        .map_err(secret_error)?
+        .read_to_end(&mut plaintext)
+        .map_err(secret_error)?;
+    serde_json::from_slice(&plaintext).map_err(secret_error)
+}
+
+pub fn store_encrypted(path: &Path, credentials: &ApiCredentials) -> Result<(), TradingError> {
+    let passphrase = passphrase("New passphrase: ")?;
+    let plaintext = serde_json::to_vec(credentials).map_err(secret_error)?;
+    let mut encrypted = Vec::new();
+    let mut writer = age::Encryptor::with_user_passphrase(Secret::new(passphrase))
+        .wrap_output(&mut encrypted)
+        .map_err(secret_error)?;
+    writer.write_all(&plaintext).map_err(secret_error)?;
+    writer.finish().map_err(secret_error)?;
+    std::fs::write(path, encrypted)
+        .map_err(|e| TradingError::DataError(format!("{}: {:?}", path.display(), e)))
+}
+
+/// CREDENTIALS_FILE (encrypted) first, then the OS keyring when
+/// CREDENTIALS_SOURCE=keyring, then plaintext BINANCE_API_KEY / BINANCE_API_SECRET
+pub fn load_credentials() -> Result<ApiCredentials, TradingError> {
+    if let Ok(path) = dotenv::var("CREDENTIALS_FILE") {
+        log::info!("Loading API credentials from {}", path);
+        return load_encrypted(Path::new(&path));
+    }
+    if dotenv::var("CREDENTIALS_SOURCE").is_ok_and(|source| source == "keyring") {
+        log::info!("Loading API credentials from the OS keyring");
+        return load_keyring();
+    }
+    let var = |name: &str| {
+        dotenv::var(name)
+            .map_err(|_| TradingError::AuthenticationError(format!("{} must be set", name)))
+    };
+    log::warn!("Using plaintext API credentials from the environment");
+    Ok(ApiCredentials {
+        api_key: var("BINANCE_API_KEY")?,
+        api_secret: var("BINANCE_API_SECRET")?,
+    })
+}
@@ -0,0 +1,227 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::*;
+use crate::execution::ParentOrder;
+use crate::executor::{Position, SharedExecutorState, Trade, WorkingOrder};
+use crate::orders::TrackedOrder;
+use crate::strategy::ParameterValue;
+
+// Bump whenever a field changes meaning or shape; older snapshots are then
+// refused unless `load` knows how to migrate them
+pub const SNAPSHOT_VERSION: u32 = 3;
+
+// Version 2 held prices, quantities and money as JSON numbers, which read into
+// the Decimal fields of version 3 as they are
+const FLOAT_AMOUNTS_VERSION: u64 = 2;
+
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(30);
+
+// Orders are cancelled and looked up by the symbol they were placed on, so
+// nothing mapping them to a venue needs saving
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutorSnapshot {
+    pub positions: Vec<Position>,
+    pub working_orders: Vec<WorkingOrder>,
+    // Entries being sliced, with their resting child if any
+    pub parent_orders: Vec<ParentOrder>,
+    // Orders still open on the exchange and what they filled so far, so
+    // reports arriving after the restart pick up where they left off
+    pub open_orders: Vec<(TrackedOrder, Vec<Fill>)>,
+    pub trades: Vec<Trade>,
+    pub daily_pnl: f64,
+    pub pnl_day: NaiveDate,
+    // A paused bot stays paused when resumed from the snapshot
+    #[serde(default)]
+    pub paused: bool,
+    // As do disabled strategies
+    #[serde(default)]
+    pub strategies: BTreeMap<String, bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategySnapshot {
+    pub name: String,
+    pub parameters: Vec<(String, ParameterValue)>,
+    // Closes the engine had seen, oldest first
+    pub closes: Vec<f64>,
+}
+
+/// Everything needed to pick up trading where a crashed or restarted bot left off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    // Seconds
+    pub saved_at: i64,
+    pub symbol: String,
+    pub risk: RiskParameters,
+    pub executor: ExecutorSnapshot,
+    pub strategy: Option<StrategySnapshot>,
+}
+
+// Latest strategy state, published by the analysis task after every candle
+pub type SharedStrategySnapshot = Arc<Mutex<Option<StrategySnapshot>>>;
+
+// Write to a temporary file and rename it over the old snapshot, so a crash
+// mid-write leaves the previous snapshot intact
+pub fn save<P: AsRef<Path>>(path: P, snapshot: &Snapshot) -> Result<(), TradingError> {
+    let path = path.as_ref();
+    let tmp = path.with_extension("tmp");
+    let write = || -> std::io::Result<()> {
+        let mut file = File::create(&tmp)?;
+        file.write_all(&serde_json::to_vec_pretty(snapshot)?)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    };
+    write().map_err(|e| TradingError::DataError(format!("{}: {:?}", path.display(), e)))
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Snapshot, TradingError> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path)
+        .map_err(|e| TradingError::DataError(format!("{}: {:?}", path.display(), e)))?;
+    // Check the version before the layout, so an old file gets a clear error
+    let value: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| TradingError::DataError(format!("{}: {:?}", path.display(), e)))?;
+    let version = value.get("version").and_then(|v| v.as_u64());
+    let migrated = version == Some(FLOAT_AMOUNTS_VERSION);
+    if version != Some(SNAPSHOT_VERSION as u64) && !migrated {
+        return Err(TradingError::DataError(format!(
+            "{} has snapshot version {:?}, this build reads versions {} and {}",
+            path.display(),
+            version,
+            FLOAT_AMOUNTS_VERSION,
+            SNAPSHOT_VERSION
+        )));
+    }
+    let mut snapshot: Snapshot = serde_json::from_value(value)
+        .map_err(|e| TradingError::DataError(format!("{}: {:?}", path.display(), e)))?;
+    if migrated {
+        log::info!(
+            "Migrated {} from snapshot version {}",
+            path.display(),
+            FLOAT_AMOUNTS_VERSION
+        );
+        snapshot.version = SNAPSHOT_VERSION;
+    }
+    Ok(snapshot)
+}
+
+/// Periodically saves executor and strategy state to `path`
+pub async fn run_snapshots(
+    path: String,
+    symbol: String,
+    state: SharedExecutorState,
+    strategy: SharedStrategySnapshot,
+) {
+    let mut interval = tokio::time::interval(SNAPSHOT_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = save_state(&path, &symbol, &state, &strategy).await {
+            log::error!("Failed to save state snapshot: {}", e);
+        }
+    }
+}
+
+// Snapshot the current state and save it to `path`
+pub async fn save_state(
+    path: &str,
+    symbol: &str,
+    state: &SharedExecutorState,
+    strategy: &SharedStrategySnapshot,
+) -> Result<(), TradingError> {
+    let (risk, executor) = {
+        let state = state.read().await;
+        (state.risk.clone(), state.snapshot())
+    };
+    let snapshot = Snapshot {
+        version: SNAPSHOT_VERSION,
+        saved_at: chrono::Utc::now().timestamp(),
+        symbol: symbol.to_string(),
+        risk,
+        executor,
+        strategy: strategy.lock().unwrap().clone(),
+    };
+    save(path, &snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    // A snapshot holding one long, with amounts written as floats the way
+    // version 2 wrote them
+    fn written(name: &str, version: u32) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "auto_trade-snapshot-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let snapshot = serde_json::json!({
+            "version": version,
+            "saved_at": 0,
+            "symbol": "BTCUSDT",
+            "risk": {
+                "max_position_size": 100.0,
+                "stop_loss_pct": 2.0,
+                "take_profit_pct": 4.0,
+                "max_open_positions": 1
+            },
+            "executor": {
+                "positions": [{
+                    "symbol": "BTCUSDT",
+                    "strategy": "rsi",
+                    "side": "Buy",
+                    "quantity": 0.1,
+                    "entry_price": 30000.5,
+                    "bracket": {"stop_loss": 29400.49, "take_profit": 31200.52},
+                    "bracket_order_id": null,
+                    "opened_at": 0,
+                    "entry_fees": 0.003
+                }],
+                "working_orders": [],
+                "parent_orders": [],
+                "open_orders": [],
+                "trades": [],
+                "daily_pnl": 0.0,
+                "pnl_day": "2024-01-01"
+            },
+            "strategy": null
+        });
+        fs::write(&path, snapshot.to_string()).unwrap();
+        path
+    }
+
+    #[test]
+    fn float_amounts_of_version_2_are_migrated() {
+        let path = written("v2", 2);
+        let snapshot = load(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(snapshot.version, SNAPSHOT_VERSION);
+        assert_eq!(snapshot.risk.max_position_size, dec!(100));
+        let position = &snapshot.executor.positions[0];
+        assert_eq!(position.quantity, dec!(0.1));
+        assert_eq!(position.entry_price, dec!(30000.5));
+        assert_eq!(position.entry_fees, dec!(0.003));
+        assert_eq!(
+            position.bracket.map(|bracket| bracket.stop_loss),
+            Some(dec!(29400.49))
+        );
+    }
+
+    #[test]
+    fn unknown_versions_are_refused() {
+        let path = written("v1", 1);
+        let loaded = load(&path);
+        let _ = fs::remove_file(&path);
+        assert!(loaded.is_err());
+    }
+}
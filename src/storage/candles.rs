@@ -0,0 +1,233 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, Row};
+
+use crate::backtest::PriceHistory;
+use crate::dto::{Error, KlineResponse};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS candles (
+    symbol TEXT NOT NULL,
+    interval_ms INTEGER NOT NULL,
+    open_time INTEGER NOT NULL,
+    open REAL NOT NULL,
+    high REAL NOT NULL,
+    low REAL NOT NULL,
+    close REAL NOT NULL,
+    volume REAL NOT NULL,
+    close_time INTEGER NOT NULL,
+    quote_asset_volume REAL NOT NULL,
+    number_of_trades INTEGER NOT NULL,
+    taker_buy_base_volume REAL NOT NULL,
+    taker_buy_quote_volume REAL NOT NULL,
+    PRIMARY KEY (symbol, interval_ms, open_time)
+) WITHOUT ROWID;
+";
+
+const COLUMNS: &str = "open_time, open, high, low, close, volume, close_time, \
+    quote_asset_volume, number_of_trades, taker_buy_base_volume, taker_buy_quote_volume";
+
+/// Closed candles per symbol and interval, so backtests and warm-up don't refetch
+/// what was already downloaded
+#[derive(Debug, Clone)]
+pub struct CandleStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+fn db_error(e: rusqlite::Error) -> Error {
+    Error::DatabaseError(format!("{:?}", e))
+}
+
+fn time(millis: i64) -> rusqlite::Result<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(millis)
+        .ok_or(rusqlite::Error::IntegralValueOutOfRange(0, millis))
+}
+
+fn candle(row: &Row) -> rusqlite::Result<KlineResponse> {
+    Ok(KlineResponse {
+        open_time: time(row.get(0)?)?,
+        open_price: row.get(1)?,
+        high_price: row.get(2)?,
+        low_price: row.get(3)?,
+        close_price: row.get(4)?,
+        volume: row.get(5)?,
+        close_time: time(row.get(6)?)?,
+        quote_asset_volume: row.get(7)?,
+        number_of_trades: row.get::<_, i64>(8)? as u64,
+        taker_buy_base_volume: row.get(9)?,
+        taker_buy_quote_volume: row.get(10)?,
+    })
+}
+
+impl CandleStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let conn = Connection::open(path).map_err(db_error)?;
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        Ok(CandleStore {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    fn conn(&self) -> Result<std::sync::MutexGuard<'_, Connection>, Error> {
+        self.conn
+            .lock()
+            .map_err(|e| Error::DatabaseError(format!("{:?}", e)))
+    }
+
+    // Insert or replace by open time; returns how many candles were written
+    pub fn upsert(&self, history: &PriceHistory) -> Result<usize, Error> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(db_error)?;
+        {
+            let mut statement = tx
+                .prepare(&format!(
+                    "INSERT OR REPLACE INTO candles (symbol, interval_ms, {})
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                    COLUMNS
+                ))
+                .map_err(db_error)?;
+            for c in &history.candles {
+                statement
+                    .execute(params![
+                        history.symbol,
+                        history.interval_ms,
+                        c.open_time.timestamp_millis(),
+                        c.open_price,
+                        c.high_price,
+                        c.low_price,
+                        c.close_price,
+                        c.volume,
+                        c.close_time.timestamp_millis(),
+                        c.quote_asset_volume,
+                        c.number_of_trades as i64,
+                        c.taker_buy_base_volume,
+                        c.taker_buy_quote_volume
+                    ])
+                    .map_err(db_error)?;
+            }
+        }
+        tx.commit().map_err(db_error)?;
+        Ok(history.len())
+    }
+
+    // Candles opened in [from, to)
+    pub fn range(
+        &self,
+        symbol: &str,
+        interval_ms: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<PriceHistory, Error> {
+        let conn = self.conn()?;
+        let mut statement = conn
+            .prepare(&format!(
+                "SELECT {} FROM candles
+                 WHERE symbol = ?1 AND interval_ms = ?2 AND open_time >= ?3 AND open_time < ?4
+                 ORDER BY open_time",
+                COLUMNS
+            ))
+            .map_err(db_error)?;
+        let candles = statement
+            .query_map(
+                params![
+                    symbol,
+                    interval_ms,
+                    from.timestamp_millis(),
+                    to.timestamp_millis()
+                ],
+                candle,
+            )
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error)?;
+        Ok(PriceHistory {
+            symbol: symbol.to_string(),
+            interval_ms,
+            candles,
+        })
+    }
+
+    // The most recent `count` candles, oldest first
+    pub fn latest(
+        &self,
+        symbol: &str,
+        interval_ms: i64,
+        count: usize,
+    ) -> Result<PriceHistory, Error> {
+        let conn = self.conn()?;
+        let mut statement = conn
+            .prepare(&format!(
+                "SELECT {} FROM candles WHERE symbol = ?1 AND interval_ms = ?2
+                 ORDER BY open_time DESC LIMIT ?3",
+                COLUMNS
+            ))
+            .map_err(db_error)?;
+        let mut candles = statement
+            .query_map(params![symbol, interval_ms, count as i64], candle)
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error)?;
+        candles.reverse();
+        Ok(PriceHistory {
+            symbol: symbol.to_string(),
+            interval_ms,
+            candles,
+        })
+    }
+
+    // Delete candles opened before `cutoff` (ms) for every symbol and interval
+    pub fn prune_before(&self, cutoff: i64) -> Result<usize, Error> {
+        self.conn()?
+            .execute("DELETE FROM candles WHERE open_time < ?1", params![cutoff])
+            .map_err(db_error)
+    }
+
+    // Spans of [from, to) with no stored candles, to be fetched from the exchange
+    pub fn missing_ranges(
+        &self,
+        symbol: &str,
+        interval_ms: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, DateTime<Utc>)>, Error> {
+        let conn = self.conn()?;
+        let mut statement = conn
+            .prepare(
+                "SELECT open_time FROM candles
+                 WHERE symbol = ?1 AND interval_ms = ?2 AND open_time >= ?3 AND open_time < ?4
+                 ORDER BY open_time",
+            )
+            .map_err(db_error)?;
+        let open_times = statement
+            .query_map(
+                params![
+                    symbol,
+                    interval_ms,
+                    from.timestamp_millis(),
+                    to.timestamp_millis()
+                ],
+                |row| row.get::<_, i64>(0),
+            )
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error)?;
+
+        let mut missing = Vec::new();
+        let mut cursor = from.timestamp_millis();
+        let end = to.timestamp_millis();
+        for open_time in open_times.into_iter().chain(std::iter::once(end)) {
+            if open_time - cursor >= interval_ms {
+                if let (Some(start), Some(stop)) = (
+                    DateTime::from_timestamp_millis(cursor),
+                    DateTime::from_timestamp_millis(open_time),
+                ) {
+                    missing.push((start, stop));
+                }
+            }
+            cursor = open_time + interval_ms;
+        }
+        Ok(missing)
+    }
+}
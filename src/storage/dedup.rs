@@ -0,0 +1,94 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::domain::*;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS executed_signals (
+    strategy TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    bar_time INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    order_id TEXT NOT NULL,
+    recorded_at INTEGER NOT NULL,
+    PRIMARY KEY (strategy, symbol, bar_time, action)
+) WITHOUT ROWID;
+";
+
+/// Signals that already led to an order, keyed by strategy, symbol, bar
+/// timestamp and action, so a bot resumed during the same bar doesn't act on
+/// its signal twice
+#[derive(Debug, Clone)]
+pub struct SignalDedup {
+    conn: Arc<Mutex<Connection>>,
+}
+
+fn db_error(e: rusqlite::Error) -> TradingError {
+    TradingError::DataError(format!("{:?}", e))
+}
+
+fn action(signal: &TradingSignal) -> String {
+    format!("{:?}", signal.action)
+}
+
+impl SignalDedup {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, TradingError> {
+        let conn = Connection::open(path).map_err(db_error)?;
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        Ok(SignalDedup {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    // Order placed for the same signal by this or an earlier run
+    pub fn executed(&self, signal: &TradingSignal) -> Result<Option<String>, TradingError> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT order_id FROM executed_signals
+             WHERE strategy = ?1 AND symbol = ?2 AND bar_time = ?3 AND action = ?4",
+            params![
+                signal.strategy,
+                signal.symbol,
+                signal.timestamp,
+                action(signal)
+            ],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(db_error)
+    }
+
+    // Keeps the first order recorded for a signal
+    pub fn record(&self, signal: &TradingSignal, order_id: &str) -> Result<(), TradingError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR IGNORE INTO executed_signals
+             (strategy, symbol, bar_time, action, order_id, recorded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                signal.strategy,
+                signal.symbol,
+                signal.timestamp,
+                action(signal),
+                order_id,
+                super::now()
+            ],
+        )
+        .map_err(db_error)?;
+        Ok(())
+    }
+}
+
+// Dedup store at SIGNAL_DEDUP_PATH, if set
+pub fn from_env() -> Option<SignalDedup> {
+    let path = dotenv::var("SIGNAL_DEDUP_PATH").ok()?;
+    match SignalDedup::open(&path) {
+        Ok(dedup) => Some(dedup),
+        Err(e) => {
+            log::error!("Failed to open signal dedup store {}: {}", path, e);
+            None
+        }
+    }
+}
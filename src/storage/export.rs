@@ -0,0 +1,89 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use chrono::DateTime;
+use chrono_tz::Tz;
+
+use super::OrderRecord;
+use crate::dto::Error;
+use crate::executor::Trade;
+
+fn format_time(time: i64, timezone: Tz) -> String {
+    DateTime::from_timestamp(time, 0)
+        .map(|t| {
+            t.with_timezone(&timezone)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| time.to_string())
+}
+
+// One row per closed trade with cost, proceeds and realized PnL, times in `timezone`
+pub fn write_trades_csv<P: AsRef<Path>>(
+    path: P,
+    trades: &[Trade],
+    timezone: Tz,
+) -> Result<(), Error> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(
+        out,
+        "closed_at,opened_at,timezone,symbol,strategy,side,quantity,entry_price,exit_price,cost,proceeds,fees,realized_pnl"
+    )?;
+    for trade in trades {
+        writeln!(
+            out,
+            "{},{},{},{},{},{:?},{:.8},{:.8},{:.8},{:.8},{:.8},{:.8},{:.8}",
+            format_time(trade.closed_at, timezone),
+            format_time(trade.opened_at, timezone),
+            timezone.name(),
+            trade.symbol,
+            trade.strategy,
+            trade.side,
+            trade.quantity,
+            trade.entry_price,
+            trade.exit_price,
+            trade.entry_price * trade.quantity,
+            trade.exit_price * trade.quantity,
+            trade.fees,
+            trade.pnl
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+pub fn write_orders_csv<P: AsRef<Path>>(
+    path: P,
+    orders: &[OrderRecord],
+    timezone: Tz,
+) -> Result<(), Error> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(
+        out,
+        "time,timezone,order_id,symbol,side,type,quantity,status,filled_quantity,average_price,commission,commission_asset"
+    )?;
+    for order in orders {
+        writeln!(
+            out,
+            "{},{},{},{},{:?},{},{:.8},{},{:.8},{},{:.8},{}",
+            format_time(order.timestamp, timezone),
+            timezone.name(),
+            order.order_id,
+            order.symbol,
+            order.side,
+            order.order_type,
+            order.quantity,
+            order.status,
+            order.filled_quantity,
+            order
+                .average_price()
+                .map(|price| format!("{:.8}", price))
+                .unwrap_or_default(),
+            order.commission,
+            order.commission_asset
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
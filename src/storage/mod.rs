@@ -0,0 +1,137 @@
+pub mod candles;
+pub mod dedup;
+pub mod export;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod sqlite;
+
+use std::sync::Arc;
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::domain::*;
+use crate::executor::{Position, Trade};
+
+pub use candles::CandleStore;
+pub use dedup::SignalDedup;
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresStore;
+pub use sqlite::SqliteStore;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionEvent {
+    Opened,
+    Closed,
+    // Closed on the exchange by its stop loss or take profit leg
+    BracketExit,
+    // Bought into while open
+    Increased,
+    // Partly sold; carries the slice that was sold
+    Reduced,
+}
+
+/// Durable history of signals, orders, fills, position changes and closed trades
+pub trait TradeStore: Send + Sync + std::fmt::Debug {
+    fn record_signal(&self, signal: &TradingSignal) -> Result<(), TradingError>;
+    // The order as sent plus whatever fills the exchange reported for it
+    fn record_order(&self, order: &Order, response: &OrderResponse) -> Result<(), TradingError>;
+    fn record_position(
+        &self,
+        event: PositionEvent,
+        position: &Position,
+        price: Decimal,
+    ) -> Result<(), TradingError>;
+    fn record_trade(&self, trade: &Trade) -> Result<(), TradingError>;
+    // Trades closed in [from, to) seconds, oldest first
+    fn trades_between(&self, from: i64, to: i64) -> Result<Vec<Trade>, TradingError>;
+    // Orders placed in [from, to) seconds with their fills summed up, oldest first
+    fn orders_between(&self, from: i64, to: i64) -> Result<Vec<OrderRecord>, TradingError>;
+
+    fn load_trades(&self) -> Result<Vec<Trade>, TradingError> {
+        self.trades_between(i64::MIN, i64::MAX)
+    }
+}
+
+pub type Storage = Arc<dyn TradeStore>;
+
+/// A stored order and what was filled of it
+#[derive(Debug, Clone)]
+pub struct OrderRecord {
+    pub order_id: String,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub order_type: String,
+    pub quantity: f64,
+    pub status: String,
+    pub timestamp: i64,
+    pub filled_quantity: f64,
+    // Sum of price * quantity over the fills
+    pub filled_value: f64,
+    pub commission: f64,
+    pub commission_asset: String,
+}
+
+impl OrderRecord {
+    pub fn average_price(&self) -> Option<f64> {
+        if self.filled_quantity > 0.0 {
+            Some(self.filled_value / self.filled_quantity)
+        } else {
+            None
+        }
+    }
+}
+
+fn parse_side(side: &str) -> OrderSide {
+    if side == "Sell" {
+        OrderSide::Sell
+    } else {
+        OrderSide::Buy
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+// Storage failures never stop trading; they are only logged
+pub fn log_error(result: Result<(), TradingError>) {
+    if let Err(e) = result {
+        log::warn!("Failed to persist trading history: {}", e);
+    }
+}
+
+// TRADE_DB_URL (a shared Postgres database) takes precedence over TRADE_DB_PATH
+// (a local SQLite file)
+pub async fn from_env() -> Option<Storage> {
+    #[cfg(feature = "postgres")]
+    if let Ok(url) = dotenv::var("TRADE_DB_URL") {
+        let bot_id = dotenv::var("BOT_ID").unwrap_or_else(|_| "default".to_string());
+        return match PostgresStore::connect(&url, &bot_id).await {
+            Ok(store) => {
+                log::info!("Persisting trading history to Postgres as bot {}", bot_id);
+                Some(Arc::new(store))
+            }
+            Err(e) => {
+                log::error!("Failed to connect to trade database: {}", e);
+                None
+            }
+        };
+    }
+    #[cfg(not(feature = "postgres"))]
+    if dotenv::var("TRADE_DB_URL").is_ok() {
+        log::warn!("TRADE_DB_URL set but built without the postgres feature");
+    }
+    let path = dotenv::var("TRADE_DB_PATH").ok()?;
+    match SqliteStore::open(&path) {
+        Ok(store) => {
+            log::info!("Persisting trading history to {}", path);
+            Some(Arc::new(store))
+        }
+        Err(e) => {
+            log::error!("Failed to open trade database {}: {}", path, e);
+            None
+        }
+    }
+}
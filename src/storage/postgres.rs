@@ -0,0 +1,279 @@
+use std::future::Future;
+
+use rust_decimal::Decimal;
+use tokio_postgres::{Client, NoTls};
+
+use super::{OrderRecord, PositionEvent, TradeStore};
+use crate::domain::*;
+use crate::executor::{Position, Trade};
+
+// Same tables as the SQLite store, plus the bot each row came from
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS signals (
+    id BIGSERIAL PRIMARY KEY,
+    bot_id TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    strategy TEXT NOT NULL,
+    action TEXT NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    stop_loss DOUBLE PRECISION,
+    take_profit DOUBLE PRECISION,
+    timestamp BIGINT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS orders (
+    id BIGSERIAL PRIMARY KEY,
+    bot_id TEXT NOT NULL,
+    order_id TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    side TEXT NOT NULL,
+    order_type TEXT NOT NULL,
+    quantity DOUBLE PRECISION NOT NULL,
+    status TEXT NOT NULL,
+    timestamp BIGINT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS fills (
+    id BIGSERIAL PRIMARY KEY,
+    bot_id TEXT NOT NULL,
+    order_id TEXT NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    quantity DOUBLE PRECISION NOT NULL,
+    commission DOUBLE PRECISION NOT NULL,
+    commission_asset TEXT NOT NULL,
+    timestamp BIGINT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS position_events (
+    id BIGSERIAL PRIMARY KEY,
+    bot_id TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    strategy TEXT NOT NULL,
+    event TEXT NOT NULL,
+    side TEXT NOT NULL,
+    quantity DOUBLE PRECISION NOT NULL,
+    entry_price DOUBLE PRECISION NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    timestamp BIGINT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS trades (
+    id BIGSERIAL PRIMARY KEY,
+    bot_id TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    strategy TEXT NOT NULL,
+    side TEXT NOT NULL,
+    quantity DOUBLE PRECISION NOT NULL,
+    entry_price DOUBLE PRECISION NOT NULL,
+    exit_price DOUBLE PRECISION NOT NULL,
+    fees DOUBLE PRECISION NOT NULL,
+    pnl DOUBLE PRECISION NOT NULL,
+    opened_at BIGINT NOT NULL,
+    closed_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS trades_bot_id ON trades (bot_id, closed_at);
+";
+
+/// Central store shared by several bots, each writing under its own `bot_id`.
+/// Calls block the current worker thread, so it needs the multi-threaded runtime.
+pub struct PostgresStore {
+    client: Client,
+    bot_id: String,
+}
+
+impl std::fmt::Debug for PostgresStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("PostgresStore")
+            .field("bot_id", &self.bot_id)
+            .finish()
+    }
+}
+
+fn db_error(e: tokio_postgres::Error) -> TradingError {
+    TradingError::DataError(format!("{:?}", e))
+}
+
+// The executor records history from synchronous bookkeeping code
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+}
+
+impl PostgresStore {
+    pub async fn connect(url: &str, bot_id: &str) -> Result<Self, TradingError> {
+        let (client, connection) = tokio_postgres::connect(url, NoTls)
+            .await
+            .map_err(db_error)?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                log::error!("Trade database connection closed: {}", e);
+            }
+        });
+        client.batch_execute(SCHEMA).await.map_err(db_error)?;
+        Ok(PostgresStore {
+            client,
+            bot_id: bot_id.to_string(),
+        })
+    }
+
+    fn execute(
+        &self,
+        sql: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Sync)],
+    ) -> Result<(), TradingError> {
+        block_on(self.client.execute(sql, params)).map_err(db_error)?;
+        Ok(())
+    }
+}
+
+impl TradeStore for PostgresStore {
+    fn record_signal(&self, signal: &TradingSignal) -> Result<(), TradingError> {
+        self.execute(
+            "INSERT INTO signals (bot_id, symbol, strategy, action, price, stop_loss, take_profit, timestamp)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            &[
+                &self.bot_id,
+                &signal.symbol,
+                &signal.strategy,
+                &format!("{:?}", signal.action),
+                &from_decimal(signal.price),
+                &signal.stop_loss.map(from_decimal),
+                &signal.take_profit.map(from_decimal),
+                &signal.timestamp,
+            ],
+        )
+    }
+
+    fn record_order(&self, order: &Order, response: &OrderResponse) -> Result<(), TradingError> {
+        let timestamp = super::now();
+        self.execute(
+            "INSERT INTO orders (bot_id, order_id, symbol, side, order_type, quantity, status, timestamp)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            &[
+                &self.bot_id,
+                &response.order_id,
+                &order.symbol,
+                &format!("{:?}", order.side),
+                &order.order_type.to_string(),
+                &from_decimal(order.quantity),
+                &format!("{:?}", response.status),
+                &timestamp,
+            ],
+        )?;
+        for fill in &response.fills {
+            self.execute(
+                "INSERT INTO fills (bot_id, order_id, price, quantity, commission, commission_asset, timestamp)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                &[
+                    &self.bot_id,
+                    &response.order_id,
+                    &from_decimal(fill.price),
+                    &from_decimal(fill.quantity),
+                    &from_decimal(fill.commission),
+                    &fill.commission_asset,
+                    &timestamp,
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn record_position(
+        &self,
+        event: PositionEvent,
+        position: &Position,
+        price: Decimal,
+    ) -> Result<(), TradingError> {
+        self.execute(
+            "INSERT INTO position_events (bot_id, symbol, strategy, event, side, quantity, entry_price, price, timestamp)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+            &[
+                &self.bot_id,
+                &position.symbol,
+                &position.strategy,
+                &format!("{:?}", event),
+                &format!("{:?}", position.side),
+                &from_decimal(position.quantity),
+                &from_decimal(position.entry_price),
+                &from_decimal(price),
+                &super::now(),
+            ],
+        )
+    }
+
+    fn record_trade(&self, trade: &Trade) -> Result<(), TradingError> {
+        self.execute(
+            "INSERT INTO trades (bot_id, symbol, strategy, side, quantity, entry_price, exit_price, fees, pnl, opened_at, closed_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+            &[
+                &self.bot_id,
+                &trade.symbol,
+                &trade.strategy,
+                &format!("{:?}", trade.side),
+                &trade.quantity,
+                &trade.entry_price,
+                &trade.exit_price,
+                &trade.fees,
+                &trade.pnl,
+                &trade.opened_at,
+                &trade.closed_at,
+            ],
+        )
+    }
+
+    // Only this bot's trades
+    fn trades_between(&self, from: i64, to: i64) -> Result<Vec<Trade>, TradingError> {
+        let rows = block_on(self.client.query(
+            "SELECT symbol, strategy, side, quantity, entry_price, exit_price, fees, pnl, opened_at, closed_at
+             FROM trades WHERE bot_id = $1 AND closed_at >= $2 AND closed_at < $3
+             ORDER BY closed_at, id",
+            &[&self.bot_id, &from, &to],
+        ))
+        .map_err(db_error)?;
+        rows.iter()
+            .map(|row| {
+                let side: String = row.try_get(2).map_err(db_error)?;
+                Ok(Trade {
+                    symbol: row.try_get(0).map_err(db_error)?,
+                    strategy: row.try_get(1).map_err(db_error)?,
+                    side: super::parse_side(&side),
+                    quantity: row.try_get(3).map_err(db_error)?,
+                    entry_price: row.try_get(4).map_err(db_error)?,
+                    exit_price: row.try_get(5).map_err(db_error)?,
+                    fees: row.try_get(6).map_err(db_error)?,
+                    pnl: row.try_get(7).map_err(db_error)?,
+                    opened_at: row.try_get(8).map_err(db_error)?,
+                    closed_at: row.try_get(9).map_err(db_error)?,
+                })
+            })
+            .collect()
+    }
+
+    fn orders_between(&self, from: i64, to: i64) -> Result<Vec<OrderRecord>, TradingError> {
+        let rows = block_on(self.client.query(
+            "SELECT o.order_id, o.symbol, o.side, o.order_type, o.quantity, o.status, o.timestamp,
+                    COALESCE(SUM(f.quantity), 0), COALESCE(SUM(f.price * f.quantity), 0),
+                    COALESCE(SUM(f.commission), 0), COALESCE(MAX(f.commission_asset), '')
+             FROM orders o
+             LEFT JOIN fills f ON f.order_id = o.order_id AND f.bot_id = o.bot_id
+             WHERE o.bot_id = $1 AND o.timestamp >= $2 AND o.timestamp < $3
+             GROUP BY o.id
+             ORDER BY o.timestamp, o.id",
+            &[&self.bot_id, &from, &to],
+        ))
+        .map_err(db_error)?;
+        rows.iter()
+            .map(|row| {
+                let side: String = row.try_get(2).map_err(db_error)?;
+                Ok(OrderRecord {
+                    order_id: row.try_get(0).map_err(db_error)?,
+                    symbol: row.try_get(1).map_err(db_error)?,
+                    side: super::parse_side(&side),
+                    order_type: row.try_get(3).map_err(db_error)?,
+                    quantity: row.try_get(4).map_err(db_error)?,
+                    status: row.try_get(5).map_err(db_error)?,
+                    timestamp: row.try_get(6).map_err(db_error)?,
+                    filled_quantity: row.try_get(7).map_err(db_error)?,
+                    filled_value: row.try_get(8).map_err(db_error)?,
+                    commission: row.try_get(9).map_err(db_error)?,
+                    commission_asset: row.try_get(10).map_err(db_error)?,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,256 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rusqlite::{params, Connection};
+use rust_decimal::Decimal;
+
+use super::{OrderRecord, PositionEvent, TradeStore};
+use crate::domain::*;
+use crate::executor::{Position, Trade};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS signals (
+    id INTEGER PRIMARY KEY,
+    symbol TEXT NOT NULL,
+    strategy TEXT NOT NULL,
+    action TEXT NOT NULL,
+    price REAL NOT NULL,
+    stop_loss REAL,
+    take_profit REAL,
+    timestamp INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS orders (
+    id INTEGER PRIMARY KEY,
+    order_id TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    side TEXT NOT NULL,
+    order_type TEXT NOT NULL,
+    quantity REAL NOT NULL,
+    status TEXT NOT NULL,
+    timestamp INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS fills (
+    id INTEGER PRIMARY KEY,
+    order_id TEXT NOT NULL,
+    price REAL NOT NULL,
+    quantity REAL NOT NULL,
+    commission REAL NOT NULL,
+    commission_asset TEXT NOT NULL,
+    timestamp INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS position_events (
+    id INTEGER PRIMARY KEY,
+    symbol TEXT NOT NULL,
+    strategy TEXT NOT NULL,
+    event TEXT NOT NULL,
+    side TEXT NOT NULL,
+    quantity REAL NOT NULL,
+    entry_price REAL NOT NULL,
+    price REAL NOT NULL,
+    timestamp INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS trades (
+    id INTEGER PRIMARY KEY,
+    symbol TEXT NOT NULL,
+    strategy TEXT NOT NULL,
+    side TEXT NOT NULL,
+    quantity REAL NOT NULL,
+    entry_price REAL NOT NULL,
+    exit_price REAL NOT NULL,
+    fees REAL NOT NULL,
+    pnl REAL NOT NULL,
+    opened_at INTEGER NOT NULL,
+    closed_at INTEGER NOT NULL
+);
+";
+
+// Orders with their fills summed up
+const ORDERS_QUERY: &str = "
+SELECT o.order_id, o.symbol, o.side, o.order_type, o.quantity, o.status, o.timestamp,
+       COALESCE(SUM(f.quantity), 0), COALESCE(SUM(f.price * f.quantity), 0),
+       COALESCE(SUM(f.commission), 0), COALESCE(MAX(f.commission_asset), '')
+FROM orders o
+LEFT JOIN fills f ON f.order_id = o.order_id
+WHERE o.timestamp >= ?1 AND o.timestamp < ?2
+GROUP BY o.id
+ORDER BY o.timestamp, o.id
+";
+
+/// Single-file store for one bot. Writes are small and synchronous; the lock is
+/// never held across an await.
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+fn db_error(e: rusqlite::Error) -> TradingError {
+    TradingError::DataError(format!("{:?}", e))
+}
+
+impl SqliteStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, TradingError> {
+        let conn = Connection::open(path).map_err(db_error)?;
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        Ok(SqliteStore {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    fn execute<P: rusqlite::Params>(&self, sql: &str, params: P) -> Result<(), TradingError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| TradingError::DataError(format!("{:?}", e)))?;
+        conn.execute(sql, params).map_err(db_error)?;
+        Ok(())
+    }
+}
+
+impl TradeStore for SqliteStore {
+    fn record_signal(&self, signal: &TradingSignal) -> Result<(), TradingError> {
+        self.execute(
+            "INSERT INTO signals (symbol, strategy, action, price, stop_loss, take_profit, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                signal.symbol,
+                signal.strategy,
+                format!("{:?}", signal.action),
+                from_decimal(signal.price),
+                signal.stop_loss.map(from_decimal),
+                signal.take_profit.map(from_decimal),
+                signal.timestamp
+            ],
+        )
+    }
+
+    fn record_order(&self, order: &Order, response: &OrderResponse) -> Result<(), TradingError> {
+        let timestamp = super::now();
+        self.execute(
+            "INSERT INTO orders (order_id, symbol, side, order_type, quantity, status, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                response.order_id,
+                order.symbol,
+                format!("{:?}", order.side),
+                order.order_type.to_string(),
+                from_decimal(order.quantity),
+                format!("{:?}", response.status),
+                timestamp
+            ],
+        )?;
+        for fill in &response.fills {
+            self.execute(
+                "INSERT INTO fills (order_id, price, quantity, commission, commission_asset, timestamp)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    response.order_id,
+                    from_decimal(fill.price),
+                    from_decimal(fill.quantity),
+                    from_decimal(fill.commission),
+                    fill.commission_asset,
+                    timestamp
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn record_position(
+        &self,
+        event: PositionEvent,
+        position: &Position,
+        price: Decimal,
+    ) -> Result<(), TradingError> {
+        self.execute(
+            "INSERT INTO position_events (symbol, strategy, event, side, quantity, entry_price, price, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                position.symbol,
+                position.strategy,
+                format!("{:?}", event),
+                format!("{:?}", position.side),
+                from_decimal(position.quantity),
+                from_decimal(position.entry_price),
+                from_decimal(price),
+                super::now()
+            ],
+        )
+    }
+
+    fn record_trade(&self, trade: &Trade) -> Result<(), TradingError> {
+        self.execute(
+            "INSERT INTO trades (symbol, strategy, side, quantity, entry_price, exit_price, fees, pnl, opened_at, closed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                trade.symbol,
+                trade.strategy,
+                format!("{:?}", trade.side),
+                trade.quantity,
+                trade.entry_price,
+                trade.exit_price,
+                trade.fees,
+                trade.pnl,
+                trade.opened_at,
+                trade.closed_at
+            ],
+        )
+    }
+
+    fn trades_between(&self, from: i64, to: i64) -> Result<Vec<Trade>, TradingError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| TradingError::DataError(format!("{:?}", e)))?;
+        let mut statement = conn
+            .prepare(
+                "SELECT symbol, strategy, side, quantity, entry_price, exit_price, fees, pnl, opened_at, closed_at
+                 FROM trades WHERE closed_at >= ?1 AND closed_at < ?2 ORDER BY closed_at, id",
+            )
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(params![from, to], |row| {
+                let side: String = row.get(2)?;
+                Ok(Trade {
+                    symbol: row.get(0)?,
+                    strategy: row.get(1)?,
+                    side: super::parse_side(&side),
+                    quantity: row.get(3)?,
+                    entry_price: row.get(4)?,
+                    exit_price: row.get(5)?,
+                    fees: row.get(6)?,
+                    pnl: row.get(7)?,
+                    opened_at: row.get(8)?,
+                    closed_at: row.get(9)?,
+                })
+            })
+            .map_err(db_error)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(db_error)
+    }
+
+    fn orders_between(&self, from: i64, to: i64) -> Result<Vec<OrderRecord>, TradingError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| TradingError::DataError(format!("{:?}", e)))?;
+        let mut statement = conn.prepare(ORDERS_QUERY).map_err(db_error)?;
+        let rows = statement
+            .query_map(params![from, to], |row| {
+                let side: String = row.get(2)?;
+                Ok(OrderRecord {
+                    order_id: row.get(0)?,
+                    symbol: row.get(1)?,
+                    side: super::parse_side(&side),
+                    order_type: row.get(3)?,
+                    quantity: row.get(4)?,
+                    status: row.get(5)?,
+                    timestamp: row.get(6)?,
+                    filled_quantity: row.get(7)?,
+                    filled_value: row.get(8)?,
+                    commission: row.get(9)?,
+                    commission_asset: row.get(10)?,
+                })
+            })
+            .map_err(db_error)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(db_error)
+    }
+}
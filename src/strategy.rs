@@ -0,0 +1,329 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{TradeAction, TradingError};
+use crate::ta::{calculate_ema, calculate_rsi};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "web", derive(utoipa::ToSchema))]
+#[serde(untagged)]
+pub enum ParameterValue {
+    Int(i64),
+    Float(f64),
+}
+
+impl ParameterValue {
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            ParameterValue::Int(value) => *value as f64,
+            ParameterValue::Float(value) => *value,
+        }
+    }
+
+    pub fn as_usize(&self) -> usize {
+        match self {
+            ParameterValue::Int(value) => (*value).max(0) as usize,
+            ParameterValue::Float(value) => value.max(0.0).round() as usize,
+        }
+    }
+}
+
+/// Inclusive range a parameter may be tuned over
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "web", derive(utoipa::ToSchema))]
+pub enum ParameterRange {
+    Int { min: i64, max: i64, step: i64 },
+    Float { min: f64, max: f64, step: f64 },
+}
+
+impl ParameterRange {
+    pub fn contains(&self, value: &ParameterValue) -> bool {
+        match (self, value) {
+            (ParameterRange::Int { min, max, .. }, ParameterValue::Int(value)) => {
+                value >= min && value <= max
+            }
+            (ParameterRange::Float { min, max, .. }, value) => {
+                let value = value.as_f64();
+                value >= *min && value <= *max
+            }
+            _ => false,
+        }
+    }
+
+    // Every grid point from min to max
+    pub fn values(&self) -> Vec<ParameterValue> {
+        match *self {
+            ParameterRange::Int { min, max, step } => (min..=max)
+                .step_by(step.max(1) as usize)
+                .map(ParameterValue::Int)
+                .collect(),
+            ParameterRange::Float { min, max, step } => {
+                if step <= 0.0 {
+                    return vec![ParameterValue::Float(min)];
+                }
+                let count = ((max - min) / step + 1e-9).floor() as usize;
+                (0..=count)
+                    .map(|i| ParameterValue::Float(min + step * i as f64))
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "web", derive(utoipa::ToSchema))]
+pub struct StrategyParameter {
+    pub name: String,
+    pub value: ParameterValue,
+    pub range: ParameterRange,
+}
+
+impl StrategyParameter {
+    fn new(name: &str, value: ParameterValue, range: ParameterRange) -> Self {
+        StrategyParameter {
+            name: name.to_string(),
+            value,
+            range,
+        }
+    }
+}
+
+// Reject values outside the declared range of `name`
+fn validate(
+    parameters: &[StrategyParameter],
+    name: &str,
+    value: &ParameterValue,
+) -> Result<(), TradingError> {
+    let parameter = parameters
+        .iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| TradingError::InvalidParameter(format!("Unknown parameter: {}", name)))?;
+    if !parameter.range.contains(value) {
+        return Err(TradingError::InvalidParameter(format!(
+            "{} = {:?} is outside {:?}",
+            name, value, parameter.range
+        )));
+    }
+    Ok(())
+}
+
+/// A trading strategy driven by closed candles
+pub trait Strategy: Send {
+    fn name(&self) -> &str;
+    // `closes` holds every close up to and including the candle that just closed
+    fn analyze(&mut self, closes: &[f64]) -> TradeAction;
+    // Tunable parameters with their current values
+    fn parameters(&self) -> Vec<StrategyParameter>;
+    fn update_parameter(&mut self, name: &str, value: ParameterValue) -> Result<(), TradingError>;
+}
+
+// Lets owned and borrowed strategies drive the same `SignalEngine`
+impl<S: Strategy + ?Sized> Strategy for Box<S> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn analyze(&mut self, closes: &[f64]) -> TradeAction {
+        (**self).analyze(closes)
+    }
+
+    fn parameters(&self) -> Vec<StrategyParameter> {
+        (**self).parameters()
+    }
+
+    fn update_parameter(&mut self, name: &str, value: ParameterValue) -> Result<(), TradingError> {
+        (**self).update_parameter(name, value)
+    }
+}
+
+impl<S: Strategy + ?Sized> Strategy for &mut S {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn analyze(&mut self, closes: &[f64]) -> TradeAction {
+        (**self).analyze(closes)
+    }
+
+    fn parameters(&self) -> Vec<StrategyParameter> {
+        (**self).parameters()
+    }
+
+    fn update_parameter(&mut self, name: &str, value: ParameterValue) -> Result<(), TradingError> {
+        (**self).update_parameter(name, value)
+    }
+}
+
+// Buy when RSI is oversold, sell when overbought
+#[derive(Debug, Clone)]
+pub struct RsiStrategy {
+    pub period: usize,
+    pub oversold: f64,
+    pub overbought: f64,
+}
+
+impl Default for RsiStrategy {
+    fn default() -> Self {
+        RsiStrategy {
+            period: 14,
+            oversold: 30.0,
+            overbought: 70.0,
+        }
+    }
+}
+
+impl Strategy for RsiStrategy {
+    fn name(&self) -> &str {
+        "rsi"
+    }
+
+    fn analyze(&mut self, closes: &[f64]) -> TradeAction {
+        // RSI smoothing converges well within this many bars; keeps long backtests linear
+        let lookback = self.period * 10;
+        let window = &closes[closes.len().saturating_sub(lookback)..];
+        match calculate_rsi(window, self.period) {
+            Some(rsi) if rsi < self.oversold => TradeAction::Buy,
+            Some(rsi) if rsi > self.overbought => TradeAction::Sell,
+            _ => TradeAction::Hold,
+        }
+    }
+
+    fn parameters(&self) -> Vec<StrategyParameter> {
+        vec![
+            StrategyParameter::new(
+                "period",
+                ParameterValue::Int(self.period as i64),
+                ParameterRange::Int {
+                    min: 2,
+                    max: 50,
+                    step: 1,
+                },
+            ),
+            StrategyParameter::new(
+                "oversold",
+                ParameterValue::Float(self.oversold),
+                ParameterRange::Float {
+                    min: 5.0,
+                    max: 45.0,
+                    step: 5.0,
+                },
+            ),
+            StrategyParameter::new(
+                "overbought",
+                ParameterValue::Float(self.overbought),
+                ParameterRange::Float {
+                    min: 55.0,
+                    max: 95.0,
+                    step: 5.0,
+                },
+            ),
+        ]
+    }
+
+    fn update_parameter(&mut self, name: &str, value: ParameterValue) -> Result<(), TradingError> {
+        validate(&self.parameters(), name, &value)?;
+        match name {
+            "period" => self.period = value.as_usize(),
+            "oversold" => self.oversold = value.as_f64(),
+            "overbought" => self.overbought = value.as_f64(),
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+}
+
+// Buy when the fast EMA crosses above the slow one, sell on the opposite cross
+#[derive(Debug, Clone)]
+pub struct EmaCrossStrategy {
+    pub fast_period: usize,
+    pub slow_period: usize,
+    fast_above: Option<bool>,
+}
+
+impl EmaCrossStrategy {
+    pub fn new(fast_period: usize, slow_period: usize) -> Self {
+        EmaCrossStrategy {
+            fast_period,
+            slow_period,
+            fast_above: None,
+        }
+    }
+}
+
+impl Default for EmaCrossStrategy {
+    fn default() -> Self {
+        EmaCrossStrategy::new(5, 15)
+    }
+}
+
+impl Strategy for EmaCrossStrategy {
+    fn name(&self) -> &str {
+        "ema_cross"
+    }
+
+    fn analyze(&mut self, closes: &[f64]) -> TradeAction {
+        let fast = calculate_ema(closes, self.fast_period).last().copied();
+        let slow = calculate_ema(closes, self.slow_period).last().copied();
+        let (fast, slow) = match (fast, slow) {
+            (Some(fast), Some(slow)) => (fast, slow),
+            _ => return TradeAction::Hold,
+        };
+
+        let fast_above = fast > slow;
+        let previous = self.fast_above.replace(fast_above);
+        match previous {
+            Some(false) if fast_above => TradeAction::Buy,
+            Some(true) if !fast_above => TradeAction::Sell,
+            _ => TradeAction::Hold,
+        }
+    }
+
+    fn parameters(&self) -> Vec<StrategyParameter> {
+        vec![
+            StrategyParameter::new(
+                "fast_period",
+                ParameterValue::Int(self.fast_period as i64),
+                ParameterRange::Int {
+                    min: 2,
+                    max: 30,
+                    step: 1,
+                },
+            ),
+            StrategyParameter::new(
+                "slow_period",
+                ParameterValue::Int(self.slow_period as i64),
+                ParameterRange::Int {
+                    min: 5,
+                    max: 100,
+                    step: 5,
+                },
+            ),
+        ]
+    }
+
+    fn update_parameter(&mut self, name: &str, value: ParameterValue) -> Result<(), TradingError> {
+        validate(&self.parameters(), name, &value)?;
+        match name {
+            "fast_period" => self.fast_period = value.as_usize(),
+            "slow_period" => self.slow_period = value.as_usize(),
+            _ => unreachable!(),
+        }
+        // Periods changed, so the previous relation no longer means anything
+        self.fast_above = None;
+        Ok(())
+    }
+}
+
+/// Strategies selectable by name from the command line and config files
+pub const STRATEGY_NAMES: &[&str] = &["rsi", "ema_cross"];
+
+pub fn create_strategy(name: &str) -> Result<Box<dyn Strategy>, TradingError> {
+    match name {
+        "rsi" => Ok(Box::new(RsiStrategy::default())),
+        "ema_cross" => Ok(Box::new(EmaCrossStrategy::default())),
+        _ => Err(TradingError::InvalidParameter(format!(
+            "Unknown strategy {}, expected one of {}",
+            name,
+            STRATEGY_NAMES.join(", ")
+        ))),
+    }
+}
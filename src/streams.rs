@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::notify::{self, Notification, NotifySender};
+
+// Consecutive unparseable messages on one stream before operators are alerted
+const PARSE_ERROR_ALERT_AFTER: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "web", derive(utoipa::ToSchema))]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum StreamStatus {
+    Connecting,
+    // Since when, ms
+    Connected { since: i64 },
+    // Until the supervisor restarts the task
+    Disconnected { at: i64, reason: String },
+}
+
+/// One websocket subscription and how it is doing
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "web", derive(utoipa::ToSchema))]
+pub struct StreamInfo {
+    pub name: String,
+    // Exchange stream name, e.g. "btcusdt@kline_1m"
+    pub stream: String,
+    pub status: StreamStatus,
+    // Successful connects, restarts and resubscribes included
+    pub connects: u32,
+    pub messages: u64,
+    // Messages dropped because they could not be parsed
+    pub parse_errors: u64,
+    // Unparseable messages since the last good one
+    pub consecutive_parse_errors: u32,
+    // Time of the last message, ms
+    pub last_message: Option<i64>,
+}
+
+/// Every market data subscription, updated by the stream tasks themselves.
+/// Cloning shares the same registry.
+#[derive(Debug, Clone)]
+pub struct StreamRegistry {
+    streams: Arc<Mutex<BTreeMap<String, StreamInfo>>>,
+    // Bumped to make every stream task drop its connection and subscribe again
+    resubscribe: Arc<watch::Sender<u64>>,
+    notifiers: Vec<NotifySender>,
+    // Put before this handle's stream names, e.g. "kraken:" for a routed venue
+    prefix: String,
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+impl StreamRegistry {
+    // Repeated parse failures are reported to the notifiers
+    pub fn new(notifiers: Vec<NotifySender>) -> Self {
+        StreamRegistry {
+            streams: Arc::new(Mutex::new(BTreeMap::new())),
+            resubscribe: Arc::new(watch::channel(0).0),
+            notifiers,
+            prefix: String::new(),
+        }
+    }
+
+    // Same registry for a routed venue's streams, listed as e.g. "kraken:kline"
+    // so they don't overwrite the primary exchange's entries
+    pub fn for_venue(&self, venue: &str) -> Self {
+        StreamRegistry {
+            prefix: format!("{}:", venue),
+            ..self.clone()
+        }
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    // Called by a stream task when it starts; a restarted task keeps its counters.
+    // The receiver changes when resubscribe_all() is called.
+    pub fn register(&self, name: &str, stream: String) -> watch::Receiver<u64> {
+        let name = self.key(name);
+        let mut streams = self.streams.lock().unwrap();
+        let info = streams.entry(name.clone()).or_insert_with(|| StreamInfo {
+            name,
+            stream: stream.clone(),
+            status: StreamStatus::Connecting,
+            connects: 0,
+            messages: 0,
+            parse_errors: 0,
+            consecutive_parse_errors: 0,
+            last_message: None,
+        });
+        info.stream = stream;
+        info.status = StreamStatus::Connecting;
+        self.resubscribe.subscribe()
+    }
+
+    fn update(&self, name: &str, update: impl FnOnce(&mut StreamInfo)) {
+        if let Some(info) = self.streams.lock().unwrap().get_mut(&self.key(name)) {
+            update(info);
+        }
+    }
+
+    pub fn connecting(&self, name: &str) {
+        self.update(name, |info| info.status = StreamStatus::Connecting);
+    }
+
+    pub fn connected(&self, name: &str) {
+        self.update(name, |info| {
+            info.status = StreamStatus::Connected { since: now_ms() };
+            info.connects += 1;
+        });
+    }
+
+    pub fn disconnected(&self, name: &str, reason: String) {
+        log::warn!("{} stream disconnected: {}", self.key(name), reason);
+        self.update(name, |info| {
+            info.status = StreamStatus::Disconnected {
+                at: now_ms(),
+                reason,
+            }
+        });
+    }
+
+    pub fn message(&self, name: &str) {
+        self.update(name, |info| {
+            info.messages += 1;
+            info.last_message = Some(now_ms());
+        });
+    }
+
+    // A message was dropped because it could not be parsed
+    pub fn parse_error(&self, name: &str, error: String) {
+        log::error!("Dropped unparseable {} message: {}", self.key(name), error);
+        let mut failures = 0;
+        self.update(name, |info| {
+            info.parse_errors += 1;
+            info.consecutive_parse_errors += 1;
+            failures = info.consecutive_parse_errors;
+        });
+        // Once per run of failures
+        if failures == PARSE_ERROR_ALERT_AFTER {
+            notify::broadcast(
+                &self.notifiers,
+                Notification::Error {
+                    message: format!(
+                        "{} unparseable {} messages in a row, last: {}",
+                        failures,
+                        self.key(name),
+                        error
+                    ),
+                },
+            );
+        }
+    }
+
+    // A message was parsed and passed on
+    pub fn parsed(&self, name: &str) {
+        self.update(name, |info| info.consecutive_parse_errors = 0);
+    }
+
+    pub fn list_subscriptions(&self) -> Vec<StreamInfo> {
+        self.streams.lock().unwrap().values().cloned().collect()
+    }
+
+    // Connected streams reconnect and subscribe again right away; returns how
+    // many streams are registered
+    pub fn resubscribe_all(&self) -> usize {
+        log::warn!("Resubscribing every market data stream");
+        self.resubscribe.send_modify(|generation| *generation += 1);
+        self.streams.lock().unwrap().len()
+    }
+}
@@ -0,0 +1,178 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use tokio::task::JoinHandle;
+
+use crate::notify::{self, Notification, NotifySender};
+use crate::safe_mode;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+// A task that stays up this long starts over with a clean record
+const HEALTHY_AFTER: Duration = Duration::from_secs(300);
+// Consecutive failures before operators are alerted
+const ESCALATE_AFTER: u32 = 3;
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+type Factory = Box<dyn Fn() -> JoinHandle<()> + Send + Sync>;
+
+struct Supervised {
+    name: &'static str,
+    // None for tasks that own state that can't be rebuilt, like the executor
+    factory: Option<Factory>,
+    handle: Option<JoinHandle<()>>,
+    started_at: Instant,
+    failures: u32,
+    restart_at: Option<Instant>,
+}
+
+/// Owns the bot's long-running tasks: restarts the ones that can be restarted
+/// with exponential backoff and alerts when a task keeps failing or is lost
+pub struct Supervisor {
+    tasks: Vec<Supervised>,
+    notifiers: Vec<NotifySender>,
+}
+
+impl Supervisor {
+    pub fn new(notifiers: Vec<NotifySender>) -> Self {
+        Supervisor {
+            tasks: Vec::new(),
+            notifiers,
+        }
+    }
+
+    // Runs `make()` now and again whenever the task it returned ends, since these
+    // tasks are meant to run forever
+    pub fn spawn<F, Fut>(&mut self, name: &'static str, make: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let factory: Factory = Box::new(move || tokio::spawn(make()));
+        self.tasks.push(Supervised {
+            name,
+            handle: Some(factory()),
+            factory: Some(factory),
+            started_at: Instant::now(),
+            failures: 0,
+            restart_at: None,
+        });
+    }
+
+    // Watched but not restartable; its end is escalated straight away
+    pub fn spawn_once<Fut>(&mut self, name: &'static str, task: Fut)
+    where
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.push(Supervised {
+            name,
+            factory: None,
+            handle: Some(tokio::spawn(task)),
+            started_at: Instant::now(),
+            failures: 0,
+            restart_at: None,
+        });
+    }
+
+    fn escalate(&self, message: String) {
+        log::error!("{}", message);
+        notify::broadcast(&self.notifiers, Notification::Error { message });
+    }
+
+    // Returns once no task is left running or waiting to restart
+    pub async fn run(&mut self) {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            let now = Instant::now();
+            let mut escalations = Vec::new();
+            for task in &mut self.tasks {
+                if let Some(restart_at) = task.restart_at {
+                    if now >= restart_at {
+                        if let Some(factory) = &task.factory {
+                            log::info!("Restarting {} task", task.name);
+                            task.handle = Some(factory());
+                            task.started_at = now;
+                        }
+                        task.restart_at = None;
+                    }
+                    continue;
+                }
+                let finished = task.handle.as_ref().is_some_and(|h| h.is_finished());
+                if !finished {
+                    continue;
+                }
+                let outcome = match task.handle.take() {
+                    Some(handle) => match handle.await {
+                        Ok(()) => "exited".to_string(),
+                        Err(e) => format!("failed: {}", e),
+                    },
+                    None => continue,
+                };
+
+                if task.factory.is_none() {
+                    escalations.push(format!(
+                        "{} task {} and cannot be restarted",
+                        task.name, outcome
+                    ));
+                    continue;
+                }
+                if now.duration_since(task.started_at) >= HEALTHY_AFTER {
+                    task.failures = 0;
+                }
+                task.failures += 1;
+                safe_mode::report_error(format!("{} task {}", task.name, outcome));
+                let backoff = INITIAL_BACKOFF
+                    .saturating_mul(2u32.saturating_pow(task.failures - 1))
+                    .min(MAX_BACKOFF);
+                log::warn!(
+                    "{} task {} ({} in a row), restarting in {:?}",
+                    task.name,
+                    outcome,
+                    task.failures,
+                    backoff
+                );
+                if task.failures == ESCALATE_AFTER {
+                    escalations.push(format!(
+                        "{} task {} {} times in a row, still restarting",
+                        task.name, outcome, task.failures
+                    ));
+                }
+                task.restart_at = Some(now + backoff);
+            }
+            for message in escalations {
+                self.escalate(message);
+            }
+            let done = self
+                .tasks
+                .iter()
+                .all(|task| task.handle.is_none() && task.restart_at.is_none());
+            if done {
+                break;
+            }
+        }
+    }
+
+    // Wait up to `timeout` for the named task to end by itself; false if it didn't
+    pub async fn wait_for(&mut self, name: &str, timeout: Duration) -> bool {
+        let handle = self
+            .tasks
+            .iter_mut()
+            .find(|task| task.name == name)
+            .and_then(|task| task.handle.take());
+        match handle {
+            Some(handle) => tokio::time::timeout(timeout, handle).await.is_ok(),
+            None => true,
+        }
+    }
+
+    // Stop every remaining task without restarting it
+    pub fn abort_all(&mut self) {
+        for task in &mut self.tasks {
+            task.restart_at = None;
+            if let Some(handle) = task.handle.take() {
+                handle.abort();
+            }
+        }
+    }
+}
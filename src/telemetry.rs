@@ -0,0 +1,296 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use hyper::client::HttpConnector;
+use hyper::{Body, Client, Method, Request};
+use hyper_tls::HttpsConnector;
+#[cfg(feature = "postgres")]
+use tokio_postgres::NoTls;
+
+use crate::domain::*;
+use crate::executor::SharedExecutorState;
+use crate::latency::LatencyStage;
+
+/// One sample of a series
+#[derive(Debug, Clone)]
+pub struct Point {
+    pub name: &'static str,
+    pub symbol: Option<String>,
+    pub value: f64,
+    // Milliseconds
+    pub time: i64,
+}
+
+impl Point {
+    // InfluxDB line protocol
+    fn to_line(&self) -> String {
+        match &self.symbol {
+            Some(symbol) => format!(
+                "{},symbol={} value={} {}",
+                self.name, symbol, self.value, self.time
+            ),
+            None => format!("{} value={} {}", self.name, self.value, self.time),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TelemetryTarget {
+    // InfluxDB 2.x HTTP write API
+    Influx {
+        url: String,
+        org: String,
+        bucket: String,
+        token: String,
+    },
+    // TimescaleDB (or plain Postgres) connection string
+    #[cfg(feature = "postgres")]
+    Timescale { url: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct TelemetrySettings {
+    pub target: TelemetryTarget,
+    pub interval: Duration,
+}
+
+impl TelemetrySettings {
+    // INFLUX_URL (with INFLUX_ORG, INFLUX_BUCKET, INFLUX_TOKEN) or TIMESCALE_URL;
+    // TELEMETRY_INTERVAL_SECS defaults to 10
+    pub fn from_env() -> Option<Self> {
+        let target = if let Ok(url) = dotenv::var("INFLUX_URL") {
+            TelemetryTarget::Influx {
+                url,
+                org: dotenv::var("INFLUX_ORG").unwrap_or_default(),
+                bucket: dotenv::var("INFLUX_BUCKET").unwrap_or_else(|_| "auto_trade".to_string()),
+                token: dotenv::var("INFLUX_TOKEN").unwrap_or_default(),
+            }
+        } else {
+            timescale_target(dotenv::var("TIMESCALE_URL").ok()?)?
+        };
+        let interval = dotenv::var("TELEMETRY_INTERVAL_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .unwrap_or(10);
+        Some(TelemetrySettings {
+            target,
+            interval: Duration::from_secs(interval.max(1)),
+        })
+    }
+}
+
+#[cfg(feature = "postgres")]
+fn timescale_target(url: String) -> Option<TelemetryTarget> {
+    Some(TelemetryTarget::Timescale { url })
+}
+
+#[cfg(not(feature = "postgres"))]
+fn timescale_target(_url: String) -> Option<TelemetryTarget> {
+    log::warn!("TIMESCALE_URL set but built without the postgres feature");
+    None
+}
+
+enum Writer {
+    Influx {
+        client: Client<HttpsConnector<HttpConnector>>,
+        endpoint: String,
+        token: String,
+    },
+    #[cfg(feature = "postgres")]
+    Timescale(tokio_postgres::Client),
+}
+
+#[cfg(feature = "postgres")]
+const TIMESCALE_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS bot_metrics (
+    time TIMESTAMPTZ NOT NULL,
+    name TEXT NOT NULL,
+    symbol TEXT,
+    value DOUBLE PRECISION NOT NULL
+);
+CREATE INDEX IF NOT EXISTS bot_metrics_name_time ON bot_metrics (name, time DESC);
+";
+
+impl Writer {
+    async fn connect(target: &TelemetryTarget) -> Result<Self, TradingError> {
+        match target {
+            TelemetryTarget::Influx {
+                url,
+                org,
+                bucket,
+                token,
+            } => Ok(Writer::Influx {
+                client: Client::builder().build(HttpsConnector::new()),
+                endpoint: format!(
+                    "{}/api/v2/write?org={}&bucket={}&precision=ms",
+                    url.trim_end_matches('/'),
+                    org,
+                    bucket
+                ),
+                token: token.clone(),
+            }),
+            #[cfg(feature = "postgres")]
+            TelemetryTarget::Timescale { url } => {
+                let (client, connection) = tokio_postgres::connect(url, NoTls)
+                    .await
+                    .map_err(|e| TradingError::ConnectionError(format!("{:?}", e)))?;
+                tokio::spawn(async move {
+                    if let Err(e) = connection.await {
+                        log::error!("Telemetry database connection closed: {}", e);
+                    }
+                });
+                client
+                    .batch_execute(TIMESCALE_SCHEMA)
+                    .await
+                    .map_err(|e| TradingError::DataError(format!("{:?}", e)))?;
+                // Only available with the TimescaleDB extension; a plain table works too
+                if let Err(e) = client
+                    .batch_execute(
+                        "SELECT create_hypertable('bot_metrics', 'time', if_not_exists => TRUE)",
+                    )
+                    .await
+                {
+                    log::info!("bot_metrics stays a plain table: {}", e);
+                }
+                Ok(Writer::Timescale(client))
+            }
+        }
+    }
+
+    async fn write(&self, points: &[Point]) -> Result<(), TradingError> {
+        match self {
+            Writer::Influx {
+                client,
+                endpoint,
+                token,
+            } => {
+                let body = points
+                    .iter()
+                    .map(Point::to_line)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let request = Request::builder()
+                    .method(Method::POST)
+                    .uri(endpoint.as_str())
+                    .header("Authorization", format!("Token {}", token))
+                    .header("Content-Type", "text/plain; charset=utf-8")
+                    .body(Body::from(body))
+                    .map_err(|e| TradingError::DataError(format!("{:?}", e)))?;
+                let response = client
+                    .request(request)
+                    .await
+                    .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+                if !response.status().is_success() {
+                    return Err(TradingError::NetworkError(format!(
+                        "InfluxDB answered {}",
+                        response.status()
+                    )));
+                }
+                Ok(())
+            }
+            #[cfg(feature = "postgres")]
+            Writer::Timescale(client) => {
+                for point in points {
+                    client
+                        .execute(
+                            "INSERT INTO bot_metrics (time, name, symbol, value)
+                             VALUES (to_timestamp($1::DOUBLE PRECISION / 1000), $2, $3, $4)",
+                            &[
+                                &(point.time as f64),
+                                &point.name,
+                                &point.symbol,
+                                &point.value,
+                            ],
+                        )
+                        .await
+                        .map_err(|e| TradingError::DataError(format!("{:?}", e)))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// Price, PnL, per-position and latency series at this moment
+async fn sample(market_data: &Arc<Mutex<MarketData>>, state: &SharedExecutorState) -> Vec<Point> {
+    let time = chrono::Utc::now().timestamp_millis();
+    let point = |name: &'static str, symbol: Option<String>, value: f64| Point {
+        name,
+        symbol,
+        value,
+        time,
+    };
+    let mut points = Vec::new();
+    let (symbol, price) = {
+        let data = market_data.lock().unwrap();
+        (data.symbol.clone(), from_decimal(data.last_price))
+    };
+    if !symbol.is_empty() && price > 0.0 {
+        points.push(point("price", Some(symbol), price));
+    }
+
+    let state = state.read().await;
+    let report = state.pnl_report();
+    points.push(point("pnl_realized", None, report.realized));
+    points.push(point("pnl_unrealized", None, report.unrealized));
+    points.push(point("pnl_total", None, report.total()));
+    points.push(point("fees", None, report.fees));
+    let dropped = state.bus.dropped();
+    points.push(point(
+        "events_dropped_trading",
+        None,
+        dropped.trading as f64,
+    ));
+    points.push(point("events_dropped_market", None, dropped.market as f64));
+    points.push(point(
+        "open_positions",
+        None,
+        report.open_positions.len() as f64,
+    ));
+    for position in &report.open_positions {
+        let symbol = Some(position.symbol.clone());
+        points.push(point(
+            "position_quantity",
+            symbol.clone(),
+            position.quantity,
+        ));
+        points.push(point("position_unrealized", symbol, position.unrealized));
+    }
+    for stage in LatencyStage::ALL {
+        let histogram = state.latency.get(stage);
+        let [p50, p95, regression] = stage.metrics();
+        if let (Some(median), Some(tail)) = (histogram.quantile(0.5), histogram.quantile(0.95)) {
+            points.push(point(p50, None, median));
+            points.push(point(p95, None, tail));
+        }
+        points.push(point(
+            regression,
+            None,
+            if histogram.regressed { 1.0 } else { 0.0 },
+        ));
+    }
+    points
+}
+
+/// Samples the bot every interval and writes the series to the configured database
+pub async fn run_telemetry(
+    settings: TelemetrySettings,
+    market_data: Arc<Mutex<MarketData>>,
+    state: SharedExecutorState,
+) {
+    let writer = match Writer::connect(&settings.target).await {
+        Ok(writer) => writer,
+        Err(e) => {
+            log::error!("Failed to set up telemetry: {}", e);
+            return;
+        }
+    };
+    let mut interval = tokio::time::interval(settings.interval);
+    loop {
+        interval.tick().await;
+        let points = sample(&market_data, &state).await;
+        if let Err(e) = writer.write(&points).await {
+            log::warn!("Failed to write {} telemetry points: {}", points.len(), e);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use binance_spot_connector_rust::{
+    http::Credentials,
+    hyper::BinanceHttpClient,
+    stream::{new_listen_key, renew_listen_key},
+    tokio_tungstenite::BinanceWebSocketClient,
+    user_data_stream::UserDataStream,
+};
+use futures_util::StreamExt;
+use hyper::client::HttpConnector;
+use hyper_tls::HttpsConnector;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use tokio::sync::broadcast;
+
+use crate::domain::*;
+use crate::streams::StreamRegistry;
+use crate::{stream_url, BINANCE_TESTNET_REST_URL};
+
+// Listen keys lapse after an hour without a keepalive
+const KEEPALIVE: Duration = Duration::from_secs(30 * 60);
+
+/// Order progress as the account stream reports it, one per execution or
+/// status change
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExecutionReport {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "i")]
+    pub order_id: u64,
+    // The cancel's own id on cancel reports
+    #[serde(rename = "c")]
+    pub client_order_id: String,
+    #[serde(rename = "q")]
+    pub quantity: String,
+    // -1 unless the order is a leg of an OCO list
+    #[serde(rename = "g")]
+    pub order_list_id: i64,
+    #[serde(rename = "S")]
+    pub side: String,
+    // NEW, TRADE, CANCELED, EXPIRED, ...
+    #[serde(rename = "x")]
+    pub execution_type: String,
+    #[serde(rename = "X")]
+    pub status: String,
+    // Of this execution
+    #[serde(rename = "l")]
+    pub last_quantity: String,
+    #[serde(rename = "L")]
+    pub last_price: String,
+    #[serde(rename = "n")]
+    pub commission: String,
+    #[serde(rename = "N")]
+    pub commission_asset: Option<String>,
+    // Of the order so far
+    #[serde(rename = "z")]
+    pub cumulative_quantity: String,
+    #[serde(rename = "Z")]
+    pub cumulative_quote: String,
+}
+
+impl ExecutionReport {
+    pub fn order_side(&self) -> OrderSide {
+        if self.side == "BUY" {
+            OrderSide::Buy
+        } else {
+            OrderSide::Sell
+        }
+    }
+
+    // The fill this report carries, if it is an execution
+    pub fn fill(&self) -> Option<Fill> {
+        if self.execution_type != "TRADE" {
+            return None;
+        }
+        Some(Fill {
+            price: self.last_price.parse().ok()?,
+            quantity: self.last_quantity.parse().ok()?,
+            commission: self.commission.parse().unwrap_or_default(),
+            commission_asset: self.commission_asset.clone().unwrap_or_default(),
+        })
+    }
+
+    // The order's fills so far as one at their average price, without commission
+    pub fn cumulative_fill(&self) -> Option<Fill> {
+        let quantity: Decimal = self.cumulative_quantity.parse().ok()?;
+        let quote: Decimal = self.cumulative_quote.parse().ok()?;
+        (quantity > Decimal::ZERO).then(|| Fill {
+            price: quote / quantity,
+            quantity,
+            commission: Decimal::ZERO,
+            commission_asset: String::new(),
+        })
+    }
+}
+
+/// A deposit, withdrawal or transfer changing a free balance
+#[derive(Debug, Clone, Deserialize)]
+pub struct BalanceUpdate {
+    #[serde(rename = "a")]
+    pub asset: String,
+    #[serde(rename = "d")]
+    pub delta: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "e")]
+pub enum AccountEvent {
+    #[serde(rename = "executionReport")]
+    Execution(ExecutionReport),
+    #[serde(rename = "balanceUpdate")]
+    Balance(BalanceUpdate),
+    // Account snapshots and OCO list statuses; the execution reports say it all
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct AccountFrame {
+    data: AccountEvent,
+}
+
+#[derive(Deserialize)]
+struct ListenKey {
+    #[serde(rename = "listenKey")]
+    listen_key: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserStreamSettings {
+    pub api_key: String,
+    pub testnet: bool,
+}
+
+type HttpClient = BinanceHttpClient<HttpsConnector<HttpConnector>>;
+
+async fn listen_key(client: &HttpClient) -> Result<String, TradingError> {
+    let data = client
+        .send(new_listen_key())
+        .await
+        .map_err(|e| TradingError::ConnectionError(format!("{:?}", e)))?
+        .into_body_str()
+        .await
+        .map_err(|e| TradingError::NetworkError(format!("{:?}", e)))?;
+    let key: ListenKey = serde_json::from_str(&data)?;
+    Ok(key.listen_key)
+}
+
+// Order and balance updates of the account, published on `events` until the
+// connection drops; the supervisor restarts it with a fresh listen key
+pub async fn run(
+    settings: UserStreamSettings,
+    events: broadcast::Sender<AccountEvent>,
+    streams: StreamRegistry,
+) {
+    // Listen key requests only need the API key, not a signature
+    let credentials = Credentials::from_hmac(settings.api_key, String::new());
+    let client = if settings.testnet {
+        BinanceHttpClient::with_url(BINANCE_TESTNET_REST_URL).credentials(credentials)
+    } else {
+        BinanceHttpClient::default().credentials(credentials)
+    };
+    // Registered under a fixed name; the listen key grants access to the account
+    streams.register("user data", "userData".to_string());
+    streams.connecting("user data");
+    let key = match listen_key(&client).await {
+        Ok(key) => key,
+        Err(e) => {
+            streams.disconnected("user data", e.to_string());
+            return;
+        }
+    };
+    let (mut conn, _) =
+        match BinanceWebSocketClient::connect_async(stream_url(settings.testnet)).await {
+            Ok(conn) => conn,
+            Err(e) => {
+                streams.disconnected("user data", format!("{:?}", e));
+                return;
+            }
+        };
+    conn.subscribe(vec![&UserDataStream::new(&key).into()])
+        .await;
+    streams.connected("user data");
+
+    let mut keepalive = tokio::time::interval(KEEPALIVE);
+    // The first tick is immediate and the key is fresh
+    keepalive.tick().await;
+    let reason = loop {
+        let message = tokio::select! {
+            message = conn.as_mut().next() => message,
+            _ = keepalive.tick() => {
+                if let Err(e) = client.send(renew_listen_key(&key)).await {
+                    break format!("keepalive failed: {:?}", e);
+                }
+                continue;
+            }
+        };
+        let message = match message {
+            Some(Ok(message)) => message,
+            Some(Err(e)) => break format!("{:?}", e),
+            None => break "closed by the exchange".to_string(),
+        };
+        let binary_data = message.into_data();
+        let data = match std::str::from_utf8(&binary_data) {
+            Ok(data) => data,
+            Err(e) => {
+                streams.parse_error("user data", format!("{:?}", e));
+                continue;
+            }
+        };
+        streams.message("user data");
+        match serde_json::from_str::<AccountFrame>(data) {
+            Ok(frame) => {
+                streams.parsed("user data");
+                // No receivers just means nothing is trading yet
+                let _ = events.send(frame.data);
+            }
+            Err(e) => {
+                // Numeric frames are subscription acknowledgements
+                if data.trim().parse::<i64>().is_err() {
+                    streams.parse_error("user data", format!("{} raw data: {}", e, data));
+                }
+            }
+        }
+    };
+    if let Err(e) = conn.close().await {
+        log::warn!("Failed to close user data stream: {:?}", e);
+    }
+    streams.disconnected("user data", reason);
+}
@@ -0,0 +1,201 @@
+use std::net::SocketAddr;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+
+use crate::domain::*;
+
+#[derive(Debug, Clone)]
+pub struct WebhookSettings {
+    pub addr: SocketAddr,
+    // TradingView can't set headers, so alerts carry this in their body
+    pub secret: String,
+}
+
+impl WebhookSettings {
+    // WEBHOOK_ADDR and WEBHOOK_SECRET; the endpoint is not started without a secret
+    pub fn from_env() -> Option<Self> {
+        let addr = dotenv::var("WEBHOOK_ADDR").ok()?;
+        let secret = match dotenv::var("WEBHOOK_SECRET") {
+            Ok(secret) if !secret.is_empty() => secret,
+            _ => {
+                log::warn!("WEBHOOK_ADDR set without WEBHOOK_SECRET, webhook disabled");
+                return None;
+            }
+        };
+        match addr.parse() {
+            Ok(addr) => Some(WebhookSettings { addr, secret }),
+            Err(e) => {
+                log::error!("Invalid WEBHOOK_ADDR {}: {}", addr, e);
+                None
+            }
+        }
+    }
+}
+
+/// A TradingView alert, with the alert message set to e.g.
+/// `{"secret": "...", "ticker": "{{ticker}}", "action": "{{strategy.order.action}}", "price": {{close}}}`
+#[derive(Debug, Deserialize)]
+pub struct TradingViewAlert {
+    pub secret: String,
+    pub ticker: String,
+    // buy / long, sell / short, or exit / close to flatten what is held, in any case
+    pub action: String,
+    pub price: Decimal,
+    #[serde(default)]
+    pub strategy: Option<String>,
+    #[serde(default)]
+    pub stop_loss: Option<Decimal>,
+    #[serde(default)]
+    pub take_profit: Option<Decimal>,
+}
+
+impl TradingViewAlert {
+    pub fn to_signal(&self) -> Result<TradingSignal, TradingError> {
+        let action = match self.action.to_lowercase().as_str() {
+            "buy" | "long" => TradeAction::Buy,
+            "sell" | "short" => TradeAction::Sell,
+            "exit" | "close" => TradeAction::Close,
+            other => {
+                return Err(TradingError::InvalidParameter(format!(
+                    "Unknown alert action {}",
+                    other
+                )))
+            }
+        };
+        if self.price <= Decimal::ZERO {
+            return Err(TradingError::InvalidParameter(format!(
+                "Alert price must be positive, got {}",
+                self.price
+            )));
+        }
+        // "BINANCE:BTCUSDT" or "BTCUSDT.P" style tickers
+        let symbol = self.ticker.rsplit(':').next().unwrap_or(&self.ticker);
+        let symbol = symbol.split('.').next().unwrap_or(symbol).to_uppercase();
+        Ok(TradingSignal {
+            symbol,
+            strategy: self
+                .strategy
+                .clone()
+                .unwrap_or_else(|| "tradingview".to_string()),
+            action,
+            price: self.price,
+            timestamp: chrono::Utc::now().timestamp(),
+            stop_loss: self.stop_loss,
+            take_profit: self.take_profit,
+        })
+    }
+}
+
+#[derive(Clone)]
+struct WebhookState {
+    // Digest of the secret, compared in constant time
+    secret: [u8; 32],
+    signals: mpsc::Sender<TradingSignal>,
+}
+
+fn secret_matches(expected: &[u8; 32], given: &str) -> bool {
+    let given: [u8; 32] = Sha256::digest(given.as_bytes()).into();
+    given
+        .iter()
+        .zip(expected)
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+async fn alert(
+    State(webhook): State<WebhookState>,
+    Json(alert): Json<TradingViewAlert>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !secret_matches(&webhook.secret, &alert.secret) {
+        log::warn!(
+            "Rejected webhook alert for {} with a wrong secret",
+            alert.ticker
+        );
+        return Err((StatusCode::UNAUTHORIZED, "Wrong secret".to_string()));
+    }
+    let signal = alert
+        .to_signal()
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("{:?}", e)))?;
+    log::info!(
+        "Webhook {:?} signal for {} at {} from {}",
+        signal.action,
+        signal.symbol,
+        signal.price,
+        signal.strategy
+    );
+    // Risk checks and execution happen in the signal task, as for our own signals
+    webhook.signals.send(signal).await.map_err(|_| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Executor is not running".to_string(),
+        )
+    })?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Accepts TradingView alerts on POST /webhook/tradingview and feeds them to the executor
+pub async fn serve(settings: WebhookSettings, signals: mpsc::Sender<TradingSignal>) {
+    let state = WebhookState {
+        secret: Sha256::digest(settings.secret.as_bytes()).into(),
+        signals,
+    };
+    let app = Router::new()
+        .route("/webhook/tradingview", post(alert))
+        .with_state(state);
+    log::info!("TradingView webhook listening on {}", settings.addr);
+    if let Err(e) = axum::Server::bind(&settings.addr)
+        .serve(app.into_make_service())
+        .await
+    {
+        log::error!("Webhook stopped: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(action: &str) -> TradingViewAlert {
+        TradingViewAlert {
+            secret: "secret".to_string(),
+            ticker: "BINANCE:BTCUSDT".to_string(),
+            action: action.to_string(),
+            price: Decimal::ONE_HUNDRED,
+            strategy: None,
+            stop_loss: None,
+            take_profit: None,
+        }
+    }
+
+    #[test]
+    fn exits_close_rather_than_sell() {
+        assert_eq!(alert("Buy").to_signal().unwrap().action, TradeAction::Buy);
+        assert_eq!(
+            alert("short").to_signal().unwrap().action,
+            TradeAction::Sell
+        );
+        assert_eq!(
+            alert("exit").to_signal().unwrap().action,
+            TradeAction::Close
+        );
+        assert_eq!(
+            alert("CLOSE").to_signal().unwrap().action,
+            TradeAction::Close
+        );
+        assert!(alert("hodl").to_signal().is_err());
+    }
+
+    #[test]
+    fn signals_are_stamped_in_seconds() {
+        let signal = alert("buy").to_signal().unwrap();
+        assert_eq!(signal.symbol, "BTCUSDT");
+        assert!((signal.timestamp - chrono::Utc::now().timestamp()).abs() <= 5);
+    }
+}
@@ -0,0 +1,75 @@
+// Signals through the executor to the mock exchange, the way the simulate
+// command drives them
+use auto_trade::domain::{ExchangeClient, RiskParameters, TradeAction, TradingSignal};
+use auto_trade::executor::TradeExecutor;
+use auto_trade::mock::MockExchange;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+const SYMBOL: &str = "BTCUSDT";
+
+fn signal(action: TradeAction, price: Decimal) -> TradingSignal {
+    TradingSignal {
+        symbol: SYMBOL.to_string(),
+        strategy: "test".to_string(),
+        action,
+        price,
+        timestamp: 0,
+        stop_loss: None,
+        take_profit: None,
+    }
+}
+
+// Fee-free exchange at 100 with a position bought through the executor
+async fn holding() -> (MockExchange, TradeExecutor<MockExchange>) {
+    let exchange = MockExchange::new(dec!(10_000)).with_fee_rate(Decimal::ZERO);
+    exchange.set_price(SYMBOL, Decimal::ONE_HUNDRED);
+    let mut client = exchange.clone();
+    client.connect().await.unwrap();
+    let mut executor = TradeExecutor::new(client, RiskParameters::default());
+    executor
+        .handle_signal(&signal(TradeAction::Buy, Decimal::ONE_HUNDRED))
+        .await
+        .unwrap();
+    (exchange, executor)
+}
+
+#[tokio::test]
+async fn a_close_signal_books_the_trade() {
+    let (exchange, mut executor) = holding().await;
+    assert!(executor.positions().await.contains_key(SYMBOL));
+
+    exchange.set_price(SYMBOL, dec!(102));
+    executor.mark_price(SYMBOL, dec!(102)).await;
+    executor
+        .handle_signal(&signal(TradeAction::Close, dec!(102)))
+        .await
+        .unwrap();
+
+    let state = executor.state();
+    let state = state.read().await;
+    assert!(state.positions.is_empty());
+    assert_eq!(state.trades.len(), 1);
+    assert_eq!(state.trades[0].entry_price, 100.0);
+    assert_eq!(state.trades[0].exit_price, 102.0);
+    assert!(state.trades[0].pnl > 0.0);
+    // Nothing is left resting once the position is flat
+    assert!(exchange.get_open_orders(SYMBOL).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn a_take_profit_on_the_exchange_closes_the_position() {
+    let (exchange, mut executor) = holding().await;
+
+    // The bracket's take-profit at 104 executes on the exchange
+    exchange.set_price(SYMBOL, dec!(105));
+    executor.mark_price(SYMBOL, dec!(105)).await;
+    executor.check_brackets().await;
+
+    let state = executor.state();
+    let state = state.read().await;
+    assert!(state.positions.is_empty());
+    assert_eq!(state.trades.len(), 1);
+    assert_eq!(state.trades[0].exit_price, 104.0);
+    assert!(exchange.balance() > dec!(10_000));
+}